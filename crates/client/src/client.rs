@@ -0,0 +1,396 @@
+use std::{
+    net::SocketAddr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use goosekv_protocol::{
+    command::{
+        DecrGCommand,
+        DelGCommand,
+        ExistsGCommand,
+        GCommand,
+        GetGCommand,
+        IncrGCommand,
+        PingGCommand,
+        SetGCommand,
+    },
+    data_type::GString,
+    frame::GFrame,
+};
+
+use crate::{
+    cmd::Cmd,
+    connection::Connection,
+    connector::Connector,
+    error::ClientResult,
+    from_frame::FromGFrame,
+    pipeline::Pipeline,
+};
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub addr: SocketAddr,
+    /// Connections kept open between requests, more are opened on demand under load.
+    pub max_idle_connections: usize,
+    pub max_connect_attempts: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl ClientConfig {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            max_idle_connections: 8,
+            max_connect_attempts: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Pooled client, cheap to share between tasks of one executor.
+pub struct Client<C>
+where
+    C: Connector,
+{
+    connector: C,
+    config: ClientConfig,
+    idle: Mutex<Vec<Connection<C::Stream>>>,
+}
+
+impl<C> Client<C>
+where
+    C: Connector,
+{
+    pub fn new(connector: C, config: ClientConfig) -> Self {
+        Self { connector, config, idle: Mutex::new(Vec::new()) }
+    }
+
+    pub async fn ping(&self) -> ClientResult<String> {
+        self.command(GCommand::Ping(PingGCommand { message: None })).await
+    }
+
+    pub async fn get<T>(&self, key: impl AsRef<[u8]>) -> ClientResult<T>
+    where
+        T: FromGFrame,
+    {
+        self.command(GCommand::Get(GetGCommand { key: gstring(key) })).await
+    }
+
+    pub async fn set(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> ClientResult<()> {
+        self.command(GCommand::Set(SetGCommand { key: gstring(key), value: gstring(value) })).await
+    }
+
+    pub async fn del<I>(&self, keys: I) -> ClientResult<i64>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let keys = keys.into_iter().map(gstring).collect();
+        self.command(GCommand::Del(DelGCommand { keys })).await
+    }
+
+    pub async fn exists<I>(&self, keys: I) -> ClientResult<i64>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let keys = keys.into_iter().map(gstring).collect();
+        self.command(GCommand::Exists(ExistsGCommand { keys })).await
+    }
+
+    pub async fn incr(&self, key: impl AsRef<[u8]>) -> ClientResult<i64> {
+        self.command(GCommand::Incr(IncrGCommand { key: gstring(key) })).await
+    }
+
+    pub async fn decr(&self, key: impl AsRef<[u8]>) -> ClientResult<i64> {
+        self.command(GCommand::Decr(DecrGCommand { key: gstring(key) })).await
+    }
+
+    pub async fn command<T>(&self, command: GCommand) -> ClientResult<T>
+    where
+        T: FromGFrame,
+    {
+        self.query(command.into()).await
+    }
+
+    pub async fn query<T>(&self, cmd: Cmd) -> ClientResult<T>
+    where
+        T: FromGFrame,
+    {
+        loop {
+            let (mut connection, reused) = self.checkout().await?;
+            let written = connection.bytes_written();
+
+            match connection.request(cmd.clone().into()).await {
+                // An idle connection may have been closed by the server, retry on a fresh one
+                // unless the command may have run already.
+                Err(error)
+                    if error.is_connection_error()
+                        && reused
+                        && (connection.bytes_written() == written || cmd.is_readonly()) =>
+                {
+                    continue;
+                }
+                Err(error) if error.is_connection_error() => return Err(error),
+                reply => {
+                    self.checkin(connection);
+                    return T::from_gframe(reply?);
+                }
+            }
+        }
+    }
+
+    pub fn pipeline(&self) -> Pipeline {
+        Pipeline::new()
+    }
+
+    pub(crate) async fn send_pipeline(&self, cmds: &[Cmd]) -> ClientResult<Vec<GFrame>> {
+        loop {
+            let (mut connection, reused) = self.checkout().await?;
+            let written = connection.bytes_written();
+            let frames = cmds.iter().cloned().map(GFrame::from).collect();
+
+            match connection.pipeline(frames).await {
+                Err(error)
+                    if error.is_connection_error()
+                        && reused
+                        && (connection.bytes_written() == written
+                            || cmds.iter().all(Cmd::is_readonly)) =>
+                {
+                    continue;
+                }
+                Err(error) if error.is_connection_error() => return Err(error),
+                replies => {
+                    self.checkin(connection);
+                    return replies;
+                }
+            }
+        }
+    }
+
+    /// Take an idle connection or open a new one, also returning whether it was reused.
+    async fn checkout(&self) -> ClientResult<(Connection<C::Stream>, bool)> {
+        if let Some(connection) = self.idle.lock().unwrap().pop() {
+            return Ok((connection, true));
+        }
+
+        Ok((self.connect().await?, false))
+    }
+
+    fn checkin(&self, connection: Connection<C::Stream>) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.config.max_idle_connections {
+            idle.push(connection);
+        }
+    }
+
+    /// Open a new connection, retrying with exponential backoff.
+    async fn connect(&self) -> ClientResult<Connection<C::Stream>> {
+        let mut backoff = self.config.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            match self.connector.connect(self.config.addr).await {
+                Ok(stream) => return Ok(Connection::new(stream)),
+                Err(error) if attempt >= self.config.max_connect_attempts => {
+                    return Err(error.into());
+                }
+                Err(_) => {
+                    self.connector.sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.config.max_backoff);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+fn gstring(value: impl AsRef<[u8]>) -> GString {
+    GString::copy_from_slice(value.as_ref())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        collections::VecDeque,
+        io,
+        pin::Pin,
+        rc::Rc,
+        task::{
+            Context,
+            Poll,
+        },
+    };
+
+    use futures::{
+        AsyncRead,
+        AsyncWrite,
+        executor::block_on,
+    };
+
+    use super::*;
+    use crate::error::ClientError;
+
+    /// Stream replaying canned replies, then closed.
+    struct FakeStream {
+        replies: VecDeque<u8>,
+        /// Writes accepted before any other fails with nothing written.
+        writes_left: usize,
+        written: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl AsyncRead for FakeStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let me = self.get_mut();
+            let len = buf.len().min(me.replies.len());
+            for (byte, reply) in buf.iter_mut().zip(me.replies.drain(..len)) {
+                *byte = reply;
+            }
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    impl AsyncWrite for FakeStream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let me = self.get_mut();
+            if me.writes_left == 0 {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            me.writes_left -= 1;
+            me.written.borrow_mut().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Connector handing out the queued streams, refusing connections once there are none.
+    #[derive(Default)]
+    struct FakeConnector {
+        streams: RefCell<VecDeque<FakeStream>>,
+        refusals: RefCell<usize>,
+        connects: RefCell<usize>,
+        sleeps: RefCell<Vec<Duration>>,
+        written: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl FakeConnector {
+        fn serve(self, replies: &[u8], writes_left: usize) -> Self {
+            let replies = replies.iter().copied().collect();
+            let written = self.written.clone();
+            self.streams.borrow_mut().push_back(FakeStream { replies, writes_left, written });
+            self
+        }
+
+        fn written(&self) -> String {
+            String::from_utf8_lossy(&self.written.borrow()).into_owned()
+        }
+    }
+
+    impl Connector for FakeConnector {
+        type Stream = FakeStream;
+
+        async fn connect(&self, _addr: SocketAddr) -> io::Result<Self::Stream> {
+            *self.connects.borrow_mut() += 1;
+            if *self.refusals.borrow() > 0 {
+                *self.refusals.borrow_mut() -= 1;
+                return Err(io::ErrorKind::ConnectionRefused.into());
+            }
+            self.streams.borrow_mut().pop_front().ok_or(io::ErrorKind::ConnectionRefused.into())
+        }
+
+        async fn sleep(&self, duration: Duration) {
+            self.sleeps.borrow_mut().push(duration);
+        }
+    }
+
+    fn client(connector: FakeConnector) -> Client<FakeConnector> {
+        let config = ClientConfig {
+            max_connect_attempts: 4,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(25),
+            ..ClientConfig::new("127.0.0.1:6379".parse().unwrap())
+        };
+        Client::new(connector, config)
+    }
+
+    #[test]
+    fn reconnects_with_backoff() {
+        let connector = FakeConnector { refusals: RefCell::new(2), ..Default::default() };
+        let client = client(connector.serve(b"+PONG\r\n", usize::MAX));
+
+        assert_eq!(block_on(client.ping()).unwrap(), "PONG");
+        let sleeps = client.connector.sleeps.borrow().clone();
+        assert_eq!(sleeps, [Duration::from_millis(10), Duration::from_millis(20)]);
+
+        let client = self::client(FakeConnector::default());
+        assert!(matches!(block_on(client.ping()), Err(ClientError::Io(_))));
+        assert_eq!(*client.connector.connects.borrow(), 4);
+        let sleeps = client.connector.sleeps.borrow().clone();
+        assert_eq!(sleeps, [10, 20, 25].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn reuses_idle_connections() {
+        let client = client(FakeConnector::default().serve(b"+OK\r\n:1\r\n", usize::MAX));
+
+        block_on(client.set("k", "v")).unwrap();
+        assert_eq!(block_on(client.incr("k")).unwrap(), 1);
+        assert_eq!(*client.connector.connects.borrow(), 1);
+    }
+
+    #[test]
+    fn retries_reads_on_closed_connections() {
+        let connector = FakeConnector::default()
+            .serve(b"+OK\r\n", usize::MAX)
+            .serve(b"$1\r\nv\r\n", usize::MAX);
+        let client = client(connector);
+
+        block_on(client.set("k", "v")).unwrap();
+        assert_eq!(block_on(client.get::<String>("k")).unwrap(), "v");
+        assert_eq!(*client.connector.connects.borrow(), 2);
+    }
+
+    #[test]
+    fn does_not_retry_writes_sent() {
+        let connector =
+            FakeConnector::default().serve(b"+OK\r\n", usize::MAX).serve(b":1\r\n", usize::MAX);
+        let client = client(connector);
+
+        block_on(client.set("k", "v")).unwrap();
+        assert!(matches!(block_on(client.incr("k")), Err(ClientError::ConnectionClosed)));
+        assert_eq!(client.connector.written().matches("INCR").count(), 1);
+
+        let mut pipeline = client.pipeline();
+        pipeline.cmd(Cmd::new("INCR").arg("k"));
+        assert_eq!(block_on(pipeline.query(&client)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn retries_writes_never_sent() {
+        let connector = FakeConnector::default().serve(b"+OK\r\n", 1).serve(b":1\r\n", 1);
+        let client = client(connector);
+
+        block_on(client.set("k", "v")).unwrap();
+        assert_eq!(block_on(client.incr("k")).unwrap(), 1);
+        assert_eq!(client.connector.written().matches("INCR").count(), 1);
+    }
+}
@@ -0,0 +1,61 @@
+use goosekv_protocol::{
+    command::{
+        GCommand,
+        table::{
+            CommandFlag,
+            CommandSpec,
+        },
+    },
+    data_type::GString,
+    frame::GFrame,
+};
+
+/// Raw command builder for commands without a typed method on [`crate::client::Client`].
+#[derive(Debug, Clone)]
+pub struct Cmd {
+    args: Vec<GString>,
+}
+
+impl Cmd {
+    pub fn new(name: impl AsRef<[u8]>) -> Self {
+        Self { args: vec![GString::copy_from_slice(name.as_ref())] }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<[u8]>) -> Self {
+        self.args.push(GString::copy_from_slice(arg.as_ref()));
+        self
+    }
+
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        self.args.extend(args.into_iter().map(|arg| GString::copy_from_slice(arg.as_ref())));
+        self
+    }
+
+    /// Whether the command only reads, so that running it twice is harmless.
+    pub fn is_readonly(&self) -> bool {
+        CommandSpec::resolve(&self.args).is_ok_and(|spec| spec.has_flag(CommandFlag::Readonly))
+    }
+}
+
+impl From<Cmd> for GFrame {
+    fn from(cmd: Cmd) -> Self {
+        GFrame::Array(cmd.args.into_iter().map(GFrame::BulkString).collect())
+    }
+}
+
+impl From<GCommand> for Cmd {
+    fn from(command: GCommand) -> Self {
+        let args = match command.to_frame() {
+            GFrame::Array(frames) => {
+                frames.iter().filter_map(|frame| frame.as_bulk_string().ok()).collect()
+            }
+            _ => unreachable!("commands serialize to arrays"),
+        };
+
+        Self { args }
+    }
+}
@@ -0,0 +1,63 @@
+use futures::{
+    AsyncRead,
+    AsyncWrite,
+    SinkExt,
+    StreamExt,
+};
+use goosekv_protocol::{
+    frame::GFrame,
+    stream::GFrameStream,
+};
+
+use crate::error::{
+    ClientError,
+    ClientResult,
+};
+
+/// Single connection to a server.
+pub struct Connection<S> {
+    stream: GFrameStream<S>,
+}
+
+impl<S> Connection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(stream: S) -> Self {
+        Self { stream: GFrameStream::new(stream) }
+    }
+
+    /// Bytes of requests written so far, unchanged by a request that failed before being sent.
+    pub fn bytes_written(&self) -> u64 {
+        self.stream.bytes_written()
+    }
+
+    /// Send a request and wait for its reply.
+    pub async fn request(&mut self, frame: GFrame) -> ClientResult<GFrame> {
+        self.stream.send(frame).await?;
+        self.read().await
+    }
+
+    /// Send all requests in one write and read as many replies.
+    pub async fn pipeline(&mut self, frames: Vec<GFrame>) -> ClientResult<Vec<GFrame>> {
+        let count = frames.len();
+        for frame in frames {
+            self.stream.feed(frame).await?;
+        }
+        self.stream.flush().await?;
+
+        let mut replies = Vec::with_capacity(count);
+        for _ in 0..count {
+            replies.push(self.read().await?);
+        }
+
+        Ok(replies)
+    }
+
+    async fn read(&mut self) -> ClientResult<GFrame> {
+        match self.stream.next().await {
+            Some(frame) => Ok(frame?),
+            None => Err(ClientError::ConnectionClosed),
+        }
+    }
+}
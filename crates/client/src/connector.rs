@@ -0,0 +1,86 @@
+use std::{
+    io,
+    net::SocketAddr,
+    time::Duration,
+};
+
+use futures::{
+    AsyncRead,
+    AsyncWrite,
+};
+
+/// Runtime specific parts of the client: opening connections and waiting between reconnects.
+pub trait Connector {
+    type Stream: AsyncRead + AsyncWrite + Unpin;
+
+    fn connect(&self, addr: SocketAddr) -> impl Future<Output = io::Result<Self::Stream>>;
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()>;
+}
+
+#[cfg(feature = "glommio")]
+pub use self::glommio::GlommioConnector;
+#[cfg(feature = "tokio")]
+pub use self::tokio::TokioConnector;
+
+#[cfg(feature = "glommio")]
+mod glommio {
+    use std::{
+        io,
+        net::SocketAddr,
+        time::Duration,
+    };
+
+    use glommio::net::TcpStream;
+
+    use crate::connector::Connector;
+
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct GlommioConnector;
+
+    impl Connector for GlommioConnector {
+        type Stream = TcpStream;
+
+        async fn connect(&self, addr: SocketAddr) -> io::Result<Self::Stream> {
+            Ok(TcpStream::connect(addr).await?)
+        }
+
+        async fn sleep(&self, duration: Duration) {
+            glommio::timer::sleep(duration).await;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio {
+    use std::{
+        io,
+        net::SocketAddr,
+        time::Duration,
+    };
+
+    use tokio::net::TcpStream;
+    use tokio_util::compat::{
+        Compat,
+        TokioAsyncReadCompatExt,
+    };
+
+    use crate::connector::Connector;
+
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct TokioConnector;
+
+    impl Connector for TokioConnector {
+        type Stream = Compat<TcpStream>;
+
+        async fn connect(&self, addr: SocketAddr) -> io::Result<Self::Stream> {
+            let stream = TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            Ok(stream.compat())
+        }
+
+        async fn sleep(&self, duration: Duration) {
+            tokio::time::sleep(duration).await;
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::io;
+
+use goosekv_protocol::stream::GFrameStreamError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("stream error: {0}")]
+    Stream(#[from] GFrameStreamError),
+    #[error("server error: {0}")]
+    Server(String),
+    #[error("unexpected reply: {0}")]
+    UnexpectedReply(String),
+    #[error("connection closed")]
+    ConnectionClosed,
+}
+
+impl ClientError {
+    /// Error code of a server error reply, e.g. `ERR` or `WRONGTYPE`.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Server(message) => message.split(' ').next(),
+            _ => None,
+        }
+    }
+
+    /// Whether the error left the connection in an unknown state and it must not be reused.
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, ClientError::Io(_) | ClientError::Stream(_) | ClientError::ConnectionClosed)
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
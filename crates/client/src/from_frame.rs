@@ -0,0 +1,164 @@
+use bytes::Bytes;
+use goosekv_protocol::{
+    data_type::GString,
+    frame::GFrame,
+};
+
+use crate::error::{
+    ClientError,
+    ClientResult,
+};
+
+/// Conversion of a reply frame into a Rust type.
+pub trait FromGFrame: Sized {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self>;
+}
+
+fn unexpected(frame: &GFrame) -> ClientError {
+    ClientError::UnexpectedReply(format!("{frame:?}"))
+}
+
+/// Turn a top level error reply into [`ClientError::Server`].
+pub(crate) fn check_error(frame: GFrame) -> ClientResult<GFrame> {
+    match frame {
+        GFrame::SimpleError(message) => {
+            Err(ClientError::Server(String::from_utf8_lossy(message.as_ref()).into_owned()))
+        }
+        frame => Ok(frame),
+    }
+}
+
+impl FromGFrame for GFrame {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        Ok(frame)
+    }
+}
+
+impl FromGFrame for () {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        check_error(frame).map(|_| ())
+    }
+}
+
+impl FromGFrame for GString {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        match check_error(frame)? {
+            GFrame::SimpleString(value) | GFrame::BulkString(value) => Ok(value),
+            GFrame::Integer(value) => Ok(GString::copy_from_slice(&value.bytes())),
+            frame => Err(unexpected(&frame)),
+        }
+    }
+}
+
+impl FromGFrame for Bytes {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        GString::from_gframe(frame).map(|value| value.bytes())
+    }
+}
+
+impl FromGFrame for Vec<u8> {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        GString::from_gframe(frame).map(|value| value.as_ref().to_vec())
+    }
+}
+
+impl FromGFrame for String {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        let value = GString::from_gframe(frame)?;
+        String::from_utf8(value.as_ref().to_vec())
+            .map_err(|_| ClientError::UnexpectedReply("invalid UTF-8".to_string()))
+    }
+}
+
+impl FromGFrame for i64 {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        match check_error(frame)? {
+            GFrame::Integer(value) => Ok(value.value()),
+            GFrame::SimpleString(value) | GFrame::BulkString(value) => {
+                str::from_utf8(value.as_ref())
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| ClientError::UnexpectedReply("not an integer".to_string()))
+            }
+            frame => Err(unexpected(&frame)),
+        }
+    }
+}
+
+impl FromGFrame for bool {
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        match check_error(frame)? {
+            GFrame::Integer(value) => Ok(value.value() != 0),
+            GFrame::SimpleString(_) => Ok(true),
+            GFrame::Null => Ok(false),
+            frame => Err(unexpected(&frame)),
+        }
+    }
+}
+
+impl<T> FromGFrame for Option<T>
+where
+    T: FromGFrame,
+{
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        match check_error(frame)? {
+            GFrame::Null => Ok(None),
+            frame => T::from_gframe(frame).map(Some),
+        }
+    }
+}
+
+impl<T> FromGFrame for Vec<T>
+where
+    T: FromGFrame,
+{
+    fn from_gframe(frame: GFrame) -> ClientResult<Self> {
+        match check_error(frame)? {
+            GFrame::Array(frames) => frames.into_iter().map(T::from_gframe).collect(),
+            frame => Err(unexpected(&frame)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use goosekv_protocol::data_type::GInteger;
+
+    use super::*;
+
+    #[test]
+    fn scalars() {
+        assert_eq!(i64::from_gframe(GFrame::Integer(GInteger::new(7))).unwrap(), 7);
+        assert_eq!(
+            i64::from_gframe(GFrame::BulkString(GString::from_static(b"-12"))).unwrap(),
+            -12
+        );
+        assert_eq!(
+            String::from_gframe(GFrame::SimpleString(GString::from_static(b"OK"))).unwrap(),
+            "OK"
+        );
+        assert!(bool::from_gframe(GFrame::Integer(GInteger::new(1))).unwrap());
+    }
+
+    #[test]
+    fn option_and_vec() {
+        assert_eq!(Option::<String>::from_gframe(GFrame::Null).unwrap(), None);
+
+        let frame = GFrame::Array(
+            vec![GFrame::BulkString(GString::from_static(b"a")), GFrame::Null].into_boxed_slice(),
+        );
+        assert_eq!(
+            Vec::<Option<String>>::from_gframe(frame).unwrap(),
+            vec![Some("a".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn server_error() {
+        let error = i64::from_gframe(GFrame::SimpleError(GString::from_static(
+            b"WRONGTYPE Operation against a key holding the wrong kind of value",
+        )))
+        .unwrap_err();
+        assert_eq!(error.code(), Some("WRONGTYPE"));
+    }
+}
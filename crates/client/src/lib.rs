@@ -0,0 +1,7 @@
+pub mod client;
+pub mod cmd;
+pub mod connection;
+pub mod connector;
+pub mod error;
+pub mod from_frame;
+pub mod pipeline;
@@ -0,0 +1,44 @@
+use goosekv_protocol::frame::GFrame;
+
+use crate::{
+    client::Client,
+    cmd::Cmd,
+    connector::Connector,
+    error::ClientResult,
+};
+
+/// Batch of commands sent in a single write.
+#[derive(Debug, Default, Clone)]
+pub struct Pipeline {
+    cmds: Vec<Cmd>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cmd(&mut self, cmd: impl Into<Cmd>) -> &mut Self {
+        self.cmds.push(cmd.into());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.cmds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cmds.is_empty()
+    }
+
+    /// Run all commands, returning raw replies in order.
+    ///
+    /// Error replies are returned as [`GFrame::SimpleError`] so one failing command does not hide
+    /// the results of the others; convert them with [`crate::from_frame::FromGFrame`].
+    pub async fn query<C>(&self, client: &Client<C>) -> ClientResult<Vec<GFrame>>
+    where
+        C: Connector,
+    {
+        client.send_pipeline(&self.cmds).await
+    }
+}
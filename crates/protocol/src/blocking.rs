@@ -0,0 +1,153 @@
+use std::io::{
+    self,
+    Read,
+    Write,
+};
+
+use crate::{
+    frame::GFrame,
+    parser::Parser,
+    stream::{
+        GFrameStreamError,
+        GFrameStreamResult,
+    },
+};
+
+/// Blocking counterpart of [`crate::stream::GFrameStream`] reading frames from [`Read`].
+pub struct GFrameReader<R> {
+    inner: R,
+    parser: Parser,
+    tmp: [u8; 1024],
+    /// Set once iterating hit an error, the stream cannot resync after it.
+    failed: bool,
+}
+
+impl<R> GFrameReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, parser: Parser::new(), tmp: [0u8; 1024], failed: false }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> GFrameReader<R>
+where
+    R: Read,
+{
+    /// Read the next frame, blocking until one is complete.
+    ///
+    /// Returns `Ok(None)` on a clean end of file.
+    pub fn read_frame(&mut self) -> Result<Option<GFrame>, GFrameStreamError> {
+        loop {
+            if let Some(frame) = self.parser.parse()? {
+                return Ok(Some(frame));
+            }
+
+            let n = match self.inner.read(&mut self.tmp) {
+                Ok(n) => n,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            };
+
+            if n == 0 {
+                return if self.parser.buf().is_empty() {
+                    Ok(None)
+                } else {
+                    Err(GFrameStreamError::UnexpectedEof)
+                };
+            }
+
+            self.parser.buf_mut().extend_from_slice(&self.tmp[..n]);
+        }
+    }
+}
+
+impl<R> Iterator for GFrameReader<R>
+where
+    R: Read,
+{
+    type Item = GFrameStreamResult;
+
+    /// Next frame, the iterator ends after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let next = self.read_frame().transpose();
+        self.failed = matches!(next, Some(Err(_)));
+        next
+    }
+}
+
+/// Blocking counterpart of [`crate::stream::GFrameStream`] writing frames to [`Write`].
+pub struct GFrameWriter<W> {
+    inner: W,
+}
+
+impl<W> GFrameWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W> GFrameWriter<W>
+where
+    W: Write,
+{
+    pub fn write_frame(&mut self, frame: &GFrame) -> io::Result<()> {
+        self.inner.write_all(&frame.bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use crate::data_type::{
+        GInteger,
+        GString,
+    };
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let frames = [
+            GFrame::SimpleString(GString::copy_from_slice(b"OK")),
+            GFrame::Integer(GInteger::new(-3)),
+            GFrame::Array(
+                vec![GFrame::BulkString(GString::copy_from_slice(b"hello")), GFrame::Null]
+                    .into_boxed_slice(),
+            ),
+        ];
+
+        let mut writer = GFrameWriter::new(Vec::new());
+        for frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+
+        let reader = GFrameReader::new(Cursor::new(writer.into_inner()));
+        let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(read, frames);
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let mut reader = GFrameReader::new(Cursor::new(b"+OK\r\n?bad\r\n+OK\r\n".to_vec()));
+        assert!(matches!(reader.next(), Some(Ok(_))));
+        assert!(matches!(reader.next(), Some(Err(_))));
+        assert!(reader.next().is_none());
+    }
+}
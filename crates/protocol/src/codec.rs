@@ -0,0 +1,84 @@
+use std::io;
+
+use bytes::BytesMut;
+use tokio_util::codec::{
+    Decoder,
+    Encoder,
+};
+
+use crate::{
+    frame::GFrame,
+    parser::parse_frame,
+    stream::GFrameStreamError,
+};
+
+/// [`Decoder`]/[`Encoder`] pair for use with tokio-util `Framed`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GFrameCodec;
+
+impl GFrameCodec {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Decoder for GFrameCodec {
+    type Item = GFrame;
+    type Error = GFrameStreamError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(parse_frame(src)?)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.is_empty() => Ok(None),
+            None => Err(GFrameStreamError::UnexpectedEof),
+        }
+    }
+}
+
+impl Encoder<GFrame> for GFrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: GFrame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&item.bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::data_type::GString;
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let frame = GFrame::Array(
+            vec![
+                GFrame::BulkString(GString::copy_from_slice(b"GET")),
+                GFrame::BulkString(GString::copy_from_slice(b"key")),
+            ]
+            .into_boxed_slice(),
+        );
+
+        let mut buf = BytesMut::new();
+        GFrameCodec.encode(frame, &mut buf).unwrap();
+
+        let mut partial = buf.split_to(buf.len() - 3);
+        assert_eq!(GFrameCodec.decode(&mut partial).unwrap(), None);
+
+        partial.unsplit(buf);
+        let decoded = GFrameCodec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(decoded.as_array().unwrap().len(), 2);
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn eof_with_partial_frame() {
+        let mut buf = BytesMut::from(&b"$5\r\nhel"[..]);
+        assert!(matches!(GFrameCodec.decode_eof(&mut buf), Err(GFrameStreamError::UnexpectedEof)));
+    }
+}
@@ -0,0 +1,1337 @@
+use crate::{
+    command::table::CommandSpec,
+    data_type::GString,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+pub mod table;
+
+pub type Error = ReplyError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum GCommand {
+    Ping(PingGCommand),
+    Get(GetGCommand),
+    Set(SetGCommand),
+    Del(DelGCommand),
+    Exists(ExistsGCommand),
+    Incr(IncrGCommand),
+    Decr(DecrGCommand),
+    ConfigGet(ConfigGetGCommand),
+    ConfigSet(ConfigSetGCommand),
+    ConfigRewrite(ConfigRewriteGCommand),
+    ConfigResetStat(ConfigResetStatGCommand),
+    Command(CommandGCommand),
+    CommandCount(CommandCountGCommand),
+    CommandInfo(CommandInfoGCommand),
+    CommandDocs(CommandDocsGCommand),
+    CommandList(CommandListGCommand),
+    CommandGetKeys(CommandGetKeysGCommand),
+    Reshard(ReshardGCommand),
+    ClusterSlots(ClusterSlotsGCommand),
+    ClusterShards(ClusterShardsGCommand),
+    ClusterNodes(ClusterNodesGCommand),
+    ClusterKeyslot(ClusterKeyslotGCommand),
+    ClusterInfo(ClusterInfoGCommand),
+    ClusterMyId(ClusterMyIdGCommand),
+    ReadOnly(ReadOnlyGCommand),
+    ReadWrite(ReadWriteGCommand),
+    Asking(AskingGCommand),
+    Info(InfoGCommand),
+    ReplicaOf(ReplicaOfGCommand),
+    Psync(PsyncGCommand),
+    ReplConf(ReplConfGCommand),
+    Wait(WaitGCommand),
+    Shutdown(ShutdownGCommand),
+    SlowlogGet(SlowlogGetGCommand),
+    SlowlogLen(SlowlogLenGCommand),
+    SlowlogReset(SlowlogResetGCommand),
+    LatencyLatest(LatencyLatestGCommand),
+    LatencyHistory(LatencyHistoryGCommand),
+    LatencyReset(LatencyResetGCommand),
+    LatencyHistogram(LatencyHistogramGCommand),
+    LatencyDoctor(LatencyDoctorGCommand),
+    Monitor(MonitorGCommand),
+    ClientId(ClientIdGCommand),
+    ClientSetName(ClientSetNameGCommand),
+    ClientGetName(ClientGetNameGCommand),
+    ClientList(ClientListGCommand),
+    ClientInfo(ClientInfoGCommand),
+    ClientKill(ClientKillGCommand),
+    ClientPause(ClientPauseGCommand),
+    ClientUnpause(ClientUnpauseGCommand),
+    ClientReply(ClientReplyGCommand),
+    ClientNoEvict(ClientNoEvictGCommand),
+    ClientNoTouch(ClientNoTouchGCommand),
+    ClientSetInfo(ClientSetInfoGCommand),
+    ClientTracking(ClientTrackingGCommand),
+    ClientCaching(ClientCachingGCommand),
+    ClientGetRedir(ClientGetRedirGCommand),
+    ClientTrackingInfo(ClientTrackingInfoGCommand),
+    Hello(HelloGCommand),
+    Auth(AuthGCommand),
+    AclSetUser(AclSetUserGCommand),
+    AclGetUser(AclGetUserGCommand),
+    AclDelUser(AclDelUserGCommand),
+    AclList(AclListGCommand),
+    AclUsers(AclUsersGCommand),
+    AclWhoAmI(AclWhoAmIGCommand),
+    AclCat(AclCatGCommand),
+    AclLog(AclLogGCommand),
+    AclSave(AclSaveGCommand),
+    AclLoad(AclLoadGCommand),
+}
+
+#[derive(Debug)]
+pub struct PingGCommand {
+    pub message: Option<GString>,
+}
+
+#[derive(Debug)]
+pub struct GetGCommand {
+    pub key: GString,
+}
+
+#[derive(Debug)]
+pub struct SetGCommand {
+    pub key: GString,
+    pub value: GString,
+}
+
+#[derive(Debug)]
+pub struct DelGCommand {
+    pub keys: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct ExistsGCommand {
+    pub keys: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct IncrGCommand {
+    pub key: GString,
+}
+
+#[derive(Debug)]
+pub struct DecrGCommand {
+    pub key: GString,
+}
+
+#[derive(Debug)]
+pub struct ConfigGetGCommand {
+    /// Glob patterns of the parameters to return.
+    pub patterns: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct ConfigSetGCommand {
+    pub parameters: Box<[(GString, GString)]>,
+}
+
+#[derive(Debug)]
+pub struct ConfigRewriteGCommand;
+
+#[derive(Debug)]
+pub struct ConfigResetStatGCommand;
+
+#[derive(Debug)]
+pub struct CommandGCommand;
+
+#[derive(Debug)]
+pub struct CommandCountGCommand;
+
+#[derive(Debug)]
+pub struct CommandInfoGCommand {
+    pub names: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct CommandDocsGCommand {
+    pub names: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct CommandListGCommand {
+    pub filter: Option<CommandListFilter>,
+}
+
+#[derive(Debug)]
+pub enum CommandListFilter {
+    Module(GString),
+    AclCat(GString),
+    Pattern(GString),
+}
+
+#[derive(Debug)]
+pub struct CommandGetKeysGCommand {
+    pub args: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct ReshardGCommand {
+    pub shards: usize,
+}
+
+#[derive(Debug)]
+pub struct ClusterSlotsGCommand;
+
+#[derive(Debug)]
+pub struct ClusterShardsGCommand;
+
+#[derive(Debug)]
+pub struct ClusterNodesGCommand;
+
+#[derive(Debug)]
+pub struct ClusterKeyslotGCommand {
+    pub key: GString,
+}
+
+#[derive(Debug)]
+pub struct ClusterInfoGCommand;
+
+#[derive(Debug)]
+pub struct ClusterMyIdGCommand;
+
+#[derive(Debug)]
+pub struct ReadOnlyGCommand;
+
+#[derive(Debug)]
+pub struct ReadWriteGCommand;
+
+#[derive(Debug)]
+pub struct AskingGCommand;
+
+#[derive(Debug)]
+pub struct InfoGCommand {
+    pub sections: Box<[GString]>,
+}
+
+/// `REPLICAOF host port`, or `REPLICAOF NO ONE` with no primary.
+#[derive(Debug)]
+pub struct ReplicaOfGCommand {
+    pub primary: Option<(GString, u16)>,
+}
+
+#[derive(Debug)]
+pub struct PsyncGCommand {
+    /// Replication ID of the primary the replica last synced with, `?` if none.
+    pub replid: GString,
+    /// Offset of the first byte of the stream the replica is missing, `-1` to force a full sync.
+    pub offset: i64,
+}
+
+#[derive(Debug)]
+pub struct ReplConfGCommand {
+    /// Option names and values, in pairs.
+    pub options: Box<[(GString, GString)]>,
+}
+
+#[derive(Debug)]
+pub struct WaitGCommand {
+    pub replicas: i64,
+    /// Milliseconds to wait for, `0` blocks forever.
+    pub timeout: i64,
+}
+
+/// `SHUTDOWN [NOSAVE | SAVE] [NOW] [FORCE] [ABORT]`.
+#[derive(Debug, Default)]
+pub struct ShutdownGCommand {
+    /// `Some(true)` with `SAVE`, `Some(false)` with `NOSAVE`, by default a snapshot is taken only
+    /// if save points are configured.
+    pub save: Option<bool>,
+    /// Skip waiting for lagging replicas.
+    pub now: bool,
+    /// Ignore errors that would prevent the server from exiting.
+    pub force: bool,
+    /// Cancel a shutdown waiting for replicas.
+    pub abort: bool,
+}
+
+/// `SLOWLOG GET [count]`.
+#[derive(Debug)]
+pub struct SlowlogGetGCommand {
+    /// Number of entries to return, by default 10, negative returns all of them.
+    pub count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SlowlogLenGCommand;
+
+#[derive(Debug)]
+pub struct SlowlogResetGCommand;
+
+#[derive(Debug)]
+pub struct LatencyLatestGCommand;
+
+#[derive(Debug)]
+pub struct LatencyHistoryGCommand {
+    pub event: GString,
+}
+
+#[derive(Debug)]
+pub struct LatencyResetGCommand {
+    /// Events to reset, all of them if empty.
+    pub events: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct LatencyHistogramGCommand {
+    /// Commands to report, all of those called so far if empty.
+    pub commands: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct LatencyDoctorGCommand;
+
+#[derive(Debug)]
+pub struct MonitorGCommand;
+
+#[derive(Debug)]
+pub struct ClientIdGCommand;
+
+#[derive(Debug)]
+pub struct ClientSetNameGCommand {
+    /// Empty to remove the name.
+    pub name: GString,
+}
+
+#[derive(Debug)]
+pub struct ClientGetNameGCommand;
+
+/// `CLIENT LIST [TYPE type] [ID id...]`.
+#[derive(Debug, Default)]
+pub struct ClientListGCommand {
+    pub kind: Option<GString>,
+    pub ids: Box<[u64]>,
+}
+
+#[derive(Debug)]
+pub struct ClientInfoGCommand;
+
+/// `CLIENT KILL addr` or `CLIENT KILL <filter> <value>...`.
+#[derive(Debug)]
+pub struct ClientKillGCommand {
+    /// Set for the old form, which kills the client at the address or fails.
+    pub legacy: bool,
+    pub filters: Box<[ClientKillFilter]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientKillFilter {
+    Id(u64),
+    Type(GString),
+    User(GString),
+    Addr(GString),
+    Laddr(GString),
+    SkipMe(bool),
+    MaxAge(u64),
+}
+
+/// `CLIENT PAUSE timeout [WRITE | ALL]`.
+#[derive(Debug)]
+pub struct ClientPauseGCommand {
+    /// Milliseconds.
+    pub timeout: u64,
+    /// Only pause commands that write, with `WRITE`.
+    pub writes: bool,
+}
+
+#[derive(Debug)]
+pub struct ClientUnpauseGCommand;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientReplyMode {
+    #[default]
+    On,
+    Off,
+    /// Skip the reply of the next command only.
+    Skip,
+}
+
+#[derive(Debug)]
+pub struct ClientReplyGCommand {
+    pub mode: ClientReplyMode,
+}
+
+#[derive(Debug)]
+pub struct ClientNoEvictGCommand {
+    pub enabled: bool,
+}
+
+#[derive(Debug)]
+pub struct ClientNoTouchGCommand {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientInfoAttr {
+    LibName,
+    LibVer,
+}
+
+#[derive(Debug)]
+pub struct ClientSetInfoGCommand {
+    pub attr: ClientInfoAttr,
+    pub value: GString,
+}
+
+/// `CLIENT TRACKING ON|OFF [REDIRECT id] [PREFIX prefix...] [BCAST] [OPTIN] [OPTOUT] [NOLOOP]`.
+#[derive(Debug, Default)]
+pub struct ClientTrackingGCommand {
+    pub enabled: bool,
+    /// Client receiving the invalidation messages instead of this one.
+    pub redirect: Option<u64>,
+    pub prefixes: Box<[GString]>,
+    /// Invalidate every key matching a prefix, whether it was read or not.
+    pub bcast: bool,
+    /// Only track the keys read right after `CLIENT CACHING YES`.
+    pub optin: bool,
+    /// Track the keys read unless `CLIENT CACHING NO` was sent right before.
+    pub optout: bool,
+    /// Do not invalidate the keys this client modified itself.
+    pub noloop: bool,
+}
+
+#[derive(Debug)]
+pub struct ClientCachingGCommand {
+    pub enabled: bool,
+}
+
+#[derive(Debug)]
+pub struct ClientGetRedirGCommand;
+
+#[derive(Debug)]
+pub struct ClientTrackingInfoGCommand;
+
+/// `HELLO [protover [AUTH username password] [SETNAME name]]`.
+#[derive(Debug, Default)]
+pub struct HelloGCommand {
+    /// Protocol to switch to, `2` or `3`, the current one if not given.
+    pub protover: Option<u8>,
+    /// Username and password to authenticate with before switching.
+    pub auth: Option<(GString, GString)>,
+    pub setname: Option<GString>,
+}
+
+/// `AUTH [username] password`.
+#[derive(Debug)]
+pub struct AuthGCommand {
+    /// User to authenticate as, `default` if not given.
+    pub username: Option<GString>,
+    pub password: GString,
+}
+
+/// `ACL SETUSER username [rule...]`, the rules are parsed by the server.
+#[derive(Debug)]
+pub struct AclSetUserGCommand {
+    pub username: GString,
+    pub rules: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct AclGetUserGCommand {
+    pub username: GString,
+}
+
+#[derive(Debug)]
+pub struct AclDelUserGCommand {
+    pub usernames: Box<[GString]>,
+}
+
+#[derive(Debug)]
+pub struct AclListGCommand;
+
+#[derive(Debug)]
+pub struct AclUsersGCommand;
+
+#[derive(Debug)]
+pub struct AclWhoAmIGCommand;
+
+/// `ACL CAT [category]`.
+#[derive(Debug)]
+pub struct AclCatGCommand {
+    /// Category whose commands are listed, every category is listed if not given.
+    pub category: Option<GString>,
+}
+
+/// `ACL LOG [count|RESET]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclLogGCommand {
+    /// Newest entries, 10 if no count is given.
+    Get(Option<usize>),
+    Reset,
+}
+
+#[derive(Debug)]
+pub struct AclSaveGCommand;
+
+#[derive(Debug)]
+pub struct AclLoadGCommand;
+
+impl GCommand {
+    pub fn from_frame(frame: &GFrame) -> Result<Self> {
+        let args = args_from_frame(frame)?;
+        let spec = CommandSpec::resolve(&args)?;
+
+        Self::from_args(spec, &args)
+    }
+
+    /// Parse arguments already resolved to `spec`, command name included.
+    pub fn from_args(spec: &CommandSpec, args: &[GString]) -> Result<Self> {
+        let parse = spec.parse.ok_or_else(|| Error::unknown_command(args))?;
+
+        parse(&args[spec.args_offset()..])
+    }
+
+    /// Serialize the command into a request frame, the inverse of [`GCommand::from_frame`].
+    pub fn to_frame(&self) -> GFrame {
+        let mut args = Vec::new();
+
+        match self {
+            GCommand::Ping(command) => {
+                args.push(token(b"PING"));
+                args.extend(command.message.clone());
+            }
+            GCommand::Get(command) => args.extend([token(b"GET"), command.key.clone()]),
+            GCommand::Set(command) => {
+                args.extend([token(b"SET"), command.key.clone(), command.value.clone()])
+            }
+            GCommand::Del(command) => {
+                args.push(token(b"DEL"));
+                args.extend(command.keys.iter().cloned());
+            }
+            GCommand::Exists(command) => {
+                args.push(token(b"EXISTS"));
+                args.extend(command.keys.iter().cloned());
+            }
+            GCommand::Incr(command) => args.extend([token(b"INCR"), command.key.clone()]),
+            GCommand::Decr(command) => args.extend([token(b"DECR"), command.key.clone()]),
+            GCommand::ConfigGet(command) => {
+                args.extend([token(b"CONFIG"), token(b"GET")]);
+                args.extend(command.patterns.iter().cloned());
+            }
+            GCommand::ConfigSet(command) => {
+                args.extend([token(b"CONFIG"), token(b"SET")]);
+                for (name, value) in &command.parameters {
+                    args.extend([name.clone(), value.clone()]);
+                }
+            }
+            GCommand::ConfigRewrite(_) => args.extend([token(b"CONFIG"), token(b"REWRITE")]),
+            GCommand::ConfigResetStat(_) => args.extend([token(b"CONFIG"), token(b"RESETSTAT")]),
+            GCommand::Command(_) => args.push(token(b"COMMAND")),
+            GCommand::CommandCount(_) => args.extend([token(b"COMMAND"), token(b"COUNT")]),
+            GCommand::CommandInfo(command) => {
+                args.extend([token(b"COMMAND"), token(b"INFO")]);
+                args.extend(command.names.iter().cloned());
+            }
+            GCommand::CommandDocs(command) => {
+                args.extend([token(b"COMMAND"), token(b"DOCS")]);
+                args.extend(command.names.iter().cloned());
+            }
+            GCommand::CommandList(command) => {
+                args.extend([token(b"COMMAND"), token(b"LIST")]);
+                if let Some(filter) = &command.filter {
+                    let (kind, value) = match filter {
+                        CommandListFilter::Module(value) => (b"MODULE".as_slice(), value),
+                        CommandListFilter::AclCat(value) => (b"ACLCAT".as_slice(), value),
+                        CommandListFilter::Pattern(value) => (b"PATTERN".as_slice(), value),
+                    };
+                    args.extend([token(b"FILTERBY"), token(kind), value.clone()]);
+                }
+            }
+            GCommand::CommandGetKeys(command) => {
+                args.extend([token(b"COMMAND"), token(b"GETKEYS")]);
+                args.extend(command.args.iter().cloned());
+            }
+            GCommand::Reshard(command) => {
+                args.extend([token(b"RESHARD"), integer(command.shards as i64)])
+            }
+            GCommand::ClusterSlots(_) => args.extend([token(b"CLUSTER"), token(b"SLOTS")]),
+            GCommand::ClusterShards(_) => args.extend([token(b"CLUSTER"), token(b"SHARDS")]),
+            GCommand::ClusterNodes(_) => args.extend([token(b"CLUSTER"), token(b"NODES")]),
+            GCommand::ClusterKeyslot(command) => {
+                args.extend([token(b"CLUSTER"), token(b"KEYSLOT"), command.key.clone()])
+            }
+            GCommand::ClusterInfo(_) => args.extend([token(b"CLUSTER"), token(b"INFO")]),
+            GCommand::ClusterMyId(_) => args.extend([token(b"CLUSTER"), token(b"MYID")]),
+            GCommand::ReadOnly(_) => args.push(token(b"READONLY")),
+            GCommand::ReadWrite(_) => args.push(token(b"READWRITE")),
+            GCommand::Asking(_) => args.push(token(b"ASKING")),
+            GCommand::Info(command) => {
+                args.push(token(b"INFO"));
+                args.extend(command.sections.iter().cloned());
+            }
+            GCommand::ReplicaOf(command) => {
+                args.push(token(b"REPLICAOF"));
+                match &command.primary {
+                    Some((host, port)) => args.extend([host.clone(), integer(*port as i64)]),
+                    None => args.extend([token(b"NO"), token(b"ONE")]),
+                }
+            }
+            GCommand::Psync(command) => {
+                args.extend([token(b"PSYNC"), command.replid.clone(), integer(command.offset)])
+            }
+            GCommand::ReplConf(command) => {
+                args.push(token(b"REPLCONF"));
+                for (name, value) in &command.options {
+                    args.extend([name.clone(), value.clone()]);
+                }
+            }
+            GCommand::Wait(command) => {
+                args.extend([token(b"WAIT"), integer(command.replicas), integer(command.timeout)])
+            }
+            GCommand::Shutdown(command) => {
+                args.push(token(b"SHUTDOWN"));
+                match command.save {
+                    Some(true) => args.push(token(b"SAVE")),
+                    Some(false) => args.push(token(b"NOSAVE")),
+                    None => {}
+                }
+                let options: [(bool, &'static [u8]); 3] =
+                    [(command.now, b"NOW"), (command.force, b"FORCE"), (command.abort, b"ABORT")];
+                args.extend(
+                    options.into_iter().filter(|(set, _)| *set).map(|(_, name)| token(name)),
+                );
+            }
+            GCommand::SlowlogGet(command) => {
+                args.extend([token(b"SLOWLOG"), token(b"GET")]);
+                args.extend(command.count.map(integer));
+            }
+            GCommand::SlowlogLen(_) => args.extend([token(b"SLOWLOG"), token(b"LEN")]),
+            GCommand::SlowlogReset(_) => args.extend([token(b"SLOWLOG"), token(b"RESET")]),
+            GCommand::LatencyLatest(_) => args.extend([token(b"LATENCY"), token(b"LATEST")]),
+            GCommand::LatencyHistory(command) => {
+                args.extend([token(b"LATENCY"), token(b"HISTORY"), command.event.clone()])
+            }
+            GCommand::LatencyReset(command) => {
+                args.extend([token(b"LATENCY"), token(b"RESET")]);
+                args.extend(command.events.iter().cloned());
+            }
+            GCommand::LatencyHistogram(command) => {
+                args.extend([token(b"LATENCY"), token(b"HISTOGRAM")]);
+                args.extend(command.commands.iter().cloned());
+            }
+            GCommand::LatencyDoctor(_) => args.extend([token(b"LATENCY"), token(b"DOCTOR")]),
+            GCommand::Monitor(_) => args.push(token(b"MONITOR")),
+            GCommand::ClientId(_) => args.extend([token(b"CLIENT"), token(b"ID")]),
+            GCommand::ClientSetName(command) => {
+                args.extend([token(b"CLIENT"), token(b"SETNAME"), command.name.clone()])
+            }
+            GCommand::ClientGetName(_) => args.extend([token(b"CLIENT"), token(b"GETNAME")]),
+            GCommand::ClientList(command) => {
+                args.extend([token(b"CLIENT"), token(b"LIST")]);
+                if let Some(kind) = &command.kind {
+                    args.extend([token(b"TYPE"), kind.clone()]);
+                }
+                if !command.ids.is_empty() {
+                    args.push(token(b"ID"));
+                    args.extend(command.ids.iter().map(|id| integer(*id as i64)));
+                }
+            }
+            GCommand::ClientInfo(_) => args.extend([token(b"CLIENT"), token(b"INFO")]),
+            GCommand::ClientKill(command) => {
+                args.extend([token(b"CLIENT"), token(b"KILL")]);
+                for filter in &command.filters {
+                    match filter {
+                        ClientKillFilter::Addr(addr) if command.legacy => args.push(addr.clone()),
+                        ClientKillFilter::Id(id) => {
+                            args.extend([token(b"ID"), integer(*id as i64)])
+                        }
+                        ClientKillFilter::Type(kind) => args.extend([token(b"TYPE"), kind.clone()]),
+                        ClientKillFilter::User(user) => args.extend([token(b"USER"), user.clone()]),
+                        ClientKillFilter::Addr(addr) => args.extend([token(b"ADDR"), addr.clone()]),
+                        ClientKillFilter::Laddr(addr) => {
+                            args.extend([token(b"LADDR"), addr.clone()])
+                        }
+                        ClientKillFilter::SkipMe(skip) => {
+                            let skip = if *skip { token(b"YES") } else { token(b"NO") };
+                            args.extend([token(b"SKIPME"), skip])
+                        }
+                        ClientKillFilter::MaxAge(age) => {
+                            args.extend([token(b"MAXAGE"), integer(*age as i64)])
+                        }
+                    }
+                }
+            }
+            GCommand::ClientPause(command) => {
+                let mode = if command.writes { token(b"WRITE") } else { token(b"ALL") };
+                let timeout = integer(command.timeout as i64);
+                args.extend([token(b"CLIENT"), token(b"PAUSE"), timeout, mode]);
+            }
+            GCommand::ClientUnpause(_) => args.extend([token(b"CLIENT"), token(b"UNPAUSE")]),
+            GCommand::ClientReply(command) => {
+                let mode = match command.mode {
+                    ClientReplyMode::On => token(b"ON"),
+                    ClientReplyMode::Off => token(b"OFF"),
+                    ClientReplyMode::Skip => token(b"SKIP"),
+                };
+                args.extend([token(b"CLIENT"), token(b"REPLY"), mode]);
+            }
+            GCommand::ClientNoEvict(command) => {
+                let enabled = if command.enabled { token(b"ON") } else { token(b"OFF") };
+                args.extend([token(b"CLIENT"), token(b"NO-EVICT"), enabled]);
+            }
+            GCommand::ClientNoTouch(command) => {
+                let enabled = if command.enabled { token(b"ON") } else { token(b"OFF") };
+                args.extend([token(b"CLIENT"), token(b"NO-TOUCH"), enabled]);
+            }
+            GCommand::ClientSetInfo(command) => {
+                let attr = match command.attr {
+                    ClientInfoAttr::LibName => token(b"LIB-NAME"),
+                    ClientInfoAttr::LibVer => token(b"LIB-VER"),
+                };
+                args.extend([token(b"CLIENT"), token(b"SETINFO"), attr, command.value.clone()]);
+            }
+            GCommand::ClientTracking(command) => {
+                let enabled = if command.enabled { token(b"ON") } else { token(b"OFF") };
+                args.extend([token(b"CLIENT"), token(b"TRACKING"), enabled]);
+                if let Some(redirect) = command.redirect {
+                    args.extend([token(b"REDIRECT"), integer(redirect as i64)]);
+                }
+                for prefix in &command.prefixes {
+                    args.extend([token(b"PREFIX"), prefix.clone()]);
+                }
+                let options = [
+                    (command.bcast, b"BCAST".as_slice()),
+                    (command.optin, b"OPTIN"),
+                    (command.optout, b"OPTOUT"),
+                    (command.noloop, b"NOLOOP"),
+                ];
+                args.extend(
+                    options.into_iter().filter(|(set, _)| *set).map(|(_, name)| token(name)),
+                );
+            }
+            GCommand::ClientCaching(command) => {
+                let enabled = if command.enabled { token(b"YES") } else { token(b"NO") };
+                args.extend([token(b"CLIENT"), token(b"CACHING"), enabled]);
+            }
+            GCommand::ClientGetRedir(_) => args.extend([token(b"CLIENT"), token(b"GETREDIR")]),
+            GCommand::ClientTrackingInfo(_) => {
+                args.extend([token(b"CLIENT"), token(b"TRACKINGINFO")])
+            }
+            GCommand::Hello(command) => {
+                args.push(token(b"HELLO"));
+                if let Some(protover) = command.protover {
+                    args.push(integer(protover as i64));
+                }
+                if let Some((username, password)) = &command.auth {
+                    args.extend([token(b"AUTH"), username.clone(), password.clone()]);
+                }
+                if let Some(name) = &command.setname {
+                    args.extend([token(b"SETNAME"), name.clone()]);
+                }
+            }
+            GCommand::Auth(command) => {
+                args.push(token(b"AUTH"));
+                args.extend(command.username.clone());
+                args.push(command.password.clone());
+            }
+            GCommand::AclSetUser(command) => {
+                args.extend([token(b"ACL"), token(b"SETUSER"), command.username.clone()]);
+                args.extend(command.rules.iter().cloned());
+            }
+            GCommand::AclGetUser(command) => {
+                args.extend([token(b"ACL"), token(b"GETUSER"), command.username.clone()])
+            }
+            GCommand::AclDelUser(command) => {
+                args.extend([token(b"ACL"), token(b"DELUSER")]);
+                args.extend(command.usernames.iter().cloned());
+            }
+            GCommand::AclList(_) => args.extend([token(b"ACL"), token(b"LIST")]),
+            GCommand::AclUsers(_) => args.extend([token(b"ACL"), token(b"USERS")]),
+            GCommand::AclWhoAmI(_) => args.extend([token(b"ACL"), token(b"WHOAMI")]),
+            GCommand::AclCat(command) => {
+                args.extend([token(b"ACL"), token(b"CAT")]);
+                args.extend(command.category.clone());
+            }
+            GCommand::AclLog(command) => {
+                args.extend([token(b"ACL"), token(b"LOG")]);
+                match command {
+                    AclLogGCommand::Get(count) => args.extend(count.map(|c| integer(c as i64))),
+                    AclLogGCommand::Reset => args.push(token(b"RESET")),
+                }
+            }
+            GCommand::AclSave(_) => args.extend([token(b"ACL"), token(b"SAVE")]),
+            GCommand::AclLoad(_) => args.extend([token(b"ACL"), token(b"LOAD")]),
+        }
+
+        GFrame::Array(args.into_iter().map(GFrame::BulkString).collect())
+    }
+
+    fn parse_ping(args: &[GString]) -> Result<Self> {
+        if args.len() > 1 {
+            return Err(Error::WrongArity("ping".to_string()));
+        }
+
+        Ok(GCommand::Ping(PingGCommand { message: args.first().cloned() }))
+    }
+
+    fn parse_get(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Get(GetGCommand { key: args[0].clone() }))
+    }
+
+    fn parse_set(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Set(SetGCommand { key: args[0].clone(), value: args[1].clone() }))
+    }
+
+    fn parse_del(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Del(DelGCommand { keys: args.into() }))
+    }
+
+    fn parse_exists(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Exists(ExistsGCommand { keys: args.into() }))
+    }
+
+    fn parse_incr(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Incr(IncrGCommand { key: args[0].clone() }))
+    }
+
+    fn parse_decr(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Decr(DecrGCommand { key: args[0].clone() }))
+    }
+
+    fn parse_config_get(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ConfigGet(ConfigGetGCommand { patterns: args.into() }))
+    }
+
+    fn parse_config_set(args: &[GString]) -> Result<Self> {
+        if !args.len().is_multiple_of(2) {
+            return Err(Error::WrongArity("config|set".to_string()));
+        }
+
+        let parameters = args.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect();
+        Ok(GCommand::ConfigSet(ConfigSetGCommand { parameters }))
+    }
+
+    fn parse_config_rewrite(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ConfigRewrite(ConfigRewriteGCommand))
+    }
+
+    fn parse_config_resetstat(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ConfigResetStat(ConfigResetStatGCommand))
+    }
+
+    fn parse_command(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Command(CommandGCommand))
+    }
+
+    fn parse_command_count(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::CommandCount(CommandCountGCommand))
+    }
+
+    fn parse_command_info(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::CommandInfo(CommandInfoGCommand { names: args.into() }))
+    }
+
+    fn parse_command_docs(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::CommandDocs(CommandDocsGCommand { names: args.into() }))
+    }
+
+    fn parse_command_list(args: &[GString]) -> Result<Self> {
+        let filter = match args {
+            [] => None,
+            [filterby, kind, value] if filterby.eq_ignore_ascii_case(b"FILTERBY") => {
+                let value = value.clone();
+                if kind.eq_ignore_ascii_case(b"MODULE") {
+                    Some(CommandListFilter::Module(value))
+                } else if kind.eq_ignore_ascii_case(b"ACLCAT") {
+                    Some(CommandListFilter::AclCat(value))
+                } else if kind.eq_ignore_ascii_case(b"PATTERN") {
+                    Some(CommandListFilter::Pattern(value))
+                } else {
+                    return Err(Error::Syntax);
+                }
+            }
+            _ => return Err(Error::Syntax),
+        };
+
+        Ok(GCommand::CommandList(CommandListGCommand { filter }))
+    }
+
+    fn parse_command_getkeys(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::CommandGetKeys(CommandGetKeysGCommand { args: args.into() }))
+    }
+
+    fn parse_reshard(args: &[GString]) -> Result<Self> {
+        let shards = parse_integer(&args[0])?;
+
+        Ok(GCommand::Reshard(ReshardGCommand { shards }))
+    }
+
+    fn parse_cluster_slots(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClusterSlots(ClusterSlotsGCommand))
+    }
+
+    fn parse_cluster_shards(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClusterShards(ClusterShardsGCommand))
+    }
+
+    fn parse_cluster_nodes(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClusterNodes(ClusterNodesGCommand))
+    }
+
+    fn parse_cluster_keyslot(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClusterKeyslot(ClusterKeyslotGCommand { key: args[0].clone() }))
+    }
+
+    fn parse_cluster_info(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClusterInfo(ClusterInfoGCommand))
+    }
+
+    fn parse_cluster_myid(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClusterMyId(ClusterMyIdGCommand))
+    }
+
+    fn parse_readonly(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ReadOnly(ReadOnlyGCommand))
+    }
+
+    fn parse_readwrite(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ReadWrite(ReadWriteGCommand))
+    }
+
+    fn parse_asking(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Asking(AskingGCommand))
+    }
+
+    fn parse_info(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Info(InfoGCommand { sections: args.into() }))
+    }
+
+    fn parse_replicaof(args: &[GString]) -> Result<Self> {
+        let primary = if args[0].eq_ignore_ascii_case(b"NO") && args[1].eq_ignore_ascii_case(b"ONE")
+        {
+            None
+        } else {
+            let port = parse_integer(&args[1])
+                .map_err(|_| Error::Err("Invalid master port".to_string()))?;
+            Some((args[0].clone(), port))
+        };
+
+        Ok(GCommand::ReplicaOf(ReplicaOfGCommand { primary }))
+    }
+
+    fn parse_psync(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Psync(PsyncGCommand {
+            replid: args[0].clone(),
+            offset: parse_integer(&args[1])?,
+        }))
+    }
+
+    fn parse_replconf(args: &[GString]) -> Result<Self> {
+        if !args.len().is_multiple_of(2) {
+            return Err(Error::Syntax);
+        }
+
+        let options = args.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect();
+        Ok(GCommand::ReplConf(ReplConfGCommand { options }))
+    }
+
+    fn parse_wait(args: &[GString]) -> Result<Self> {
+        let replicas = parse_integer(&args[0])?;
+        let timeout: i64 = parse_integer(&args[1])?;
+        if timeout < 0 {
+            return Err(Error::Err("timeout is negative".to_string()));
+        }
+
+        Ok(GCommand::Wait(WaitGCommand { replicas, timeout }))
+    }
+
+    fn parse_shutdown(args: &[GString]) -> Result<Self> {
+        let mut command = ShutdownGCommand::default();
+        for arg in args {
+            let is = |option: &[u8]| arg.eq_ignore_ascii_case(option);
+            if is(b"NOSAVE") || is(b"SAVE") {
+                let save = is(b"SAVE");
+                if command.save.is_some_and(|previous| previous != save) {
+                    return Err(Error::Syntax);
+                }
+                command.save = Some(save);
+            } else if is(b"NOW") {
+                command.now = true;
+            } else if is(b"FORCE") {
+                command.force = true;
+            } else if is(b"ABORT") {
+                command.abort = true;
+            } else {
+                return Err(Error::Syntax);
+            }
+        }
+
+        // Aborting takes no other option.
+        if command.abort && (command.save.is_some() || command.now || command.force) {
+            return Err(Error::Syntax);
+        }
+
+        Ok(GCommand::Shutdown(command))
+    }
+
+    fn parse_slowlog_get(args: &[GString]) -> Result<Self> {
+        let count = match args {
+            [] => None,
+            [count] => Some(parse_integer(count).map_err(|_| {
+                Error::Err("count should be greater than or equal to -1".to_string())
+            })?),
+            _ => return Err(Error::Syntax),
+        };
+        if count.is_some_and(|count| count < -1) {
+            return Err(Error::Err("count should be greater than or equal to -1".to_string()));
+        }
+
+        Ok(GCommand::SlowlogGet(SlowlogGetGCommand { count }))
+    }
+
+    fn parse_slowlog_len(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::SlowlogLen(SlowlogLenGCommand))
+    }
+
+    fn parse_slowlog_reset(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::SlowlogReset(SlowlogResetGCommand))
+    }
+
+    fn parse_latency_latest(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::LatencyLatest(LatencyLatestGCommand))
+    }
+
+    fn parse_latency_history(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::LatencyHistory(LatencyHistoryGCommand { event: args[0].clone() }))
+    }
+
+    fn parse_latency_reset(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::LatencyReset(LatencyResetGCommand { events: args.into() }))
+    }
+
+    fn parse_latency_histogram(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::LatencyHistogram(LatencyHistogramGCommand { commands: args.into() }))
+    }
+
+    fn parse_latency_doctor(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::LatencyDoctor(LatencyDoctorGCommand))
+    }
+
+    fn parse_monitor(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::Monitor(MonitorGCommand))
+    }
+
+    fn parse_client_id(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientId(ClientIdGCommand))
+    }
+
+    fn parse_client_setname(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientSetName(ClientSetNameGCommand { name: args[0].clone() }))
+    }
+
+    fn parse_client_getname(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientGetName(ClientGetNameGCommand))
+    }
+
+    fn parse_client_list(args: &[GString]) -> Result<Self> {
+        let mut command = ClientListGCommand::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg.eq_ignore_ascii_case(b"TYPE") {
+                command.kind = Some(args.next().ok_or(Error::Syntax)?.clone());
+            } else if arg.eq_ignore_ascii_case(b"ID") {
+                let ids = args
+                    .by_ref()
+                    .map(|id| {
+                        parse_integer::<u64>(id)
+                            .ok()
+                            .filter(|id| *id > 0)
+                            .ok_or_else(|| Error::Err("Invalid client ID".to_string()))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if ids.is_empty() {
+                    return Err(Error::Syntax);
+                }
+                command.ids = ids.into();
+            } else {
+                return Err(Error::Syntax);
+            }
+        }
+
+        Ok(GCommand::ClientList(command))
+    }
+
+    fn parse_client_info(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientInfo(ClientInfoGCommand))
+    }
+
+    fn parse_client_kill(args: &[GString]) -> Result<Self> {
+        if let [addr] = args {
+            let filters = [ClientKillFilter::Addr(addr.clone())].into();
+            return Ok(GCommand::ClientKill(ClientKillGCommand { legacy: true, filters }));
+        }
+        if !args.len().is_multiple_of(2) {
+            return Err(Error::Syntax);
+        }
+
+        let filters = args
+            .chunks(2)
+            .map(|pair| {
+                let is = |filter: &[u8]| pair[0].eq_ignore_ascii_case(filter);
+                let value = pair[1].clone();
+                Ok(if is(b"ID") {
+                    ClientKillFilter::Id(
+                        parse_integer::<u64>(&value).ok().filter(|id| *id > 0).ok_or_else(
+                            || Error::Err("client-id should be greater than 0".to_string()),
+                        )?,
+                    )
+                } else if is(b"TYPE") {
+                    ClientKillFilter::Type(value)
+                } else if is(b"USER") {
+                    ClientKillFilter::User(value)
+                } else if is(b"ADDR") {
+                    ClientKillFilter::Addr(value)
+                } else if is(b"LADDR") {
+                    ClientKillFilter::Laddr(value)
+                } else if is(b"SKIPME") && value.eq_ignore_ascii_case(b"YES") {
+                    ClientKillFilter::SkipMe(true)
+                } else if is(b"SKIPME") && value.eq_ignore_ascii_case(b"NO") {
+                    ClientKillFilter::SkipMe(false)
+                } else if is(b"MAXAGE") {
+                    ClientKillFilter::MaxAge(parse_integer(&value)?)
+                } else {
+                    return Err(Error::Syntax);
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(GCommand::ClientKill(ClientKillGCommand { legacy: false, filters: filters.into() }))
+    }
+
+    fn parse_client_pause(args: &[GString]) -> Result<Self> {
+        let timeout = parse_integer::<i64>(&args[0])
+            .map_err(|_| Error::Err("timeout is not an integer or out of range".to_string()))?;
+        if timeout < 0 {
+            return Err(Error::Err("timeout is negative".to_string()));
+        }
+        let writes = match args.get(1) {
+            None => false,
+            Some(mode) if mode.eq_ignore_ascii_case(b"ALL") => false,
+            Some(mode) if mode.eq_ignore_ascii_case(b"WRITE") => true,
+            Some(_) => return Err(Error::Syntax),
+        };
+        if args.len() > 2 {
+            return Err(Error::Syntax);
+        }
+
+        Ok(GCommand::ClientPause(ClientPauseGCommand { timeout: timeout as u64, writes }))
+    }
+
+    fn parse_client_unpause(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientUnpause(ClientUnpauseGCommand))
+    }
+
+    fn parse_client_reply(args: &[GString]) -> Result<Self> {
+        let mode = match &args[0] {
+            mode if mode.eq_ignore_ascii_case(b"ON") => ClientReplyMode::On,
+            mode if mode.eq_ignore_ascii_case(b"OFF") => ClientReplyMode::Off,
+            mode if mode.eq_ignore_ascii_case(b"SKIP") => ClientReplyMode::Skip,
+            _ => return Err(Error::Syntax),
+        };
+        Ok(GCommand::ClientReply(ClientReplyGCommand { mode }))
+    }
+
+    fn parse_client_no_evict(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientNoEvict(ClientNoEvictGCommand { enabled: parse_switch(&args[0])? }))
+    }
+
+    fn parse_client_no_touch(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientNoTouch(ClientNoTouchGCommand { enabled: parse_switch(&args[0])? }))
+    }
+
+    fn parse_client_setinfo(args: &[GString]) -> Result<Self> {
+        let attr = match &args[0] {
+            attr if attr.eq_ignore_ascii_case(b"LIB-NAME") => ClientInfoAttr::LibName,
+            attr if attr.eq_ignore_ascii_case(b"LIB-VER") => ClientInfoAttr::LibVer,
+            _ => {
+                let attr = String::from_utf8_lossy(args[0].as_ref());
+                return Err(Error::Err(format!("Unrecognized option '{attr}'")));
+            }
+        };
+        Ok(GCommand::ClientSetInfo(ClientSetInfoGCommand { attr, value: args[1].clone() }))
+    }
+
+    fn parse_client_tracking(args: &[GString]) -> Result<Self> {
+        let mut command =
+            ClientTrackingGCommand { enabled: parse_switch(&args[0])?, ..Default::default() };
+        let mut prefixes = Vec::new();
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            let is = |option: &[u8]| arg.eq_ignore_ascii_case(option);
+            if is(b"REDIRECT") {
+                if command.redirect.is_some() {
+                    return Err(Error::Err(
+                        "A client can only redirect to a single other client".to_string(),
+                    ));
+                }
+                command.redirect = Some(parse_integer(args.next().ok_or(Error::Syntax)?)?);
+            } else if is(b"PREFIX") {
+                prefixes.push(args.next().ok_or(Error::Syntax)?.clone());
+            } else if is(b"BCAST") {
+                command.bcast = true;
+            } else if is(b"OPTIN") {
+                command.optin = true;
+            } else if is(b"OPTOUT") {
+                command.optout = true;
+            } else if is(b"NOLOOP") {
+                command.noloop = true;
+            } else {
+                return Err(Error::Syntax);
+            }
+        }
+        command.prefixes = prefixes.into();
+
+        Ok(GCommand::ClientTracking(command))
+    }
+
+    fn parse_client_caching(args: &[GString]) -> Result<Self> {
+        let enabled = match &args[0] {
+            enabled if enabled.eq_ignore_ascii_case(b"YES") => true,
+            enabled if enabled.eq_ignore_ascii_case(b"NO") => false,
+            _ => return Err(Error::Syntax),
+        };
+        Ok(GCommand::ClientCaching(ClientCachingGCommand { enabled }))
+    }
+
+    fn parse_client_getredir(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientGetRedir(ClientGetRedirGCommand))
+    }
+
+    fn parse_client_trackinginfo(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::ClientTrackingInfo(ClientTrackingInfoGCommand))
+    }
+
+    fn parse_hello(args: &[GString]) -> Result<Self> {
+        let mut command = HelloGCommand::default();
+        let Some((protover, options)) = args.split_first() else {
+            return Ok(GCommand::Hello(command));
+        };
+        let protover = parse_integer::<i64>(protover).map_err(|_| {
+            Error::Err("Protocol version is not an integer or out of range".to_string())
+        })?;
+        command.protover = match protover {
+            2 | 3 => Some(protover as u8),
+            _ => return Err(Error::NoProto),
+        };
+
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            if option.eq_ignore_ascii_case(b"SETNAME") {
+                command.setname = Some(options.next().ok_or(Error::Syntax)?.clone());
+            } else if option.eq_ignore_ascii_case(b"AUTH") {
+                let username = options.next().ok_or(Error::Syntax)?.clone();
+                let password = options.next().ok_or(Error::Syntax)?.clone();
+                command.auth = Some((username, password));
+            } else {
+                let option = String::from_utf8_lossy(option.as_ref());
+                return Err(Error::Err(format!("Syntax error in HELLO option '{option}'")));
+            }
+        }
+
+        Ok(GCommand::Hello(command))
+    }
+
+    fn parse_auth(args: &[GString]) -> Result<Self> {
+        let (username, password) = match args {
+            [password] => (None, password.clone()),
+            [username, password] => (Some(username.clone()), password.clone()),
+            _ => return Err(Error::Syntax),
+        };
+        Ok(GCommand::Auth(AuthGCommand { username, password }))
+    }
+
+    fn parse_acl_setuser(args: &[GString]) -> Result<Self> {
+        let command = AclSetUserGCommand { username: args[0].clone(), rules: args[1..].into() };
+        Ok(GCommand::AclSetUser(command))
+    }
+
+    fn parse_acl_getuser(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclGetUser(AclGetUserGCommand { username: args[0].clone() }))
+    }
+
+    fn parse_acl_deluser(args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclDelUser(AclDelUserGCommand { usernames: args.into() }))
+    }
+
+    fn parse_acl_list(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclList(AclListGCommand))
+    }
+
+    fn parse_acl_users(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclUsers(AclUsersGCommand))
+    }
+
+    fn parse_acl_whoami(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclWhoAmI(AclWhoAmIGCommand))
+    }
+
+    fn parse_acl_cat(args: &[GString]) -> Result<Self> {
+        match args {
+            [] | [_] => Ok(GCommand::AclCat(AclCatGCommand { category: args.first().cloned() })),
+            _ => Err(Error::Syntax),
+        }
+    }
+
+    fn parse_acl_log(args: &[GString]) -> Result<Self> {
+        let command = match args {
+            [] => AclLogGCommand::Get(None),
+            [reset] if reset.as_ref().eq_ignore_ascii_case(b"RESET") => AclLogGCommand::Reset,
+            [count] => match parse_integer::<i64>(count)? {
+                count if count < 0 => {
+                    return Err(Error::Err("value is out of range, must be positive".to_string()));
+                }
+                count => AclLogGCommand::Get(Some(count as usize)),
+            },
+            _ => return Err(Error::Syntax),
+        };
+        Ok(GCommand::AclLog(command))
+    }
+
+    fn parse_acl_save(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclSave(AclSaveGCommand))
+    }
+
+    fn parse_acl_load(_args: &[GString]) -> Result<Self> {
+        Ok(GCommand::AclLoad(AclLoadGCommand))
+    }
+}
+
+fn token(token: &'static [u8]) -> GString {
+    GString::from_static(token)
+}
+
+fn integer(value: i64) -> GString {
+    GString::copy_from_slice(value.to_string().as_bytes())
+}
+
+/// `ON` or `OFF`.
+fn parse_switch(arg: &GString) -> Result<bool> {
+    match arg {
+        arg if arg.eq_ignore_ascii_case(b"ON") => Ok(true),
+        arg if arg.eq_ignore_ascii_case(b"OFF") => Ok(false),
+        _ => Err(Error::Syntax),
+    }
+}
+
+fn parse_integer<T: std::str::FromStr>(arg: &GString) -> Result<T> {
+    std::str::from_utf8(arg.as_ref()).ok().and_then(|arg| arg.parse().ok()).ok_or(Error::NotInteger)
+}
+
+/// Flatten a request frame into its bulk string arguments, command name included.
+pub fn args_from_frame(frame: &GFrame) -> Result<Box<[GString]>> {
+    let invalid = || Error::Protocol("expected a non-empty array of bulk strings".to_string());
+
+    let frames = frame.as_array().map_err(|_| invalid())?;
+
+    if frames.is_empty() {
+        return Err(invalid());
+    }
+
+    frames.iter().map(|frame| frame.as_bulk_string().map_err(|_| invalid())).collect()
+}
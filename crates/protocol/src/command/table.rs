@@ -0,0 +1,1569 @@
+use crate::{
+    command::{
+        Error,
+        GCommand,
+        Result,
+    },
+    data_type::GString,
+};
+
+pub type ParseFn = fn(&[GString]) -> Result<GCommand>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,
+    Readonly,
+    Denyoom,
+    Admin,
+    Noscript,
+    Loading,
+    Stale,
+    Fast,
+    Sentinel,
+    /// Accepted from connections that did not authenticate yet.
+    NoAuth,
+}
+
+impl CommandFlag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandFlag::Write => "write",
+            CommandFlag::Readonly => "readonly",
+            CommandFlag::Denyoom => "denyoom",
+            CommandFlag::Admin => "admin",
+            CommandFlag::Noscript => "noscript",
+            CommandFlag::Loading => "loading",
+            CommandFlag::Stale => "stale",
+            CommandFlag::Fast => "fast",
+            CommandFlag::Sentinel => "sentinel",
+            CommandFlag::NoAuth => "no_auth",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclCategory {
+    Keyspace,
+    Read,
+    Write,
+    String,
+    Fast,
+    Slow,
+    Admin,
+    Dangerous,
+    Connection,
+}
+
+impl AclCategory {
+    pub const ALL: [AclCategory; 9] = [
+        AclCategory::Keyspace,
+        AclCategory::Read,
+        AclCategory::Write,
+        AclCategory::String,
+        AclCategory::Fast,
+        AclCategory::Slow,
+        AclCategory::Admin,
+        AclCategory::Dangerous,
+        AclCategory::Connection,
+    ];
+
+    /// Category name without the leading `@`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AclCategory::Keyspace => "keyspace",
+            AclCategory::Read => "read",
+            AclCategory::Write => "write",
+            AclCategory::String => "string",
+            AclCategory::Fast => "fast",
+            AclCategory::Slow => "slow",
+            AclCategory::Admin => "admin",
+            AclCategory::Dangerous => "dangerous",
+            AclCategory::Connection => "connection",
+        }
+    }
+
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_str().as_bytes().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Legacy key range, positions are counted from the command name.
+///
+/// Negative `last` counts from the end of the arguments, `-1` being the last one.
+#[derive(Debug, Clone, Copy)]
+pub struct KeySpec {
+    pub first: i64,
+    pub last: i64,
+    pub step: i64,
+    /// Permission the keys need in the ACL rules of the user sending the command.
+    pub access: KeyAccess,
+}
+
+impl KeySpec {
+    pub const NONE: KeySpec = KeySpec { first: 0, last: 0, step: 0, access: KeyAccess::Read };
+}
+
+/// How a command uses its keys, matched against the `%R~` and `%W~` key patterns of ACL users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAccess {
+    Read,
+    /// Overwritten or deleted without being read.
+    Write,
+    ReadWrite,
+}
+
+impl KeyAccess {
+    pub fn reads(&self) -> bool {
+        matches!(self, KeyAccess::Read | KeyAccess::ReadWrite)
+    }
+
+    pub fn writes(&self) -> bool {
+        matches!(self, KeyAccess::Write | KeyAccess::ReadWrite)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CommandDocs {
+    pub summary: &'static str,
+    pub since: &'static str,
+    pub group: &'static str,
+    pub complexity: &'static str,
+}
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Name of the container command for subcommands, e.g. `CONFIG` for `CONFIG GET`.
+    pub container: Option<&'static str>,
+    /// Number of arguments including the command name, negative means "at least".
+    pub arity: i64,
+    pub flags: &'static [CommandFlag],
+    pub keys: KeySpec,
+    pub acl_categories: &'static [AclCategory],
+    pub docs: CommandDocs,
+    pub subcommands: &'static [CommandSpec],
+    pub(crate) parse: Option<ParseFn>,
+}
+
+impl CommandSpec {
+    pub fn all() -> &'static [CommandSpec] {
+        COMMANDS
+    }
+
+    pub fn find(name: &[u8]) -> Option<&'static CommandSpec> {
+        COMMANDS.iter().find(|spec| spec.name.as_bytes().eq_ignore_ascii_case(name))
+    }
+
+    pub fn find_subcommand(&self, name: &[u8]) -> Option<&'static CommandSpec> {
+        self.subcommands.iter().find(|spec| spec.name.as_bytes().eq_ignore_ascii_case(name))
+    }
+
+    /// Find the (sub)command spec for given arguments and validate its arity.
+    pub fn resolve(args: &[GString]) -> Result<&'static CommandSpec> {
+        let spec = args
+            .first()
+            .and_then(|name| Self::find(name.as_ref()))
+            .ok_or_else(|| Error::unknown_command(args))?;
+
+        let spec = match args.get(1) {
+            Some(subcommand) if !spec.subcommands.is_empty() => spec
+                .find_subcommand(subcommand.as_ref())
+                .ok_or_else(|| Error::unknown_subcommand(spec.name, subcommand))?,
+            _ => spec,
+        };
+
+        spec.check_arity(args.len())?;
+
+        Ok(spec)
+    }
+
+    pub fn check_arity(&self, argc: usize) -> Result<()> {
+        let argc = argc as i64;
+        let valid = if self.arity >= 0 { argc == self.arity } else { argc >= -self.arity };
+
+        if valid { Ok(()) } else { Err(Error::WrongArity(self.full_name())) }
+    }
+
+    /// Number of leading arguments naming the command.
+    pub fn args_offset(&self) -> usize {
+        if self.container.is_some() { 2 } else { 1 }
+    }
+
+    /// Lowercase name as reported by COMMAND, `container|name` for subcommands.
+    pub fn full_name(&self) -> String {
+        match self.container {
+            Some(container) => format!("{container}|{name}", name = self.name),
+            None => self.name.to_string(),
+        }
+        .to_ascii_lowercase()
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn has_acl_category(&self, category: AclCategory) -> bool {
+        self.acl_categories.contains(&category)
+    }
+
+    /// Positions of key arguments for a call with `argc` arguments including the command name.
+    pub fn key_indices(&self, argc: usize) -> Vec<usize> {
+        let KeySpec { first, last, step, .. } = self.keys;
+        if first <= 0 || step <= 0 {
+            return Vec::new();
+        }
+
+        let argc = argc as i64;
+        let last = if last < 0 { argc + last } else { last.min(argc - 1) };
+
+        (first..=last).step_by(step as usize).map(|index| index as usize).collect()
+    }
+}
+
+const DENYOOM_WRITE_FAST: &[CommandFlag] =
+    &[CommandFlag::Write, CommandFlag::Denyoom, CommandFlag::Fast];
+const STRING_WRITE_FAST: &[AclCategory] =
+    &[AclCategory::Write, AclCategory::String, AclCategory::Fast];
+const INTROSPECTION_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Loading, CommandFlag::Stale, CommandFlag::Sentinel];
+const INTROSPECTION_CATEGORIES: &[AclCategory] = &[AclCategory::Slow, AclCategory::Connection];
+const CLUSTER_FLAGS: &[CommandFlag] = &[CommandFlag::Loading, CommandFlag::Stale];
+const CONNECTION_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Loading, CommandFlag::Stale, CommandFlag::Fast];
+const REPLICATION_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Admin, CommandFlag::Noscript, CommandFlag::Stale];
+const CONFIG_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Admin, CommandFlag::Noscript, CommandFlag::Loading, CommandFlag::Stale];
+const SLOWLOG_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Admin, CommandFlag::Loading, CommandFlag::Stale];
+const ADMIN_CATEGORIES: &[AclCategory] =
+    &[AclCategory::Admin, AclCategory::Slow, AclCategory::Dangerous];
+const CLIENT_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Noscript, CommandFlag::Loading, CommandFlag::Stale];
+const ACL_FLAGS: &[CommandFlag] =
+    &[CommandFlag::Noscript, CommandFlag::Loading, CommandFlag::Stale, CommandFlag::Sentinel];
+const ACL_ADMIN_FLAGS: &[CommandFlag] = &[
+    CommandFlag::Admin,
+    CommandFlag::Noscript,
+    CommandFlag::Loading,
+    CommandFlag::Stale,
+    CommandFlag::Sentinel,
+];
+const CLIENT_ADMIN_CATEGORIES: &[AclCategory] =
+    &[AclCategory::Admin, AclCategory::Slow, AclCategory::Dangerous, AclCategory::Connection];
+
+static COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "PING",
+        container: None,
+        arity: -1,
+        flags: &[CommandFlag::Fast, CommandFlag::Sentinel],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Fast, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Returns the server's liveliness response.",
+            since: "1.0.0",
+            group: "connection",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_ping),
+    },
+    CommandSpec {
+        name: "GET",
+        container: None,
+        arity: 2,
+        flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+        keys: KeySpec { first: 1, last: 1, step: 1, access: KeyAccess::Read },
+        acl_categories: &[AclCategory::Read, AclCategory::String, AclCategory::Fast],
+        docs: CommandDocs {
+            summary: "Returns the string value of a key.",
+            since: "1.0.0",
+            group: "string",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_get),
+    },
+    CommandSpec {
+        name: "SET",
+        container: None,
+        arity: 3,
+        flags: &[CommandFlag::Write, CommandFlag::Denyoom],
+        keys: KeySpec { first: 1, last: 1, step: 1, access: KeyAccess::Write },
+        acl_categories: &[AclCategory::Write, AclCategory::String, AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "Sets the string value of a key, ignoring its type.",
+            since: "1.0.0",
+            group: "string",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_set),
+    },
+    CommandSpec {
+        name: "DEL",
+        container: None,
+        arity: -2,
+        flags: &[CommandFlag::Write],
+        keys: KeySpec { first: 1, last: -1, step: 1, access: KeyAccess::Write },
+        acl_categories: &[AclCategory::Keyspace, AclCategory::Write, AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "Deletes one or more keys.",
+            since: "1.0.0",
+            group: "generic",
+            complexity: "O(N) where N is the number of keys that will be removed.",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_del),
+    },
+    CommandSpec {
+        name: "EXISTS",
+        container: None,
+        arity: -2,
+        flags: &[CommandFlag::Readonly, CommandFlag::Fast],
+        keys: KeySpec { first: 1, last: -1, step: 1, access: KeyAccess::Read },
+        acl_categories: &[AclCategory::Keyspace, AclCategory::Read, AclCategory::Fast],
+        docs: CommandDocs {
+            summary: "Determines whether one or more keys exist.",
+            since: "1.0.0",
+            group: "generic",
+            complexity: "O(N) where N is the number of keys to check.",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_exists),
+    },
+    CommandSpec {
+        name: "INCR",
+        container: None,
+        arity: 2,
+        flags: DENYOOM_WRITE_FAST,
+        keys: KeySpec { first: 1, last: 1, step: 1, access: KeyAccess::ReadWrite },
+        acl_categories: STRING_WRITE_FAST,
+        docs: CommandDocs {
+            summary: "Increments the integer value of a key by one. Uses 0 as initial value if \
+                      the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_incr),
+    },
+    CommandSpec {
+        name: "DECR",
+        container: None,
+        arity: 2,
+        flags: DENYOOM_WRITE_FAST,
+        keys: KeySpec { first: 1, last: 1, step: 1, access: KeyAccess::ReadWrite },
+        acl_categories: STRING_WRITE_FAST,
+        docs: CommandDocs {
+            summary: "Decrements the integer value of a key by one. Uses 0 as initial value if \
+                      the key doesn't exist.",
+            since: "1.0.0",
+            group: "string",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_decr),
+    },
+    CommandSpec {
+        name: "CONFIG",
+        container: None,
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "A container for server configuration commands.",
+            since: "2.0.0",
+            group: "server",
+            complexity: "Depends on subcommand.",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "GET",
+                container: Some("CONFIG"),
+                arity: -3,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the effective values of configuration parameters.",
+                    since: "2.0.0",
+                    group: "server",
+                    complexity: "O(N) when N is the number of configuration parameters provided",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_config_get),
+            },
+            CommandSpec {
+                name: "SET",
+                container: Some("CONFIG"),
+                arity: -4,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Sets configuration parameters in-flight.",
+                    since: "2.0.0",
+                    group: "server",
+                    complexity: "O(N) when N is the number of configuration parameters provided",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_config_set),
+            },
+            CommandSpec {
+                name: "REWRITE",
+                container: Some("CONFIG"),
+                arity: 2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Persists the effective configuration to file.",
+                    since: "2.8.0",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_config_rewrite),
+            },
+            CommandSpec {
+                name: "RESETSTAT",
+                container: Some("CONFIG"),
+                arity: 2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Resets the server's statistics.",
+                    since: "2.0.0",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_config_resetstat),
+            },
+        ],
+        parse: None,
+    },
+    CommandSpec {
+        name: "COMMAND",
+        container: None,
+        arity: -1,
+        flags: INTROSPECTION_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: INTROSPECTION_CATEGORIES,
+        docs: CommandDocs {
+            summary: "Returns detailed information about all commands.",
+            since: "2.8.13",
+            group: "server",
+            complexity: "O(N) where N is the total number of commands",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "COUNT",
+                container: Some("COMMAND"),
+                arity: 2,
+                flags: INTROSPECTION_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns a count of commands.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_command_count),
+            },
+            CommandSpec {
+                name: "INFO",
+                container: Some("COMMAND"),
+                arity: -2,
+                flags: INTROSPECTION_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns information about one, multiple or all commands.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(N) where N is the number of commands to look up",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_command_info),
+            },
+            CommandSpec {
+                name: "DOCS",
+                container: Some("COMMAND"),
+                arity: -2,
+                flags: INTROSPECTION_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns documentary information about one, multiple or all \
+                              commands.",
+                    since: "7.0.0",
+                    group: "server",
+                    complexity: "O(N) where N is the number of commands to look up",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_command_docs),
+            },
+            CommandSpec {
+                name: "LIST",
+                container: Some("COMMAND"),
+                arity: -2,
+                flags: INTROSPECTION_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns a list of command names.",
+                    since: "7.0.0",
+                    group: "server",
+                    complexity: "O(N) where N is the total number of commands",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_command_list),
+            },
+            CommandSpec {
+                name: "GETKEYS",
+                container: Some("COMMAND"),
+                arity: -3,
+                flags: INTROSPECTION_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Extracts the key names from an arbitrary command.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(N) where N is the number of arguments to the command",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_command_getkeys),
+            },
+        ],
+        parse: Some(GCommand::parse_command),
+    },
+    CommandSpec {
+        name: "RESHARD",
+        container: None,
+        arity: 2,
+        flags: &[CommandFlag::Admin, CommandFlag::Noscript],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Admin, AclCategory::Slow, AclCategory::Dangerous],
+        docs: CommandDocs {
+            summary: "Migrates hash slots so that only the given number of storage shards own \
+                      keys.",
+            since: "0.1.0",
+            group: "server",
+            complexity: "O(N) where N is the number of keys stored.",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_reshard),
+    },
+    CommandSpec {
+        name: "CLUSTER",
+        container: None,
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "A container for Redis Cluster commands.",
+            since: "3.0.0",
+            group: "cluster",
+            complexity: "Depends on subcommand.",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "SLOTS",
+                container: Some("CLUSTER"),
+                arity: 2,
+                flags: CLUSTER_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns the mapping of cluster slots to nodes.",
+                    since: "3.0.0",
+                    group: "cluster",
+                    complexity: "O(N) where N is the total number of Cluster nodes",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_cluster_slots),
+            },
+            CommandSpec {
+                name: "SHARDS",
+                container: Some("CLUSTER"),
+                arity: 2,
+                flags: CLUSTER_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns the mapping of cluster slots to shards.",
+                    since: "7.0.0",
+                    group: "cluster",
+                    complexity: "O(N) where N is the total number of cluster nodes",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_cluster_shards),
+            },
+            CommandSpec {
+                name: "NODES",
+                container: Some("CLUSTER"),
+                arity: 2,
+                flags: CLUSTER_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns the cluster configuration for a node.",
+                    since: "3.0.0",
+                    group: "cluster",
+                    complexity: "O(N) where N is the total number of Cluster nodes",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_cluster_nodes),
+            },
+            CommandSpec {
+                name: "KEYSLOT",
+                container: Some("CLUSTER"),
+                arity: 3,
+                flags: &[CommandFlag::Stale],
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns the hash slot for a key.",
+                    since: "3.0.0",
+                    group: "cluster",
+                    complexity: "O(N) where N is the number of bytes in the key",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_cluster_keyslot),
+            },
+            CommandSpec {
+                name: "INFO",
+                container: Some("CLUSTER"),
+                arity: 2,
+                flags: CLUSTER_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns information about the state of a node.",
+                    since: "3.0.0",
+                    group: "cluster",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_cluster_info),
+            },
+            CommandSpec {
+                name: "MYID",
+                container: Some("CLUSTER"),
+                arity: 2,
+                flags: CLUSTER_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns the ID of a node.",
+                    since: "3.0.0",
+                    group: "cluster",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_cluster_myid),
+            },
+        ],
+        parse: None,
+    },
+    CommandSpec {
+        name: "READONLY",
+        container: None,
+        arity: 1,
+        flags: CONNECTION_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Fast, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Enables read-only queries for a connection to a Redis Cluster replica node.",
+            since: "3.0.0",
+            group: "cluster",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_readonly),
+    },
+    CommandSpec {
+        name: "READWRITE",
+        container: None,
+        arity: 1,
+        flags: CONNECTION_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Fast, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Enables read-write queries for a connection to a Redis Cluster replica node.",
+            since: "3.0.0",
+            group: "cluster",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_readwrite),
+    },
+    CommandSpec {
+        name: "ASKING",
+        container: None,
+        arity: 1,
+        flags: &[CommandFlag::Fast],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Fast, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Signals that a cluster client is following an -ASK redirect.",
+            since: "3.0.0",
+            group: "cluster",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_asking),
+    },
+    CommandSpec {
+        name: "INFO",
+        container: None,
+        arity: -1,
+        flags: &[CommandFlag::Loading, CommandFlag::Stale],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow, AclCategory::Dangerous],
+        docs: CommandDocs {
+            summary: "Returns information and statistics about the server.",
+            since: "1.0.0",
+            group: "server",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_info),
+    },
+    CommandSpec {
+        name: "REPLICAOF",
+        container: None,
+        arity: 3,
+        flags: REPLICATION_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: ADMIN_CATEGORIES,
+        docs: CommandDocs {
+            summary: "Configures a server as replica of another, or promotes it to a master.",
+            since: "5.0.0",
+            group: "server",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_replicaof),
+    },
+    CommandSpec {
+        name: "SLAVEOF",
+        container: None,
+        arity: 3,
+        flags: REPLICATION_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: ADMIN_CATEGORIES,
+        docs: CommandDocs {
+            summary: "Sets a Redis server as a replica of another, or promotes it to being a \
+                      master.",
+            since: "1.0.0",
+            group: "server",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_replicaof),
+    },
+    CommandSpec {
+        name: "PSYNC",
+        container: None,
+        arity: -3,
+        flags: &[CommandFlag::Admin, CommandFlag::Noscript],
+        keys: KeySpec::NONE,
+        acl_categories: ADMIN_CATEGORIES,
+        docs: CommandDocs {
+            summary: "An internal command used in replication.",
+            since: "2.8.0",
+            group: "server",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_psync),
+    },
+    CommandSpec {
+        name: "REPLCONF",
+        container: None,
+        arity: -1,
+        flags: &[
+            CommandFlag::Admin,
+            CommandFlag::Noscript,
+            CommandFlag::Loading,
+            CommandFlag::Stale,
+        ],
+        keys: KeySpec::NONE,
+        acl_categories: ADMIN_CATEGORIES,
+        docs: CommandDocs {
+            summary: "An internal command for configuring the replication stream.",
+            since: "3.0.0",
+            group: "server",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_replconf),
+    },
+    CommandSpec {
+        name: "WAIT",
+        container: None,
+        arity: 3,
+        flags: &[CommandFlag::Noscript],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Blocks until the asynchronous replication of all preceding write commands \
+                      sent by the connection is completed.",
+            since: "3.0.0",
+            group: "generic",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_wait),
+    },
+    CommandSpec {
+        name: "SHUTDOWN",
+        container: None,
+        arity: -1,
+        flags: CONFIG_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: ADMIN_CATEGORIES,
+        docs: CommandDocs {
+            summary: "Synchronously saves the database(s) to disk and shuts down the Redis server.",
+            since: "1.0.0",
+            group: "server",
+            complexity: "O(N) when saving, where N is the total number of keys in all databases \
+                         when saving data, otherwise O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_shutdown),
+    },
+    CommandSpec {
+        name: "SLOWLOG",
+        container: None,
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "A container for slow log commands.",
+            since: "2.2.12",
+            group: "server",
+            complexity: "Depends on subcommand.",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "GET",
+                container: Some("SLOWLOG"),
+                arity: -2,
+                flags: SLOWLOG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the slow log's entries.",
+                    since: "2.2.12",
+                    group: "server",
+                    complexity: "O(N) where N is the number of entries returned",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_slowlog_get),
+            },
+            CommandSpec {
+                name: "LEN",
+                container: Some("SLOWLOG"),
+                arity: 2,
+                flags: SLOWLOG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the number of entries in the slow log.",
+                    since: "2.2.12",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_slowlog_len),
+            },
+            CommandSpec {
+                name: "RESET",
+                container: Some("SLOWLOG"),
+                arity: 2,
+                flags: SLOWLOG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Clears all entries from the slow log.",
+                    since: "2.2.12",
+                    group: "server",
+                    complexity: "O(N) where N is the number of entries in the slowlog",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_slowlog_reset),
+            },
+        ],
+        parse: None,
+    },
+    CommandSpec {
+        name: "LATENCY",
+        container: None,
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "A container for latency diagnostics commands.",
+            since: "2.8.13",
+            group: "server",
+            complexity: "Depends on subcommand.",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "DOCTOR",
+                container: Some("LATENCY"),
+                arity: 2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns a human-readable latency analysis report.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_latency_doctor),
+            },
+            CommandSpec {
+                name: "HISTOGRAM",
+                container: Some("LATENCY"),
+                arity: -2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the cumulative distribution of latencies of a subset or all commands.",
+                    since: "7.0.0",
+                    group: "server",
+                    complexity: "O(N) where N is the number of commands with latency information being retrieved.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_latency_histogram),
+            },
+            CommandSpec {
+                name: "HISTORY",
+                container: Some("LATENCY"),
+                arity: 3,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns timestamp-latency samples for an event.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_latency_history),
+            },
+            CommandSpec {
+                name: "LATEST",
+                container: Some("LATENCY"),
+                arity: 2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the latest latency samples for all events.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_latency_latest),
+            },
+            CommandSpec {
+                name: "RESET",
+                container: Some("LATENCY"),
+                arity: -2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Resets the latency data for one or more events.",
+                    since: "2.8.13",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_latency_reset),
+            },
+        ],
+        parse: None,
+    },
+    CommandSpec {
+        name: "MONITOR",
+        container: None,
+        arity: 1,
+        flags: CONFIG_FLAGS,
+        keys: KeySpec::NONE,
+        acl_categories: ADMIN_CATEGORIES,
+        docs: CommandDocs {
+            summary: "Listens for all requests received by the server in real-time.",
+            since: "1.0.0",
+            group: "server",
+            complexity: "",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_monitor),
+    },
+    CommandSpec {
+        name: "CLIENT",
+        container: None,
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "A container for client connection commands.",
+            since: "2.4.0",
+            group: "connection",
+            complexity: "Depends on subcommand.",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "CACHING",
+                container: Some("CLIENT"),
+                arity: 3,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Instructs the server whether to track the keys in the next request.",
+                    since: "6.0.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_caching),
+            },
+            CommandSpec {
+                name: "GETNAME",
+                container: Some("CLIENT"),
+                arity: 2,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the name of the connection.",
+                    since: "2.6.9",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_getname),
+            },
+            CommandSpec {
+                name: "GETREDIR",
+                container: Some("CLIENT"),
+                arity: 2,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the client ID to which the connection's tracking notifications are redirected.",
+                    since: "6.0.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_getredir),
+            },
+            CommandSpec {
+                name: "ID",
+                container: Some("CLIENT"),
+                arity: 2,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns the unique client ID of the connection.",
+                    since: "5.0.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_id),
+            },
+            CommandSpec {
+                name: "INFO",
+                container: Some("CLIENT"),
+                arity: 2,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns information about the connection.",
+                    since: "6.2.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_info),
+            },
+            CommandSpec {
+                name: "KILL",
+                container: Some("CLIENT"),
+                arity: -3,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: CLIENT_ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Terminates open connections.",
+                    since: "2.4.0",
+                    group: "connection",
+                    complexity: "O(N) where N is the number of client connections",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_kill),
+            },
+            CommandSpec {
+                name: "LIST",
+                container: Some("CLIENT"),
+                arity: -2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: CLIENT_ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Lists open connections.",
+                    since: "2.4.0",
+                    group: "connection",
+                    complexity: "O(N) where N is the number of client connections",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_list),
+            },
+            CommandSpec {
+                name: "NO-EVICT",
+                container: Some("CLIENT"),
+                arity: 3,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: CLIENT_ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Sets the client eviction mode of the connection.",
+                    since: "7.0.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_no_evict),
+            },
+            CommandSpec {
+                name: "NO-TOUCH",
+                container: Some("CLIENT"),
+                arity: 3,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Controls whether commands sent by the client affect the LRU/LFU of accessed keys.",
+                    since: "7.2.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_no_touch),
+            },
+            CommandSpec {
+                name: "PAUSE",
+                container: Some("CLIENT"),
+                arity: -3,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: CLIENT_ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Suspends commands processing.",
+                    since: "3.0.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_pause),
+            },
+            CommandSpec {
+                name: "REPLY",
+                container: Some("CLIENT"),
+                arity: 3,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Instructs the server whether to reply to commands.",
+                    since: "3.2.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_reply),
+            },
+            CommandSpec {
+                name: "SETINFO",
+                container: Some("CLIENT"),
+                arity: 4,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Sets information specific to the client or connection.",
+                    since: "7.2.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_setinfo),
+            },
+            CommandSpec {
+                name: "SETNAME",
+                container: Some("CLIENT"),
+                arity: 3,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Sets the connection name.",
+                    since: "2.6.9",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_setname),
+            },
+            CommandSpec {
+                name: "TRACKING",
+                container: Some("CLIENT"),
+                arity: -3,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Controls server-assisted client-side caching for the connection.",
+                    since: "6.0.0",
+                    group: "connection",
+                    complexity: "O(1). Some options may introduce additional complexity.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_tracking),
+            },
+            CommandSpec {
+                name: "TRACKINGINFO",
+                container: Some("CLIENT"),
+                arity: 2,
+                flags: CLIENT_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: INTROSPECTION_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Returns information about server-assisted client-side caching for the connection.",
+                    since: "6.2.0",
+                    group: "connection",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_trackinginfo),
+            },
+            CommandSpec {
+                name: "UNPAUSE",
+                container: Some("CLIENT"),
+                arity: 2,
+                flags: CONFIG_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: CLIENT_ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Resumes processing commands from paused clients.",
+                    since: "6.2.0",
+                    group: "connection",
+                    complexity: "O(N) Where N is the number of paused clients",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_client_unpause),
+            },
+        ],
+        parse: None,
+    },
+    CommandSpec {
+        name: "HELLO",
+        container: None,
+        arity: -1,
+        flags: &[
+            CommandFlag::Noscript,
+            CommandFlag::Loading,
+            CommandFlag::Stale,
+            CommandFlag::Fast,
+            CommandFlag::Sentinel,
+            CommandFlag::NoAuth,
+        ],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Fast, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Handshakes with the Redis server.",
+            since: "6.0.0",
+            group: "connection",
+            complexity: "O(1)",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_hello),
+    },
+    CommandSpec {
+        name: "AUTH",
+        container: None,
+        arity: -2,
+        flags: &[
+            CommandFlag::Noscript,
+            CommandFlag::Loading,
+            CommandFlag::Stale,
+            CommandFlag::Fast,
+            CommandFlag::NoAuth,
+        ],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Fast, AclCategory::Connection],
+        docs: CommandDocs {
+            summary: "Authenticates the connection.",
+            since: "1.0.0",
+            group: "connection",
+            complexity: "O(N) where N is the number of passwords defined for the user",
+        },
+        subcommands: &[],
+        parse: Some(GCommand::parse_auth),
+    },
+    CommandSpec {
+        name: "ACL",
+        container: None,
+        arity: -2,
+        flags: &[CommandFlag::Sentinel],
+        keys: KeySpec::NONE,
+        acl_categories: &[AclCategory::Slow],
+        docs: CommandDocs {
+            summary: "A container for Access List Control commands.",
+            since: "6.0.0",
+            group: "server",
+            complexity: "Depends on subcommand.",
+        },
+        subcommands: &[
+            CommandSpec {
+                name: "CAT",
+                container: Some("ACL"),
+                arity: -2,
+                flags: ACL_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Lists the ACL categories, or the commands inside a category.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(1) since the categories and commands are a fixed set.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_cat),
+            },
+            CommandSpec {
+                name: "DELUSER",
+                container: Some("ACL"),
+                arity: -3,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Deletes ACL users, and terminates their connections.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(1) amortized time considering the typical user.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_deluser),
+            },
+            CommandSpec {
+                name: "GETUSER",
+                container: Some("ACL"),
+                arity: 3,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Lists the ACL rules of a user.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N). Where N is the number of password, command and pattern rules that the user has.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_getuser),
+            },
+            CommandSpec {
+                name: "LIST",
+                container: Some("ACL"),
+                arity: 2,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Dumps the effective rules in ACL file format.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N). Where N is the number of configured users.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_list),
+            },
+            CommandSpec {
+                name: "LOAD",
+                container: Some("ACL"),
+                arity: 2,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Reloads the rules from the configured ACL file.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N). Where N is the number of configured users.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_load),
+            },
+            CommandSpec {
+                name: "LOG",
+                container: Some("ACL"),
+                arity: -2,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Lists recent security events generated due to ACL rules.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N) with N being the number of entries shown.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_log),
+            },
+            CommandSpec {
+                name: "SAVE",
+                container: Some("ACL"),
+                arity: 2,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Saves the effective ACL rules in the configured ACL file.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N). Where N is the number of configured users.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_save),
+            },
+            CommandSpec {
+                name: "SETUSER",
+                container: Some("ACL"),
+                arity: -3,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Creates and modifies an ACL user and its rules.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N). Where N is the number of rules provided.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_setuser),
+            },
+            CommandSpec {
+                name: "USERS",
+                container: Some("ACL"),
+                arity: 2,
+                flags: ACL_ADMIN_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: ADMIN_CATEGORIES,
+                docs: CommandDocs {
+                    summary: "Lists all ACL users.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(N). Where N is the number of configured users.",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_users),
+            },
+            CommandSpec {
+                name: "WHOAMI",
+                container: Some("ACL"),
+                arity: 2,
+                flags: ACL_FLAGS,
+                keys: KeySpec::NONE,
+                acl_categories: &[AclCategory::Slow],
+                docs: CommandDocs {
+                    summary: "Returns the authenticated username of the current connection.",
+                    since: "6.0.0",
+                    group: "server",
+                    complexity: "O(1)",
+                },
+                subcommands: &[],
+                parse: Some(GCommand::parse_acl_whoami),
+            },
+        ],
+        parse: None,
+    },
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&'static [u8]]) -> Vec<GString> {
+        args.iter().map(|arg| GString::from_static(arg)).collect()
+    }
+
+    #[test]
+    fn resolve_subcommand() {
+        let spec = CommandSpec::resolve(&args(&[b"CONFIG", b"GET", b"save"])).unwrap();
+        assert_eq!(spec.full_name(), "config|get");
+    }
+
+    #[test]
+    fn resolve_checks_arity() {
+        let wrong_arity = Err(Error::WrongArity("get".to_string()));
+        assert_eq!(CommandSpec::resolve(&args(&[b"GET"])).map(|_| ()), wrong_arity);
+        assert_eq!(CommandSpec::resolve(&args(&[b"GET", b"a", b"b"])).map(|_| ()), wrong_arity);
+        assert_eq!(
+            CommandSpec::resolve(&args(&[b"CONFIG", b"FOO"])).unwrap_err().to_string(),
+            "ERR unknown subcommand 'FOO'. Try CONFIG HELP."
+        );
+        assert!(CommandSpec::resolve(&args(&[b"COMMAND"])).is_ok());
+    }
+
+    #[test]
+    fn resolve_ignores_case() {
+        let spec = CommandSpec::resolve(&args(&[b"config", b"Get", b"save"])).unwrap();
+        assert_eq!(spec.full_name(), "config|get");
+
+        assert!(CommandSpec::resolve(&args(&[b"gEt", b"key"])).is_ok());
+    }
+
+    #[test]
+    fn key_indices() {
+        let del = CommandSpec::find(b"DEL").unwrap();
+        assert_eq!(del.key_indices(4), vec![1, 2, 3]);
+
+        let get = CommandSpec::find(b"GET").unwrap();
+        assert_eq!(get.key_indices(2), vec![1]);
+
+        let ping = CommandSpec::find(b"PING").unwrap();
+        assert!(ping.key_indices(2).is_empty());
+    }
+}
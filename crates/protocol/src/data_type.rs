@@ -35,6 +35,17 @@ impl GString {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Compare with a keyword, e.g. a command name or option token, ignoring ASCII case.
+    pub fn eq_ignore_ascii_case(&self, other: &[u8]) -> bool {
+        self.value.eq_ignore_ascii_case(other)
+    }
+}
+
+impl AsRef<[u8]> for GString {
+    fn as_ref(&self) -> &[u8] {
+        &self.value
+    }
 }
 
 impl Default for GString {
@@ -58,6 +69,11 @@ impl GInteger {
     pub fn new(value: i64) -> Self {
         Self { value }
     }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
     pub fn bytes(&self) -> Bytes {
         let value = self.value.to_string();
         Bytes::copy_from_slice(value.as_bytes())
@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use crate::{
+    data_type::GString,
+    frame::GFrame,
+};
+
+/// Error reply sent back to the client, rendered with the same texts Redis uses.
+///
+/// The first word of the rendered message is the error code clients use to pick an exception
+/// type, see [`ReplyError::code`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReplyError {
+    #[error("ERR Protocol error: {0}")]
+    Protocol(String),
+    #[error("ERR unknown command '{name}', with args beginning with: {args}")]
+    UnknownCommand { name: String, args: String },
+    #[error("ERR unknown subcommand '{subcommand}'. Try {container} HELP.")]
+    UnknownSubcommand { container: String, subcommand: String },
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    #[error("ERR syntax error")]
+    Syntax,
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("MOVED {slot} {addr}")]
+    Moved { slot: u16, addr: String },
+    #[error("ASK {slot} {addr}")]
+    Ask { slot: u16, addr: String },
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+    #[error("CLUSTERDOWN {0}")]
+    ClusterDown(String),
+    #[error("READONLY You can't write against a read only replica.")]
+    ReadOnly,
+    #[error("OOM command not allowed when used memory > 'maxmemory'.")]
+    OutOfMemory,
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+    #[error("NOAUTH Authentication required.")]
+    NoAuth,
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+    /// Command, key or channel denied by the ACL rules of the user.
+    #[error("NOPERM {0}")]
+    NoPerm(String),
+    /// Any other `ERR` reply, the message is rendered after the code.
+    #[error("ERR {0}")]
+    Err(String),
+}
+
+impl ReplyError {
+    pub fn unknown_command(args: &[GString]) -> Self {
+        let name = args.first().map(lossy).unwrap_or_default();
+        let args = args.iter().skip(1).fold(String::new(), |mut out, arg| {
+            let _ = write!(out, "'{}' ", lossy(arg));
+            out
+        });
+
+        ReplyError::UnknownCommand { name, args }
+    }
+
+    pub fn unknown_subcommand(container: &str, subcommand: &GString) -> Self {
+        ReplyError::UnknownSubcommand {
+            container: container.to_ascii_uppercase(),
+            subcommand: lossy(subcommand),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReplyError::WrongType => "WRONGTYPE",
+            ReplyError::Moved { .. } => "MOVED",
+            ReplyError::Ask { .. } => "ASK",
+            ReplyError::CrossSlot => "CROSSSLOT",
+            ReplyError::ClusterDown(_) => "CLUSTERDOWN",
+            ReplyError::ReadOnly => "READONLY",
+            ReplyError::OutOfMemory => "OOM",
+            ReplyError::NoProto => "NOPROTO",
+            ReplyError::NoAuth => "NOAUTH",
+            ReplyError::WrongPass => "WRONGPASS",
+            ReplyError::NoPerm(_) => "NOPERM",
+            _ => "ERR",
+        }
+    }
+}
+
+impl From<ReplyError> for GFrame {
+    fn from(error: ReplyError) -> Self {
+        GFrame::SimpleError(GString::copy_from_slice(error.to_string().as_bytes()))
+    }
+}
+
+fn lossy(value: &GString) -> String {
+    String::from_utf8_lossy(value.as_ref()).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unknown_command() {
+        let args = [GString::from_static(b"FOO"), GString::from_static(b"bar")];
+        assert_eq!(
+            ReplyError::unknown_command(&args).to_string(),
+            "ERR unknown command 'FOO', with args beginning with: 'bar' "
+        );
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(ReplyError::WrongType.code(), "WRONGTYPE");
+        assert_eq!(ReplyError::Syntax.code(), "ERR");
+        assert_eq!(ReplyError::ReadOnly.code(), "READONLY");
+        assert!(ReplyError::WrongType.to_string().starts_with(ReplyError::WrongType.code()));
+    }
+
+    #[test]
+    fn redirections() {
+        let moved = ReplyError::Moved { slot: 3999, addr: "127.0.0.1:6381".to_string() };
+        assert_eq!(moved.to_string(), "MOVED 3999 127.0.0.1:6381");
+        assert_eq!(moved.code(), "MOVED");
+    }
+}
@@ -24,8 +24,10 @@ pub const INTEGER_FIRST_BYTE: u8 = b':';
 pub const BULK_STRING_FIRST_BYTE: u8 = b'$';
 pub const ARRAY_FIRST_BYTE: u8 = b'*';
 pub const NULL_FIRST_BYTE: u8 = b'_';
+pub const MAP_FIRST_BYTE: u8 = b'%';
+pub const PUSH_FIRST_BYTE: u8 = b'>';
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GFrame {
     SimpleString(GString),
     SimpleError(GString),
@@ -33,6 +35,10 @@ pub enum GFrame {
     BulkString(GString),
     Array(Box<[GFrame]>),
     Null,
+    /// RESP3 map, only sent to connections that switched to RESP3 with `HELLO 3`.
+    Map(Box<[(GFrame, GFrame)]>),
+    /// RESP3 out-of-band data such as invalidation messages, sent between replies.
+    Push(Box<[GFrame]>),
 }
 
 impl Display for GFrame {
@@ -85,6 +91,26 @@ impl GFrame {
                 bytes.put(TERMINATOR.as_ref());
             }
             GFrame::Array(frames) => {
+                bytes.put_u8(ARRAY_FIRST_BYTE);
+                bytes.write_fmt(format_args!("{len}", len = frames.len())).unwrap();
+                bytes.put(TERMINATOR.as_ref());
+                for frame in frames {
+                    frame.read_bytes(bytes);
+                }
+            }
+            GFrame::Map(entries) => {
+                bytes.put_u8(MAP_FIRST_BYTE);
+                bytes.write_fmt(format_args!("{len}", len = entries.len())).unwrap();
+                bytes.put(TERMINATOR.as_ref());
+                for (key, value) in entries {
+                    key.read_bytes(bytes);
+                    value.read_bytes(bytes);
+                }
+            }
+            GFrame::Push(frames) => {
+                bytes.put_u8(PUSH_FIRST_BYTE);
+                bytes.write_fmt(format_args!("{len}", len = frames.len())).unwrap();
+                bytes.put(TERMINATOR.as_ref());
                 for frame in frames {
                     frame.read_bytes(bytes);
                 }
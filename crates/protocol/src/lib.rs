@@ -1,5 +1,9 @@
+pub mod blocking;
+#[cfg(feature = "tokio-codec")]
+pub mod codec;
 pub mod command;
 pub mod data_type;
+pub mod error;
 pub mod frame;
 pub mod parser;
 pub mod stream;
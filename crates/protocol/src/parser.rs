@@ -11,7 +11,9 @@ use crate::{
         BULK_STRING_FIRST_BYTE,
         GFrame,
         INTEGER_FIRST_BYTE,
+        MAP_FIRST_BYTE,
         NULL_FIRST_BYTE,
+        PUSH_FIRST_BYTE,
         SIMPLE_ERROR_FIRST_BYTE,
         SIMPLE_STRING_FIRST_BYTE,
         TERMINATOR,
@@ -49,14 +51,7 @@ impl Parser {
     }
 
     pub fn parse(&mut self) -> ParseResult<Option<GFrame>> {
-        let result = parse_buf(&self.buf)?;
-        match result {
-            Some(parsed_frame) => {
-                self.buf.advance(parsed_frame.advance_by);
-                Ok(Some(parsed_frame.frame))
-            }
-            None => Ok(None),
-        }
+        parse_frame(&mut self.buf)
     }
 
     pub fn buf(&self) -> &BytesMut {
@@ -68,6 +63,19 @@ impl Parser {
     }
 }
 
+/// Parse a single frame from the front of `buf`, advancing it past the parsed frame.
+///
+/// Returns `Ok(None)` and leaves `buf` untouched if it does not yet hold a complete frame.
+pub fn parse_frame(buf: &mut BytesMut) -> ParseResult<Option<GFrame>> {
+    match parse_buf(buf)? {
+        Some(parsed_frame) => {
+            buf.advance(parsed_frame.advance_by);
+            Ok(Some(parsed_frame.frame))
+        }
+        None => Ok(None),
+    }
+}
+
 fn parse_buf(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
     if buf.is_empty() {
         return Ok(None);
@@ -80,6 +88,8 @@ fn parse_buf(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
         BULK_STRING_FIRST_BYTE => parse_bulk_string(buf),
         ARRAY_FIRST_BYTE => parse_array(buf),
         NULL_FIRST_BYTE => parse_null(buf),
+        MAP_FIRST_BYTE => parse_map(buf),
+        PUSH_FIRST_BYTE => parse_push(buf),
         _ => Err(ParseError::InvalidFirstByte),
     }
 }
@@ -132,7 +142,12 @@ fn parse_bulk_string(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
         let length = str::from_utf8(length).map_err(|_| ParseError::InvalidUtf8)?;
         let length = length.parse::<usize>().map_err(|_| ParseError::InvalidInteger)?;
 
-        let value = &buf[end_index + TERMINATOR.len()..end_index + TERMINATOR.len() + length];
+        let value_start = end_index + TERMINATOR.len();
+        if buf.len() < value_start + length + TERMINATOR.len() {
+            return Ok(None);
+        }
+
+        let value = &buf[value_start..value_start + length];
 
         let frame = GFrame::BulkString(GString::copy_from_slice(value));
 
@@ -148,10 +163,32 @@ fn parse_bulk_string(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
 }
 
 fn parse_array(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
+    parse_aggregate(buf, 1, |frames| GFrame::Array(frames.into_boxed_slice()))
+}
+
+fn parse_push(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
+    parse_aggregate(buf, 1, |frames| GFrame::Push(frames.into_boxed_slice()))
+}
+
+fn parse_map(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
+    parse_aggregate(buf, 2, |frames| {
+        let mut frames = frames.into_iter();
+        let entries = std::iter::from_fn(|| Some((frames.next()?, frames.next()?)));
+        GFrame::Map(entries.collect())
+    })
+}
+
+/// Parse a count followed by `count * per_entry` frames, built into a frame by `build`.
+fn parse_aggregate(
+    buf: &[u8],
+    per_entry: usize,
+    build: impl FnOnce(Vec<GFrame>) -> GFrame,
+) -> ParseResult<Option<ParsedFrame>> {
     if let Some(end_index) = buf.windows(2).position(|w| w == TERMINATOR) {
         let count = &buf[1..end_index];
         let count = str::from_utf8(count).map_err(|_| ParseError::InvalidUtf8)?;
         let count = count.parse::<usize>().map_err(|_| ParseError::InvalidInteger)?;
+        let count = count.checked_mul(per_entry).ok_or(ParseError::InvalidArray)?;
 
         let mut advance_by = end_index + TERMINATOR.len();
 
@@ -166,7 +203,7 @@ fn parse_array(buf: &[u8]) -> ParseResult<Option<ParsedFrame>> {
             }
         }
 
-        let frame = GFrame::Array(frames.into_boxed_slice());
+        let frame = build(frames);
 
         let parsed_frame = ParsedFrame { frame, advance_by };
 
@@ -247,4 +284,38 @@ mod test {
     );
     test_parse!(empty_array, b"*0\r\n", GFrame::Array([].into()));
     test_parse!(null, b"_\r\n", GFrame::Null);
+    test_parse!(
+        map,
+        b"%1\r\n$5\r\nproto\r\n:3\r\n",
+        GFrame::Map(
+            [(
+                GFrame::BulkString(GString::from_static(b"proto")),
+                GFrame::Integer(GInteger::new(3))
+            )]
+            .into()
+        )
+    );
+    test_parse!(
+        push,
+        b">2\r\n$10\r\ninvalidate\r\n_\r\n",
+        GFrame::Push(
+            [GFrame::BulkString(GString::from_static(b"invalidate")), GFrame::Null].into()
+        )
+    );
+
+    #[test]
+    fn incomplete_bulk_string() {
+        let mut parser = Parser::new();
+        parser.buf_mut().extend_from_slice(b"$5\r\nhel");
+
+        assert_eq!(parser.parse().unwrap(), None);
+
+        parser.buf_mut().extend_from_slice(b"lo\r\n");
+
+        assert_eq!(
+            parser.parse().unwrap(),
+            Some(GFrame::BulkString(GString::copy_from_slice(b"hello")))
+        );
+        assert!(parser.buf().is_empty());
+    }
 }
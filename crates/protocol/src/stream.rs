@@ -44,11 +44,44 @@ pub struct GFrameStream<I> {
     parser: Parser,
     tmp: [u8; 1024],
     write_buf: BytesMut,
+    bytes_read: u64,
+    bytes_written: u64,
 }
 
 impl<I> GFrameStream<I> {
     pub fn new(inner: I) -> Self {
-        Self { inner, parser: Parser::new(), tmp: [0u8; 1024], write_buf: BytesMut::new() }
+        Self {
+            inner,
+            parser: Parser::new(),
+            tmp: [0u8; 1024],
+            write_buf: BytesMut::new(),
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &I {
+        &self.inner
+    }
+
+    /// Bytes read from the inner stream so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Bytes written to the inner stream so far, not counting those still buffered.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Bytes read and not yet parsed into a frame.
+    pub fn read_buffered(&self) -> usize {
+        self.parser.buf().len()
+    }
+
+    /// Bytes of replies not yet written to the inner stream.
+    pub fn write_buffered(&self) -> usize {
+        self.write_buf.len()
     }
 }
 
@@ -76,6 +109,7 @@ where
                 }
             }
             Poll::Ready(Ok(n)) => {
+                me.bytes_read += n as u64;
                 me.parser.buf_mut().extend_from_slice(&me.tmp[..n]);
                 if let Some(frame) = me.parser.parse()? {
                     return Poll::Ready(Some(Ok(frame)));
@@ -104,19 +138,22 @@ where
         Ok(())
     }
 
-    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let mut buf = self.write_buf.split();
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let me = self.get_mut();
 
-        while !buf.is_empty() {
-            let n = match Pin::new(&mut self.inner).poll_write(cx, &buf) {
+        // Unwritten bytes stay in `write_buf` so a pending write resumes where it stopped.
+        while !me.write_buf.is_empty() {
+            let n = match Pin::new(&mut me.inner).poll_write(cx, &me.write_buf) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
                 Poll::Ready(Ok(n)) => n,
                 Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
                 Poll::Pending => return Poll::Pending,
             };
-            buf.advance(n);
+            me.write_buf.advance(n);
+            me.bytes_written += n as u64;
         }
 
-        Poll::Ready(Ok(()))
+        Pin::new(&mut me.inner).poll_flush(cx)
     }
 
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -0,0 +1,164 @@
+//! Throughput of multi-key DEL/EXISTS storage access, one request per key versus one batch per
+//! destination shard.
+
+use std::{
+    hint::black_box,
+    time::Duration,
+};
+
+use criterion::{
+    BatchSize,
+    BenchmarkId,
+    Criterion,
+    Throughput,
+    criterion_group,
+    criterion_main,
+};
+use futures::future::join_all;
+use glommio::{
+    LocalExecutor,
+    LocalExecutorBuilder,
+};
+use goosekv_protocol::data_type::GString;
+use goosekv_server::storage::{
+    actor::StorageActor,
+    mesh::{
+        DEFAULT_CHANNEL_CAPACITY,
+        StorageMesh,
+    },
+    request::{
+        DeleteRequest,
+        GetRequest,
+        Operation,
+        SetRequest,
+    },
+    router::StorageRouter,
+    value::{
+        Data,
+        Value,
+    },
+};
+
+const SHARDS: usize = 4;
+const KEYS: usize = 100;
+
+fn keys() -> Vec<GString> {
+    (0..KEYS).map(|i| GString::copy_from_slice(format!("key:{i}").as_bytes())).collect()
+}
+
+/// Start remote storage shards on their own executors and return a router for `executor`.
+fn router(executor: &LocalExecutor) -> StorageRouter {
+    let mesh = StorageMesh::new(SHARDS, DEFAULT_CHANNEL_CAPACITY);
+
+    for _ in 1..SHARDS {
+        let mesh = mesh.clone();
+        LocalExecutorBuilder::default()
+            .spawn(move || async move {
+                let (handle, lanes) = mesh.join().await;
+                let (_, task) =
+                    StorageActor::new(Default::default(), Default::default(), Default::default())
+                        .run(lanes, handle);
+                task.await;
+            })
+            .unwrap();
+    }
+
+    // Nothing sends requests to the bench executor, its storage is only accessed directly.
+    let (handle, lanes) = executor.run(mesh.join());
+    let (local_storage, _) =
+        StorageActor::new(Default::default(), Default::default(), Default::default())
+            .run(lanes, handle.clone());
+
+    StorageRouter::new(handle, local_storage)
+}
+
+fn populate(executor: &LocalExecutor, router: &StorageRouter, keys: &[GString]) {
+    executor.run(async {
+        let operations = keys
+            .iter()
+            .map(|key| {
+                Operation::Set(SetRequest {
+                    key: key.clone(),
+                    value: Value::new(Data::from_gstring(GString::from_static(b"value"))),
+                    client: None,
+                })
+            })
+            .collect();
+        router.batch(operations).await.unwrap();
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let executor = LocalExecutor::default();
+    let router = router(&executor);
+    let keys = keys();
+
+    let mut group = c.benchmark_group("multi_key");
+    group.throughput(Throughput::Elements(KEYS as u64));
+    group.measurement_time(Duration::from_secs(5));
+
+    group.bench_function(BenchmarkId::new("exists", "per_key"), |b| {
+        populate(&executor, &router, &keys);
+        b.iter(|| {
+            executor.run(async {
+                let tasks = keys.iter().map(|key| {
+                    router.get(GetRequest { key: key.clone(), touch: true, tracking: None })
+                });
+                black_box(join_all(tasks).await);
+            })
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("exists", "batched"), |b| {
+        populate(&executor, &router, &keys);
+        b.iter(|| {
+            executor.run(async {
+                let operations = keys
+                    .iter()
+                    .map(|key| {
+                        Operation::Get(GetRequest { key: key.clone(), touch: true, tracking: None })
+                    })
+                    .collect();
+                black_box(router.batch(operations).await.unwrap());
+            })
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("del", "per_key"), |b| {
+        b.iter_batched(
+            || populate(&executor, &router, &keys),
+            |_| {
+                executor.run(async {
+                    let tasks = keys
+                        .iter()
+                        .map(|key| router.delete(DeleteRequest { key: key.clone(), client: None }));
+                    black_box(join_all(tasks).await);
+                })
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.bench_function(BenchmarkId::new("del", "batched"), |b| {
+        b.iter_batched(
+            || populate(&executor, &router, &keys),
+            |_| {
+                executor.run(async {
+                    let operations = keys
+                        .iter()
+                        .map(|key| {
+                            Operation::Delete(DeleteRequest { key: key.clone(), client: None })
+                        })
+                        .collect();
+                    black_box(router.batch(operations).await.unwrap());
+                })
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
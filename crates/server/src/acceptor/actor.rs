@@ -1,30 +1,85 @@
 use std::net::SocketAddr;
 
+use futures::{
+    FutureExt,
+    future::join_all,
+};
 use glommio::net::TcpListener;
 use goosekv_protocol::stream::GFrameStream;
 use tracing::info;
 
-use crate::processor::{
-    command::ProcessCommand,
-    handle::ProcessorHandle,
+use crate::{
+    processor::{
+        command::{
+            MemcacheCommand,
+            MetricsCommand,
+            ProcessCommand,
+            TlsCommand,
+        },
+        handle::ProcessorHandle,
+    },
+    shutdown::Stopping,
+    tls::Connection,
 };
 
 pub struct AcceptorActor {
-    addr: SocketAddr,
+    addrs: Vec<SocketAddr>,
+    /// Addresses of the RESP listeners accepting TLS connections, if enabled.
+    tls_addrs: Vec<SocketAddr>,
+    /// Addresses of the listeners speaking the memcached protocol, if enabled.
+    memcache_addrs: Vec<SocketAddr>,
+    /// Addresses of the listeners serving Prometheus metrics over HTTP, if enabled.
+    metrics_addrs: Vec<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Protocol {
+    Resp,
+    Tls,
+    Memcache,
+    Metrics,
 }
 
 impl AcceptorActor {
-    pub fn new(addr: SocketAddr) -> Self {
-        Self { addr }
+    pub fn new(
+        addrs: Vec<SocketAddr>,
+        tls_addrs: Vec<SocketAddr>,
+        memcache_addrs: Vec<SocketAddr>,
+        metrics_addrs: Vec<SocketAddr>,
+    ) -> Self {
+        Self { addrs, tls_addrs, memcache_addrs, metrics_addrs }
     }
 
-    pub async fn run(self, mut handle: ProcessorHandle) {
-        let listener = TcpListener::bind(self.addr).unwrap();
+    /// Accept connections until `stopping`, the listeners are then closed.
+    pub async fn run(self, handle: ProcessorHandle, stopping: Stopping) {
+        let resp = self.addrs.into_iter().map(|addr| (addr, Protocol::Resp));
+        let tls = self.tls_addrs.into_iter().map(|addr| (addr, Protocol::Tls));
+        let memcache = self.memcache_addrs.into_iter().map(|addr| (addr, Protocol::Memcache));
+        let metrics = self.metrics_addrs.into_iter().map(|addr| (addr, Protocol::Metrics));
+        let accept = resp
+            .chain(tls)
+            .chain(memcache)
+            .chain(metrics)
+            .map(|(addr, protocol)| accept(addr, protocol, &handle));
+        stopping.until(join_all(accept).map(drop)).await;
+        info!("stopped accepting connections");
+    }
+}
+
+async fn accept(addr: SocketAddr, protocol: Protocol, handle: &ProcessorHandle) {
+    let listener = TcpListener::bind(addr).unwrap();
 
-        while let Ok(tcp_stream) = listener.accept().await {
-            info!("accepted");
-            let stream = GFrameStream::new(tcp_stream);
-            handle.process(ProcessCommand { stream }).await;
+    while let Ok(stream) = listener.accept().await {
+        info!(?protocol, "accepted");
+        match protocol {
+            Protocol::Resp => {
+                let stream = GFrameStream::new(Connection::Plain(stream));
+                handle.process(ProcessCommand { stream }).await
+            }
+            // The handshake is left to the processor, not to hold back other connections.
+            Protocol::Tls => handle.tls(TlsCommand { stream }).await,
+            Protocol::Memcache => handle.memcache(MemcacheCommand { stream }).await,
+            Protocol::Metrics => handle.metrics(MetricsCommand { stream }).await,
         }
     }
 }
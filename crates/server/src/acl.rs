@@ -0,0 +1,645 @@
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+        VecDeque,
+    },
+    fs,
+    io,
+    iter,
+    path::Path,
+    sync::{
+        Arc,
+        Mutex,
+        RwLock,
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use goosekv_protocol::command::table::{
+    AclCategory,
+    CommandSpec,
+    KeyAccess,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use thiserror::Error;
+
+use crate::{
+    config::write_atomically,
+    glob::glob_match,
+};
+
+/// User connections run as until they authenticate, it cannot be deleted.
+pub const DEFAULT_USER: &str = "default";
+
+/// Denials repeating within this many milliseconds are counted in the same log entry.
+const LOG_GROUPING_MS: u64 = 60_000;
+
+const UNKNOWN_COMMAND: &str = "Unknown command or category name in ACL";
+
+#[derive(Debug, Error)]
+pub enum AclError {
+    #[error("Error in ACL SETUSER modifier '{rule}': {message}")]
+    Rule { rule: String, message: &'static str },
+    #[error("line {line}: {error}")]
+    Line { line: usize, error: Box<AclError> },
+    #[error("{0}")]
+    Syntax(String),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+/// Key pattern of a user with the accesses it grants, `~` grants both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyPattern {
+    pattern: String,
+    read: bool,
+    write: bool,
+}
+
+impl KeyPattern {
+    fn rule(&self) -> String {
+        match (self.read, self.write) {
+            (true, true) => format!("~{}", self.pattern),
+            (true, false) => format!("%R~{}", self.pattern),
+            _ => format!("%W~{}", self.pattern),
+        }
+    }
+}
+
+/// User defined by `ACL SETUSER` or the ACL file.
+#[derive(Debug, Clone)]
+pub struct User {
+    name: String,
+    enabled: bool,
+    /// Any password is accepted.
+    nopass: bool,
+    /// SHA-256 digests of the passwords, in lowercase hex.
+    passwords: Vec<String>,
+    /// Commands allowed, by container and name like the command statistics.
+    commands: BTreeSet<(Option<&'static str>, &'static str)>,
+    /// Command rules applied since the last `+@all` or `-@all`, describing `commands`.
+    command_rules: Vec<String>,
+    keys: Vec<KeyPattern>,
+    /// Channel patterns, kept for clients although there is no Pub/Sub to apply them to.
+    channels: Vec<String>,
+}
+
+impl User {
+    /// Disabled user without password nor permission, as `ACL SETUSER` creates them.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled: false,
+            nopass: false,
+            passwords: Vec::new(),
+            commands: BTreeSet::new(),
+            command_rules: vec!["-@all".to_string()],
+            keys: Vec::new(),
+            channels: Vec::new(),
+        }
+    }
+
+    /// `default` user before any rule was applied to it, allowed everything without password.
+    pub fn default_user() -> Self {
+        let mut user = User::new(DEFAULT_USER);
+        for rule in ["on", "nopass", "~*", "&*", "+@all"] {
+            user.apply(rule).expect("default rules are valid");
+        }
+        user
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_nopass(&self) -> bool {
+        self.nopass
+    }
+
+    /// Digests of the passwords, as listed by `ACL GETUSER`.
+    pub fn passwords(&self) -> &[String] {
+        &self.passwords
+    }
+
+    /// Whether `password` authenticates the user, as long as it is enabled.
+    pub fn check_password(&self, password: &[u8]) -> bool {
+        self.enabled && (self.nopass || self.passwords.contains(&digest(password)))
+    }
+
+    pub fn can_run(&self, spec: &CommandSpec) -> bool {
+        self.commands.contains(&(spec.container, spec.name))
+    }
+
+    /// Whether a key pattern grants `access` to `key`.
+    pub fn can_access(&self, key: &[u8], access: KeyAccess) -> bool {
+        self.keys.iter().any(|pattern| {
+            (pattern.read || !access.reads())
+                && (pattern.write || !access.writes())
+                && glob_match(pattern.pattern.as_bytes(), key, false)
+        })
+    }
+
+    /// Apply one rule of `ACL SETUSER`, such as `on`, `>password`, `~key*` or `+@read`.
+    pub fn apply(&mut self, rule: &str) -> Result<(), AclError> {
+        let error = |message| AclError::Rule { rule: rule.to_string(), message };
+        match rule.to_ascii_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => {
+                self.keys = vec![KeyPattern { pattern: "*".into(), read: true, write: true }]
+            }
+            "resetkeys" => self.keys.clear(),
+            "allchannels" => self.channels = vec!["*".to_string()],
+            "resetchannels" => self.channels.clear(),
+            "allcommands" => self.allow_all(true),
+            "nocommands" => self.allow_all(false),
+            "reset" => *self = User::new(&self.name),
+            _ => {
+                let (first, rest) = rule.split_at(rule.chars().next().map_or(0, char::len_utf8));
+                match first {
+                    ">" => self.add_password(digest(rest.as_bytes())),
+                    "#" => self.add_password(parse_digest(rest).map_err(error)?),
+                    "<" => self.remove_password(&digest(rest.as_bytes())).map_err(error)?,
+                    "!" => {
+                        self.remove_password(&parse_digest(rest).map_err(error)?).map_err(error)?
+                    }
+                    "~" => self.add_key_pattern(rest, true, true).map_err(error)?,
+                    "%" => {
+                        let (flags, pattern) = rest.split_once('~').ok_or(error("Syntax error"))?;
+                        let flags = flags.to_ascii_uppercase();
+                        if flags.is_empty() || flags.chars().any(|flag| !matches!(flag, 'R' | 'W'))
+                        {
+                            return Err(error("Syntax error"));
+                        }
+                        let (read, write) = (flags.contains('R'), flags.contains('W'));
+                        self.add_key_pattern(pattern, read, write).map_err(error)?;
+                    }
+                    "&" => self.add_channel(rest).map_err(error)?,
+                    "+" | "-" => self.command_rule(first == "+", rest).map_err(error)?,
+                    _ => return Err(error("Syntax error")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_password(&mut self, digest: String) {
+        self.nopass = false;
+        if !self.passwords.contains(&digest) {
+            self.passwords.push(digest);
+        }
+    }
+
+    fn remove_password(&mut self, digest: &str) -> Result<(), &'static str> {
+        let index = self
+            .passwords
+            .iter()
+            .position(|password| password == digest)
+            .ok_or("The password you are trying to remove from the user does not exist")?;
+        self.passwords.remove(index);
+        Ok(())
+    }
+
+    fn add_key_pattern(
+        &mut self,
+        pattern: &str,
+        read: bool,
+        write: bool,
+    ) -> Result<(), &'static str> {
+        let pattern = KeyPattern { pattern: pattern.to_string(), read, write };
+        if self.keys.iter().any(|key| key.pattern == "*" && key.read && key.write) {
+            return Err(
+                "Adding a pattern after the * pattern (or the 'allkeys' flag) is not valid and \
+                 does not have any effect. Try 'resetkeys' to start with an empty list of patterns",
+            );
+        }
+        if !self.keys.contains(&pattern) {
+            self.keys.push(pattern);
+        }
+        Ok(())
+    }
+
+    fn add_channel(&mut self, pattern: &str) -> Result<(), &'static str> {
+        if self.channels.iter().any(|channel| channel == "*") {
+            return Err(
+                "Adding a pattern after the * pattern (or the 'allchannels' flag) is not valid \
+                 and does not have any effect. Try 'resetchannels' to start with an empty list of \
+                 channels",
+            );
+        }
+        if !self.channels.iter().any(|channel| channel == pattern) {
+            self.channels.push(pattern.to_string());
+        }
+        Ok(())
+    }
+
+    /// Allow or deny `name`: a command, a `command|subcommand` or an `@category`.
+    fn command_rule(&mut self, allow: bool, name: &str) -> Result<(), &'static str> {
+        let specs: Vec<&'static CommandSpec> = match name.strip_prefix('@') {
+            Some(category) if category.eq_ignore_ascii_case("all") => {
+                self.allow_all(allow);
+                return Ok(());
+            }
+            Some(category) => {
+                let category =
+                    AclCategory::from_name(category.as_bytes()).ok_or(UNKNOWN_COMMAND)?;
+                all_specs().filter(|spec| spec.has_acl_category(category)).collect()
+            }
+            None => {
+                let (command, subcommand) = match name.split_once('|') {
+                    Some((command, subcommand)) => (command, Some(subcommand)),
+                    None => (name, None),
+                };
+                let spec = CommandSpec::find(command.as_bytes()).ok_or(UNKNOWN_COMMAND)?;
+                match subcommand {
+                    Some(subcommand) => {
+                        vec![spec.find_subcommand(subcommand.as_bytes()).ok_or(UNKNOWN_COMMAND)?]
+                    }
+                    None => iter::once(spec).chain(spec.subcommands).collect(),
+                }
+            }
+        };
+
+        for spec in specs {
+            match allow {
+                true => self.commands.insert((spec.container, spec.name)),
+                false => self.commands.remove(&(spec.container, spec.name)),
+            };
+        }
+        let sign = if allow { '+' } else { '-' };
+        self.command_rules.push(format!("{sign}{}", name.to_ascii_lowercase()));
+        Ok(())
+    }
+
+    fn allow_all(&mut self, allow: bool) {
+        self.commands = match allow {
+            true => all_specs().map(|spec| (spec.container, spec.name)).collect(),
+            false => BTreeSet::new(),
+        };
+        self.command_rules = vec![if allow { "+@all" } else { "-@all" }.to_string()];
+    }
+
+    /// Rules allowing the commands of the user, `+@all -config` for instance.
+    pub fn command_rules(&self) -> String {
+        self.command_rules.join(" ")
+    }
+
+    /// Rules of the key patterns, `~*` or `%R~cache:*` for instance.
+    pub fn key_rules(&self) -> String {
+        self.keys.iter().map(KeyPattern::rule).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Rules of the channel patterns, `&*` for instance.
+    pub fn channel_rules(&self) -> String {
+        self.channels.iter().map(|channel| format!("&{channel}")).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Line describing the user in `ACL LIST` and the ACL file.
+    pub fn line(&self) -> String {
+        let mut rules = vec![format!("user {}", self.name)];
+        rules.push(if self.enabled { "on" } else { "off" }.to_string());
+        if self.nopass {
+            rules.push("nopass".to_string());
+        }
+        rules.extend(self.passwords.iter().map(|password| format!("#{password}")));
+        rules.extend(self.keys.iter().map(KeyPattern::rule));
+        match self.channels.is_empty() {
+            true => rules.push("resetchannels".to_string()),
+            false => rules.push(self.channel_rules()),
+        }
+        rules.push(self.command_rules());
+        rules.join(" ")
+    }
+}
+
+/// Commands and subcommands, containers included.
+pub fn all_specs() -> impl Iterator<Item = &'static CommandSpec> {
+    CommandSpec::all().iter().flat_map(|spec| iter::once(spec).chain(spec.subcommands))
+}
+
+fn digest(password: &[u8]) -> String {
+    Sha256::digest(password).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn parse_digest(digest: &str) -> Result<String, &'static str> {
+    match digest.len() == 64 && digest.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        true => Ok(digest.to_string()),
+        false => Err("The password hash must be exactly 64 characters and contain only lowercase \
+             hexadecimal characters"),
+    }
+}
+
+/// Why a denial was logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DenialReason {
+    /// Wrong password, or disabled user.
+    Auth,
+    Command,
+    Key,
+}
+
+impl DenialReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DenialReason::Auth => "auth",
+            DenialReason::Command => "command",
+            DenialReason::Key => "key",
+        }
+    }
+}
+
+/// Denial listed by `ACL LOG`, counting the same denials repeated shortly after it.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub count: u64,
+    pub reason: DenialReason,
+    /// Command or key denied, `AUTH` for authentication failures.
+    pub object: String,
+    pub username: String,
+    /// Client line of the last connection denied, as in `CLIENT LIST`.
+    pub client_info: String,
+    pub entry_id: u64,
+    /// Milliseconds since the Unix epoch.
+    pub created: u64,
+    pub updated: u64,
+}
+
+impl LogEntry {
+    /// Seconds since the entry was created.
+    pub fn age(&self) -> f64 {
+        now_millis().saturating_sub(self.created) as f64 / 1000.0
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[derive(Debug, Default)]
+struct AclLog {
+    /// Newest first.
+    entries: VecDeque<LogEntry>,
+    next_id: u64,
+}
+
+/// Users shared by every shard, with the denials they ran into.
+#[derive(Debug)]
+pub struct Acl {
+    users: RwLock<BTreeMap<String, Arc<User>>>,
+    /// Bumped by every change to the users, connections reload their user when it moved.
+    epoch: AtomicU64,
+    log: Mutex<AclLog>,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        let users = BTreeMap::from([(DEFAULT_USER.to_string(), Arc::new(User::default_user()))]);
+        Self { users: RwLock::new(users), epoch: AtomicU64::new(0), log: Default::default() }
+    }
+}
+
+impl Acl {
+    /// Users of the ACL file at `path`, the `default` user keeps its defaults if not defined.
+    pub fn from_file(path: &Path) -> Result<Self, AclError> {
+        let acl = Acl::default();
+        acl.load(path)?;
+        Ok(acl)
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::Acquire)
+    }
+
+    pub fn user(&self, name: &str) -> Option<Arc<User>> {
+        self.users.read().unwrap().get(name).cloned()
+    }
+
+    /// Every user, ordered by name.
+    pub fn users(&self) -> Vec<Arc<User>> {
+        self.users.read().unwrap().values().cloned().collect()
+    }
+
+    /// Whether connections must authenticate before running commands, as the `default` user
+    /// requires a password or is disabled.
+    pub fn auth_required(&self) -> bool {
+        self.user(DEFAULT_USER).is_none_or(|user| !user.nopass || !user.enabled)
+    }
+
+    /// User `name` if it is enabled and `password` is one of its passwords.
+    pub fn authenticate(&self, name: &str, password: &[u8]) -> Option<Arc<User>> {
+        self.user(name).filter(|user| user.check_password(password))
+    }
+
+    /// Create user `name` or modify it, applying every rule or none of them.
+    pub fn set_user<'a>(
+        &self,
+        name: &str,
+        rules: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), AclError> {
+        let mut users = self.users.write().unwrap();
+        let mut user = users.get(name).map_or_else(|| User::new(name), |user| (**user).clone());
+        for rule in rules {
+            user.apply(rule)?;
+        }
+        users.insert(name.to_string(), Arc::new(user));
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Delete the users named, returning those that existed. The `default` user is kept.
+    pub fn delete_users(&self, names: &[String]) -> Vec<String> {
+        let mut users = self.users.write().unwrap();
+        let deleted: Vec<_> = names
+            .iter()
+            .filter(|name| *name != DEFAULT_USER && users.remove(*name).is_some())
+            .cloned()
+            .collect();
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+        deleted
+    }
+
+    /// Replace every user with those of the ACL file at `path`, returning the names of the users
+    /// removed. Nothing changes if the file is invalid.
+    pub fn load(&self, path: &Path) -> Result<Vec<String>, AclError> {
+        let loaded = parse(&fs::read_to_string(path)?)?;
+        let mut users = self.users.write().unwrap();
+        let removed = users.keys().filter(|name| !loaded.contains_key(*name)).cloned().collect();
+        *users = loaded;
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+        Ok(removed)
+    }
+
+    /// Write every user to the ACL file at `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let lines: String = self.users().iter().map(|user| user.line() + "\n").collect();
+        write_atomically(path, lines)
+    }
+
+    /// Log a denial, keeping at most `max_len` entries.
+    pub fn record_denial(
+        &self,
+        reason: DenialReason,
+        object: String,
+        username: &str,
+        client_info: String,
+        max_len: usize,
+    ) {
+        let now = now_millis();
+        let mut log = self.log.lock().unwrap();
+        let repeated = log.entries.iter().position(|entry| {
+            entry.reason == reason
+                && entry.object == object
+                && entry.username == username
+                && now.saturating_sub(entry.updated) < LOG_GROUPING_MS
+        });
+        let entry = match repeated.and_then(|index| log.entries.remove(index)) {
+            Some(entry) => LogEntry { count: entry.count + 1, client_info, updated: now, ..entry },
+            None => {
+                log.next_id += 1;
+                LogEntry {
+                    count: 1,
+                    reason,
+                    object,
+                    username: username.to_string(),
+                    client_info,
+                    entry_id: log.next_id - 1,
+                    created: now,
+                    updated: now,
+                }
+            }
+        };
+        log.entries.push_front(entry);
+        log.entries.truncate(max_len);
+    }
+
+    /// Newest `count` entries of the log.
+    pub fn log(&self, count: usize) -> Vec<LogEntry> {
+        self.log.lock().unwrap().entries.iter().take(count).cloned().collect()
+    }
+
+    pub fn reset_log(&self) {
+        self.log.lock().unwrap().entries.clear();
+    }
+}
+
+/// Users of an ACL file, one `user <name> [rule...]` line each.
+fn parse(text: &str) -> Result<BTreeMap<String, Arc<User>>, AclError> {
+    let mut users = BTreeMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_error = |error| AclError::Line { line: index + 1, error: Box::new(error) };
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        if keyword != "user" {
+            return Err(line_error(AclError::Syntax("should start with user keyword".into())));
+        }
+        let name =
+            tokens.next().ok_or_else(|| line_error(AclError::Syntax("missing user".into())))?;
+        if users.contains_key(name) {
+            return Err(line_error(AclError::Syntax(format!("Duplicate user '{name}' found"))));
+        }
+        let mut user = User::new(name);
+        for rule in tokens {
+            user.apply(rule).map_err(line_error)?;
+        }
+        users.insert(name.to_string(), Arc::new(user));
+    }
+    users.entry(DEFAULT_USER.to_string()).or_insert_with(|| Arc::new(User::default_user()));
+    Ok(users)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user(rules: &[&str]) -> User {
+        let mut user = User::new("alice");
+        for rule in rules {
+            user.apply(rule).unwrap();
+        }
+        user
+    }
+
+    #[test]
+    fn applies_rules() {
+        let user = user(&["on", ">secret", "%R~cache:*", "~app:*", "+@read", "-exists", "+set"]);
+        assert!(user.check_password(b"secret") && !user.check_password(b"wrong"));
+
+        let spec = |name: &[u8]| CommandSpec::find(name).unwrap();
+        assert!(user.can_run(spec(b"GET")) && user.can_run(spec(b"SET")));
+        assert!(!user.can_run(spec(b"EXISTS")) && !user.can_run(spec(b"DEL")));
+
+        assert!(user.can_access(b"cache:1", KeyAccess::Read));
+        assert!(!user.can_access(b"cache:1", KeyAccess::ReadWrite));
+        assert!(user.can_access(b"app:1", KeyAccess::Write));
+        assert!(!user.can_access(b"other", KeyAccess::Read));
+
+        assert_eq!(
+            user.line(),
+            format!(
+                "user alice on #{} %R~cache:* ~app:* resetchannels -@all +@read -exists +set",
+                digest(b"secret")
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let mut user = user(&[]);
+        assert!(user.apply("+nosuchcommand").is_err());
+        assert!(user.apply("%X~key").is_err());
+        assert!(user.apply("#abc").is_err());
+        assert!(user.apply("<never-added").is_err());
+        assert!(user.apply("allkeys").is_ok() && user.apply("~more").is_err());
+    }
+
+    #[test]
+    fn parses_files() {
+        let users = parse("user alice on nopass ~* +@all\n\nuser bob off\n").unwrap();
+        assert_eq!(users.keys().collect::<Vec<_>>(), ["alice", "bob", "default"]);
+        assert!(users["alice"].check_password(b"anything"));
+
+        assert!(parse("user alice on\nuser alice off\n").is_err());
+        assert!(parse("alice on\n").is_err());
+    }
+
+    #[test]
+    fn groups_log_entries() {
+        let acl = Acl::default();
+        acl.record_denial(DenialReason::Command, "get".into(), "alice", String::new(), 2);
+        acl.record_denial(DenialReason::Command, "get".into(), "alice", String::new(), 2);
+        acl.record_denial(DenialReason::Key, "k".into(), "alice", String::new(), 2);
+        acl.record_denial(DenialReason::Auth, "AUTH".into(), "bob", String::new(), 2);
+
+        let log = acl.log(10);
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].reason, log[1].reason), (DenialReason::Auth, DenialReason::Key));
+        acl.record_denial(DenialReason::Key, "k".into(), "alice", String::new(), 2);
+        assert_eq!(acl.log(1)[0].count, 2);
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    fs,
+    io,
+    net::SocketAddr,
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use thiserror::Error;
+
+use crate::slot::SLOT_COUNT;
+
+/// Static membership of several goosekv processes serving one keyspace, loaded from a nodes
+/// file.
+#[derive(Debug)]
+pub struct Cluster {
+    nodes: Vec<ClusterNode>,
+    myself: usize,
+    /// Index of the primary serving each slot.
+    owners: Box<[Option<u16>]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClusterNode {
+    pub id: String,
+    pub addr: SocketAddr,
+    /// Id of the primary this node replicates, `None` for primaries.
+    pub primary: Option<String>,
+    pub slots: Vec<RangeInclusive<u16>>,
+    /// Slots being moved from this node to the node with the given id.
+    pub migrating: Vec<(u16, String)>,
+    /// Slots being moved to this node from the node with the given id.
+    pub importing: Vec<(u16, String)>,
+}
+
+impl ClusterNode {
+    pub fn is_primary(&self) -> bool {
+        self.primary.is_none()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ClusterConfigError {
+    #[error("failed to read nodes file: {0}")]
+    Io(#[from] io::Error),
+    #[error("line {line}: {message}")]
+    Invalid { line: usize, message: String },
+    #[error("{0}")]
+    Inconsistent(String),
+    #[error("no node in the nodes file listens on {0}")]
+    Myself(SocketAddr),
+}
+
+impl Cluster {
+    pub fn load(path: impl AsRef<Path>, myself: SocketAddr) -> Result<Self, ClusterConfigError> {
+        Self::parse(&fs::read_to_string(path)?, myself)
+    }
+
+    /// Parse a nodes file, `myself` is the address this process listens on.
+    ///
+    /// Every non-empty line not starting with `#` describes one node:
+    ///
+    /// ```text
+    /// <id> <ip:port> master - <slot>|<first>-<last>|[<slot>->-<id>]|[<slot>-<-<id>] ...
+    /// <id> <ip:port> replica <primary-id>
+    /// ```
+    ///
+    /// `[<slot>->-<id>]` marks a slot being migrated to another node and `[<slot>-<-<id>]` a slot
+    /// being imported from it, the same notation `CLUSTER NODES` uses.
+    pub fn parse(text: &str, myself: SocketAddr) -> Result<Self, ClusterConfigError> {
+        let nodes = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                parse_node(line)
+                    .map_err(|message| ClusterConfigError::Invalid { line: index + 1, message })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let myself = nodes
+            .iter()
+            .position(|node| same_addr(node.addr, myself))
+            .ok_or(ClusterConfigError::Myself(myself))?;
+
+        let mut owners: Box<[Option<u16>]> = vec![None; SLOT_COUNT].into();
+        for (index, node) in nodes.iter().enumerate() {
+            let known = |id: &String| nodes.iter().any(|node| &node.id == id);
+            let unknown = node
+                .primary
+                .iter()
+                .chain(node.migrating.iter().chain(&node.importing).map(|(_, id)| id))
+                .find(|id| !known(id));
+            if let Some(id) = unknown {
+                return Err(ClusterConfigError::Inconsistent(format!(
+                    "node {} refers to unknown node {id}",
+                    node.id
+                )));
+            }
+
+            for slot in node.slots.iter().cloned().flatten() {
+                if let Some(owner) = owners[slot as usize].replace(index as u16) {
+                    return Err(ClusterConfigError::Inconsistent(format!(
+                        "slot {slot} is served by both {} and {}",
+                        nodes[owner as usize].id, node.id
+                    )));
+                }
+            }
+        }
+
+        Ok(Self { nodes, myself, owners })
+    }
+
+    pub fn nodes(&self) -> &[ClusterNode] {
+        &self.nodes
+    }
+
+    pub fn myself(&self) -> &ClusterNode {
+        &self.nodes[self.myself]
+    }
+
+    pub fn node(&self, id: &str) -> Option<&ClusterNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Primary serving `slot`, `None` if no node does.
+    pub fn owner(&self, slot: u16) -> Option<&ClusterNode> {
+        self.owners[slot as usize].map(|index| &self.nodes[index as usize])
+    }
+
+    pub fn replicas<'a>(
+        &'a self,
+        primary: &'a ClusterNode,
+    ) -> impl Iterator<Item = &'a ClusterNode> {
+        self.nodes.iter().filter(|node| node.primary.as_ref() == Some(&primary.id))
+    }
+
+    /// Node `slot` is being migrated to from this node.
+    pub fn migrating(&self, slot: u16) -> Option<&ClusterNode> {
+        find_transfer(&self.myself().migrating, slot).and_then(|id| self.node(id))
+    }
+
+    /// Node `slot` is being imported from into this node.
+    pub fn importing(&self, slot: u16) -> Option<&ClusterNode> {
+        find_transfer(&self.myself().importing, slot).and_then(|id| self.node(id))
+    }
+
+    pub fn assigned_slots(&self) -> usize {
+        self.owners.iter().filter(|owner| owner.is_some()).count()
+    }
+}
+
+fn find_transfer(transfers: &[(u16, String)], slot: u16) -> Option<&str> {
+    transfers.iter().find(|(transferred, _)| *transferred == slot).map(|(_, id)| id.as_str())
+}
+
+fn same_addr(node: SocketAddr, myself: SocketAddr) -> bool {
+    node == myself || (myself.ip().is_unspecified() && node.port() == myself.port())
+}
+
+fn parse_node(line: &str) -> Result<ClusterNode, String> {
+    let mut tokens = line.split_whitespace();
+    let mut next = |what: &str| tokens.next().ok_or_else(|| format!("missing {what}"));
+
+    let id = next("node id")?.to_string();
+    let addr = next("address")?;
+    let addr = addr.parse().map_err(|_| format!("invalid address {addr}"))?;
+    let role = next("role")?;
+    let primary = next("primary id")?;
+
+    let mut node = ClusterNode {
+        id,
+        addr,
+        primary: None,
+        slots: Vec::new(),
+        migrating: Vec::new(),
+        importing: Vec::new(),
+    };
+
+    match (role, primary) {
+        ("master", "-") => {}
+        ("replica", "-") => return Err("replica without a primary id".to_string()),
+        ("replica", primary) => node.primary = Some(primary.to_string()),
+        ("master", _) => return Err("master with a primary id".to_string()),
+        (role, _) => return Err(format!("invalid role {role}")),
+    }
+
+    for token in tokens {
+        if node.primary.is_some() {
+            return Err("replica serving slots".to_string());
+        }
+
+        if let Some(transfer) = token.strip_prefix('[').and_then(|token| token.strip_suffix(']')) {
+            if let Some((slot, id)) = transfer.split_once("->-") {
+                node.migrating.push((parse_slot(slot)?, id.to_string()));
+            } else if let Some((slot, id)) = transfer.split_once("-<-") {
+                node.importing.push((parse_slot(slot)?, id.to_string()));
+            } else {
+                return Err(format!("invalid slot transfer {token}"));
+            }
+        } else if let Some((first, last)) = token.split_once('-') {
+            let (first, last) = (parse_slot(first)?, parse_slot(last)?);
+            if first > last {
+                return Err(format!("invalid slot range {token}"));
+            }
+            node.slots.push(first..=last);
+        } else {
+            let slot = parse_slot(token)?;
+            node.slots.push(slot..=slot);
+        }
+    }
+
+    Ok(node)
+}
+
+fn parse_slot(slot: &str) -> Result<u16, String> {
+    slot.parse()
+        .ok()
+        .filter(|&slot| (slot as usize) < SLOT_COUNT)
+        .ok_or(format!("invalid slot {slot}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NODES: &str = "
+        # id addr role primary slots
+        a 127.0.0.1:7000 master - 0-8000 [8000->-b]
+        b 127.0.0.1:7001 master - 8001-16383 [8000-<-a]
+        c 127.0.0.1:7002 replica a
+    ";
+
+    #[test]
+    fn parse() {
+        let cluster = Cluster::parse(NODES, "127.0.0.1:7001".parse().unwrap()).unwrap();
+
+        assert_eq!(cluster.myself().id, "b");
+        assert_eq!(cluster.owner(0).unwrap().id, "a");
+        assert_eq!(cluster.owner(16383).unwrap().id, "b");
+        assert_eq!(cluster.importing(8000).unwrap().id, "a");
+        assert!(cluster.migrating(8000).is_none());
+        assert_eq!(cluster.replicas(cluster.node("a").unwrap()).count(), 1);
+        assert_eq!(cluster.assigned_slots(), SLOT_COUNT);
+    }
+
+    #[test]
+    fn invalid() {
+        let myself = "127.0.0.1:7000".parse().unwrap();
+        assert!(Cluster::parse("a 127.0.0.1:7000 master - 0-16384", myself).is_err());
+        assert!(Cluster::parse("a 127.0.0.1:7000 replica x", myself).is_err());
+        assert!(Cluster::parse("a 127.0.0.1:7001 master - 0", myself).is_err());
+        assert!(
+            Cluster::parse("a 127.0.0.1:7000 master - 0-10\nb 127.0.0.1:7001 master - 10", myself)
+                .is_err()
+        );
+    }
+}
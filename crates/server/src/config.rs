@@ -0,0 +1,722 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        self,
+        Write,
+    },
+    net::{
+        IpAddr,
+        Ipv4Addr,
+        SocketAddr,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    rc::Rc,
+    sync::{
+        Arc,
+        Mutex,
+        RwLock,
+        RwLockReadGuard,
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+    },
+};
+
+use thiserror::Error;
+
+use crate::{
+    glob::glob_match,
+    processor,
+    replication::backlog::DEFAULT_BACKLOG_SIZE,
+    storage::{
+        self,
+        memory::EvictionPolicy,
+    },
+    tls::{
+        CertificateUser,
+        TlsAuthClients,
+    },
+};
+
+/// Settings of the server, loaded from a redis.conf style file and command-line flags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub bind: Vec<IpAddr>,
+    pub port: u16,
+    /// Port of the memcached listener, `0` disables it.
+    pub memcache_port: u16,
+    /// Port of the HTTP listener serving Prometheus metrics, `0` disables it.
+    pub metrics_port: u16,
+    /// Port of the RESP listener accepting TLS connections, `0` disables it.
+    pub tls_port: u16,
+    /// PEM files of the certificate chain and private key of the server.
+    pub tls_cert_file: String,
+    pub tls_key_file: String,
+    /// PEM file of the certificates authorities client certificates are verified with.
+    pub tls_ca_cert_file: String,
+    pub tls_auth_clients: TlsAuthClients,
+    /// Field of client certificates naming the ACL user their clients are authenticated as.
+    pub tls_auth_clients_user: CertificateUser,
+    /// Number of shards to start, `0` starts one per CPU.
+    pub shards: usize,
+    /// Pin the executor of each shard to its own CPU.
+    pub cpu_pinning: bool,
+    pub maxclients: usize,
+    /// Seconds after which idle clients are disconnected, `0` never does.
+    pub timeout: u64,
+    /// Seconds a shutdown waits for replicas to catch up and for clients to drain.
+    pub shutdown_timeout: u64,
+    pub repl_backlog_size: ByteSize,
+    /// Memory the keys may use, split evenly between shards, `0` for no limit.
+    pub maxmemory: ByteSize,
+    pub maxmemory_policy: EvictionPolicy,
+    /// Keys sampled to pick each key to evict.
+    pub maxmemory_samples: usize,
+    /// Microseconds after which a command is logged to the slow log, negative disables it.
+    pub slowlog_log_slower_than: i64,
+    /// Entries kept in the slow log of each shard.
+    pub slowlog_max_len: usize,
+    /// Milliseconds from which events are sampled by the latency monitor, `0` disables it.
+    pub latency_monitor_threshold: u64,
+    /// Keys remembered for the clients tracking the keys they read, split evenly between shards,
+    /// `0` for no limit.
+    pub tracking_table_max_keys: usize,
+    pub processor_channel_capacity: usize,
+    pub storage_channel_capacity: usize,
+    /// Nodes file of the cluster, empty outside cluster mode.
+    pub cluster_config_file: String,
+    /// File the users are loaded from on start and by `ACL LOAD`, saved to by `ACL SAVE`.
+    pub aclfile: String,
+    /// Entries kept in the log of denials listed by `ACL LOG`.
+    pub acllog_max_len: usize,
+    /// User and password a replica authenticates with to its primary, no `AUTH` if empty.
+    pub masteruser: String,
+    pub masterauth: String,
+    // Persistence settings, a snapshot is only written on shutdown.
+    pub save: String,
+    pub dir: String,
+    pub dbfilename: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            port: 6379,
+            memcache_port: 0,
+            metrics_port: 0,
+            tls_port: 0,
+            tls_cert_file: String::new(),
+            tls_key_file: String::new(),
+            tls_ca_cert_file: String::new(),
+            tls_auth_clients: TlsAuthClients::Yes,
+            tls_auth_clients_user: CertificateUser::Off,
+            shards: 0,
+            cpu_pinning: false,
+            maxclients: 10000,
+            timeout: 0,
+            shutdown_timeout: 10,
+            repl_backlog_size: ByteSize(DEFAULT_BACKLOG_SIZE as u64),
+            maxmemory: ByteSize(0),
+            maxmemory_policy: EvictionPolicy::NoEviction,
+            maxmemory_samples: 5,
+            slowlog_log_slower_than: 10_000,
+            slowlog_max_len: 128,
+            latency_monitor_threshold: 0,
+            tracking_table_max_keys: 1_000_000,
+            processor_channel_capacity: processor::actor::DEFAULT_CHANNEL_CAPACITY,
+            storage_channel_capacity: storage::mesh::DEFAULT_CHANNEL_CAPACITY,
+            cluster_config_file: String::new(),
+            aclfile: String::new(),
+            acllog_max_len: 128,
+            masteruser: String::new(),
+            masterauth: String::new(),
+            save: String::new(),
+            dir: ".".into(),
+            dbfilename: "dump.resp".into(),
+        }
+    }
+}
+
+impl Config {
+    /// Addresses the RESP listener binds.
+    pub fn addrs(&self) -> Vec<SocketAddr> {
+        self.bind.iter().map(|ip| SocketAddr::new(*ip, self.port)).collect()
+    }
+
+    /// Addresses the memcached listener binds, none if it is disabled.
+    pub fn memcache_addrs(&self) -> Vec<SocketAddr> {
+        match self.memcache_port {
+            0 => Vec::new(),
+            port => self.bind.iter().map(|ip| SocketAddr::new(*ip, port)).collect(),
+        }
+    }
+
+    /// Addresses the TLS listener binds, none if it is disabled.
+    pub fn tls_addrs(&self) -> Vec<SocketAddr> {
+        match self.tls_port {
+            0 => Vec::new(),
+            port => self.bind.iter().map(|ip| SocketAddr::new(*ip, port)).collect(),
+        }
+    }
+
+    /// Addresses the metrics listener binds, none if it is disabled.
+    pub fn metrics_addrs(&self) -> Vec<SocketAddr> {
+        match self.metrics_port {
+            0 => Vec::new(),
+            port => self.bind.iter().map(|ip| SocketAddr::new(*ip, port)).collect(),
+        }
+    }
+
+    /// Apply the directives of a config file, one `name value...` per line.
+    pub fn apply_file(&mut self, text: &str) -> Result<(), ConfigError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |error| ConfigError::Line { line: index + 1, error: Box::new(error) };
+            let tokens = split_line(line).map_err(|message| error(ConfigError::Syntax(message)))?;
+            if let Some((name, values)) = tokens.split_first() {
+                self.set(name, &values.join(" ")).map_err(error)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the parameter `name` from its textual `value`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        let parameter = Parameter::find(name)?;
+        (parameter.set)(self, value)
+            .map_err(|message| ConfigError::Invalid { name: parameter.name.to_string(), message })
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, ConfigError> {
+        Ok((Parameter::find(name)?.get)(self))
+    }
+}
+
+/// Configuration shared by every shard, parameters set at runtime are seen by all of them.
+#[derive(Debug, Default)]
+pub struct SharedConfig {
+    config: RwLock<Config>,
+    /// File the configuration was loaded from, rewritten by `CONFIG REWRITE`.
+    file: Option<PathBuf>,
+    /// Bumped by `CONFIG RESETSTAT`, shards reset their statistics when they see it change.
+    stats_epoch: AtomicU64,
+    /// Bumped by `CONFIG SET`, shards refresh their [`LocalConfig`] when they see it change.
+    version: AtomicU64,
+    /// Held by `CONFIG SET` from reading the configuration to applying the update.
+    setting: Mutex<()>,
+}
+
+impl SharedConfig {
+    pub fn new(config: Config, file: Option<PathBuf>) -> Self {
+        Self {
+            config: RwLock::new(config),
+            file,
+            stats_epoch: AtomicU64::new(0),
+            version: AtomicU64::new(0),
+            setting: Mutex::new(()),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Config> {
+        self.config.read().unwrap()
+    }
+
+    /// File the configuration was loaded from, if any.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Names and values of the parameters matching the glob `pattern`.
+    pub fn matching(&self, pattern: &[u8]) -> Vec<(&'static str, String)> {
+        let config = self.read();
+        PARAMETERS
+            .iter()
+            .filter(|parameter| glob_match(pattern, parameter.name.as_bytes(), true))
+            .map(|parameter| (parameter.name, (parameter.get)(&config)))
+            .collect()
+    }
+
+    /// Set runtime-mutable parameters, either all of them are applied or none is.
+    pub fn set(&self, parameters: &[(String, String)]) -> Result<(), ConfigError> {
+        self.set_with(parameters, |_| Ok(()), |()| ())
+    }
+
+    /// Set runtime-mutable parameters like [`SharedConfig::set`], once `prepare` accepted the
+    /// updated configuration.
+    ///
+    /// `prepare` runs without blocking readers of the configuration, what it returns is passed to
+    /// `commit` right after the update. Concurrent updates are applied one after the other.
+    pub fn set_with<T>(
+        &self,
+        parameters: &[(String, String)],
+        prepare: impl FnOnce(&Config) -> Result<T, String>,
+        commit: impl FnOnce(T),
+    ) -> Result<(), ConfigError> {
+        let _setting = self.setting.lock().unwrap();
+        let mut updated = self.read().clone();
+        for (name, value) in parameters {
+            let parameter = Parameter::find(name)?;
+            if !parameter.mutable {
+                return Err(ConfigError::Immutable(parameter.name.to_string()));
+            }
+            updated.set(name, value)?;
+        }
+
+        let prepared = prepare(&updated).map_err(ConfigError::Rejected)?;
+        *self.config.write().unwrap() = updated;
+        self.version.fetch_add(1, Ordering::AcqRel);
+        commit(prepared);
+        Ok(())
+    }
+
+    /// Write the current configuration to the file it was loaded from.
+    ///
+    /// Lines of known parameters are replaced in place, comments and unknown lines are kept and
+    /// parameters differing from their default are appended.
+    pub fn rewrite(&self) -> Result<(), ConfigError> {
+        let path = self.file.as_deref().ok_or(ConfigError::NoFile)?;
+        let text = match fs::read_to_string(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            text => text?,
+        };
+
+        let config = self.read();
+        let mut written = HashSet::new();
+        let mut lines = Vec::new();
+        for line in text.lines() {
+            let name = split_line(line).ok().and_then(|tokens| tokens.into_iter().next());
+            match name.and_then(|name| Parameter::find(&name).ok()) {
+                Some(parameter) if written.insert(parameter.name) => {
+                    lines.push(parameter.directive(&config))
+                }
+                Some(_) => {}
+                None => lines.push(line.to_string()),
+            }
+        }
+
+        let default = Config::default();
+        for parameter in PARAMETERS {
+            if !written.contains(parameter.name)
+                && (parameter.get)(&config) != (parameter.get)(&default)
+            {
+                lines.push(parameter.directive(&config));
+            }
+        }
+
+        write_atomically(path, &(lines.join("\n") + "\n"))?;
+        Ok(())
+    }
+
+    pub fn reset_stats(&self) {
+        self.stats_epoch.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn stats_epoch(&self) -> u64 {
+        self.stats_epoch.load(Ordering::Acquire)
+    }
+}
+
+/// Copy of the [`SharedConfig`] kept by one shard, read without taking its lock.
+///
+/// The copy is refreshed by the first read after `CONFIG SET` changed the configuration.
+#[derive(Debug, Default)]
+pub struct LocalConfig {
+    shared: Arc<SharedConfig>,
+    /// Version of the shared configuration the copy was taken at.
+    cached: RefCell<(u64, Rc<Config>)>,
+}
+
+impl LocalConfig {
+    pub fn new(shared: Arc<SharedConfig>) -> Self {
+        let config = Self { shared, cached: Default::default() };
+        config.refresh();
+        config
+    }
+
+    pub fn read(&self) -> Rc<Config> {
+        let version = self.shared.version.load(Ordering::Acquire);
+        if self.cached.borrow().0 != version {
+            self.refresh();
+        }
+        self.cached.borrow().1.clone()
+    }
+
+    /// Configuration of every shard, to update it or for the settings not cached.
+    pub fn shared(&self) -> &Arc<SharedConfig> {
+        &self.shared
+    }
+
+    fn refresh(&self) {
+        // Read before the copy, an update in between is picked up by the next read.
+        let version = self.shared.version.load(Ordering::Acquire);
+        *self.cached.borrow_mut() = (version, Rc::new(self.shared.read().clone()));
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unknown option '{0}'")]
+    Unknown(String),
+    #[error("can't set immutable config '{0}'")]
+    Immutable(String),
+    #[error("argument '{name}' is invalid: {message}")]
+    Invalid { name: String, message: String },
+    #[error("{0}")]
+    Syntax(String),
+    #[error("line {line}: {error}")]
+    Line { line: usize, error: Box<ConfigError> },
+    /// The updated configuration could not be applied.
+    #[error("{0}")]
+    Rejected(String),
+    #[error("The server is running without a config file")]
+    NoFile,
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+/// Amount of memory, written in bytes or with a `k`, `kb`, `m`, `mb`, `g` or `gb` suffix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteSize(pub u64);
+
+/// Value of a parameter as written in the config file and exchanged with `CONFIG`.
+trait ConfigValue: Sized {
+    fn parse(value: &str) -> Result<Self, String>;
+    fn render(&self) -> String;
+}
+
+macro_rules! integer_value {
+    ($($type:ty),*) => {
+        $(impl ConfigValue for $type {
+            fn parse(value: &str) -> Result<Self, String> {
+                value.parse().map_err(|_| "argument couldn't be parsed into an integer".to_string())
+            }
+
+            fn render(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+integer_value!(u16, u64, i64, usize);
+
+impl ConfigValue for bool {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "yes" => Ok(true),
+            "no" => Ok(false),
+            _ => Err("argument must be 'yes' or 'no'".into()),
+        }
+    }
+
+    fn render(&self) -> String {
+        if *self { "yes" } else { "no" }.into()
+    }
+}
+
+impl ConfigValue for String {
+    fn parse(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+
+    fn render(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ConfigValue for EvictionPolicy {
+    fn parse(value: &str) -> Result<Self, String> {
+        EvictionPolicy::from_name(value).ok_or_else(|| format!("invalid policy {value}"))
+    }
+
+    fn render(&self) -> String {
+        self.as_str().into()
+    }
+}
+
+impl ConfigValue for TlsAuthClients {
+    fn parse(value: &str) -> Result<Self, String> {
+        TlsAuthClients::from_name(value)
+            .ok_or_else(|| "argument must be 'yes', 'no' or 'optional'".into())
+    }
+
+    fn render(&self) -> String {
+        self.as_str().into()
+    }
+}
+
+impl ConfigValue for CertificateUser {
+    fn parse(value: &str) -> Result<Self, String> {
+        CertificateUser::from_name(value).ok_or_else(|| "argument must be 'off' or 'CN'".into())
+    }
+
+    fn render(&self) -> String {
+        self.as_str().into()
+    }
+}
+
+impl ConfigValue for Vec<IpAddr> {
+    fn parse(value: &str) -> Result<Self, String> {
+        let addrs = value
+            .split_whitespace()
+            .map(|addr| addr.parse().map_err(|_| format!("invalid address {addr}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        if addrs.is_empty() {
+            return Err("at least one address is required".into());
+        }
+        Ok(addrs)
+    }
+
+    fn render(&self) -> String {
+        self.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl ConfigValue for ByteSize {
+    fn parse(value: &str) -> Result<Self, String> {
+        let lower = value.to_ascii_lowercase();
+        let digits = lower.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let unit = match &lower[digits.len()..] {
+            "" | "b" => 1,
+            "k" => 1000,
+            "kb" => 1 << 10,
+            "m" => 1000 * 1000,
+            "mb" => 1 << 20,
+            "g" => 1000 * 1000 * 1000,
+            "gb" => 1 << 30,
+            _ => return Err("argument must be a memory value".into()),
+        };
+
+        digits
+            .parse::<u64>()
+            .ok()
+            .and_then(|bytes| bytes.checked_mul(unit))
+            .map(ByteSize)
+            .ok_or_else(|| "argument must be a memory value".into())
+    }
+
+    fn render(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+struct Parameter {
+    name: &'static str,
+    /// Can be changed by `CONFIG SET` while the server runs.
+    mutable: bool,
+    get: fn(&Config) -> String,
+    set: fn(&mut Config, &str) -> Result<(), String>,
+}
+
+impl Parameter {
+    fn find(name: &str) -> Result<&'static Parameter, ConfigError> {
+        PARAMETERS
+            .iter()
+            .find(|parameter| parameter.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| ConfigError::Unknown(name.to_string()))
+    }
+
+    /// Line setting the parameter to its value in `config`.
+    fn directive(&self, config: &Config) -> String {
+        format!("{} {}", self.name, quote(&(self.get)(config)))
+    }
+}
+
+macro_rules! parameter {
+    ($name:literal, $field:ident, $mutable:literal) => {
+        Parameter {
+            name: $name,
+            mutable: $mutable,
+            get: |config| ConfigValue::render(&config.$field),
+            set: |config, value| {
+                config.$field = ConfigValue::parse(value)?;
+                Ok(())
+            },
+        }
+    };
+}
+
+const PARAMETERS: &[Parameter] = &[
+    parameter!("bind", bind, false),
+    parameter!("port", port, false),
+    parameter!("memcache-port", memcache_port, false),
+    parameter!("metrics-port", metrics_port, false),
+    parameter!("tls-port", tls_port, false),
+    parameter!("tls-cert-file", tls_cert_file, true),
+    parameter!("tls-key-file", tls_key_file, true),
+    parameter!("tls-ca-cert-file", tls_ca_cert_file, true),
+    parameter!("tls-auth-clients", tls_auth_clients, true),
+    parameter!("tls-auth-clients-user", tls_auth_clients_user, true),
+    parameter!("shards", shards, false),
+    parameter!("cpu-pinning", cpu_pinning, false),
+    parameter!("maxclients", maxclients, true),
+    parameter!("timeout", timeout, true),
+    parameter!("shutdown-timeout", shutdown_timeout, true),
+    parameter!("repl-backlog-size", repl_backlog_size, true),
+    parameter!("maxmemory", maxmemory, true),
+    parameter!("maxmemory-policy", maxmemory_policy, true),
+    parameter!("maxmemory-samples", maxmemory_samples, true),
+    parameter!("slowlog-log-slower-than", slowlog_log_slower_than, true),
+    parameter!("slowlog-max-len", slowlog_max_len, true),
+    parameter!("latency-monitor-threshold", latency_monitor_threshold, true),
+    parameter!("tracking-table-max-keys", tracking_table_max_keys, true),
+    parameter!("processor-channel-capacity", processor_channel_capacity, false),
+    parameter!("storage-channel-capacity", storage_channel_capacity, false),
+    parameter!("cluster-config-file", cluster_config_file, false),
+    parameter!("aclfile", aclfile, false),
+    parameter!("acllog-max-len", acllog_max_len, true),
+    parameter!("masteruser", masteruser, true),
+    parameter!("masterauth", masterauth, true),
+    parameter!("save", save, true),
+    parameter!("dir", dir, true),
+    parameter!("dbfilename", dbfilename, true),
+];
+
+/// Split a config line into arguments, double or single quotes group words with spaces.
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    if chars.peek() == Some(&'#') {
+        return Ok(tokens);
+    }
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut token = String::new();
+        if c == '"' || c == '\'' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('\\') if c == '"' => match chars.next() {
+                        Some('n') => token.push('\n'),
+                        Some('t') => token.push('\t'),
+                        Some(escaped) => token.push(escaped),
+                        None => return Err("unbalanced quotes".into()),
+                    },
+                    Some(next) if next == c => break,
+                    Some(next) => token.push(next),
+                    None => return Err("unbalanced quotes".into()),
+                }
+            }
+            if chars.peek().is_some_and(|next| !next.is_whitespace()) {
+                return Err("closing quote must be followed by a space".into());
+            }
+        } else {
+            while let Some(next) = chars.next_if(|next| !next.is_whitespace()) {
+                token.push(next);
+            }
+        }
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        return value.to_string();
+    }
+
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+/// Replace the file at `path`, readers see either the old or the new content.
+///
+/// The content and the rename are synced to disk before returning, so that a crash loses neither.
+pub(crate) fn write_atomically(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let mut file = File::create(&temporary)?;
+    file.write_all(content.as_ref())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apply_file() {
+        let mut config = Config::default();
+        config
+            .apply_file(
+                "# comment\nport 7000\nbind 127.0.0.1 ::1\nsave \"\"\nrepl-backlog-size 2mb\n",
+            )
+            .unwrap();
+
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.get("bind").unwrap(), "127.0.0.1 ::1");
+        assert_eq!(config.repl_backlog_size, ByteSize(2 << 20));
+        assert_eq!(
+            config.apply_file("\nmaxclients lots").unwrap_err().to_string(),
+            "line 2: argument 'maxclients' is invalid: argument couldn't be parsed into an integer"
+        );
+    }
+
+    #[test]
+    fn set_is_atomic() {
+        let config = SharedConfig::default();
+        let set = |name: &str, value: &str| [(name.to_string(), value.to_string())];
+
+        assert!(matches!(config.set(&set("port", "1")), Err(ConfigError::Immutable(_))));
+        let error =
+            config.set(&[set("timeout", "5")[0].clone(), set("maxclients", "x")[0].clone()]);
+        assert!(error.is_err());
+        assert_eq!(config.read().timeout, 0);
+
+        config.set(&set("TIMEOUT", "5")).unwrap();
+        assert_eq!(config.matching(b"time*"), [("timeout", "5".to_string())]);
+    }
+
+    #[test]
+    fn set_rejected_by_apply() {
+        let config = SharedConfig::default();
+        let set = [("tls-auth-clients".to_string(), "optional".to_string())];
+
+        let prepare = |updated: &Config| {
+            assert_eq!(updated.tls_auth_clients, TlsAuthClients::Optional);
+            Err::<(), _>("invalid certificate".into())
+        };
+        let error = config.set_with(&set, prepare, |()| unreachable!("the update was rejected"));
+        assert!(matches!(error, Err(ConfigError::Rejected(_))));
+        assert_eq!(config.read().tls_auth_clients, TlsAuthClients::Yes);
+    }
+
+    #[test]
+    fn local_copy_follows_updates() {
+        let shared = Arc::new(SharedConfig::default());
+        let local = LocalConfig::new(shared.clone());
+        let before = local.read();
+        assert!(Rc::ptr_eq(&before, &local.read()));
+
+        shared.set(&[("timeout".to_string(), "5".to_string())]).unwrap();
+        assert_eq!(local.read().timeout, 5);
+        assert_eq!(before.timeout, 0);
+    }
+
+    #[test]
+    fn quoting_round_trips() {
+        for value in ["", "a b", "say \"hi\"", "plain"] {
+            assert_eq!(split_line(&format!("dir {}", quote(value))).unwrap(), ["dir", value]);
+        }
+    }
+}
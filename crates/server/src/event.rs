@@ -0,0 +1,52 @@
+use std::sync::Mutex;
+
+use futures::channel::mpsc;
+
+/// Wakes tasks on any shard waiting for some shared state to change.
+///
+/// Listeners check the state after calling [`Event::listen`], so that a change made in between
+/// still wakes them.
+#[derive(Debug, Default)]
+pub struct Event {
+    listeners: Mutex<Vec<mpsc::Sender<()>>>,
+}
+
+impl Event {
+    /// Wakeups sent once the event fires, repeated ones merged until they are received.
+    pub fn listen(&self) -> mpsc::Receiver<()> {
+        let (sender, receiver) = mpsc::channel(0);
+        let mut listeners = self.listeners.lock().unwrap();
+        listeners.retain(|listener| !listener.is_closed());
+        listeners.push(sender);
+        receiver
+    }
+
+    pub fn notify(&self) {
+        // A full channel already holds a wakeup, a closed one belongs to a stopped task.
+        self.listeners.lock().unwrap().retain_mut(|listener| match listener.try_send(()) {
+            Ok(()) => true,
+            Err(error) => error.is_full(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merges_wakeups() {
+        let event = Event::default();
+        let mut wakeups = event.listen();
+        assert!(wakeups.try_next().is_err());
+
+        event.notify();
+        event.notify();
+        assert_eq!(wakeups.try_next().unwrap(), Some(()));
+        assert!(wakeups.try_next().is_err());
+
+        drop(wakeups);
+        event.notify();
+        assert!(event.listeners.lock().unwrap().is_empty());
+    }
+}
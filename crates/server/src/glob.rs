@@ -0,0 +1,121 @@
+/// Redis style glob matching supporting `*`, `?`, `[...]` classes and `\` escapes.
+pub fn glob_match(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+    let eq = |a: u8, b: u8| if nocase { a.eq_ignore_ascii_case(&b) } else { a == b };
+
+    let mut p = 0;
+    let mut s = 0;
+    // Position in pattern after the last `*` and position in string it was tried against.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        let matched = match pattern.get(p) {
+            Some(b'*') => {
+                while pattern.get(p) == Some(&b'*') {
+                    p += 1;
+                }
+                if p == pattern.len() {
+                    return true;
+                }
+                backtrack = Some((p, s));
+                continue;
+            }
+            Some(b'?') => {
+                p += 1;
+                true
+            }
+            Some(b'[') => match match_class(&pattern[p..], string[s], nocase) {
+                Some((true, consumed)) => {
+                    p += consumed;
+                    true
+                }
+                _ => false,
+            },
+            Some(b'\\') if p + 1 < pattern.len() && eq(pattern[p + 1], string[s]) => {
+                p += 2;
+                true
+            }
+            Some(&c) if eq(c, string[s]) => {
+                p += 1;
+                true
+            }
+            _ => false,
+        };
+
+        if matched {
+            s += 1;
+        } else if let Some((star_p, star_s)) = backtrack {
+            p = star_p;
+            s = star_s + 1;
+            backtrack = Some((star_p, star_s + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Match `c` against a `[...]` class at the start of `pattern`.
+///
+/// Returns whether it matched and how many pattern bytes the class spans.
+fn match_class(pattern: &[u8], c: u8, nocase: bool) -> Option<(bool, usize)> {
+    let fold = |b: u8| if nocase { b.to_ascii_lowercase() } else { b };
+    let c = fold(c);
+
+    let mut i = 1;
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    loop {
+        match *pattern.get(i)? {
+            b']' => break,
+            b'\\' if i + 1 < pattern.len() => {
+                matched |= fold(pattern[i + 1]) == c;
+                i += 2;
+            }
+            start if pattern.get(i + 1) == Some(&b'-') && i + 2 < pattern.len() => {
+                let (start, end) = (fold(start), fold(pattern[i + 2]));
+                let (start, end) = if start <= end { (start, end) } else { (end, start) };
+                matched |= (start..=end).contains(&c);
+                i += 3;
+            }
+            other => {
+                matched |= fold(other) == c;
+                i += 1;
+            }
+        }
+    }
+
+    Some((matched != negate, i + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(glob_match(b"*", b"anything", false));
+        assert!(glob_match(b"h?llo", b"hello", false));
+        assert!(glob_match(b"h*llo", b"heeeello", false));
+        assert!(glob_match(b"*max*", b"maxmemory-policy", false));
+        assert!(!glob_match(b"h*llo", b"helloo", false));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(glob_match(b"h[ae]llo", b"hallo", false));
+        assert!(!glob_match(b"h[^e]llo", b"hello", false));
+        assert!(glob_match(b"h[a-b]llo", b"hbllo", false));
+        assert!(glob_match(b"h\\*llo", b"h*llo", false));
+    }
+
+    #[test]
+    fn nocase() {
+        assert!(glob_match(b"GET", b"get", true));
+        assert!(!glob_match(b"GET", b"get", false));
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    cell::RefCell,
+    collections::{
+        BTreeMap,
+        VecDeque,
+    },
+    rc::Rc,
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use crate::config::LocalConfig;
+
+/// Samples kept per event, the oldest are dropped first.
+pub const HISTORY_LEN: usize = 160;
+
+/// Kind of event sampled by the latency monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LatencyEvent {
+    /// Execution of a command that is not flagged as fast.
+    Command,
+    /// Execution of a command flagged as fast, O(1) or O(log N).
+    FastCommand,
+    /// Request sent to the storage of another shard, until its response came back.
+    StorageRoundTrip,
+    /// Keys evicted by a shard to fit maxmemory.
+    EvictionCycle,
+    /// Expired keys removed by the active expire pass of a shard.
+    ExpireCycle,
+    /// Keys of every shard serialized for a replica or before shutting down.
+    Snapshot,
+    /// File written and synced to disk: the snapshot, the rewritten config or the ACL file.
+    Fsync,
+}
+
+impl LatencyEvent {
+    pub const ALL: [LatencyEvent; 7] = [
+        LatencyEvent::Command,
+        LatencyEvent::FastCommand,
+        LatencyEvent::StorageRoundTrip,
+        LatencyEvent::EvictionCycle,
+        LatencyEvent::ExpireCycle,
+        LatencyEvent::Snapshot,
+        LatencyEvent::Fsync,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LatencyEvent::Command => "command",
+            LatencyEvent::FastCommand => "fast-command",
+            LatencyEvent::StorageRoundTrip => "storage-round-trip",
+            LatencyEvent::EvictionCycle => "eviction-cycle",
+            LatencyEvent::ExpireCycle => "expire-cycle",
+            LatencyEvent::Snapshot => "snapshot",
+            LatencyEvent::Fsync => "fsync",
+        }
+    }
+
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.name().as_bytes().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Latency of an event in the second it was sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    /// Unix time in seconds.
+    pub time: u64,
+    /// Highest latency of the second, in milliseconds.
+    pub latency: u64,
+}
+
+/// Samples of one event, oldest first with at most one per second.
+#[derive(Debug, Clone, Default)]
+pub struct EventHistory {
+    pub samples: VecDeque<LatencySample>,
+    /// Highest latency ever sampled, in milliseconds.
+    pub max: u64,
+}
+
+impl EventHistory {
+    pub fn add(&mut self, sample: LatencySample) {
+        self.max = self.max.max(sample.latency);
+        match self.samples.back_mut() {
+            Some(last) if last.time == sample.time => {
+                last.latency = last.latency.max(sample.latency)
+            }
+            _ => self.samples.push_back(sample),
+        }
+        if self.samples.len() > HISTORY_LEN {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Add the samples of another shard, keeping the highest latency of each second.
+    pub fn merge(&mut self, other: &EventHistory) {
+        let mut samples: BTreeMap<u64, u64> =
+            self.samples.iter().map(|sample| (sample.time, sample.latency)).collect();
+        for sample in &other.samples {
+            let latency = samples.entry(sample.time).or_default();
+            *latency = (*latency).max(sample.latency);
+        }
+        let skip = samples.len().saturating_sub(HISTORY_LEN);
+        self.samples = samples
+            .into_iter()
+            .skip(skip)
+            .map(|(time, latency)| LatencySample { time, latency })
+            .collect();
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn latest(&self) -> Option<LatencySample> {
+        self.samples.back().copied()
+    }
+}
+
+/// Events of one shard that took at least `latency-monitor-threshold` milliseconds.
+#[derive(Debug)]
+pub struct LatencyMonitor {
+    config: Rc<LocalConfig>,
+    events: RefCell<BTreeMap<LatencyEvent, EventHistory>>,
+}
+
+impl LatencyMonitor {
+    pub fn new(config: Rc<LocalConfig>) -> Self {
+        Self { config, events: Default::default() }
+    }
+
+    /// Sample an event that took `duration` if the monitor is enabled and it is slow enough.
+    pub fn record(&self, event: LatencyEvent, duration: Duration) {
+        let threshold = self.config.read().latency_monitor_threshold;
+        let latency = duration.as_millis() as u64;
+        if threshold == 0 || latency < threshold {
+            return;
+        }
+
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.events.borrow_mut().entry(event).or_default().add(LatencySample { time, latency });
+    }
+
+    pub fn events(&self) -> BTreeMap<LatencyEvent, EventHistory> {
+        self.events.borrow().clone()
+    }
+
+    /// Forget the samples of `events`, or of every event if empty, returning the events reset.
+    pub fn reset(&self, events: &[LatencyEvent]) -> Vec<LatencyEvent> {
+        let mut histories = self.events.borrow_mut();
+        if events.is_empty() {
+            return std::mem::take(&mut *histories).into_keys().collect();
+        }
+        events.iter().filter(|event| histories.remove(event).is_some()).copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::{
+        Config,
+        SharedConfig,
+    };
+
+    fn sample(time: u64, latency: u64) -> LatencySample {
+        LatencySample { time, latency }
+    }
+
+    #[test]
+    fn samples_above_threshold() {
+        let config = Config { latency_monitor_threshold: 5, ..Default::default() };
+        let shared = SharedConfig::new(config, None);
+        let monitor = LatencyMonitor::new(Rc::new(LocalConfig::new(Arc::new(shared))));
+
+        monitor.record(LatencyEvent::Command, Duration::from_millis(4));
+        assert!(monitor.events().is_empty());
+
+        monitor.record(LatencyEvent::Command, Duration::from_millis(7));
+        monitor.record(LatencyEvent::Command, Duration::from_millis(6));
+        let history = &monitor.events()[&LatencyEvent::Command];
+        assert_eq!(history.max, 7);
+        assert!(history.latest().unwrap().latency >= 6);
+
+        assert_eq!(monitor.reset(&[LatencyEvent::Snapshot]), vec![]);
+        assert_eq!(monitor.reset(&[]), vec![LatencyEvent::Command]);
+        assert!(monitor.events().is_empty());
+    }
+
+    #[test]
+    fn merges_histories() {
+        let mut history = EventHistory::default();
+        for time in 0..HISTORY_LEN as u64 {
+            history.add(sample(time, 1));
+        }
+        history.add(sample(HISTORY_LEN as u64, 3));
+        assert_eq!(history.samples.len(), HISTORY_LEN);
+        assert_eq!(history.samples[0], sample(1, 1));
+
+        let mut other = EventHistory::default();
+        other.add(sample(HISTORY_LEN as u64, 2));
+        other.add(sample(HISTORY_LEN as u64 + 1, 9));
+        history.merge(&other);
+        assert_eq!(history.samples.len(), HISTORY_LEN);
+        assert_eq!(history.samples[0], sample(2, 1));
+        assert_eq!(history.samples[HISTORY_LEN - 2], sample(HISTORY_LEN as u64, 3));
+        assert_eq!(history.latest(), Some(sample(HISTORY_LEN as u64 + 1, 9)));
+        assert_eq!(history.max, 9);
+    }
+}
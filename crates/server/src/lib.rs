@@ -1,4 +1,20 @@
 pub mod acceptor;
+pub mod acl;
+pub mod cluster;
+pub mod config;
+pub mod event;
+pub mod glob;
+pub mod latency;
+pub mod memcache;
+pub mod metrics;
+pub mod outbox;
 pub mod processor;
+pub mod replication;
 pub mod shard;
+pub mod shutdown;
+pub mod slot;
+pub mod slowlog;
+pub mod stats;
 pub mod storage;
+pub mod tls;
+pub mod tracking;
@@ -1,23 +1,124 @@
 use std::{
-    net::SocketAddr,
+    env,
+    fs,
     num::NonZeroUsize,
-    str::FromStr,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::ExitCode,
+    sync::Arc,
     thread::available_parallelism,
 };
 
-use goosekv_server::shard::{
-    ShardBuilder,
-    Shards,
+use anyhow::{
+    Context,
+    bail,
 };
+use goosekv_server::{
+    acl::Acl,
+    cluster::Cluster,
+    config::{
+        Config,
+        SharedConfig,
+    },
+    shard::{
+        ShardBuilder,
+        Shards,
+    },
+    shutdown::Snapshot,
+    tls::Tls,
+};
+use tracing::info;
+
+/// Command line: an optional config file followed by `--<parameter> <value>...` flags.
+struct Args {
+    config_file: Option<PathBuf>,
+    /// Parameters set by flags, applied over the config file.
+    parameters: Vec<(String, String)>,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut args = env::args().skip(1).peekable();
+    let config_file = args.next_if(|arg| !arg.starts_with("--")).map(PathBuf::from);
+
+    let mut parameters = Vec::new();
+    while let Some(flag) = args.next() {
+        let Some(name) = flag.strip_prefix("--") else {
+            bail!("unexpected argument {flag}");
+        };
+        let values: Vec<_> =
+            std::iter::from_fn(|| args.next_if(|arg| !arg.starts_with("--"))).collect();
+        if values.is_empty() {
+            bail!("missing value for {flag}");
+        }
+        parameters.push((name.to_string(), values.join(" ")));
+    }
+
+    Ok(Args { config_file, parameters })
+}
+
+fn load_config(args: &Args) -> anyhow::Result<Config> {
+    let mut config = Config::default();
+    if let Some(path) = &args.config_file {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        config
+            .apply_file(&text)
+            .with_context(|| format!("invalid config file {}", path.display()))?;
+    }
+
+    for (name, value) in &args.parameters {
+        config.set(name, value).with_context(|| format!("invalid flag --{name}"))?;
+    }
+
+    Ok(config)
+}
 
-fn main() {
+fn main() -> anyhow::Result<ExitCode> {
     tracing_subscriber::fmt().with_thread_names(true).init();
 
-    let thread_count = available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap());
-    let addr = SocketAddr::from_str("127.0.0.1:6379").unwrap();
+    let args = parse_args()?;
+    let config = load_config(&args)?;
+    let shard_count = match config.shards {
+        0 => available_parallelism().map_or(1, NonZeroUsize::get),
+        shards => shards,
+    };
+    let cluster = match config.cluster_config_file.as_str() {
+        "" => None,
+        path => Some(
+            Cluster::load(path, config.addrs()[0])
+                .with_context(|| format!("invalid cluster config file {path}"))?,
+        ),
+    };
+    let acl = match config.aclfile.as_str() {
+        "" => None,
+        path => Some(
+            Acl::from_file(Path::new(path)).with_context(|| format!("invalid ACL file {path}"))?,
+        ),
+    };
+    let tls = Tls::new(&config).context("invalid TLS configuration")?;
+    let path = Path::new(&config.dir).join(&config.dbfilename);
+    let snapshot =
+        Snapshot::load(&path).with_context(|| format!("invalid snapshot {}", path.display()))?;
+    if !snapshot.is_empty() {
+        info!(keys = snapshot.len(), path = %path.display(), "loading snapshot");
+    }
 
-    let shard_builder = ShardBuilder::new(addr);
-    let shards = Shards::from_builder(shard_builder, thread_count.get(), "SHARD".to_string());
+    let mut shard_builder =
+        ShardBuilder::new(Arc::new(SharedConfig::new(config, args.config_file)))
+            .tls(tls)
+            .snapshot(snapshot);
+    if let Some(cluster) = cluster {
+        shard_builder = shard_builder.cluster(cluster);
+    }
+    if let Some(acl) = acl {
+        shard_builder = shard_builder.acl(acl);
+    }
+    let shutdown = shard_builder.shutdown().clone();
+    shutdown.handle_signals().context("failed to handle signals")?;
+    let shards = Shards::from_builder(shard_builder, shard_count, "SHARD".to_string());
 
     shards.start().join_all();
+    Ok(ExitCode::from(shutdown.exit_code()))
 }
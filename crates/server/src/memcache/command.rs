@@ -0,0 +1,516 @@
+use std::str::FromStr;
+
+use bytes::{
+    Buf,
+    BytesMut,
+};
+use goosekv_protocol::{
+    command::table::CommandSpec,
+    data_type::GString,
+};
+use thiserror::Error;
+
+use crate::storage::response::StorageError;
+
+/// Longest command line accepted, the connection is closed past it.
+pub const MAX_LINE_LENGTH: usize = 2048;
+/// Longest key accepted, as in memcached.
+pub const MAX_KEY_LENGTH: usize = 250;
+/// Largest data block accepted, memcached's default `item_size_max`.
+pub const MAX_ITEM_SIZE: usize = 1024 * 1024;
+
+/// Error reply of the memcached protocol, rendered as sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum McError {
+    #[error("ERROR")]
+    UnknownCommand,
+    #[error("CLIENT_ERROR {0}")]
+    Client(&'static str),
+    #[error("SERVER_ERROR {0}")]
+    Server(String),
+}
+
+impl From<StorageError> for McError {
+    fn from(error: StorageError) -> Self {
+        McError::Server(error.to_string())
+    }
+}
+
+impl McError {
+    const BAD_FORMAT: McError = McError::Client("bad command line format");
+
+    /// The stream can't be parsed any further after this error.
+    pub fn is_fatal(&self) -> bool {
+        *self == McError::Client("line too long")
+    }
+}
+
+/// Command of the memcached text or meta protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McCommand {
+    /// `get` and `gets`, the latter also returns CAS tokens.
+    Get {
+        keys: Vec<GString>,
+        cas: bool,
+    },
+    /// `set`, `add`, `replace`, `append`, `prepend` and `cas`.
+    Store(StoreCommand),
+    /// `incr` and `decr`.
+    Arithmetic {
+        key: GString,
+        delta: u64,
+        incr: bool,
+        noreply: bool,
+    },
+    Delete {
+        key: GString,
+        noreply: bool,
+    },
+    Touch {
+        key: GString,
+        exptime: i64,
+        noreply: bool,
+    },
+    MetaGet {
+        key: GString,
+        flags: MetaFlags,
+    },
+    MetaSet {
+        key: GString,
+        data: GString,
+        flags: MetaFlags,
+    },
+    MetaDelete {
+        key: GString,
+        flags: MetaFlags,
+    },
+    MetaArithmetic {
+        key: GString,
+        flags: MetaFlags,
+    },
+    MetaNoop,
+    Version,
+    Quit,
+}
+
+impl McCommand {
+    /// Keys accessed by the command.
+    pub fn keys(&self) -> &[GString] {
+        match self {
+            McCommand::Get { keys, .. } => keys,
+            McCommand::Store(command) => std::slice::from_ref(&command.key),
+            McCommand::Arithmetic { key, .. }
+            | McCommand::Delete { key, .. }
+            | McCommand::Touch { key, .. }
+            | McCommand::MetaGet { key, .. }
+            | McCommand::MetaSet { key, .. }
+            | McCommand::MetaDelete { key, .. }
+            | McCommand::MetaArithmetic { key, .. } => std::slice::from_ref(key),
+            McCommand::MetaNoop | McCommand::Version | McCommand::Quit => &[],
+        }
+    }
+
+    /// Redis command doing the same, whose ACL rules apply to the command. `touch` counts as
+    /// `SET` since it rewrites the expiration time.
+    pub fn spec(&self) -> Option<&'static CommandSpec> {
+        let name: &[u8] = match self {
+            McCommand::Get { .. } | McCommand::MetaGet { .. } => b"GET",
+            McCommand::Store(_) | McCommand::MetaSet { .. } | McCommand::Touch { .. } => b"SET",
+            McCommand::Arithmetic { incr: false, .. } => b"DECR",
+            McCommand::MetaArithmetic { flags, .. }
+                if flags.token(b'M').is_some_and(|mode| {
+                    matches!(mode.as_ref().first(), Some(b'D' | b'd' | b'-'))
+                }) =>
+            {
+                b"DECR"
+            }
+            McCommand::Arithmetic { .. } | McCommand::MetaArithmetic { .. } => b"INCR",
+            McCommand::Delete { .. } | McCommand::MetaDelete { .. } => b"DEL",
+            McCommand::MetaNoop | McCommand::Version | McCommand::Quit => return None,
+        };
+        CommandSpec::find(name)
+    }
+
+    /// The command only reads keys.
+    pub fn is_readonly(&self) -> bool {
+        match self {
+            McCommand::MetaGet { flags, .. } => !flags.has(b'T'),
+            McCommand::Get { .. } | McCommand::MetaNoop | McCommand::Version | McCommand::Quit => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the command may store more data, rejected once out of memory.
+    pub fn may_grow(&self) -> bool {
+        matches!(
+            self,
+            McCommand::Store(_)
+                | McCommand::Arithmetic { .. }
+                | McCommand::MetaSet { .. }
+                | McCommand::MetaArithmetic { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreCommand {
+    pub mode: StoreMode,
+    pub key: GString,
+    pub flags: u32,
+    pub exptime: i64,
+    pub data: GString,
+    /// Store only if the CAS token of the key is still this one.
+    pub cas: Option<u64>,
+    pub noreply: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreMode {
+    Set,
+    /// Store only if the key is missing.
+    Add,
+    /// Store only if the key exists.
+    Replace,
+    Append,
+    Prepend,
+}
+
+/// Flags of a meta command, a letter optionally followed by a token, e.g. `v`, `T30` or `Oabc`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetaFlags {
+    flags: Vec<(u8, GString)>,
+}
+
+impl MetaFlags {
+    pub fn has(&self, flag: u8) -> bool {
+        self.flags.iter().any(|(name, _)| *name == flag)
+    }
+
+    pub fn token(&self, flag: u8) -> Option<&GString> {
+        self.flags.iter().find(|(name, _)| *name == flag).map(|(_, token)| token)
+    }
+
+    /// Token of `flag` parsed as a number, `None` if the flag is missing.
+    pub fn number<T: FromStr>(&self, flag: u8) -> Result<Option<T>, McError> {
+        self.token(flag)
+            .map(|token| {
+                number(token.as_ref()).ok_or(McError::Client("bad token in command line format"))
+            })
+            .transpose()
+    }
+
+    /// Flags in the order they were sent, replies echo them in the same order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &GString)> {
+        self.flags.iter().map(|(name, token)| (*name, token))
+    }
+
+    fn parse(tokens: &[&[u8]]) -> Result<Self, McError> {
+        let flags = tokens
+            .iter()
+            .map(|token| match token.split_first() {
+                // Base64 encoded keys are not supported.
+                Some((&b'b', _)) => Err(McError::Client("base64 keys are not supported")),
+                Some((&name, token)) if name.is_ascii_alphabetic() => {
+                    Ok((name, GString::copy_from_slice(token)))
+                }
+                _ => Err(McError::Client("invalid flag")),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { flags })
+    }
+}
+
+/// Parser of the commands sent on one connection.
+#[derive(Debug, Default)]
+pub struct McParser {
+    /// Bytes left to discard of a data block too large to be stored.
+    skip: usize,
+}
+
+impl McParser {
+    /// Parse the next command buffered in `buffer`, `None` if it is not complete yet.
+    ///
+    /// The command is removed from the buffer, along with its data block for storage commands,
+    /// even when it turns out to be invalid.
+    pub fn parse(&mut self, buffer: &mut BytesMut) -> Result<Option<McCommand>, McError> {
+        if self.skip > 0 {
+            let skipped = self.skip.min(buffer.len());
+            buffer.advance(skipped);
+            self.skip -= skipped;
+            if self.skip > 0 {
+                return Ok(None);
+            }
+        }
+
+        let Some(end) = buffer.iter().position(|&byte| byte == b'\n') else {
+            if buffer.len() > MAX_LINE_LENGTH {
+                return Err(McError::Client("line too long"));
+            }
+            return Ok(None);
+        };
+
+        let data_length = match data_length(&tokenize(&buffer[..end])) {
+            Some(Ok(length)) if length > MAX_ITEM_SIZE => {
+                // Like memcached, the data block is read and dropped rather than buffered.
+                buffer.advance(end + 1);
+                self.skip = length.saturating_add(2);
+                return Err(McError::Server("object too large for cache".to_string()));
+            }
+            Some(Ok(length)) => Some(length),
+            Some(Err(error)) => {
+                buffer.advance(end + 1);
+                return Err(error);
+            }
+            None => None,
+        };
+        let block_end = data_length.and_then(|length| (end + 1).checked_add(length + 2));
+        if block_end.is_some_and(|block_end| buffer.len() < block_end) {
+            return Ok(None);
+        }
+
+        let line = buffer.split_to(end + 1).freeze();
+        let data = data_length.map(|length| buffer.split_to(length + 2).freeze());
+        let data = match data {
+            Some(data) if !data.ends_with(b"\r\n") => {
+                // Skip the rest of the oversized block.
+                if let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                    buffer.advance(end + 1);
+                }
+                return Err(McError::Client("bad data chunk"));
+            }
+            Some(data) => Some(GString::copy_from_slice(&data[..data.len() - 2])),
+            None => None,
+        };
+
+        parse_line(&tokenize(&line), data).map(Some)
+    }
+}
+
+fn tokenize(line: &[u8]) -> Vec<&[u8]> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    line.split(|&byte| byte == b' ').filter(|token| !token.is_empty()).collect()
+}
+
+/// Length of the data block following the command line, for storage commands.
+fn data_length(tokens: &[&[u8]]) -> Option<Result<usize, McError>> {
+    let index = match *tokens.first()? {
+        b"set" | b"add" | b"replace" | b"append" | b"prepend" | b"cas" => 4,
+        b"ms" => 2,
+        _ => return None,
+    };
+
+    Some(tokens.get(index).and_then(|length| number(length)).ok_or(McError::BAD_FORMAT))
+}
+
+fn parse_line(tokens: &[&[u8]], data: Option<GString>) -> Result<McCommand, McError> {
+    let (&name, args) = tokens.split_first().ok_or(McError::UnknownCommand)?;
+
+    let command = match name {
+        b"get" | b"gets" if !args.is_empty() => McCommand::Get {
+            keys: args.iter().map(|key| key_from(key)).collect::<Result<_, _>>()?,
+            cas: name == b"gets",
+        },
+        b"set" | b"add" | b"replace" | b"append" | b"prepend" | b"cas" => {
+            let mode = match name {
+                b"add" => StoreMode::Add,
+                b"replace" => StoreMode::Replace,
+                b"append" => StoreMode::Append,
+                b"prepend" => StoreMode::Prepend,
+                _ => StoreMode::Set,
+            };
+            let (cas, rest) = match name {
+                b"cas" => (Some(arg(args, 4)?), &args[5..]),
+                _ => (None, args.get(4..).unwrap_or_default()),
+            };
+
+            McCommand::Store(StoreCommand {
+                mode,
+                key: key_from(args[0])?,
+                flags: arg(args, 1)?,
+                exptime: arg(args, 2)?,
+                data: data.unwrap_or_default(),
+                cas,
+                noreply: noreply(rest)?,
+            })
+        }
+        b"incr" | b"decr" if args.len() >= 2 => McCommand::Arithmetic {
+            key: key_from(args[0])?,
+            delta: number(args[1]).ok_or(McError::Client("invalid numeric delta argument"))?,
+            incr: name == b"incr",
+            noreply: noreply(&args[2..])?,
+        },
+        b"delete" if !args.is_empty() => {
+            McCommand::Delete { key: key_from(args[0])?, noreply: noreply(&args[1..])? }
+        }
+        b"touch" if args.len() >= 2 => McCommand::Touch {
+            key: key_from(args[0])?,
+            exptime: arg(args, 1)?,
+            noreply: noreply(&args[2..])?,
+        },
+        b"mg" | b"md" | b"ma" if !args.is_empty() => {
+            let key = key_from(args[0])?;
+            let flags = MetaFlags::parse(&args[1..])?;
+            match name {
+                b"mg" => McCommand::MetaGet { key, flags },
+                b"md" => McCommand::MetaDelete { key, flags },
+                _ => McCommand::MetaArithmetic { key, flags },
+            }
+        }
+        b"ms" if args.len() >= 2 => McCommand::MetaSet {
+            key: key_from(args[0])?,
+            data: data.unwrap_or_default(),
+            flags: MetaFlags::parse(&args[2..])?,
+        },
+        b"mn" => McCommand::MetaNoop,
+        b"version" => McCommand::Version,
+        b"quit" => McCommand::Quit,
+        b"get" | b"gets" | b"incr" | b"decr" | b"delete" | b"touch" | b"mg" | b"md" | b"ma"
+        | b"ms" => return Err(McError::BAD_FORMAT),
+        _ => return Err(McError::UnknownCommand),
+    };
+
+    Ok(command)
+}
+
+fn key_from(key: &[u8]) -> Result<GString, McError> {
+    if key.len() > MAX_KEY_LENGTH {
+        return Err(McError::BAD_FORMAT);
+    }
+
+    Ok(GString::copy_from_slice(key))
+}
+
+fn arg<T: FromStr>(args: &[&[u8]], index: usize) -> Result<T, McError> {
+    args.get(index).and_then(|arg| number(arg)).ok_or(McError::BAD_FORMAT)
+}
+
+fn noreply(rest: &[&[u8]]) -> Result<bool, McError> {
+    match rest {
+        [] => Ok(false),
+        [b"noreply"] => Ok(true),
+        _ => Err(McError::BAD_FORMAT),
+    }
+}
+
+fn number<T: FromStr>(token: &[u8]) -> Option<T> {
+    std::str::from_utf8(token).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_all(input: &'static [u8]) -> Vec<Result<McCommand, McError>> {
+        let mut buffer = BytesMut::from(input);
+        let mut parser = McParser::default();
+        let mut commands = Vec::new();
+        while let Some(command) = parser.parse(&mut buffer).transpose() {
+            commands.push(command);
+        }
+        commands
+    }
+
+    #[test]
+    fn storage_commands() {
+        let commands = parse_all(b"set a 5 0 3\r\nabc\r\ncas b 0 10 2 42 noreply\r\nxy\r\n");
+
+        assert_eq!(
+            commands,
+            [
+                Ok(McCommand::Store(StoreCommand {
+                    mode: StoreMode::Set,
+                    key: GString::from_static(b"a"),
+                    flags: 5,
+                    exptime: 0,
+                    data: GString::from_static(b"abc"),
+                    cas: None,
+                    noreply: false,
+                })),
+                Ok(McCommand::Store(StoreCommand {
+                    mode: StoreMode::Set,
+                    key: GString::from_static(b"b"),
+                    flags: 0,
+                    exptime: 10,
+                    data: GString::from_static(b"xy"),
+                    cas: Some(42),
+                    noreply: true,
+                })),
+            ]
+        );
+    }
+
+    #[test]
+    fn waits_for_data_block() {
+        let mut buffer = BytesMut::from(&b"set a 0 0 3\r\nab"[..]);
+        let mut parser = McParser::default();
+
+        assert_eq!(parser.parse(&mut buffer), Ok(None));
+        buffer.extend_from_slice(b"c\r\n");
+        assert!(matches!(parser.parse(&mut buffer), Ok(Some(McCommand::Store(_)))));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn skips_items_too_large() {
+        let too_large = McError::Server("object too large for cache".to_string());
+        let commands = parse_all(b"set k 0 0 18446744073709551615\r\nabc\r\n");
+        assert_eq!(commands, [Err(too_large.clone())]);
+
+        let mut buffer = BytesMut::from(format!("set k 0 0 {}\r\n", MAX_ITEM_SIZE + 1).as_bytes());
+        let mut parser = McParser::default();
+        assert_eq!(parser.parse(&mut buffer), Err(too_large));
+        for _ in 0..2 {
+            buffer.extend_from_slice(&[b'x'; MAX_ITEM_SIZE / 2]);
+            assert_eq!(parser.parse(&mut buffer), Ok(None));
+        }
+        buffer.extend_from_slice(b"x\r\nversion\r\n");
+        assert_eq!(parser.parse(&mut buffer), Ok(Some(McCommand::Version)));
+    }
+
+    #[test]
+    fn meta_flags() {
+        let commands = parse_all(b"mg foo v c T30 Oab\r\nms foo 2 F3 MA\r\nhi\r\nmn\r\n");
+
+        let Ok(McCommand::MetaGet { key, flags }) = &commands[0] else { panic!() };
+        assert_eq!(key, &GString::from_static(b"foo"));
+        assert!(flags.has(b'v') && flags.has(b'c') && !flags.has(b'f'));
+        assert_eq!(flags.number::<i64>(b'T'), Ok(Some(30)));
+        assert_eq!(flags.token(b'O'), Some(&GString::from_static(b"ab")));
+
+        let Ok(McCommand::MetaSet { data, flags, .. }) = &commands[1] else { panic!() };
+        assert_eq!(data, &GString::from_static(b"hi"));
+        assert_eq!(flags.number::<u32>(b'F'), Ok(Some(3)));
+        assert_eq!(commands[2], Ok(McCommand::MetaNoop));
+    }
+
+    #[test]
+    fn redis_equivalents() {
+        let commands = parse_all(b"gets a b\r\ntouch a 10\r\ndecr a 1\r\nma a MD\r\nmn\r\n");
+        let names: Vec<_> = commands
+            .iter()
+            .map(|command| command.as_ref().unwrap().spec().map(|spec| spec.name))
+            .collect();
+
+        assert_eq!(names, [Some("GET"), Some("SET"), Some("DECR"), Some("DECR"), None]);
+    }
+
+    #[test]
+    fn errors() {
+        let commands = parse_all(b"bogus\r\nget\r\nset a 0 0 1\r\nxyz\r\nincr a x\r\nversion\r\n");
+
+        assert_eq!(
+            commands,
+            [
+                Err(McError::UnknownCommand),
+                Err(McError::BAD_FORMAT),
+                Err(McError::Client("bad data chunk")),
+                Err(McError::Client("invalid numeric delta argument")),
+                Ok(McCommand::Version),
+            ]
+        );
+    }
+}
@@ -0,0 +1,552 @@
+use std::{
+    fmt::Write,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use bytes::BytesMut;
+use futures::future::join_all;
+use goosekv_protocol::data_type::GString;
+
+use crate::{
+    acl::{
+        DEFAULT_USER,
+        DenialReason,
+    },
+    memcache::command::{
+        McCommand,
+        McError,
+        MetaFlags,
+        StoreCommand,
+        StoreMode,
+    },
+    storage::{
+        request::{
+            GetRequest,
+            Modification,
+            ModifyRequest,
+        },
+        router::{
+            KeyAccess,
+            StorageRouter,
+        },
+        value::{
+            Data,
+            Value,
+        },
+    },
+};
+
+/// Largest exptime taken as a number of seconds from now, larger ones are unix timestamps.
+const MAX_RELATIVE_EXPTIME: i64 = 60 * 60 * 24 * 30;
+
+/// Outcome of a storage command, rendered differently by the text and meta protocols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreOutcome {
+    Stored,
+    NotStored,
+    /// The CAS token of the key changed.
+    Exists,
+    NotFound,
+}
+
+/// Execute `command` and append its reply to `output`.
+pub async fn execute(command: McCommand, router: &StorageRouter, output: &mut BytesMut) {
+    if let Err(error) = check(&command, router).await {
+        let _ = write!(output, "{error}\r\n");
+        return;
+    }
+
+    let result = match command {
+        McCommand::Get { keys, cas } => get(router, keys, cas, output).await,
+        McCommand::Store(command) => {
+            let noreply = command.noreply;
+            store(router, command).await.map(|(outcome, _)| {
+                let reply = match outcome {
+                    StoreOutcome::Stored => "STORED",
+                    StoreOutcome::NotStored => "NOT_STORED",
+                    StoreOutcome::Exists => "EXISTS",
+                    StoreOutcome::NotFound => "NOT_FOUND",
+                };
+                reply_unless(noreply, output, reply);
+            })
+        }
+        McCommand::Arithmetic { key, delta, incr, noreply } => {
+            arithmetic(router, key, incr, delta, None).await.map(|value| {
+                let reply = value.map_or("NOT_FOUND".to_string(), |(number, _)| number.to_string());
+                reply_unless(noreply, output, &reply);
+            })
+        }
+        McCommand::Delete { key, noreply } => delete(router, key, None).await.map(|outcome| {
+            let reply = match outcome {
+                StoreOutcome::Stored => "DELETED",
+                _ => "NOT_FOUND",
+            };
+            reply_unless(noreply, output, reply);
+        }),
+        McCommand::Touch { key, exptime, noreply } => {
+            touch(router, key, exptime).await.map(|value| {
+                let reply = match value {
+                    Some(_) => "TOUCHED",
+                    None => "NOT_FOUND",
+                };
+                reply_unless(noreply, output, reply);
+            })
+        }
+        McCommand::MetaGet { key, flags } => meta_get(router, key, flags, output).await,
+        McCommand::MetaSet { key, data, flags } => meta_set(router, key, data, flags, output).await,
+        McCommand::MetaDelete { key, flags } => meta_delete(router, key, flags, output).await,
+        McCommand::MetaArithmetic { key, flags } => {
+            meta_arithmetic(router, key, flags, output).await
+        }
+        McCommand::MetaNoop => {
+            output.extend_from_slice(b"MN\r\n");
+            Ok(())
+        }
+        McCommand::Version => {
+            let _ = write!(output, "VERSION {}\r\n", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        McCommand::Quit => Ok(()),
+    };
+
+    if let Err(error) = result {
+        let _ = write!(output, "{error}\r\n");
+    }
+}
+
+/// Reject writes on a replica or out of memory, and keys served by another node of the cluster.
+async fn check(command: &McCommand, router: &StorageRouter) -> Result<(), McError> {
+    authorize(command, router)?;
+    let readonly = command.is_readonly();
+    if !readonly && router.replication().is_replica() {
+        return Err(McError::Server("You can't write against a read only replica.".into()));
+    }
+    if command.may_grow() && router.out_of_memory() {
+        return Err(McError::Server("out of memory storing object".into()));
+    }
+
+    let access = KeyAccess { readonly, ..Default::default() };
+    router
+        .redirect(command.keys(), access)
+        .await
+        .map_err(|redirect| McError::Server(redirect.to_string()))
+}
+
+/// Check the `default` user may run `command`, memcached clients have no way to authenticate as
+/// another one.
+fn authorize(command: &McCommand, router: &StorageRouter) -> Result<(), McError> {
+    let acl = router.acl();
+    let user = match acl.user(DEFAULT_USER) {
+        Some(user) if !acl.auth_required() => user,
+        _ => return Err(McError::Client("unauthenticated")),
+    };
+    let Some(spec) = command.spec() else {
+        return Ok(());
+    };
+
+    let (reason, object) = match user.can_run(spec) {
+        true => {
+            match command.keys().iter().find(|key| !user.can_access(key.as_ref(), spec.keys.access))
+            {
+                Some(key) => (DenialReason::Key, lossy(key)),
+                None => return Ok(()),
+            }
+        }
+        false => (DenialReason::Command, spec.full_name()),
+    };
+    let max_len = router.config().read().acllog_max_len;
+    acl.record_denial(reason, object, DEFAULT_USER, "memcache".to_string(), max_len);
+    let mut counters = router.shard_stats().counters();
+    match reason {
+        DenialReason::Key => counters.acl_access_denied_key += 1,
+        _ => counters.acl_access_denied_cmd += 1,
+    }
+    Err(McError::Client("no permissions"))
+}
+
+async fn get(
+    router: &StorageRouter,
+    keys: Vec<GString>,
+    cas: bool,
+    output: &mut BytesMut,
+) -> Result<(), McError> {
+    let values = join_all(
+        keys.iter()
+            .map(|key| router.get(GetRequest { key: key.clone(), touch: true, tracking: None })),
+    );
+    let values = values.await.into_iter().collect::<Result<Vec<_>, _>>()?;
+    for (key, response) in keys.iter().zip(values) {
+        let Some(value) = response.value else {
+            continue;
+        };
+
+        let data = value.data.bytes();
+        let _ = write!(output, "VALUE {} {} {}", lossy(key), value.flags, data.len());
+        if cas {
+            let _ = write!(output, " {}", value.cas);
+        }
+        output.extend_from_slice(b"\r\n");
+        output.extend_from_slice(&data);
+        output.extend_from_slice(b"\r\n");
+    }
+    output.extend_from_slice(b"END\r\n");
+    Ok(())
+}
+
+/// Outcome of `command` along with the value of the key after it.
+async fn store(
+    router: &StorageRouter,
+    command: StoreCommand,
+) -> Result<(StoreOutcome, Option<Value>), McError> {
+    let key = command.key.clone();
+    let command = Arc::new(command);
+    let f = {
+        let command = command.clone();
+        move |current: Option<&Value>| match store_outcome(&command, current) {
+            StoreOutcome::Stored => Modification::Set(stored_value(&command, current)),
+            _ => Modification::Keep,
+        }
+    };
+
+    let response = router.modify(ModifyRequest { key, f: Arc::new(f) }).await?;
+    Ok((store_outcome(&command, response.previous.as_ref()), response.current))
+}
+
+fn store_outcome(command: &StoreCommand, current: Option<&Value>) -> StoreOutcome {
+    match (current, command.cas) {
+        (None, Some(_)) => StoreOutcome::NotFound,
+        (Some(value), Some(cas)) if value.cas != cas => StoreOutcome::Exists,
+        (Some(_), _) if command.mode == StoreMode::Add => StoreOutcome::NotStored,
+        (None, _) if command.mode != StoreMode::Set && command.mode != StoreMode::Add => {
+            StoreOutcome::NotStored
+        }
+        _ => StoreOutcome::Stored,
+    }
+}
+
+/// Value stored by `command`, appending and prepending keep the flags and expiry of `current`.
+fn stored_value(command: &StoreCommand, current: Option<&Value>) -> Value {
+    let concat = |first: &[u8], second: &[u8]| {
+        Data::from_gstring(GString::copy_from_slice(&[first, second].concat()))
+    };
+
+    match (command.mode, current) {
+        (StoreMode::Append, Some(current)) => {
+            Value { data: concat(&current.data.bytes(), command.data.as_ref()), ..current.clone() }
+        }
+        (StoreMode::Prepend, Some(current)) => {
+            Value { data: concat(command.data.as_ref(), &current.data.bytes()), ..current.clone() }
+        }
+        _ => Value {
+            flags: command.flags,
+            expires_at: expires_at(command.exptime),
+            ..Value::new(Data::from_gstring(command.data.clone()))
+        },
+    }
+}
+
+/// Add or subtract `delta`, returning the new number along with the value, `None` if the key is
+/// missing.
+///
+/// `vivify` creates a missing key with the given initial value and exptime instead.
+async fn arithmetic(
+    router: &StorageRouter,
+    key: GString,
+    incr: bool,
+    delta: u64,
+    vivify: Option<(u64, i64)>,
+) -> Result<Option<(u64, Value)>, McError> {
+    let f = move |current: Option<&Value>| match current {
+        Some(current) => match number(current) {
+            Some(number) => Modification::Set(Value {
+                data: number_data(apply_delta(number, incr, delta)),
+                ..current.clone()
+            }),
+            None => Modification::Keep,
+        },
+        None => match vivify {
+            Some((initial, exptime)) => Modification::Set(Value {
+                expires_at: expires_at(exptime),
+                ..Value::new(number_data(initial))
+            }),
+            None => Modification::Keep,
+        },
+    };
+
+    let response = router.modify(ModifyRequest { key, f: Arc::new(f) }).await?;
+    if response.previous.as_ref().is_some_and(|previous| number(previous).is_none()) {
+        return Err(McError::Client("cannot increment or decrement non-numeric value"));
+    }
+
+    Ok(response.current.and_then(|value| Some((number(&value)?, value))))
+}
+
+/// Incrementing wraps around at 64 bits, decrementing stops at 0, as in memcached.
+fn apply_delta(number: u64, incr: bool, delta: u64) -> u64 {
+    if incr { number.wrapping_add(delta) } else { number.saturating_sub(delta) }
+}
+
+fn number(value: &Value) -> Option<u64> {
+    std::str::from_utf8(&value.data.bytes()).ok()?.parse().ok()
+}
+
+fn number_data(number: u64) -> Data {
+    Data::from_gstring(GString::copy_from_slice(number.to_string().as_bytes()))
+}
+
+/// Delete `key`, only if its CAS token is still `cas` when set.
+async fn delete(
+    router: &StorageRouter,
+    key: GString,
+    cas: Option<u64>,
+) -> Result<StoreOutcome, McError> {
+    let outcome = move |current: Option<&Value>| match (current, cas) {
+        (None, _) => StoreOutcome::NotFound,
+        (Some(current), Some(cas)) if current.cas != cas => StoreOutcome::Exists,
+        _ => StoreOutcome::Stored,
+    };
+    let f = move |current: Option<&Value>| match outcome(current) {
+        StoreOutcome::Stored => Modification::Delete,
+        _ => Modification::Keep,
+    };
+
+    let response = router.modify(ModifyRequest { key, f: Arc::new(f) }).await?;
+    Ok(outcome(response.previous.as_ref()))
+}
+
+async fn touch(
+    router: &StorageRouter,
+    key: GString,
+    exptime: i64,
+) -> Result<Option<Value>, McError> {
+    let f = move |_: Option<&Value>| Modification::Touch(expires_at(exptime));
+    Ok(router.modify(ModifyRequest { key, f: Arc::new(f) }).await?.current)
+}
+
+async fn meta_get(
+    router: &StorageRouter,
+    key: GString,
+    flags: MetaFlags,
+    output: &mut BytesMut,
+) -> Result<(), McError> {
+    let value = match flags.number(b'T')? {
+        Some(exptime) => touch(router, key.clone(), exptime).await?,
+        None => {
+            router.get(GetRequest { key: key.clone(), touch: true, tracking: None }).await?.value
+        }
+    };
+
+    let Some(value) = value else {
+        reply_unless(flags.has(b'q'), output, "EN");
+        return Ok(());
+    };
+
+    let data = value.data.bytes();
+    let returned = meta_returned(&flags, &key, Some(&value));
+    if flags.has(b'v') {
+        let _ = write!(output, "VA {}{returned}\r\n", data.len());
+        output.extend_from_slice(&data);
+        output.extend_from_slice(b"\r\n");
+    } else {
+        let _ = write!(output, "HD{returned}\r\n");
+    }
+
+    Ok(())
+}
+
+async fn meta_set(
+    router: &StorageRouter,
+    key: GString,
+    data: GString,
+    flags: MetaFlags,
+    output: &mut BytesMut,
+) -> Result<(), McError> {
+    let mode = match flags.token(b'M').and_then(|mode| mode.as_ref().first().copied()) {
+        None | Some(b'S' | b's') => StoreMode::Set,
+        Some(b'E' | b'e') => StoreMode::Add,
+        Some(b'R' | b'r') => StoreMode::Replace,
+        Some(b'A' | b'a') => StoreMode::Append,
+        Some(b'P' | b'p') => StoreMode::Prepend,
+        Some(_) => return Err(McError::Client("invalid mode for ms STORE")),
+    };
+    let command = StoreCommand {
+        mode,
+        key: key.clone(),
+        flags: flags.number(b'F')?.unwrap_or_default(),
+        exptime: flags.number(b'T')?.unwrap_or_default(),
+        data,
+        cas: flags.number(b'C')?,
+        noreply: false,
+    };
+
+    let (outcome, current) = store(router, command).await?;
+    let code = match outcome {
+        StoreOutcome::Stored => "HD",
+        StoreOutcome::NotStored => "NS",
+        StoreOutcome::Exists => "EX",
+        StoreOutcome::NotFound => "NF",
+    };
+    meta_reply(output, &flags, code, &key, current.as_ref(), outcome == StoreOutcome::Stored);
+    Ok(())
+}
+
+async fn meta_delete(
+    router: &StorageRouter,
+    key: GString,
+    flags: MetaFlags,
+    output: &mut BytesMut,
+) -> Result<(), McError> {
+    let outcome = delete(router, key.clone(), flags.number(b'C')?).await?;
+    let code = match outcome {
+        StoreOutcome::Stored => "HD",
+        StoreOutcome::Exists => "EX",
+        _ => "NF",
+    };
+    meta_reply(output, &flags, code, &key, None, outcome != StoreOutcome::Exists);
+    Ok(())
+}
+
+async fn meta_arithmetic(
+    router: &StorageRouter,
+    key: GString,
+    flags: MetaFlags,
+    output: &mut BytesMut,
+) -> Result<(), McError> {
+    let incr = match flags.token(b'M').and_then(|mode| mode.as_ref().first().copied()) {
+        None | Some(b'I' | b'i' | b'+') => true,
+        Some(b'D' | b'd' | b'-') => false,
+        Some(_) => return Err(McError::Client("invalid mode for ma")),
+    };
+    let delta = flags.number(b'D')?.unwrap_or(1);
+    let vivify = match flags.number(b'N')? {
+        Some(exptime) => Some((flags.number(b'J')?.unwrap_or_default(), exptime)),
+        None => None,
+    };
+
+    let Some((number, value)) = arithmetic(router, key.clone(), incr, delta, vivify).await? else {
+        meta_reply(output, &flags, "NF", &key, None, true);
+        return Ok(());
+    };
+
+    let returned = meta_returned(&flags, &key, Some(&value));
+    if flags.has(b'v') {
+        let number = number.to_string();
+        let _ = write!(output, "VA {}{returned}\r\n{number}\r\n", number.len());
+    } else {
+        reply_unless(flags.has(b'q'), output, &format!("HD{returned}"));
+    }
+    Ok(())
+}
+
+/// Reply `code` with the returned flags, `quiet` replies are omitted in `q` mode.
+fn meta_reply(
+    output: &mut BytesMut,
+    flags: &MetaFlags,
+    code: &str,
+    key: &GString,
+    value: Option<&Value>,
+    quiet: bool,
+) {
+    let returned = meta_returned(flags, key, value);
+    reply_unless(quiet && flags.has(b'q'), output, &format!("{code}{returned}"));
+}
+
+/// Flags returned along a meta reply, in the order they were requested.
+fn meta_returned(flags: &MetaFlags, key: &GString, value: Option<&Value>) -> String {
+    let mut returned = String::new();
+    for (flag, token) in flags.iter() {
+        let _ = match (flag, value) {
+            (b'O', _) => write!(returned, " O{}", lossy(token)),
+            (b'k', _) => write!(returned, " k{}", lossy(key)),
+            (b'f', Some(value)) => write!(returned, " f{}", value.flags),
+            (b'c', Some(value)) => write!(returned, " c{}", value.cas),
+            (b's', Some(value)) => write!(returned, " s{}", value.data.bytes().len()),
+            (b't', Some(value)) => write!(returned, " t{}", ttl(value)),
+            _ => Ok(()),
+        };
+    }
+    returned
+}
+
+fn reply_unless(quiet: bool, output: &mut BytesMut, reply: &str) {
+    if !quiet {
+        output.extend_from_slice(reply.as_bytes());
+        output.extend_from_slice(b"\r\n");
+    }
+}
+
+/// Instant an exptime stands for: seconds from now up to 30 days, a unix timestamp past that,
+/// already expired when negative and never for 0.
+fn expires_at(exptime: i64) -> Option<Instant> {
+    let now = Instant::now();
+    let seconds = match exptime {
+        0 => return None,
+        ..0 => return Some(now),
+        1..=MAX_RELATIVE_EXPTIME => exptime,
+        _ => {
+            let unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            exptime.saturating_sub(unix.as_secs() as i64).max(0)
+        }
+    };
+
+    Some(now + Duration::from_secs(seconds as u64))
+}
+
+/// Seconds left before `value` expires, -1 if it never does.
+fn ttl(value: &Value) -> i64 {
+    value.expires_at.map_or(-1, |expires_at| {
+        expires_at.saturating_duration_since(Instant::now()).as_secs_f64().ceil() as i64
+    })
+}
+
+fn lossy(data: &GString) -> String {
+    String::from_utf8_lossy(data.as_ref()).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn store_command(mode: StoreMode, cas: Option<u64>) -> StoreCommand {
+        StoreCommand {
+            mode,
+            key: GString::from_static(b"key"),
+            flags: 7,
+            exptime: 0,
+            data: GString::from_static(b"new"),
+            cas,
+            noreply: false,
+        }
+    }
+
+    #[test]
+    fn store_outcomes() {
+        let current =
+            Value { cas: 3, ..Value::new(Data::from_gstring(GString::from_static(b"old"))) };
+        let outcome = |mode, cas, current| store_outcome(&store_command(mode, cas), current);
+
+        assert_eq!(outcome(StoreMode::Add, None, Some(&current)), StoreOutcome::NotStored);
+        assert_eq!(outcome(StoreMode::Add, None, None), StoreOutcome::Stored);
+        assert_eq!(outcome(StoreMode::Replace, None, None), StoreOutcome::NotStored);
+        assert_eq!(outcome(StoreMode::Append, None, None), StoreOutcome::NotStored);
+        assert_eq!(outcome(StoreMode::Set, Some(3), Some(&current)), StoreOutcome::Stored);
+        assert_eq!(outcome(StoreMode::Set, Some(4), Some(&current)), StoreOutcome::Exists);
+        assert_eq!(outcome(StoreMode::Set, Some(3), None), StoreOutcome::NotFound);
+    }
+
+    #[test]
+    fn append_keeps_flags() {
+        let current =
+            Value { flags: 1, ..Value::new(Data::from_gstring(GString::from_static(b"old"))) };
+        let value = stored_value(&store_command(StoreMode::Append, None), Some(&current));
+
+        assert_eq!(value.data.to_gstring(), GString::from_static(b"oldnew"));
+        assert_eq!(value.flags, 1);
+    }
+}
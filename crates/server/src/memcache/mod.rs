@@ -0,0 +1,94 @@
+use std::rc::Rc;
+
+use bytes::BytesMut;
+use futures::{
+    AsyncReadExt,
+    AsyncWriteExt,
+};
+use glommio::net::TcpStream;
+use tracing::{
+    error,
+    info,
+};
+
+use crate::{
+    memcache::{
+        command::{
+            McCommand,
+            McParser,
+        },
+        execute::execute,
+    },
+    shutdown::Stopping,
+    storage::router::StorageRouter,
+};
+
+pub mod command;
+pub mod execute;
+
+/// Serve a connection speaking the memcached text and meta protocols until it closes.
+///
+/// Pipelined commands are executed in order and their replies written back together.
+pub async fn serve(mut stream: TcpStream, router: Rc<StorageRouter>, stopping: Stopping) {
+    let maxclients = router.config().read().maxclients;
+    let Some(_client) = router.clients().connect(maxclients) else {
+        router.shard_stats().counters().rejected_connections += 1;
+        let _ = stream.write_all(b"SERVER_ERROR max number of clients reached\r\n").await;
+        return;
+    };
+
+    info!("started memcache processing");
+    let mut parser = McParser::default();
+    let mut input = BytesMut::new();
+    let mut output = BytesMut::new();
+    let mut read_buf = [0u8; 4096];
+    loop {
+        let mut quit = false;
+        loop {
+            match parser.parse(&mut input) {
+                Ok(Some(McCommand::Quit)) => quit = true,
+                Ok(Some(command)) => execute(command, &router, &mut output).await,
+                Ok(None) => break,
+                Err(error) => {
+                    router.shard_stats().counters().protocol_errors += 1;
+                    output.extend_from_slice(format!("{error}\r\n").as_bytes());
+                    quit = error.is_fatal();
+                }
+            }
+            if quit {
+                break;
+            }
+        }
+
+        if !output.is_empty() {
+            if let Err(error) = stream.write_all(&output).await {
+                error!("failed to respond: {error}");
+                return;
+            }
+            router.shard_stats().record_bytes(0, output.len() as u64);
+            output.clear();
+        }
+        if quit {
+            return;
+        }
+
+        let timeout = router.config().read().timeout;
+        match stopping.next(timeout, stream.read(&mut read_buf)).await {
+            None => {
+                info!("closing idle client");
+                break;
+            }
+            Some(Ok(0)) => break,
+            Some(Ok(read)) => {
+                router.shard_stats().record_bytes(read as u64, 0);
+                input.extend_from_slice(&read_buf[..read]);
+            }
+            Some(Err(error)) => {
+                error!("failed to read: {error}");
+                break;
+            }
+        }
+    }
+
+    info!("finished memcache processing");
+}
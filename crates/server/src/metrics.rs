@@ -0,0 +1,315 @@
+use std::{
+    fmt::{
+        Display,
+        Write,
+    },
+    rc::Rc,
+};
+
+use futures::{
+    AsyncReadExt,
+    AsyncWriteExt,
+};
+use glommio::net::TcpStream;
+use tracing::{
+    debug,
+    error,
+};
+
+use crate::{
+    processor::clients::idle_timeout,
+    stats::{
+        CommandStats,
+        Counters,
+        LATENCY_BUCKETS,
+    },
+    storage::{
+        response::{
+            StatsResponse,
+            StorageResult,
+        },
+        router::StorageRouter,
+    },
+};
+
+/// Seconds a scraper has to send its request.
+const REQUEST_TIMEOUT: u64 = 10;
+/// Longest request head accepted, scrapers send a few short headers.
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Name, help and value of a metric read from `T`.
+type Metric<T> = (&'static str, &'static str, fn(&T) -> u64);
+
+/// Serve one HTTP request on the metrics port, `GET /metrics` returns the metrics of every shard
+/// in the Prometheus text format.
+///
+/// The counters are kept by each shard without synchronization and collected from the storage
+/// actors like any other request, so scrapes never lock the shards.
+pub async fn serve(mut stream: TcpStream, router: Rc<StorageRouter>) {
+    let Some(head) = idle_timeout(REQUEST_TIMEOUT, read_head(&mut stream)).await.flatten() else {
+        debug!("invalid metrics request");
+        return;
+    };
+
+    let mut request_line = head.split(' ');
+    let response = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => match render(&router).await {
+            Ok(body) => response("200 OK", "text/plain; version=0.0.4; charset=utf-8", &body),
+            Err(error) => response("503 Service Unavailable", "text/plain", &format!("{error}\n")),
+        },
+        (Some("GET"), _) => response("404 Not Found", "text/plain", "not found\n"),
+        _ => response("405 Method Not Allowed", "text/plain", "method not allowed\n"),
+    };
+
+    if let Err(error) = stream.write_all(response.as_bytes()).await {
+        error!("failed to respond: {error}");
+    }
+    let _ = stream.close().await;
+}
+
+/// Read the request up to the end of its headers, returning its first line.
+async fn read_head(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") && !head.ends_with(b"\n\n") {
+        let read = stream.read(&mut buf).await.ok().filter(|read| *read > 0)?;
+        head.extend_from_slice(&buf[..read]);
+        if head.len() > MAX_REQUEST {
+            return None;
+        }
+    }
+
+    let line = head.split(|byte| *byte == b'\n').next()?;
+    Some(String::from_utf8_lossy(line).trim_end().to_string())
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Metrics of every shard, in the Prometheus text format.
+pub async fn render(router: &StorageRouter) -> StorageResult<String> {
+    let shards = router.collect_stats().await?;
+    let mut total = Counters::default();
+    for shard in &shards {
+        total.merge(&shard.counters);
+    }
+
+    let mut metrics = Exposition::default();
+    let uptime = router.shard_stats().uptime().as_secs();
+    metrics.family("goosekv_uptime_seconds", "gauge", "Seconds since the server started.");
+    metrics.sample("goosekv_uptime_seconds", &[], uptime);
+
+    shard_metrics(&mut metrics, router, &shards);
+
+    let depths = router.lane_depths();
+    metrics.family(
+        "goosekv_storage_queue_depth",
+        "gauge",
+        "Requests sent by the storage handle of a shard and not yet picked up by another.",
+    );
+    for from in 0..depths.shards() {
+        for to in (0..depths.shards()).filter(|to| *to != from) {
+            let (from_label, to_label) = (from.to_string(), to.to_string());
+            let labels = [("from", from_label.as_str()), ("to", to_label.as_str())];
+            metrics.sample("goosekv_storage_queue_depth", &labels, depths.depth(from, to));
+        }
+    }
+
+    let memory = router.memory();
+    metrics.family("goosekv_maxmemory_bytes", "gauge", "Memory the keys may use, 0 for no limit.");
+    metrics.sample("goosekv_maxmemory_bytes", &[], router.config().read().maxmemory.0);
+    metrics.family("goosekv_evicted_keys_total", "counter", "Keys evicted to fit maxmemory.");
+    metrics.sample("goosekv_evicted_keys_total", &[], memory.evicted());
+
+    command_metrics(&mut metrics, &total);
+
+    metrics.family("goosekv_errors_total", "counter", "Error replies, by error code.");
+    for (code, count) in &total.errors {
+        metrics.sample("goosekv_errors_total", &[("code", code)], count);
+    }
+
+    Ok(metrics.text)
+}
+
+/// Keys, memory, connections and traffic of each shard.
+fn shard_metrics(metrics: &mut Exposition, router: &StorageRouter, shards: &[StatsResponse]) {
+    let memory = router.memory();
+    let gauges: [Metric<StatsResponse>; 3] = [
+        ("goosekv_keys", "Keys stored by the shard.", |shard| shard.keys as u64),
+        ("goosekv_expiring_keys", "Keys with an expiration time.", |shard| shard.expires as u64),
+        ("goosekv_connected_clients", "Connections served by the shard.", |shard| {
+            shard.connected as u64
+        }),
+    ];
+    let counters: [Metric<Counters>; 9] = [
+        ("goosekv_connections_received_total", "Connections accepted by the shard.", |counters| {
+            counters.connections_received
+        }),
+        (
+            "goosekv_rejected_connections_total",
+            "Connections refused because of maxclients.",
+            |counters| counters.rejected_connections,
+        ),
+        ("goosekv_net_input_bytes_total", "Bytes read from clients.", |counters| {
+            counters.net_input_bytes
+        }),
+        ("goosekv_net_output_bytes_total", "Bytes written to clients.", |counters| {
+            counters.net_output_bytes
+        }),
+        ("goosekv_keyspace_hits_total", "Reads of existing keys.", |counters| {
+            counters.keyspace_hits
+        }),
+        ("goosekv_keyspace_misses_total", "Reads of missing keys.", |counters| {
+            counters.keyspace_misses
+        }),
+        ("goosekv_protocol_errors_total", "Requests that could not be parsed.", |counters| {
+            counters.protocol_errors
+        }),
+        (
+            "goosekv_local_requests_total",
+            "Storage requests served by the storage of the shard.",
+            |counters| counters.local_requests,
+        ),
+        ("goosekv_remote_requests_total", "Storage requests sent to another shard.", |counters| {
+            counters.remote_requests
+        }),
+    ];
+
+    for (name, help, value) in gauges {
+        metrics.family(name, "gauge", help);
+        for (index, shard) in shards.iter().enumerate() {
+            metrics.sample(name, &[("shard", &index.to_string())], value(shard));
+        }
+    }
+    metrics.family("goosekv_used_memory_bytes", "gauge", "Estimated memory used by the keys.");
+    for index in 0..shards.len() {
+        metrics.sample(
+            "goosekv_used_memory_bytes",
+            &[("shard", &index.to_string())],
+            memory.shard(index),
+        );
+    }
+    for (name, help, value) in counters {
+        metrics.family(name, "counter", help);
+        for (index, shard) in shards.iter().enumerate() {
+            metrics.sample(name, &[("shard", &index.to_string())], value(&shard.counters));
+        }
+    }
+    metrics.family(
+        "goosekv_local_request_ratio",
+        "gauge",
+        "Fraction of storage requests served without crossing shards.",
+    );
+    for (index, shard) in shards.iter().enumerate() {
+        metrics.sample(
+            "goosekv_local_request_ratio",
+            &[("shard", &index.to_string())],
+            shard.counters.local_ratio(),
+        );
+    }
+}
+
+/// Calls, errors and latency histogram of each command, summed over every shard.
+fn command_metrics(metrics: &mut Exposition, total: &Counters) {
+    let commands: Vec<_> = total.named_commands().collect();
+
+    let counters: [Metric<CommandStats>; 3] = [
+        ("goosekv_commands_total", "Commands executed.", |stats| stats.calls),
+        ("goosekv_commands_failed_total", "Commands answered with an error.", |stats| {
+            stats.failed_calls
+        }),
+        ("goosekv_commands_rejected_total", "Commands refused before being executed.", |stats| {
+            stats.rejected_calls
+        }),
+    ];
+    for (name, help, value) in counters {
+        metrics.family(name, "counter", help);
+        for (command, stats) in &commands {
+            metrics.sample(name, &[("command", command)], value(stats));
+        }
+    }
+
+    let histogram = "goosekv_command_duration_seconds";
+    metrics.family(histogram, "histogram", "Time spent executing commands.");
+    for (command, stats) in &commands {
+        metrics.histogram(histogram, command, stats);
+    }
+}
+
+/// Metrics being written in the Prometheus text format.
+#[derive(Debug, Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = write!(self.text, "# HELP {name} {help}\n# TYPE {name} {kind}\n");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {value}");
+    }
+
+    /// Cumulative buckets of the latencies of `command`, in seconds.
+    fn histogram(&mut self, name: &str, command: &str, stats: &CommandStats) {
+        let bucket = format!("{name}_bucket");
+        let mut calls = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.latency) {
+            calls += count;
+            let le = (*bound as f64 / 1_000_000.0).to_string();
+            self.sample(&bucket, &[("command", command), ("le", &le)], calls);
+        }
+        self.sample(&bucket, &[("command", command), ("le", "+Inf")], stats.calls);
+        let sum = stats.usec as f64 / 1_000_000.0;
+        self.sample(&format!("{name}_sum"), &[("command", command)], sum);
+        self.sample(&format!("{name}_count"), &[("command", command)], stats.calls);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn histogram_is_cumulative() {
+        let mut latency = [0; LATENCY_BUCKETS.len()];
+        latency[0] = 2;
+        latency[2] = 1;
+        let stats = CommandStats { calls: 4, usec: 2_000_070, latency, ..Default::default() };
+
+        let mut metrics = Exposition::default();
+        metrics.histogram("latency", "config|get", &stats);
+        let lines: Vec<_> = metrics.text.lines().collect();
+        assert_eq!(lines[0], "latency_bucket{command=\"config|get\",le=\"0.000001\"} 2");
+        assert_eq!(lines[2], "latency_bucket{command=\"config|get\",le=\"0.000004\"} 3");
+        assert_eq!(lines[24], "latency_bucket{command=\"config|get\",le=\"16.777216\"} 3");
+        assert_eq!(lines[25], "latency_bucket{command=\"config|get\",le=\"+Inf\"} 4");
+        assert_eq!(lines[26], "latency_sum{command=\"config|get\"} 2.00007");
+        assert_eq!(lines[27], "latency_count{command=\"config|get\"} 4");
+    }
+
+    #[test]
+    fn escapes_labels() {
+        let mut metrics = Exposition::default();
+        metrics.sample("errors", &[("code", "a\"b\\c")], 1);
+        assert_eq!(metrics.text, "errors{code=\"a\\\"b\\\\c\"} 1\n");
+    }
+}
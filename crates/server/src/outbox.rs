@@ -0,0 +1,56 @@
+use futures::channel::mpsc;
+
+/// Messages a connection may fall behind by before it is disconnected.
+pub const MAX_PENDING: usize = 64 * 1024;
+
+/// Messages queued by any shard for one connection, such as a monitor or a tracking client.
+///
+/// A connection reading too slowly is disconnected rather than buffered without bound: the queue
+/// is closed once [`MAX_PENDING`] messages wait in it, which ends its receiver.
+#[derive(Debug)]
+pub struct Outbox<T> {
+    sender: mpsc::Sender<T>,
+}
+
+impl<T> Outbox<T> {
+    /// Outbox and the receiver of the connection it feeds.
+    pub fn new() -> (Self, mpsc::Receiver<T>) {
+        let (sender, receiver) = mpsc::channel(MAX_PENDING);
+        (Self { sender }, receiver)
+    }
+
+    /// Queue `message`, or close the outbox if the connection fell too far behind.
+    pub fn push(&mut self, message: T) {
+        if let Err(error) = self.sender.try_send(message)
+            && error.is_full()
+        {
+            self.sender.close_channel();
+        }
+    }
+
+    pub fn feeds(&self, receiver: &mpsc::Receiver<T>) -> bool {
+        self.sender.is_connected_to(receiver)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn closes_once_full() {
+        let (mut outbox, mut receiver) = Outbox::new();
+        assert!(outbox.feeds(&receiver));
+        for message in 0..=MAX_PENDING + 1 {
+            outbox.push(message);
+        }
+
+        let mut received = 0;
+        while let Ok(Some(_)) = receiver.try_next() {
+            received += 1;
+        }
+        assert!(received >= MAX_PENDING);
+        // The channel ended, the connection is disconnected.
+        assert!(matches!(receiver.try_next(), Ok(None)));
+    }
+}
@@ -1,11 +1,22 @@
 use std::{
+    pin::pin,
     rc::Rc,
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+    },
 };
 
 use futures::{
+    FutureExt,
     SinkExt,
     StreamExt,
+    future::{
+        Either,
+        pending,
+        select,
+    },
 };
 use glommio::{
     Latency,
@@ -15,109 +26,441 @@ use glommio::{
         LocalReceiver,
     },
     executor,
-    net::TcpStream,
-    spawn_local,
     spawn_local_into,
+    sync::Gate,
 };
 use goosekv_protocol::{
-    command::GCommand,
+    command::{
+        ClientReplyMode,
+        GCommand,
+        args_from_frame,
+        table::{
+            CommandFlag,
+            CommandSpec,
+        },
+    },
     data_type::GString,
+    error::ReplyError,
     frame::GFrame,
     stream::GFrameStream,
 };
 use tracing::{
+    debug,
     error,
     info,
+    warn,
 };
 
 use crate::{
+    acl::DenialReason,
+    latency::LatencyEvent,
+    memcache,
+    metrics,
     processor::{
+        clients::{
+            Client,
+            ClientInfo,
+        },
         command::{
             ProcessCommand,
             ProcessorCommand,
         },
         handle::ProcessorHandle,
         handler::handle_gcommand,
+        monitor,
+        session::Session,
+    },
+    replication::sync,
+    shutdown::Stopping,
+    slowlog,
+    storage::router::{
+        KeyAccess,
+        StorageRouter,
+    },
+    tls::{
+        self,
+        Connection,
+    },
+    tracking::{
+        Invalidation,
+        Listener,
     },
-    storage::router::StorageRouter,
 };
 
-pub struct ProcessorActor;
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 32;
+
+pub struct ProcessorActor {
+    channel_capacity: usize,
+}
 
 impl Default for ProcessorActor {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_CHANNEL_CAPACITY)
     }
 }
 
 impl ProcessorActor {
-    pub fn new() -> Self {
-        Self
+    pub fn new(channel_capacity: usize) -> Self {
+        Self { channel_capacity }
     }
 
-    pub fn run(self, router: StorageRouter) -> (impl Future<Output = ()>, ProcessorHandle) {
-        let (sender, receiver) = local_channel::new_bounded(32);
+    /// Start serving the connections sent to the returned handle.
+    ///
+    /// Once `stopping`, the task resolves when every connection drained or was dropped past the
+    /// deadline.
+    pub fn run(
+        self,
+        router: Rc<StorageRouter>,
+        stopping: Stopping,
+    ) -> (impl Future<Output = ()>, ProcessorHandle) {
+        let (sender, receiver) = local_channel::new_bounded(self.channel_capacity);
         let task_queue = executor().create_task_queue(
             Shares::default(),
             Latency::Matters(Duration::from_millis(1)),
             "PROCESSOR",
         );
-        let task = spawn_local_into(async { run(receiver, router).await }, task_queue).unwrap();
+        let task =
+            spawn_local_into(async { run(receiver, router, stopping).await }, task_queue).unwrap();
         (task, ProcessorHandle::new(sender))
     }
 }
 
-async fn run(receiver: LocalReceiver<ProcessorCommand>, router: StorageRouter) {
-    let router = Rc::new(router);
+async fn run(
+    receiver: LocalReceiver<ProcessorCommand>,
+    router: Rc<StorageRouter>,
+    stopping: Stopping,
+) {
+    let connections = Gate::new();
     while let Some(command) = receiver.recv().await {
-        match command {
+        let stats = router.shard_stats().clone();
+        let router = router.clone();
+        // Scrapes of the metrics are not counted as client connections.
+        let (connection, client) = match command {
             ProcessorCommand::Process(process_command) => {
-                let router = router.clone();
-                spawn_local(async move { process(process_command, router.clone()).await }).detach();
+                (process(*process_command, router, stopping.clone()).boxed_local(), true)
             }
-        }
+            ProcessorCommand::Tls(tls_command) => {
+                let stopping = stopping.clone();
+                let handshake = async move {
+                    let Some(acceptor) = router.tls().acceptor() else {
+                        return;
+                    };
+                    match Connection::accept(acceptor, tls_command.stream).await {
+                        Ok(connection) => {
+                            let stream = GFrameStream::new(connection);
+                            process(ProcessCommand { stream }, router, stopping).await;
+                        }
+                        Err(error) => warn!("TLS handshake failed: {error}"),
+                    }
+                };
+                (handshake.boxed_local(), true)
+            }
+            ProcessorCommand::Memcache(memcache_command) => {
+                let serve = memcache::serve(memcache_command.stream, router, stopping.clone());
+                (serve.boxed_local(), true)
+            }
+            ProcessorCommand::Metrics(metrics_command) => {
+                (metrics::serve(metrics_command.stream, router).boxed_local(), false)
+            }
+        };
+        let stopping = stopping.clone();
+        let task = async move {
+            if client {
+                stats.connect();
+            }
+            if let Either::Right(_) = select(connection, pin!(stopping.deadline())).await {
+                warn!("dropped a client still busy past the shutdown deadline");
+            }
+            if client {
+                stats.disconnect();
+            }
+        };
+        connections.spawn(task).unwrap().detach();
     }
+
+    connections.close().await.unwrap();
 }
 
-async fn process(mut command: ProcessCommand, router: Rc<StorageRouter>) {
+async fn process(mut command: ProcessCommand, router: Rc<StorageRouter>, stopping: Stopping) {
+    let maxclients = router.config().read().maxclients;
+    let Some(_client) = router.clients().connect(maxclients) else {
+        router.shard_stats().counters().rejected_connections += 1;
+        let error = ReplyError::Err("max number of clients reached".into());
+        handle_error(&mut command.stream, error).await;
+        return;
+    };
+
     info!("started processing");
-    while let Some(frame) = command.stream.next().await {
+    let socket = command.stream.get_ref().tcp();
+    let (client, mut killed) = Client::new(ClientInfo {
+        id: router.clients().next_id(),
+        shard: router.shard(),
+        addr: socket.peer_addr().ok(),
+        laddr: socket.local_addr().ok(),
+        ..Default::default()
+    });
+    let _registration = router.shard_clients().register(client.clone());
+    // Connections are authenticated as `default` as long as it needs no password.
+    let authenticated = !router.acl().auth_required();
+    let mut session = Session { client, authenticated, ..Default::default() };
+    // A client certificate authenticates its client as the user it names, if there is one.
+    let field = router.config().read().tls_auth_clients_user;
+    if let Some(certificate) = command.stream.get_ref().peer_certificate()
+        && let Some(name) = tls::certificate_user(field, certificate, router.acl())
+    {
+        session.login(&name);
+    }
+    let (mut read, mut written) = (0, 0);
+    loop {
+        let timeout = router.config().read().timeout;
+        let next = {
+            let next = select(command.stream.next(), &mut killed).map(|next| match next {
+                Either::Left((frame, _)) => frame,
+                Either::Right(_) => {
+                    info!("client killed");
+                    None
+                }
+            });
+            let invalidation = pin!(next_invalidation(&mut session.invalidations));
+            let next = select(next, invalidation).map(|next| match next {
+                Either::Left((frame, _)) => Either::Left(frame),
+                Either::Right((invalidation, _)) => Either::Right(invalidation),
+            });
+            stopping.next(timeout, next).await
+        };
+        let frame = match next {
+            Some(Either::Left(frame)) => frame,
+            // Invalidations are pushed between replies, as the RESP3 connection expects them.
+            Some(Either::Right(Some(invalidation))) => {
+                if let Err(error) = command.stream.send(invalidation.frame()).await {
+                    error!("failed to push invalidation: {error}");
+                }
+                continue;
+            }
+            Some(Either::Right(None)) => {
+                warn!("client fell behind on invalidations, disconnecting it");
+                break;
+            }
+            None => {
+                info!("closing idle client");
+                break;
+            }
+        };
+        let Some(frame) = frame else {
+            break;
+        };
         info!("new frame");
         match frame {
             Ok(frame) => {
-                let response = handle_frame(frame, &router).await;
-                if let Err(error) = command.stream.send(response).await {
+                // `CLIENT REPLY SKIP` drops the reply of the command following it.
+                let skip_next = session.reply == ClientReplyMode::Skip;
+                let response = handle_frame(frame, &router, &mut session).await;
+                if session.quit {
+                    break;
+                }
+                if skip_next && session.reply == ClientReplyMode::Skip {
+                    session.reply = ClientReplyMode::On;
+                }
+                let silent = std::mem::take(&mut session.skip_reply)
+                    || skip_next
+                    || session.reply == ClientReplyMode::Off;
+                if !silent && let Err(error) = command.stream.send(response).await {
                     error!("failed to respond: {error}");
                 }
+                let stream = &command.stream;
+                let (input, output) =
+                    (stream.bytes_read() - read, stream.bytes_written() - written);
+                router.shard_stats().record_bytes(input, output);
+                (read, written) = (stream.bytes_read(), stream.bytes_written());
+                session.client.update(|info| {
+                    info.last_interaction = Instant::now();
+                    info.query_buffer = stream.read_buffered();
+                    info.output_buffer = stream.write_buffered();
+                    (info.net_input, info.net_output) = (read, written);
+                    info.flags.readonly = session.readonly;
+                });
+
+                if let Some(sync) = session.replica_sync.take() {
+                    session.client.update(|info| info.flags.replica = true);
+                    let serve = sync::serve(command.stream, sync, router, session.listening_port);
+                    stopping.until(select(pin!(serve), killed).map(drop)).await;
+                    return;
+                }
+                if session.monitor {
+                    let serve = monitor::serve(command.stream, router);
+                    stopping.until(select(pin!(serve), killed).map(drop)).await;
+                    return;
+                }
             }
             Err(error) => {
-                let message = format!("invalid frame: {error}");
-                error!("{message}");
-                handle_error(&mut command.stream, message.as_str()).await;
+                error!("invalid frame: {error}");
+                router.shard_stats().counters().protocol_errors += 1;
+                handle_error(&mut command.stream, ReplyError::Protocol(error.to_string())).await;
+                // The stream cannot resync after an invalid frame.
+                break;
             }
         }
     }
 }
 
-async fn handle_frame(frame: GFrame, router: &StorageRouter) -> GFrame {
-    let command = GCommand::from_frame(&frame);
-    match command {
-        Ok(command) => handle_gcommand(command, router).await,
+/// Next invalidation pushed to the connection, `None` once it fell too far behind.
+///
+/// Never resolves before the connection switched to RESP3.
+async fn next_invalidation(invalidations: &mut Option<Listener>) -> Option<Invalidation> {
+    match invalidations {
+        Some(invalidations) => invalidations.next().await,
+        None => pending().await,
+    }
+}
+
+async fn handle_frame(frame: GFrame, router: &StorageRouter, session: &mut Session) -> GFrame {
+    let asking = std::mem::take(&mut session.asking);
+
+    let stats = router.shard_stats();
+
+    let (command, keys, spec) = match parse_frame(&frame) {
+        Ok(parsed) => parsed,
         Err(error) => {
-            let message = format!("invalid command: {error}");
-            error!("{message}");
-            error_frame(&message)
+            error!("invalid command: {error}");
+            let spec = named_spec(&frame);
+            let reply = error.into();
+            stats.record_rejection(spec, &reply);
+            return reply;
+        }
+    };
+    session.client.update(|info| info.last_command = Some(spec));
+    let reject = |error: ReplyError| {
+        let reply = error.into();
+        stats.record_rejection(Some(spec), &reply);
+        reply
+    };
+
+    // `AUTH` and `HELLO` are how a connection authenticates, so they are never denied.
+    if !spec.has_flag(CommandFlag::NoAuth)
+        && let Err(error) = authorize(router, session, spec, &keys)
+    {
+        return reject(error);
+    }
+
+    // `CLIENT UNPAUSE` is let through, or nothing could end the pause early.
+    if !matches!(command, GCommand::ClientUnpause(_)) {
+        router.clients().unpaused(spec.has_flag(CommandFlag::Write)).await;
+    }
+
+    if spec.has_flag(CommandFlag::Write) && router.replication().is_replica() {
+        return reject(ReplyError::ReadOnly);
+    }
+
+    if spec.has_flag(CommandFlag::Denyoom) && router.out_of_memory() {
+        return reject(ReplyError::OutOfMemory);
+    }
+
+    let readonly = spec.has_flag(CommandFlag::Readonly);
+    let access = KeyAccess { readonly, replica_reads: session.readonly, asking };
+    if let Err(redirect) = router.redirect(&keys, access).await {
+        debug!("redirected command: {redirect}");
+        return reject(redirect);
+    }
+
+    let args = || args_from_frame(&frame).map_or_else(|_| Vec::new(), Vec::from);
+    if monitor::shows(spec) {
+        let monitors = router.clients().monitors();
+        let addr = session.client.info().addr;
+        monitors.feed(|| monitor::line(SystemTime::now(), addr, &args()));
+    }
+
+    // `CLIENT CACHING` applies to the command following it only.
+    let caching = matches!(command, GCommand::ClientCaching(_));
+    let started = Instant::now();
+    let reply = handle_gcommand(command, router, session).await;
+    if !caching {
+        session.caching = None;
+    }
+    let elapsed = started.elapsed();
+    stats.record_call(spec, elapsed, &reply);
+    session.client.update(|info| info.commands += 1);
+    router.slowlog().record(elapsed, || {
+        let client = session.client.info();
+        let addr = client.addr.map_or_else(String::new, |addr| addr.to_string());
+        (slowlog::redact(spec, args()), addr, client.name.clone())
+    });
+    let event = match spec.has_flag(CommandFlag::Fast) {
+        true => LatencyEvent::FastCommand,
+        false => LatencyEvent::Command,
+    };
+    router.latency().record(event, elapsed);
+    reply
+}
+
+/// Check the user of the connection may run `spec` on `keys`, logging denials to `ACL LOG`.
+fn authorize(
+    router: &StorageRouter,
+    session: &mut Session,
+    spec: &'static CommandSpec,
+    keys: &[GString],
+) -> Result<(), ReplyError> {
+    let acl = router.acl();
+    if !session.authenticated && acl.auth_required() {
+        return Err(ReplyError::NoAuth);
+    }
+
+    let (reason, object) = match session.user(acl) {
+        Some(user) if user.can_run(spec) => {
+            let access = spec.keys.access;
+            match keys.iter().find(|key| !user.can_access(key.as_ref(), access)) {
+                Some(key) => {
+                    (DenialReason::Key, String::from_utf8_lossy(key.as_ref()).into_owned())
+                }
+                None => return Ok(()),
+            }
+        }
+        _ => (DenialReason::Command, spec.full_name()),
+    };
+    let info = session.client.info();
+    let max_len = router.config().read().acllog_max_len;
+    acl.record_denial(reason, object, &info.user, info.line(), max_len);
+    let mut counters = router.shard_stats().counters();
+    match reason {
+        DenialReason::Key => {
+            counters.acl_access_denied_key += 1;
+            Err(ReplyError::NoPerm("No permissions to access a key".into()))
+        }
+        _ => {
+            counters.acl_access_denied_cmd += 1;
+            let command = spec.full_name();
+            let message =
+                format!("User {} has no permissions to run the '{command}' command", info.user);
+            Err(ReplyError::NoPerm(message))
         }
     }
 }
 
-fn error_frame(message: &str) -> GFrame {
-    GFrame::SimpleError(GString::copy_from_slice(message.as_bytes()))
+/// Parse a command along with the keys it accesses and its spec.
+fn parse_frame(
+    frame: &GFrame,
+) -> Result<(GCommand, Vec<GString>, &'static CommandSpec), ReplyError> {
+    let args = args_from_frame(frame)?;
+    let spec = CommandSpec::resolve(&args)?;
+    let command = GCommand::from_args(spec, &args)?;
+    let keys = spec.key_indices(args.len()).into_iter().map(|index| args[index].clone()).collect();
+
+    Ok((command, keys, spec))
+}
+
+/// Spec of the command named by `frame`, even if its arguments are invalid.
+fn named_spec(frame: &GFrame) -> Option<&'static CommandSpec> {
+    let args = args_from_frame(frame).ok()?;
+    let spec = CommandSpec::find(args.first()?.as_ref())?;
+    match args.get(1) {
+        Some(subcommand) if !spec.subcommands.is_empty() => {
+            spec.find_subcommand(subcommand.as_ref())
+        }
+        _ => Some(spec),
+    }
 }
 
-async fn handle_error(stream: &mut GFrameStream<TcpStream>, message: &str) {
-    error!("{message}");
-    if let Err(error) = stream.send(error_frame(message)).await {
+async fn handle_error(stream: &mut GFrameStream<Connection>, error: ReplyError) {
+    if let Err(error) = stream.send(error.into()).await {
         error!("failed to respond: {error}");
     }
 }
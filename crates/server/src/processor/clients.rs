@@ -0,0 +1,466 @@
+use std::{
+    cell::{
+        Ref,
+        RefCell,
+    },
+    collections::BTreeMap,
+    net::SocketAddr,
+    pin::pin,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            AtomicUsize,
+            Ordering,
+        },
+    },
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use futures::{
+    StreamExt,
+    channel::oneshot,
+    future::{
+        Either,
+        select,
+    },
+};
+use glommio::timer::sleep;
+use goosekv_protocol::{
+    command::table::CommandSpec,
+    data_type::GString,
+};
+
+use crate::{
+    acl::DEFAULT_USER,
+    event::Event,
+    processor::monitor::Monitors,
+    tracking::Trackers,
+};
+
+/// Client connections served by every shard, counted against `maxclients`.
+#[derive(Debug, Default)]
+pub struct Clients {
+    connected: AtomicUsize,
+    /// Id of the last client connected, ids are unique across shards.
+    last_id: AtomicU64,
+    /// End of `CLIENT PAUSE` in milliseconds since the Unix epoch, `0` if clients are not paused.
+    paused_until: AtomicU64,
+    /// Only commands that write are paused.
+    pause_writes: AtomicBool,
+    /// Fired by `CLIENT PAUSE` and `CLIENT UNPAUSE`.
+    pauses: Event,
+    monitors: Monitors,
+    trackers: Arc<Trackers>,
+}
+
+impl Clients {
+    /// Register a connection, `None` if `maxclients` connections are already served.
+    pub fn connect(self: &Arc<Self>, maxclients: usize) -> Option<ClientGuard> {
+        self.connected
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |connected| {
+                (connected < maxclients).then_some(connected + 1)
+            })
+            .ok()
+            .map(|_| ClientGuard { clients: self.clone() })
+    }
+
+    pub fn connected(&self) -> usize {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    pub fn next_id(&self) -> u64 {
+        self.last_id.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    pub fn monitors(&self) -> &Monitors {
+        &self.monitors
+    }
+
+    /// Clients tracking keys for client-side caching.
+    pub fn trackers(&self) -> &Arc<Trackers> {
+        &self.trackers
+    }
+
+    /// Pause the commands of every client for `timeout`, or only those writing with `writes`.
+    ///
+    /// A pause already in progress is only extended, and stays on every command unless both pause
+    /// writes only.
+    pub fn pause(&self, timeout: Duration, writes: bool) {
+        let now = unix_millis();
+        let until = now + timeout.as_millis() as u64;
+        let previous = self.paused_until.load(Ordering::Acquire);
+        let writes = match previous > now {
+            true => writes && self.pause_writes.load(Ordering::Acquire),
+            false => writes,
+        };
+        self.pause_writes.store(writes, Ordering::Release);
+        self.paused_until.store(until.max(previous), Ordering::Release);
+        self.pauses.notify();
+    }
+
+    pub fn unpause(&self) {
+        self.paused_until.store(0, Ordering::Release);
+        self.pauses.notify();
+    }
+
+    /// Time left before a command is no longer paused, `None` if it may run now.
+    pub fn paused(&self, write: bool) -> Option<Duration> {
+        let until = self.paused_until.load(Ordering::Acquire);
+        if until == 0 || (!write && self.pause_writes.load(Ordering::Acquire)) {
+            return None;
+        }
+        let left = until.saturating_sub(unix_millis());
+        (left > 0).then(|| Duration::from_millis(left))
+    }
+
+    /// Resolves once a command is no longer paused, when the pause ends or changes.
+    pub async fn unpaused(&self, write: bool) {
+        let mut pauses = self.pauses.listen();
+        while let Some(left) = self.paused(write) {
+            select(pauses.next(), pin!(sleep(left))).await;
+        }
+    }
+}
+
+/// Registered connection, unregistered when dropped.
+#[derive(Debug)]
+pub struct ClientGuard {
+    clients: Arc<Clients>,
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.clients.connected.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Connection of a client, listed by `CLIENT LIST` on the shard serving it.
+#[derive(Debug, Default)]
+pub struct Client {
+    info: RefCell<ClientInfo>,
+    /// Fired by `CLIENT KILL` to close the connection.
+    kill: RefCell<Option<oneshot::Sender<()>>>,
+}
+
+impl Client {
+    /// Create a client along with the receiver resolving once it is killed.
+    pub fn new(info: ClientInfo) -> (Rc<Self>, oneshot::Receiver<()>) {
+        let (sender, receiver) = oneshot::channel();
+        let client = Self { info: RefCell::new(info), kill: RefCell::new(Some(sender)) };
+        (Rc::new(client), receiver)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.info.borrow().id
+    }
+
+    pub fn info(&self) -> Ref<'_, ClientInfo> {
+        self.info.borrow()
+    }
+
+    pub fn update(&self, update: impl FnOnce(&mut ClientInfo)) {
+        update(&mut self.info.borrow_mut());
+    }
+
+    /// Close the connection once its current command is answered.
+    pub fn kill(&self) {
+        if let Some(kill) = self.kill.borrow_mut().take() {
+            let _ = kill.send(());
+        }
+    }
+}
+
+/// State of a client connection, as `CLIENT LIST` and `CLIENT INFO` report it.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+    /// Shard serving the connection.
+    pub shard: usize,
+    pub addr: Option<SocketAddr>,
+    /// Local address the client connected to.
+    pub laddr: Option<SocketAddr>,
+    /// Set by `CLIENT SETNAME`, empty if the client has no name.
+    pub name: GString,
+    /// Set by `CLIENT SETINFO`.
+    pub lib_name: GString,
+    pub lib_ver: GString,
+    pub user: String,
+    pub created: Instant,
+    pub last_interaction: Instant,
+    /// Last command executed, `None` before the first one.
+    pub last_command: Option<&'static CommandSpec>,
+    pub flags: ClientFlags,
+    /// Bytes read and not yet parsed into a command.
+    pub query_buffer: usize,
+    /// Bytes of replies not yet written.
+    pub output_buffer: usize,
+    pub net_input: u64,
+    pub net_output: u64,
+    pub commands: u64,
+    /// Protocol version negotiated with `HELLO`.
+    pub resp: u8,
+    /// Client receiving the invalidations of this one, set by `CLIENT TRACKING ON REDIRECT`.
+    pub redirect: Option<u64>,
+}
+
+impl Default for ClientInfo {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            id: 0,
+            shard: 0,
+            addr: None,
+            laddr: None,
+            name: GString::from_static(b""),
+            lib_name: GString::from_static(b""),
+            lib_ver: GString::from_static(b""),
+            user: DEFAULT_USER.to_string(),
+            created: now,
+            last_interaction: now,
+            last_command: None,
+            flags: ClientFlags::default(),
+            query_buffer: 0,
+            output_buffer: 0,
+            net_input: 0,
+            net_output: 0,
+            commands: 0,
+            resp: 2,
+            redirect: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientFlags {
+    /// Sent `MONITOR`.
+    pub monitor: bool,
+    /// Sent `PSYNC` and receives the replication stream.
+    pub replica: bool,
+    /// Sent `READONLY`.
+    pub readonly: bool,
+    /// Set by `CLIENT NO-EVICT ON`.
+    pub no_evict: bool,
+    /// Set by `CLIENT NO-TOUCH ON`, reads do not count as accesses for eviction.
+    pub no_touch: bool,
+    /// Set by `CLIENT TRACKING ON`.
+    pub tracking: bool,
+}
+
+impl ClientInfo {
+    pub fn kind(&self) -> ClientType {
+        if self.flags.replica && !self.flags.monitor {
+            ClientType::Replica
+        } else {
+            ClientType::Normal
+        }
+    }
+
+    /// Line describing the client in `CLIENT LIST` and `CLIENT INFO`.
+    pub fn line(&self) -> String {
+        let addr = |addr: Option<SocketAddr>| addr.map_or_else(String::new, |a| a.to_string());
+        let cmd = self.last_command.map_or_else(|| "NULL".to_string(), CommandSpec::full_name);
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db=0 sub=0 psub=0 ssub=0 \
+             multi=-1 qbuf={} omem={} tot-net-in={} tot-net-out={} tot-cmds={} cmd={cmd} user={} \
+             redir={} resp={} lib-name={} lib-ver={} shard={}",
+            self.id,
+            addr(self.addr),
+            addr(self.laddr),
+            String::from_utf8_lossy(self.name.as_ref()),
+            self.created.elapsed().as_secs(),
+            self.last_interaction.elapsed().as_secs(),
+            self.flags(),
+            self.query_buffer,
+            self.output_buffer,
+            self.net_input,
+            self.net_output,
+            self.commands,
+            self.user,
+            self.redir(),
+            self.resp,
+            String::from_utf8_lossy(self.lib_name.as_ref()),
+            String::from_utf8_lossy(self.lib_ver.as_ref()),
+            self.shard,
+        )
+    }
+
+    /// Client the invalidations are redirected to, `0` if not redirected and `-1` without tracking.
+    pub fn redir(&self) -> i64 {
+        match (self.flags.tracking, self.redirect) {
+            (false, _) => -1,
+            (true, redirect) => redirect.map_or(0, |redirect| redirect as i64),
+        }
+    }
+
+    fn flags(&self) -> String {
+        let flags = [
+            (self.flags.replica, 'S'),
+            (self.flags.monitor, 'O'),
+            (self.flags.readonly, 'r'),
+            (self.flags.no_evict, 'e'),
+            (self.flags.no_touch, 'T'),
+            (self.flags.tracking, 't'),
+        ];
+        let flags: String = flags.iter().filter(|(set, _)| *set).map(|(_, flag)| flag).collect();
+        if flags.is_empty() { "N".to_string() } else { flags }
+    }
+}
+
+/// Kind of client, as filtered by `CLIENT LIST TYPE` and `CLIENT KILL TYPE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientType {
+    Normal,
+    /// Link of a replica to its primary, not served as a client connection.
+    Master,
+    Replica,
+    PubSub,
+}
+
+impl ClientType {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name.to_ascii_lowercase().as_slice() {
+            b"normal" => Some(ClientType::Normal),
+            b"master" => Some(ClientType::Master),
+            b"replica" | b"slave" => Some(ClientType::Replica),
+            b"pubsub" => Some(ClientType::PubSub),
+            _ => None,
+        }
+    }
+}
+
+/// Clients `CLIENT LIST` or `CLIENT KILL` apply to, matching every filter set.
+#[derive(Debug, Clone, Default)]
+pub struct ClientFilter {
+    /// Any of these ids, any id if empty.
+    pub ids: Vec<u64>,
+    pub kind: Option<ClientType>,
+    pub user: Option<String>,
+    pub addr: Option<String>,
+    pub laddr: Option<String>,
+    /// Client left out, the one sending `CLIENT KILL` unless it passed `SKIPME no`.
+    pub skip: Option<u64>,
+    /// Only clients connected for more than this many seconds.
+    pub max_age: Option<u64>,
+}
+
+impl ClientFilter {
+    pub fn matches(&self, client: &ClientInfo) -> bool {
+        let addr = |addr: Option<SocketAddr>, filter: &Option<String>| {
+            filter.as_ref().is_none_or(|filter| addr.is_some_and(|a| a.to_string() == *filter))
+        };
+        (self.ids.is_empty() || self.ids.contains(&client.id))
+            && self.kind.is_none_or(|kind| kind == client.kind())
+            && self.user.as_ref().is_none_or(|user| *user == client.user)
+            && addr(client.addr, &self.addr)
+            && addr(client.laddr, &self.laddr)
+            && self.skip != Some(client.id)
+            && self.max_age.is_none_or(|age| client.created.elapsed().as_secs() > age)
+    }
+}
+
+/// Clients served by one shard.
+#[derive(Debug, Default)]
+pub struct ShardClients {
+    clients: RefCell<BTreeMap<u64, Rc<Client>>>,
+}
+
+impl ShardClients {
+    /// List `client` until the returned registration is dropped.
+    pub fn register(self: &Rc<Self>, client: Rc<Client>) -> ClientRegistration {
+        let id = client.id();
+        self.clients.borrow_mut().insert(id, client);
+        ClientRegistration { clients: self.clone(), id }
+    }
+
+    pub fn list(&self, filter: &ClientFilter) -> Vec<ClientInfo> {
+        let clients = self.clients.borrow();
+        let infos = clients.values().map(|client| client.info().clone());
+        infos.filter(|info| filter.matches(info)).collect()
+    }
+
+    /// Kill the clients matching `filter`, returning them.
+    pub fn kill(&self, filter: &ClientFilter) -> Vec<ClientInfo> {
+        let clients = self.clients.borrow();
+        let killed = clients.values().filter(|client| filter.matches(&client.info()));
+        killed
+            .map(|client| {
+                client.kill();
+                client.info().clone()
+            })
+            .collect()
+    }
+}
+
+/// Listed client, removed from its shard when dropped.
+#[derive(Debug)]
+pub struct ClientRegistration {
+    clients: Rc<ShardClients>,
+    id: u64,
+}
+
+impl Drop for ClientRegistration {
+    fn drop(&mut self) {
+        self.clients.clients.borrow_mut().remove(&self.id);
+    }
+}
+
+/// Wait for `future` unless `timeout` seconds pass first, `0` waits forever.
+pub async fn idle_timeout<F: Future>(timeout: u64, future: F) -> Option<F::Output> {
+    if timeout == 0 {
+        return Some(future.await);
+    }
+
+    match select(pin!(future), pin!(sleep(Duration::from_secs(timeout)))).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_clients() {
+        let addr = "127.0.0.1:6000".parse().ok();
+        let client = ClientInfo { id: 3, addr, ..Default::default() };
+        assert!(ClientFilter::default().matches(&client));
+        assert!(ClientFilter { ids: vec![1, 3], ..Default::default() }.matches(&client));
+        assert!(!ClientFilter { skip: Some(3), ..Default::default() }.matches(&client));
+        assert!(
+            !ClientFilter { kind: Some(ClientType::Replica), ..Default::default() }
+                .matches(&client)
+        );
+        let addr = Some("127.0.0.1:6001".to_string());
+        assert!(!ClientFilter { addr, ..Default::default() }.matches(&client));
+        assert!(client.line().starts_with("id=3 addr=127.0.0.1:6000 laddr= name= age=0"));
+    }
+
+    #[test]
+    fn pauses_clients() {
+        let clients = Clients::default();
+        assert_eq!(clients.paused(true), None);
+
+        clients.pause(Duration::from_secs(10), true);
+        assert_eq!(clients.paused(false), None);
+        assert!(clients.paused(true).is_some());
+
+        clients.pause(Duration::from_secs(1), false);
+        assert!(clients.paused(false).is_some());
+
+        clients.unpause();
+        assert_eq!(clients.paused(true), None);
+    }
+}
@@ -1,10 +1,30 @@
 use glommio::net::TcpStream;
 use goosekv_protocol::stream::GFrameStream;
 
+use crate::tls::Connection;
+
 pub enum ProcessorCommand {
-    Process(ProcessCommand),
+    Process(Box<ProcessCommand>),
+    Tls(TlsCommand),
+    Memcache(MemcacheCommand),
+    Metrics(MetricsCommand),
 }
 
 pub struct ProcessCommand {
-    pub stream: GFrameStream<TcpStream>,
+    pub stream: GFrameStream<Connection>,
+}
+
+/// Connection accepted on the TLS port, before its handshake.
+pub struct TlsCommand {
+    pub stream: TcpStream,
+}
+
+/// Connection accepted on the memcached port.
+pub struct MemcacheCommand {
+    pub stream: TcpStream,
+}
+
+/// Connection accepted on the metrics port.
+pub struct MetricsCommand {
+    pub stream: TcpStream,
 }
@@ -1,8 +1,11 @@
 use glommio::channels::local_channel::LocalSender;
 
 use crate::processor::command::{
+    MemcacheCommand,
+    MetricsCommand,
     ProcessCommand,
     ProcessorCommand,
+    TlsCommand,
 };
 
 pub struct ProcessorHandle {
@@ -14,7 +17,19 @@ impl ProcessorHandle {
         ProcessorHandle { sender }
     }
 
-    pub async fn process(&mut self, command: ProcessCommand) {
-        self.sender.send(ProcessorCommand::Process(command)).await.unwrap();
+    pub async fn process(&self, command: ProcessCommand) {
+        self.sender.send(ProcessorCommand::Process(Box::new(command))).await.unwrap();
+    }
+
+    pub async fn tls(&self, command: TlsCommand) {
+        self.sender.send(ProcessorCommand::Tls(command)).await.unwrap();
+    }
+
+    pub async fn memcache(&self, command: MemcacheCommand) {
+        self.sender.send(ProcessorCommand::Memcache(command)).await.unwrap();
+    }
+
+    pub async fn metrics(&self, command: MetricsCommand) {
+        self.sender.send(ProcessorCommand::Metrics(command)).await.unwrap();
     }
 }
@@ -0,0 +1,284 @@
+use std::{
+    path::Path,
+    time::Instant,
+};
+
+use goosekv_protocol::{
+    command::{
+        AclCatGCommand,
+        AclDelUserGCommand,
+        AclGetUserGCommand,
+        AclListGCommand,
+        AclLoadGCommand,
+        AclLogGCommand,
+        AclSaveGCommand,
+        AclSetUserGCommand,
+        AclUsersGCommand,
+        AclWhoAmIGCommand,
+        table::AclCategory,
+    },
+    data_type::{
+        GInteger,
+        GString,
+    },
+    error::ReplyError,
+    frame::GFrame,
+};
+use tracing::error;
+
+use crate::{
+    acl::{
+        DEFAULT_USER,
+        LogEntry,
+        all_specs,
+    },
+    latency::LatencyEvent,
+    processor::{
+        clients::ClientFilter,
+        handler::{
+            Handler,
+            SessionHandler,
+            bulk_string,
+            map,
+            simple_string,
+        },
+        session::Session,
+    },
+    storage::{
+        request::ClientsRequest,
+        response::StorageResult,
+        router::StorageRouter,
+    },
+};
+
+/// Entries returned by `ACL LOG` without a count.
+const DEFAULT_LOG_COUNT: usize = 10;
+
+pub struct AclSetUserHandler;
+
+impl Handler<AclSetUserGCommand> for AclSetUserHandler {
+    async fn handle(&self, command: AclSetUserGCommand, storage: &StorageRouter) -> GFrame {
+        let name = text(&command.username);
+        if name.contains([' ', '\0']) {
+            return ReplyError::Err("Usernames can't contain spaces or null characters".into())
+                .into();
+        }
+
+        let rules: Vec<String> = command.rules.iter().map(text).collect();
+        match storage.acl().set_user(&name, rules.iter().map(String::as_str)) {
+            Ok(()) => simple_string("OK"),
+            Err(error) => ReplyError::Err(error.to_string()).into(),
+        }
+    }
+}
+
+pub struct AclGetUserHandler;
+
+impl SessionHandler<AclGetUserGCommand> for AclGetUserHandler {
+    async fn handle(
+        &self,
+        command: AclGetUserGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let Some(user) = storage.acl().user(&text(&command.username)) else {
+            return GFrame::Null;
+        };
+
+        let mut flags = vec![bulk_string(if user.is_enabled() { "on" } else { "off" })];
+        if user.is_nopass() {
+            flags.push(bulk_string("nopass"));
+        }
+        let passwords = user.passwords().iter().map(|password| bulk_string(password)).collect();
+        let entries = vec![
+            (bulk_string("flags"), GFrame::Array(flags.into())),
+            (bulk_string("passwords"), GFrame::Array(passwords)),
+            (bulk_string("commands"), bulk_string(&user.command_rules())),
+            (bulk_string("keys"), bulk_string(&user.key_rules())),
+            (bulk_string("channels"), bulk_string(&user.channel_rules())),
+            (bulk_string("selectors"), GFrame::Array(Box::new([]))),
+        ];
+        map(session.client.info().resp, entries)
+    }
+}
+
+pub struct AclDelUserHandler;
+
+impl Handler<AclDelUserGCommand> for AclDelUserHandler {
+    async fn handle(&self, command: AclDelUserGCommand, storage: &StorageRouter) -> GFrame {
+        let names: Vec<String> = command.usernames.iter().map(text).collect();
+        if names.iter().any(|name| name == DEFAULT_USER) {
+            return ReplyError::Err("The 'default' user cannot be removed".into()).into();
+        }
+
+        let deleted = storage.acl().delete_users(&names);
+        match kill_clients(storage, &deleted).await {
+            Ok(()) => GFrame::Integer(GInteger::new(deleted.len() as i64)),
+            Err(error) => ReplyError::from(error).into(),
+        }
+    }
+}
+
+pub struct AclListHandler;
+
+impl Handler<AclListGCommand> for AclListHandler {
+    async fn handle(&self, _command: AclListGCommand, storage: &StorageRouter) -> GFrame {
+        let users = storage.acl().users();
+        GFrame::Array(users.iter().map(|user| bulk_string(&user.line())).collect())
+    }
+}
+
+pub struct AclUsersHandler;
+
+impl Handler<AclUsersGCommand> for AclUsersHandler {
+    async fn handle(&self, _command: AclUsersGCommand, storage: &StorageRouter) -> GFrame {
+        let users = storage.acl().users();
+        GFrame::Array(users.iter().map(|user| bulk_string(user.name())).collect())
+    }
+}
+
+pub struct AclWhoAmIHandler;
+
+impl SessionHandler<AclWhoAmIGCommand> for AclWhoAmIHandler {
+    async fn handle(
+        &self,
+        _command: AclWhoAmIGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        bulk_string(&session.client.info().user)
+    }
+}
+
+pub struct AclCatHandler;
+
+impl Handler<AclCatGCommand> for AclCatHandler {
+    async fn handle(&self, command: AclCatGCommand, _storage: &StorageRouter) -> GFrame {
+        let Some(name) = command.category else {
+            let categories = AclCategory::ALL.iter().map(|category| bulk_string(category.as_str()));
+            return GFrame::Array(categories.collect());
+        };
+        let Some(category) = AclCategory::from_name(name.as_ref()) else {
+            return ReplyError::Err(format!("Unknown category '{}'", text(&name))).into();
+        };
+
+        // Containers are listed through their subcommands.
+        let commands = all_specs()
+            .filter(|spec| spec.subcommands.is_empty() && spec.has_acl_category(category));
+        GFrame::Array(commands.map(|spec| bulk_string(&spec.full_name())).collect())
+    }
+}
+
+pub struct AclLogHandler;
+
+impl SessionHandler<AclLogGCommand> for AclLogHandler {
+    async fn handle(
+        &self,
+        command: AclLogGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let count = match command {
+            AclLogGCommand::Reset => {
+                storage.acl().reset_log();
+                return simple_string("OK");
+            }
+            AclLogGCommand::Get(count) => count.unwrap_or(DEFAULT_LOG_COUNT),
+        };
+
+        let resp = session.client.info().resp;
+        let entries = storage.acl().log(count);
+        GFrame::Array(entries.iter().map(|entry| log_entry(entry, resp)).collect())
+    }
+}
+
+pub struct AclSaveHandler;
+
+impl Handler<AclSaveGCommand> for AclSaveHandler {
+    async fn handle(&self, _command: AclSaveGCommand, storage: &StorageRouter) -> GFrame {
+        let Some(path) = aclfile(storage) else {
+            return not_configured();
+        };
+
+        let started = Instant::now();
+        match storage.acl().save(Path::new(&path)) {
+            Ok(()) => {
+                storage.latency().record(LatencyEvent::Fsync, started.elapsed());
+                simple_string("OK")
+            }
+            Err(save_error) => {
+                error!("failed to save ACL file {path}: {save_error}");
+                ReplyError::Err(
+                    "There was an error trying to save the ACLs. Please check the server logs \
+                     for more information"
+                        .into(),
+                )
+                .into()
+            }
+        }
+    }
+}
+
+pub struct AclLoadHandler;
+
+impl Handler<AclLoadGCommand> for AclLoadHandler {
+    async fn handle(&self, _command: AclLoadGCommand, storage: &StorageRouter) -> GFrame {
+        let Some(path) = aclfile(storage) else {
+            return not_configured();
+        };
+
+        match storage.acl().load(Path::new(&path)) {
+            Ok(removed) => match kill_clients(storage, &removed).await {
+                Ok(()) => simple_string("OK"),
+                Err(error) => ReplyError::from(error).into(),
+            },
+            Err(error) => ReplyError::Err(format!(
+                "{path}: {error}. No change to the previously active ACL rules was performed"
+            ))
+            .into(),
+        }
+    }
+}
+
+fn aclfile(storage: &StorageRouter) -> Option<String> {
+    let path = storage.config().read().aclfile.clone();
+    (!path.is_empty()).then_some(path)
+}
+
+fn not_configured() -> GFrame {
+    ReplyError::Err("This instance is not configured to use an ACL file".into()).into()
+}
+
+/// Disconnect the clients of users that no longer exist, on every shard.
+async fn kill_clients(storage: &StorageRouter, users: &[String]) -> StorageResult<()> {
+    for user in users {
+        let filter = ClientFilter { user: Some(user.clone()), ..Default::default() };
+        storage.collect_clients(ClientsRequest::Kill(filter)).await?;
+    }
+    Ok(())
+}
+
+/// Entry as Redis replies it, with its age and timestamps in milliseconds.
+fn log_entry(entry: &LogEntry, resp: u8) -> GFrame {
+    let integer = |value: u64| GFrame::Integer(GInteger::new(value as i64));
+    let age = entry.age();
+    map(
+        resp,
+        vec![
+            (bulk_string("count"), integer(entry.count)),
+            (bulk_string("reason"), bulk_string(entry.reason.as_str())),
+            (bulk_string("context"), bulk_string("toplevel")),
+            (bulk_string("object"), bulk_string(&entry.object)),
+            (bulk_string("username"), bulk_string(&entry.username)),
+            (bulk_string("age-seconds"), bulk_string(&format!("{age:.3}"))),
+            (bulk_string("client-info"), bulk_string(&entry.client_info)),
+            (bulk_string("entry-id"), integer(entry.entry_id)),
+            (bulk_string("timestamp-created"), integer(entry.created)),
+            (bulk_string("timestamp-last-updated"), integer(entry.updated)),
+        ],
+    )
+}
+
+fn text(value: &GString) -> String {
+    String::from_utf8_lossy(value.as_ref()).into_owned()
+}
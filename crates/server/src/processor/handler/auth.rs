@@ -0,0 +1,73 @@
+use goosekv_protocol::{
+    command::AuthGCommand,
+    data_type::GString,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    acl::{
+        DEFAULT_USER,
+        DenialReason,
+    },
+    processor::{
+        handler::{
+            SessionHandler,
+            simple_string,
+        },
+        session::Session,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct AuthHandler;
+
+impl SessionHandler<AuthGCommand> for AuthHandler {
+    async fn handle(
+        &self,
+        command: AuthGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if command.username.is_none()
+            && storage.acl().user(DEFAULT_USER).is_some_and(|user| user.is_nopass())
+        {
+            return ReplyError::Err(
+                "AUTH <password> called without any password configured for the default user. \
+                 Are you sure your configuration is correct?"
+                    .into(),
+            )
+            .into();
+        }
+
+        match authenticate(command.username, command.password, storage, session) {
+            Ok(()) => simple_string("OK"),
+            Err(error) => error.into(),
+        }
+    }
+}
+
+/// Run the connection as `username`, `default` if not given, if `password` is one of its
+/// passwords. Failures are logged to `ACL LOG`.
+pub(super) fn authenticate(
+    username: Option<GString>,
+    password: GString,
+    storage: &StorageRouter,
+    session: &mut Session,
+) -> Result<(), ReplyError> {
+    let acl = storage.acl();
+    let name = username.map_or_else(
+        || DEFAULT_USER.to_string(),
+        |username| String::from_utf8_lossy(username.as_ref()).into_owned(),
+    );
+    if acl.authenticate(&name, password.as_ref()).is_some() {
+        session.login(&name);
+        return Ok(());
+    }
+
+    let max_len = storage.config().read().acllog_max_len;
+    let client = session.client.info().line();
+    acl.record_denial(DenialReason::Auth, "AUTH".into(), &name, client, max_len);
+    storage.shard_stats().counters().acl_access_denied_auth += 1;
+    Err(ReplyError::WrongPass)
+}
@@ -0,0 +1,507 @@
+use std::time::Duration;
+
+use goosekv_protocol::{
+    command::{
+        ClientCachingGCommand,
+        ClientGetNameGCommand,
+        ClientGetRedirGCommand,
+        ClientIdGCommand,
+        ClientInfoAttr,
+        ClientInfoGCommand,
+        ClientKillFilter,
+        ClientKillGCommand,
+        ClientListGCommand,
+        ClientNoEvictGCommand,
+        ClientNoTouchGCommand,
+        ClientPauseGCommand,
+        ClientReplyGCommand,
+        ClientReplyMode,
+        ClientSetInfoGCommand,
+        ClientSetNameGCommand,
+        ClientTrackingGCommand,
+        ClientTrackingInfoGCommand,
+        ClientUnpauseGCommand,
+    },
+    data_type::{
+        GInteger,
+        GString,
+    },
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::{
+        clients::{
+            ClientFilter,
+            ClientInfo,
+            ClientType,
+        },
+        handler::{
+            Handler,
+            SessionHandler,
+            bulk_string,
+            map,
+            simple_string,
+        },
+        session::Session,
+    },
+    storage::{
+        request::ClientsRequest,
+        router::StorageRouter,
+    },
+    tracking::TrackingMode,
+};
+
+pub struct ClientIdHandler;
+
+impl SessionHandler<ClientIdGCommand> for ClientIdHandler {
+    async fn handle(
+        &self,
+        _command: ClientIdGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        GFrame::Integer(GInteger::new(session.client.id() as i64))
+    }
+}
+
+pub struct ClientSetNameHandler;
+
+impl SessionHandler<ClientSetNameGCommand> for ClientSetNameHandler {
+    async fn handle(
+        &self,
+        command: ClientSetNameGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if !printable(&command.name) {
+            return ReplyError::Err(
+                "Client names cannot contain spaces, newlines or special characters.".into(),
+            )
+            .into();
+        }
+        session.client.update(|info| info.name = command.name);
+        simple_string("OK")
+    }
+}
+
+pub struct ClientGetNameHandler;
+
+impl SessionHandler<ClientGetNameGCommand> for ClientGetNameHandler {
+    async fn handle(
+        &self,
+        _command: ClientGetNameGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let name = session.client.info().name.clone();
+        match name.as_ref().is_empty() {
+            true => GFrame::Null,
+            false => GFrame::BulkString(name),
+        }
+    }
+}
+
+pub struct ClientSetInfoHandler;
+
+impl SessionHandler<ClientSetInfoGCommand> for ClientSetInfoHandler {
+    async fn handle(
+        &self,
+        command: ClientSetInfoGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let name = match command.attr {
+            ClientInfoAttr::LibName => "lib-name",
+            ClientInfoAttr::LibVer => "lib-ver",
+        };
+        if !printable(&command.value) {
+            return ReplyError::Err(format!(
+                "{name} cannot contain spaces, newlines or special characters."
+            ))
+            .into();
+        }
+        session.client.update(|info| match command.attr {
+            ClientInfoAttr::LibName => info.lib_name = command.value,
+            ClientInfoAttr::LibVer => info.lib_ver = command.value,
+        });
+        simple_string("OK")
+    }
+}
+
+pub struct ClientListHandler;
+
+impl Handler<ClientListGCommand> for ClientListHandler {
+    async fn handle(&self, command: ClientListGCommand, storage: &StorageRouter) -> GFrame {
+        let kind = match command.kind.as_ref().map(client_type).transpose() {
+            Ok(kind) => kind,
+            Err(error) => return error.into(),
+        };
+        let filter = ClientFilter { ids: command.ids.into(), kind, ..Default::default() };
+        let clients = match storage.collect_clients(ClientsRequest::List(filter)).await {
+            Ok(clients) => clients,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let mut clients: Vec<_> = clients.into_iter().flat_map(|shard| shard.clients).collect();
+        clients.sort_by_key(|client| client.id);
+        bulk_string(&lines(&clients))
+    }
+}
+
+pub struct ClientInfoHandler;
+
+impl SessionHandler<ClientInfoGCommand> for ClientInfoHandler {
+    async fn handle(
+        &self,
+        _command: ClientInfoGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        bulk_string(&lines(&[session.client.info().clone()]))
+    }
+}
+
+pub struct ClientKillHandler;
+
+impl SessionHandler<ClientKillGCommand> for ClientKillHandler {
+    async fn handle(
+        &self,
+        command: ClientKillGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        // Only the old form may kill the client sending it.
+        let skip = (!command.legacy).then(|| session.client.id());
+        let mut filter = ClientFilter { skip, ..Default::default() };
+        let text = |value: GString| String::from_utf8_lossy(value.as_ref()).into_owned();
+        for kill_filter in command.filters {
+            match kill_filter {
+                ClientKillFilter::Id(id) => filter.ids = vec![id],
+                ClientKillFilter::Type(kind) => match client_type(&kind) {
+                    Ok(kind) => filter.kind = Some(kind),
+                    Err(error) => return error.into(),
+                },
+                ClientKillFilter::User(user) => filter.user = Some(text(user)),
+                ClientKillFilter::Addr(addr) => filter.addr = Some(text(addr)),
+                ClientKillFilter::Laddr(addr) => filter.laddr = Some(text(addr)),
+                ClientKillFilter::SkipMe(skip) => {
+                    filter.skip = skip.then(|| session.client.id());
+                }
+                ClientKillFilter::MaxAge(age) => filter.max_age = Some(age),
+            }
+        }
+
+        let killed = match storage.collect_clients(ClientsRequest::Kill(filter)).await {
+            Ok(killed) => killed,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let killed: usize = killed.iter().map(|shard| shard.clients.len()).sum();
+        match command.legacy {
+            true if killed == 0 => ReplyError::Err("No such client".into()).into(),
+            true => simple_string("OK"),
+            false => GFrame::Integer(GInteger::new(killed as i64)),
+        }
+    }
+}
+
+pub struct ClientPauseHandler;
+
+impl Handler<ClientPauseGCommand> for ClientPauseHandler {
+    async fn handle(&self, command: ClientPauseGCommand, storage: &StorageRouter) -> GFrame {
+        storage.clients().pause(Duration::from_millis(command.timeout), command.writes);
+        simple_string("OK")
+    }
+}
+
+pub struct ClientUnpauseHandler;
+
+impl Handler<ClientUnpauseGCommand> for ClientUnpauseHandler {
+    async fn handle(&self, _command: ClientUnpauseGCommand, storage: &StorageRouter) -> GFrame {
+        storage.clients().unpause();
+        simple_string("OK")
+    }
+}
+
+pub struct ClientReplyHandler;
+
+impl SessionHandler<ClientReplyGCommand> for ClientReplyHandler {
+    async fn handle(
+        &self,
+        command: ClientReplyGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        session.reply = command.mode;
+        // Only `ON` is acknowledged, the others already silence their own reply.
+        session.skip_reply = command.mode != ClientReplyMode::On;
+        simple_string("OK")
+    }
+}
+
+pub struct ClientNoEvictHandler;
+
+impl SessionHandler<ClientNoEvictGCommand> for ClientNoEvictHandler {
+    async fn handle(
+        &self,
+        command: ClientNoEvictGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        session.client.update(|info| info.flags.no_evict = command.enabled);
+        simple_string("OK")
+    }
+}
+
+pub struct ClientNoTouchHandler;
+
+impl SessionHandler<ClientNoTouchGCommand> for ClientNoTouchHandler {
+    async fn handle(
+        &self,
+        command: ClientNoTouchGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        session.client.update(|info| info.flags.no_touch = command.enabled);
+        simple_string("OK")
+    }
+}
+
+pub struct ClientTrackingHandler;
+
+impl SessionHandler<ClientTrackingGCommand> for ClientTrackingHandler {
+    async fn handle(
+        &self,
+        command: ClientTrackingGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if !command.enabled {
+            session.tracking = None;
+            session.client.update(|info| {
+                info.flags.tracking = false;
+                info.redirect = None;
+            });
+            return simple_string("OK");
+        }
+
+        let current = session.tracking.as_ref().map(|tracking| tracking.mode.clone());
+        let mode = match tracking_mode(command, current.as_deref()) {
+            Ok(mode) => mode,
+            Err(error) => return error.into(),
+        };
+        if let Some(redirect) = mode.redirect {
+            let filter = ClientFilter { ids: vec![redirect], ..Default::default() };
+            let clients = match storage.collect_clients(ClientsRequest::List(filter)).await {
+                Ok(clients) => clients,
+                Err(error) => return ReplyError::from(error).into(),
+            };
+            if clients.iter().all(|shard| shard.clients.is_empty()) {
+                let error = "The client ID you want redirect to does not exist";
+                return ReplyError::Err(error.into()).into();
+            }
+        }
+
+        session.client.update(|info| {
+            info.flags.tracking = true;
+            info.redirect = mode.redirect;
+        });
+        let trackers = storage.clients().trackers();
+        session.tracking = Some(trackers.enable(session.client.id(), mode));
+        simple_string("OK")
+    }
+}
+
+pub struct ClientCachingHandler;
+
+impl SessionHandler<ClientCachingGCommand> for ClientCachingHandler {
+    async fn handle(
+        &self,
+        command: ClientCachingGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let mode = session.tracking.as_ref().map(|tracking| &tracking.mode);
+        let error = match mode {
+            Some(mode) if command.enabled && mode.optin => None,
+            Some(mode) if !command.enabled && mode.optout => None,
+            Some(mode) if command.enabled && mode.optout => {
+                Some("CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode.")
+            }
+            Some(mode) if !command.enabled && mode.optin => {
+                Some("CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode.")
+            }
+            _ => Some(
+                "CLIENT CACHING can be called only when the client is in tracking mode with \
+                 OPTIN or OPTOUT mode enabled",
+            ),
+        };
+        if let Some(error) = error {
+            return ReplyError::Err(error.into()).into();
+        }
+        session.caching = Some(command.enabled);
+        simple_string("OK")
+    }
+}
+
+pub struct ClientGetRedirHandler;
+
+impl SessionHandler<ClientGetRedirGCommand> for ClientGetRedirHandler {
+    async fn handle(
+        &self,
+        _command: ClientGetRedirGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        GFrame::Integer(GInteger::new(session.client.info().redir()))
+    }
+}
+
+pub struct ClientTrackingInfoHandler;
+
+impl SessionHandler<ClientTrackingInfoGCommand> for ClientTrackingInfoHandler {
+    async fn handle(
+        &self,
+        _command: ClientTrackingInfoGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let mode = session.tracking.as_ref().map(|tracking| &tracking.mode);
+        let mut flags = Vec::new();
+        let mut prefixes = Vec::new();
+        match mode {
+            None => flags.push("off"),
+            Some(mode) => {
+                let set = [
+                    (true, "on"),
+                    (mode.bcast, "bcast"),
+                    (mode.optin, "optin"),
+                    (mode.optout, "optout"),
+                    (session.caching == Some(true), "caching-yes"),
+                    (session.caching == Some(false), "caching-no"),
+                    (mode.noloop, "noloop"),
+                ];
+                flags.extend(set.into_iter().filter(|(set, _)| *set).map(|(_, flag)| flag));
+                prefixes.extend(mode.prefixes.iter().cloned().map(GFrame::BulkString));
+            }
+        }
+        let info = session.client.info();
+        let flags = flags.into_iter().map(bulk_string).collect();
+        let entries = vec![
+            (bulk_string("flags"), GFrame::Array(flags)),
+            (bulk_string("redirect"), GFrame::Integer(GInteger::new(info.redir()))),
+            (bulk_string("prefixes"), GFrame::Array(prefixes.into())),
+        ];
+        map(info.resp, entries)
+    }
+}
+
+/// Mode asked for by `command`, merged into the `current` one of a client already tracking keys.
+fn tracking_mode(
+    command: ClientTrackingGCommand,
+    current: Option<&TrackingMode>,
+) -> Result<TrackingMode, ReplyError> {
+    let error = |error: &str| Err(ReplyError::Err(error.into()));
+    if !command.prefixes.is_empty() && !command.bcast {
+        return error("PREFIX option requires BCAST mode to be enabled");
+    }
+    if command.optin && command.optout {
+        return error("You can't use both OPTIN and OPTOUT");
+    }
+    if command.bcast && (command.optin || command.optout) {
+        return error("OPTIN and OPTOUT are not compatible with BCAST");
+    }
+    if let Some(current) = current {
+        if current.bcast != command.bcast {
+            return error(
+                "You can't switch BCAST mode on/off before disabling tracking for this client, \
+                 and then re-enabling it with a different mode.",
+            );
+        }
+        if (current.optin, current.optout) != (command.optin, command.optout) {
+            return error(
+                "You can't switch OPTIN/OPTOUT mode before disabling tracking for this client, \
+                 and then re-enabling it with a different mode.",
+            );
+        }
+    }
+
+    let mut prefixes = current.map_or_else(Vec::new, |current| current.prefixes.clone());
+    for prefix in command.prefixes {
+        let overlapping = prefixes.iter().find(|other| {
+            let (prefix, other) = (prefix.as_ref(), other.as_ref());
+            prefix.starts_with(other) || other.starts_with(prefix)
+        });
+        match overlapping {
+            // Prefixes given again are kept once.
+            Some(other) if *other == prefix => {}
+            Some(other) => {
+                let text = |value: &GString| String::from_utf8_lossy(value.as_ref()).into_owned();
+                return Err(ReplyError::Err(format!(
+                    "Prefix '{}' overlaps with an existing prefix '{}'. Prefixes for a single \
+                     client must not overlap.",
+                    text(&prefix),
+                    text(other),
+                )));
+            }
+            None => prefixes.push(prefix),
+        }
+    }
+
+    Ok(TrackingMode {
+        redirect: command.redirect,
+        bcast: command.bcast,
+        prefixes,
+        optin: command.optin,
+        optout: command.optout,
+        noloop: command.noloop,
+    })
+}
+
+/// Whether `value` can be shown in a client line, where spaces separate the fields.
+pub(super) fn printable(value: &GString) -> bool {
+    value.as_ref().iter().all(|byte| (b'!'..=b'~').contains(byte))
+}
+
+fn client_type(name: &GString) -> Result<ClientType, ReplyError> {
+    ClientType::from_name(name.as_ref()).ok_or_else(|| {
+        let name = String::from_utf8_lossy(name.as_ref());
+        ReplyError::Err(format!("Unknown client type '{name}'"))
+    })
+}
+
+/// One line per client, each ending with a newline.
+fn lines(clients: &[ClientInfo]) -> String {
+    clients.iter().map(|client| client.line() + "\n").collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejects_unprintable_names() {
+        assert!(printable(&GString::from_static(b"worker-1")));
+        assert!(printable(&GString::from_static(b"")));
+        assert!(!printable(&GString::from_static(b"worker 1")));
+        assert!(!printable(&GString::from_static(b"worker\n")));
+    }
+
+    #[test]
+    fn merges_tracking_prefixes() {
+        let prefix = |prefix: &'static [u8]| GString::from_static(prefix);
+        let command = |prefixes: &[&'static [u8]]| ClientTrackingGCommand {
+            enabled: true,
+            bcast: true,
+            prefixes: prefixes.iter().map(|p| prefix(p)).collect(),
+            ..Default::default()
+        };
+
+        let mode = tracking_mode(command(&[b"user:"]), None).unwrap();
+        let mode = tracking_mode(command(&[b"order:", b"user:"]), Some(&mode)).unwrap();
+        assert_eq!(mode.prefixes, vec![prefix(b"user:"), prefix(b"order:")]);
+        assert!(tracking_mode(command(&[b"user:1"]), Some(&mode)).is_err());
+        let optin = ClientTrackingGCommand { enabled: true, optin: true, ..Default::default() };
+        assert!(tracking_mode(optin, Some(&mode)).is_err());
+    }
+}
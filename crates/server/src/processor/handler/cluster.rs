@@ -0,0 +1,294 @@
+use std::fmt::Write;
+
+use goosekv_protocol::{
+    command::{
+        AskingGCommand,
+        ClusterInfoGCommand,
+        ClusterKeyslotGCommand,
+        ClusterMyIdGCommand,
+        ClusterNodesGCommand,
+        ClusterShardsGCommand,
+        ClusterSlotsGCommand,
+        ReadOnlyGCommand,
+        ReadWriteGCommand,
+    },
+    data_type::GInteger,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    cluster::{
+        Cluster,
+        ClusterNode,
+    },
+    processor::{
+        handler::{
+            Handler,
+            SessionHandler,
+            bulk_string,
+            simple_string,
+        },
+        session::Session,
+    },
+    slot::{
+        SLOT_COUNT,
+        key_slot,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct ClusterSlotsHandler;
+
+impl Handler<ClusterSlotsGCommand> for ClusterSlotsHandler {
+    async fn handle(&self, _command: ClusterSlotsGCommand, storage: &StorageRouter) -> GFrame {
+        let cluster = match enabled(storage) {
+            Ok(cluster) => cluster,
+            Err(error) => return error,
+        };
+
+        let ranges = primaries(cluster).flat_map(|primary| {
+            primary.slots.iter().map(move |range| {
+                let mut frames = vec![integer(*range.start() as i64), integer(*range.end() as i64)];
+                frames.push(slots_node_frame(primary));
+                frames.extend(cluster.replicas(primary).map(slots_node_frame));
+                GFrame::Array(frames.into_boxed_slice())
+            })
+        });
+
+        GFrame::Array(ranges.collect())
+    }
+}
+
+pub struct ClusterShardsHandler;
+
+impl Handler<ClusterShardsGCommand> for ClusterShardsHandler {
+    async fn handle(&self, _command: ClusterShardsGCommand, storage: &StorageRouter) -> GFrame {
+        let cluster = match enabled(storage) {
+            Ok(cluster) => cluster,
+            Err(error) => return error,
+        };
+
+        let shards = primaries(cluster).map(|primary| {
+            let slots = primary
+                .slots
+                .iter()
+                .flat_map(|range| [integer(*range.start() as i64), integer(*range.end() as i64)])
+                .collect();
+            let nodes = std::iter::once(primary)
+                .chain(cluster.replicas(primary))
+                .map(shards_node_frame)
+                .collect();
+
+            GFrame::Array(
+                vec![
+                    bulk_string("slots"),
+                    GFrame::Array(slots),
+                    bulk_string("nodes"),
+                    GFrame::Array(nodes),
+                ]
+                .into_boxed_slice(),
+            )
+        });
+
+        GFrame::Array(shards.collect())
+    }
+}
+
+pub struct ClusterNodesHandler;
+
+impl Handler<ClusterNodesGCommand> for ClusterNodesHandler {
+    async fn handle(&self, _command: ClusterNodesGCommand, storage: &StorageRouter) -> GFrame {
+        let cluster = match enabled(storage) {
+            Ok(cluster) => cluster,
+            Err(error) => return error,
+        };
+
+        let mut nodes = String::new();
+        for node in cluster.nodes() {
+            let myself = if node.id == cluster.myself().id { "myself," } else { "" };
+            let role = if node.is_primary() { "master" } else { "slave" };
+            let _ = write!(
+                nodes,
+                "{} {}@{} {myself}{role} {} 0 0 0 connected",
+                node.id,
+                node.addr,
+                node.addr.port() as u32 + 10000,
+                node.primary.as_deref().unwrap_or("-"),
+            );
+            for range in &node.slots {
+                let _ = match (range.start(), range.end()) {
+                    (first, last) if first == last => write!(nodes, " {first}"),
+                    (first, last) => write!(nodes, " {first}-{last}"),
+                };
+            }
+            for (slot, id) in &node.migrating {
+                let _ = write!(nodes, " [{slot}->-{id}]");
+            }
+            for (slot, id) in &node.importing {
+                let _ = write!(nodes, " [{slot}-<-{id}]");
+            }
+            nodes.push('\n');
+        }
+
+        bulk_string(&nodes)
+    }
+}
+
+pub struct ClusterKeyslotHandler;
+
+impl Handler<ClusterKeyslotGCommand> for ClusterKeyslotHandler {
+    async fn handle(&self, command: ClusterKeyslotGCommand, storage: &StorageRouter) -> GFrame {
+        match enabled(storage) {
+            Ok(_) => integer(key_slot(command.key.as_ref()) as i64),
+            Err(error) => error,
+        }
+    }
+}
+
+pub struct ClusterInfoHandler;
+
+impl Handler<ClusterInfoGCommand> for ClusterInfoHandler {
+    async fn handle(&self, _command: ClusterInfoGCommand, storage: &StorageRouter) -> GFrame {
+        let cluster = match enabled(storage) {
+            Ok(cluster) => cluster,
+            Err(error) => return error,
+        };
+
+        let assigned = cluster.assigned_slots();
+        let state = if assigned == SLOT_COUNT { "ok" } else { "fail" };
+        let fields = [
+            ("cluster_state", state.to_string()),
+            ("cluster_slots_assigned", assigned.to_string()),
+            ("cluster_slots_ok", assigned.to_string()),
+            ("cluster_slots_pfail", "0".to_string()),
+            ("cluster_slots_fail", "0".to_string()),
+            ("cluster_known_nodes", cluster.nodes().len().to_string()),
+            ("cluster_size", primaries(cluster).count().to_string()),
+            ("cluster_current_epoch", "0".to_string()),
+            ("cluster_my_epoch", "0".to_string()),
+        ];
+
+        let info = fields.iter().fold(String::new(), |mut info, (name, value)| {
+            let _ = write!(info, "{name}:{value}\r\n");
+            info
+        });
+        bulk_string(&info)
+    }
+}
+
+pub struct ClusterMyIdHandler;
+
+impl Handler<ClusterMyIdGCommand> for ClusterMyIdHandler {
+    async fn handle(&self, _command: ClusterMyIdGCommand, storage: &StorageRouter) -> GFrame {
+        match enabled(storage) {
+            Ok(cluster) => bulk_string(&cluster.myself().id),
+            Err(error) => error,
+        }
+    }
+}
+
+pub struct ReadOnlyHandler;
+
+impl SessionHandler<ReadOnlyGCommand> for ReadOnlyHandler {
+    async fn handle(
+        &self,
+        _command: ReadOnlyGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if let Err(error) = enabled(storage) {
+            return error;
+        }
+
+        session.readonly = true;
+        simple_string("OK")
+    }
+}
+
+pub struct ReadWriteHandler;
+
+impl SessionHandler<ReadWriteGCommand> for ReadWriteHandler {
+    async fn handle(
+        &self,
+        _command: ReadWriteGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if let Err(error) = enabled(storage) {
+            return error;
+        }
+
+        session.readonly = false;
+        simple_string("OK")
+    }
+}
+
+pub struct AskingHandler;
+
+impl SessionHandler<AskingGCommand> for AskingHandler {
+    async fn handle(
+        &self,
+        _command: AskingGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if let Err(error) = enabled(storage) {
+            return error;
+        }
+
+        session.asking = true;
+        simple_string("OK")
+    }
+}
+
+/// Cluster configuration of the node, or the error reply sent when not in cluster mode.
+fn enabled(storage: &StorageRouter) -> Result<&Cluster, GFrame> {
+    storage
+        .cluster()
+        .ok_or_else(|| ReplyError::Err("This instance has cluster support disabled".into()).into())
+}
+
+fn primaries(cluster: &Cluster) -> impl Iterator<Item = &ClusterNode> {
+    cluster.nodes().iter().filter(|node| node.is_primary())
+}
+
+fn slots_node_frame(node: &ClusterNode) -> GFrame {
+    GFrame::Array(
+        vec![
+            bulk_string(&node.addr.ip().to_string()),
+            integer(node.addr.port() as i64),
+            bulk_string(&node.id),
+        ]
+        .into_boxed_slice(),
+    )
+}
+
+fn shards_node_frame(node: &ClusterNode) -> GFrame {
+    let ip = node.addr.ip().to_string();
+    let role = if node.is_primary() { "master" } else { "replica" };
+
+    GFrame::Array(
+        vec![
+            bulk_string("id"),
+            bulk_string(&node.id),
+            bulk_string("port"),
+            integer(node.addr.port() as i64),
+            bulk_string("ip"),
+            bulk_string(&ip),
+            bulk_string("endpoint"),
+            bulk_string(&ip),
+            bulk_string("role"),
+            bulk_string(role),
+            bulk_string("replication-offset"),
+            integer(0),
+            bulk_string("health"),
+            bulk_string("online"),
+        ]
+        .into_boxed_slice(),
+    )
+}
+
+fn integer(value: i64) -> GFrame {
+    GFrame::Integer(GInteger::new(value))
+}
@@ -0,0 +1,192 @@
+use goosekv_protocol::{
+    command::{
+        CommandCountGCommand,
+        CommandDocsGCommand,
+        CommandGCommand,
+        CommandGetKeysGCommand,
+        CommandInfoGCommand,
+        CommandListFilter,
+        CommandListGCommand,
+        table::{
+            AclCategory,
+            CommandSpec,
+        },
+    },
+    data_type::GInteger,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    glob::glob_match,
+    processor::handler::{
+        Handler,
+        bulk_string,
+        simple_string,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct CommandHandler;
+
+impl Handler<CommandGCommand> for CommandHandler {
+    async fn handle(&self, _command: CommandGCommand, _storage: &StorageRouter) -> GFrame {
+        GFrame::Array(CommandSpec::all().iter().map(info_frame).collect())
+    }
+}
+
+pub struct CommandCountHandler;
+
+impl Handler<CommandCountGCommand> for CommandCountHandler {
+    async fn handle(&self, _command: CommandCountGCommand, _storage: &StorageRouter) -> GFrame {
+        GFrame::Integer(GInteger::new(CommandSpec::all().len() as i64))
+    }
+}
+
+pub struct CommandInfoHandler;
+
+impl Handler<CommandInfoGCommand> for CommandInfoHandler {
+    async fn handle(&self, command: CommandInfoGCommand, _storage: &StorageRouter) -> GFrame {
+        if command.names.is_empty() {
+            return GFrame::Array(CommandSpec::all().iter().map(info_frame).collect());
+        }
+
+        GFrame::Array(
+            command
+                .names
+                .iter()
+                .map(|name| find_by_full_name(name.as_ref()).map_or(GFrame::Null, info_frame))
+                .collect(),
+        )
+    }
+}
+
+pub struct CommandDocsHandler;
+
+impl Handler<CommandDocsGCommand> for CommandDocsHandler {
+    async fn handle(&self, command: CommandDocsGCommand, _storage: &StorageRouter) -> GFrame {
+        let specs: Vec<_> = if command.names.is_empty() {
+            CommandSpec::all().iter().collect()
+        } else {
+            command.names.iter().filter_map(|name| find_by_full_name(name.as_ref())).collect()
+        };
+
+        GFrame::Array(
+            specs
+                .into_iter()
+                .flat_map(|spec| [bulk_string(&spec.full_name()), docs_frame(spec)])
+                .collect(),
+        )
+    }
+}
+
+pub struct CommandListHandler;
+
+impl Handler<CommandListGCommand> for CommandListHandler {
+    async fn handle(&self, command: CommandListGCommand, _storage: &StorageRouter) -> GFrame {
+        let specs = CommandSpec::all()
+            .iter()
+            .flat_map(|spec| std::iter::once(spec).chain(spec.subcommands.iter()));
+
+        let names = match command.filter {
+            None => specs.map(|spec| bulk_string(&spec.full_name())).collect(),
+            // There is no module support, so no command can ever match.
+            Some(CommandListFilter::Module(_)) => Box::default(),
+            Some(CommandListFilter::AclCat(category)) => {
+                match AclCategory::from_name(category.as_ref()) {
+                    Some(category) => specs
+                        .filter(|spec| spec.has_acl_category(category))
+                        .map(|spec| bulk_string(&spec.full_name()))
+                        .collect(),
+                    None => Box::default(),
+                }
+            }
+            Some(CommandListFilter::Pattern(pattern)) => specs
+                .map(|spec| spec.full_name())
+                .filter(|name| glob_match(pattern.as_ref(), name.as_bytes(), true))
+                .map(|name| bulk_string(&name))
+                .collect(),
+        };
+
+        GFrame::Array(names)
+    }
+}
+
+pub struct CommandGetKeysHandler;
+
+impl Handler<CommandGetKeysGCommand> for CommandGetKeysHandler {
+    async fn handle(&self, command: CommandGetKeysGCommand, _storage: &StorageRouter) -> GFrame {
+        let spec = match CommandSpec::resolve(&command.args) {
+            Ok(spec) => spec,
+            Err(_) => return ReplyError::Err("Invalid command specified".to_string()).into(),
+        };
+
+        let keys = spec.key_indices(command.args.len());
+        if keys.is_empty() {
+            return ReplyError::Err("The command has no key arguments".to_string()).into();
+        }
+
+        GFrame::Array(
+            keys.into_iter().map(|i| GFrame::BulkString(command.args[i].clone())).collect(),
+        )
+    }
+}
+
+/// Look up a command by the name reported in COMMAND output, e.g. `get` or `config|get`.
+fn find_by_full_name(name: &[u8]) -> Option<&'static CommandSpec> {
+    let mut parts = name.splitn(2, |&b| b == b'|');
+    let spec = CommandSpec::find(parts.next()?)?;
+
+    match parts.next() {
+        Some(subcommand) => spec.find_subcommand(subcommand),
+        None => Some(spec),
+    }
+}
+
+fn info_frame(spec: &CommandSpec) -> GFrame {
+    GFrame::Array(
+        vec![
+            bulk_string(&spec.full_name()),
+            GFrame::Integer(GInteger::new(spec.arity)),
+            GFrame::Array(spec.flags.iter().map(|flag| simple_string(flag.as_str())).collect()),
+            GFrame::Integer(GInteger::new(spec.keys.first)),
+            GFrame::Integer(GInteger::new(spec.keys.last)),
+            GFrame::Integer(GInteger::new(spec.keys.step)),
+            GFrame::Array(
+                spec.acl_categories
+                    .iter()
+                    .map(|category| simple_string(&format!("@{}", category.as_str())))
+                    .collect(),
+            ),
+            GFrame::Array(Box::default()),
+            GFrame::Array(Box::default()),
+            GFrame::Array(spec.subcommands.iter().map(info_frame).collect()),
+        ]
+        .into_boxed_slice(),
+    )
+}
+
+fn docs_frame(spec: &CommandSpec) -> GFrame {
+    let mut fields = vec![
+        bulk_string("summary"),
+        bulk_string(spec.docs.summary),
+        bulk_string("since"),
+        bulk_string(spec.docs.since),
+        bulk_string("group"),
+        bulk_string(spec.docs.group),
+        bulk_string("complexity"),
+        bulk_string(spec.docs.complexity),
+    ];
+
+    if !spec.subcommands.is_empty() {
+        fields.push(bulk_string("subcommands"));
+        fields.push(GFrame::Array(
+            spec.subcommands
+                .iter()
+                .flat_map(|spec| [bulk_string(&spec.full_name()), docs_frame(spec)])
+                .collect(),
+        ));
+    }
+
+    GFrame::Array(fields.into_boxed_slice())
+}
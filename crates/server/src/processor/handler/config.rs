@@ -0,0 +1,107 @@
+use std::time::Instant;
+
+use goosekv_protocol::{
+    command::{
+        ConfigGetGCommand,
+        ConfigResetStatGCommand,
+        ConfigRewriteGCommand,
+        ConfigSetGCommand,
+    },
+    data_type::GString,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    config::Config,
+    latency::LatencyEvent,
+    processor::handler::{
+        Handler,
+        bulk_string,
+        simple_string,
+    },
+    storage::router::StorageRouter,
+    tls::{
+        Tls,
+        TlsSettings,
+    },
+};
+
+pub struct ConfigGetHandler;
+
+impl Handler<ConfigGetGCommand> for ConfigGetHandler {
+    async fn handle(&self, command: ConfigGetGCommand, storage: &StorageRouter) -> GFrame {
+        let mut parameters = Vec::new();
+        for pattern in &command.patterns {
+            for (name, value) in storage.config().shared().matching(pattern.as_ref()) {
+                if !parameters.iter().any(|(known, _)| *known == name) {
+                    parameters.push((name, value));
+                }
+            }
+        }
+
+        GFrame::Array(
+            parameters
+                .into_iter()
+                .flat_map(|(name, value)| [bulk_string(name), bulk_string(&value)])
+                .collect(),
+        )
+    }
+}
+
+pub struct ConfigSetHandler;
+
+impl Handler<ConfigSetGCommand> for ConfigSetHandler {
+    async fn handle(&self, command: ConfigSetGCommand, storage: &StorageRouter) -> GFrame {
+        let lossy = |value: &GString| String::from_utf8_lossy(value.as_ref()).into_owned();
+        let parameters: Vec<_> =
+            command.parameters.iter().map(|(name, value)| (lossy(name), lossy(value))).collect();
+
+        // Certificates are loaded again whenever a TLS parameter is set, even to the same file.
+        let tls = parameters.iter().any(|(name, _)| name.to_ascii_lowercase().starts_with("tls-"));
+        let load = |updated: &Config| match tls {
+            true => Tls::load(updated)
+                .map(Some)
+                .map_err(|error| format!("Unable to update TLS configuration: {error}")),
+            false => Ok(None),
+        };
+        let swap = |settings: Option<TlsSettings>| {
+            if let Some(settings) = settings {
+                storage.tls().set(settings);
+            }
+        };
+        let config = storage.config();
+        if let Err(error) = config.shared().set_with(&parameters, load, swap) {
+            return ReplyError::Err(format!("CONFIG SET failed - {error}")).into();
+        }
+
+        // Parameters read on every use need nothing more, the others are applied here.
+        let backlog_size = config.read().repl_backlog_size.0 as usize;
+        storage.replication().backlog().set_capacity(backlog_size);
+        simple_string("OK")
+    }
+}
+
+pub struct ConfigRewriteHandler;
+
+impl Handler<ConfigRewriteGCommand> for ConfigRewriteHandler {
+    async fn handle(&self, _command: ConfigRewriteGCommand, storage: &StorageRouter) -> GFrame {
+        let started = Instant::now();
+        match storage.config().shared().rewrite() {
+            Ok(()) => {
+                storage.latency().record(LatencyEvent::Fsync, started.elapsed());
+                simple_string("OK")
+            }
+            Err(error) => ReplyError::Err(error.to_string()).into(),
+        }
+    }
+}
+
+pub struct ConfigResetStatHandler;
+
+impl Handler<ConfigResetStatGCommand> for ConfigResetStatHandler {
+    async fn handle(&self, _command: ConfigResetStatGCommand, storage: &StorageRouter) -> GFrame {
+        storage.config().shared().reset_stats();
+        simple_string("OK")
+    }
+}
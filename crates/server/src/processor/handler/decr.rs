@@ -0,0 +1,28 @@
+use goosekv_protocol::{
+    command::DecrGCommand,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::{
+        handler::{
+            SessionHandler,
+            incr::increment,
+        },
+        session::Session,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct DecrHandler;
+
+impl SessionHandler<DecrGCommand> for DecrHandler {
+    async fn handle(
+        &self,
+        command: DecrGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        increment(storage, command.key, -1, session).await
+    }
+}
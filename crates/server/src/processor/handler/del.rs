@@ -1,26 +1,53 @@
-use futures::future::join_all;
 use goosekv_protocol::{
     command::DelGCommand,
     data_type::GInteger,
+    error::ReplyError,
     frame::GFrame,
 };
 
 use crate::{
-    processor::handler::Handler,
+    processor::{
+        handler::SessionHandler,
+        session::Session,
+    },
     storage::{
-        request::DeleteRequest,
+        request::{
+            DeleteRequest,
+            Operation,
+        },
+        response::{
+            DeleteResponse,
+            OperationResponse,
+        },
         router::StorageRouter,
     },
 };
 
 pub struct DelHandler;
 
-impl Handler<DelGCommand> for DelHandler {
-    async fn handle(&self, command: DelGCommand, storage: &StorageRouter) -> GFrame {
-        let tasks =
-            command.keys.iter().map(|key| storage.delete(DeleteRequest { key: key.clone() }));
-        let deleted =
-            join_all(tasks).await.iter().filter(|response| response.deleted.is_some()).count();
+impl SessionHandler<DelGCommand> for DelHandler {
+    async fn handle(
+        &self,
+        command: DelGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let client = Some(session.client.id());
+        let operations = command
+            .keys
+            .iter()
+            .map(|key| Operation::Delete(DeleteRequest { key: key.clone(), client }))
+            .collect();
+        let responses = match storage.batch(operations).await {
+            Ok(responses) => responses,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let deleted = responses
+            .iter()
+            .filter(|response| {
+                matches!(response, OperationResponse::Delete(DeleteResponse { deleted: Some(_) }))
+            })
+            .count();
         GFrame::Integer(GInteger::new(deleted as i64))
     }
 }
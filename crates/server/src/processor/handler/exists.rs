@@ -1,25 +1,54 @@
-use futures::future::join_all;
 use goosekv_protocol::{
     command::ExistsGCommand,
     data_type::GInteger,
+    error::ReplyError,
     frame::GFrame,
 };
 
 use crate::{
-    processor::handler::Handler,
+    processor::{
+        handler::SessionHandler,
+        session::Session,
+    },
     storage::{
-        request::GetRequest,
+        request::{
+            GetRequest,
+            Operation,
+        },
+        response::{
+            GetResponse,
+            OperationResponse,
+        },
         router::StorageRouter,
     },
 };
 
 pub struct ExistsHandler;
 
-impl Handler<ExistsGCommand> for ExistsHandler {
-    async fn handle(&self, command: ExistsGCommand, storage: &StorageRouter) -> GFrame {
-        let tasks = command.keys.iter().map(|key| storage.get(GetRequest { key: key.clone() }));
-        let existing =
-            join_all(tasks).await.iter().filter(|response| response.value.is_some()).count();
+impl SessionHandler<ExistsGCommand> for ExistsHandler {
+    async fn handle(
+        &self,
+        command: ExistsGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let touch = !session.client.info().flags.no_touch;
+        let tracking = session.tracking_reads();
+        let operations = command
+            .keys
+            .iter()
+            .map(|key| Operation::Get(GetRequest { key: key.clone(), touch, tracking }))
+            .collect();
+        let responses = match storage.batch(operations).await {
+            Ok(responses) => responses,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let existing = responses
+            .iter()
+            .filter(|response| {
+                matches!(response, OperationResponse::Get(GetResponse { value: Some(_) }))
+            })
+            .count();
         GFrame::Integer(GInteger::new(existing as i64))
     }
 }
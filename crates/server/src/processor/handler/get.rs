@@ -1,10 +1,14 @@
 use goosekv_protocol::{
     command::GetGCommand,
+    error::ReplyError,
     frame::GFrame,
 };
 
 use crate::{
-    processor::handler::Handler,
+    processor::{
+        handler::SessionHandler,
+        session::Session,
+    },
     storage::{
         request::GetRequest,
         router::StorageRouter,
@@ -13,9 +17,19 @@ use crate::{
 
 pub struct GetHandler;
 
-impl Handler<GetGCommand> for GetHandler {
-    async fn handle(&self, command: GetGCommand, storage: &StorageRouter) -> GFrame {
-        let response = storage.get(GetRequest { key: command.key }).await;
+impl SessionHandler<GetGCommand> for GetHandler {
+    async fn handle(
+        &self,
+        command: GetGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let touch = !session.client.info().flags.no_touch;
+        let tracking = session.tracking_reads();
+        let response = match storage.get(GetRequest { key: command.key, touch, tracking }).await {
+            Ok(response) => response,
+            Err(error) => return ReplyError::from(error).into(),
+        };
 
         match response.value {
             Some(value) => GFrame::BulkString(value.data.to_gstring()),
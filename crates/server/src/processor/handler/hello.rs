@@ -0,0 +1,82 @@
+use goosekv_protocol::{
+    command::HelloGCommand,
+    data_type::GInteger,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::{
+        handler::{
+            SessionHandler,
+            auth::authenticate,
+            bulk_string,
+            client::printable,
+            map,
+        },
+        session::Session,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct HelloHandler;
+
+impl SessionHandler<HelloGCommand> for HelloHandler {
+    async fn handle(
+        &self,
+        command: HelloGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        if let Some(name) = &command.setname
+            && !printable(name)
+        {
+            return ReplyError::Err(
+                "Client names cannot contain spaces, newlines or special characters.".into(),
+            )
+            .into();
+        }
+        if let Some((username, password)) = command.auth
+            && let Err(error) = authenticate(Some(username), password, storage, session)
+        {
+            return error.into();
+        }
+        if !session.authenticated && storage.acl().auth_required() {
+            return ReplyError::NoAuth.into();
+        }
+
+        // Only RESP3 connections can receive the invalidations of tracked keys.
+        match command.protover {
+            Some(3) if session.invalidations.is_none() => {
+                let trackers = storage.clients().trackers();
+                session.invalidations = Some(trackers.listen(session.client.id()));
+            }
+            Some(2) => session.invalidations = None,
+            _ => {}
+        }
+        session.client.update(|info| {
+            if let Some(resp) = command.protover {
+                info.resp = resp;
+            }
+            if let Some(name) = command.setname {
+                info.name = name;
+            }
+        });
+
+        let mode = if storage.cluster().is_some() { "cluster" } else { "standalone" };
+        let role = if storage.replication().is_replica() { "replica" } else { "master" };
+        let resp = session.client.info().resp;
+        let integer = |value: i64| GFrame::Integer(GInteger::new(value));
+        let entries = vec![
+            (bulk_string("server"), bulk_string("redis")),
+            // Version of Redis whose commands are served, as reported by `INFO`.
+            (bulk_string("version"), bulk_string("7.2.0")),
+            (bulk_string("proto"), integer(resp as i64)),
+            (bulk_string("id"), integer(session.client.id() as i64)),
+            (bulk_string("mode"), bulk_string(mode)),
+            (bulk_string("role"), bulk_string(role)),
+            (bulk_string("modules"), GFrame::Array(Box::new([]))),
+        ];
+        map(resp, entries)
+    }
+}
@@ -6,11 +6,15 @@ use goosekv_protocol::{
         GInteger,
         GString,
     },
+    error::ReplyError,
     frame::GFrame,
 };
 
 use crate::{
-    processor::handler::Handler,
+    processor::{
+        handler::SessionHandler,
+        session::Session,
+    },
     storage::{
         request::UpdateRequest,
         router::StorageRouter,
@@ -23,24 +27,77 @@ use crate::{
 
 pub struct IncrHandler;
 
-impl Handler<IncrGCommand> for IncrHandler {
-    async fn handle(&self, command: IncrGCommand, storage: &StorageRouter) -> GFrame {
-        let update_f = Arc::new(|value: Option<Value>| match value {
-            Some(Value { data: Data::Integer(existing_integer) }) => {
-                existing_integer.checked_add(1).map(|value| Value { data: Data::Integer(value) })
-            }
-            Some(value) => Some(value),
-            None => Some(Value { data: Data::Integer(GInteger::new(1)) }),
-        });
-
-        let response = storage.update(UpdateRequest { key: command.key, f: update_f }).await;
-
-        match response.updated {
-            Some(Value { data: Data::Integer(updated) }) => GFrame::Integer(updated),
-            Some(_) => GFrame::SimpleError(GString::copy_from_slice(b"not an integer")),
-            None => {
-                GFrame::SimpleError(GString::copy_from_slice(b"tried to increment with overflow"))
-            }
+impl SessionHandler<IncrGCommand> for IncrHandler {
+    async fn handle(
+        &self,
+        command: IncrGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        increment(storage, command.key, 1, session).await
+    }
+}
+
+/// Add `delta` to the integer stored at `key`, shared by `INCR` and `DECR`.
+pub async fn increment(
+    storage: &StorageRouter,
+    key: GString,
+    delta: i64,
+    session: &Session,
+) -> GFrame {
+    let response = storage
+        .update(UpdateRequest {
+            key,
+            f: Arc::new(move |value| incremented(value, delta)),
+            client: Some(session.client.id()),
+        })
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(error) => return ReplyError::from(error).into(),
+    };
+
+    match (response.updated, response.previous) {
+        (Some(Value { data: Data::Integer(updated), .. }), _) => GFrame::Integer(updated),
+        (None, Some(Value { data: Data::Integer(_), .. })) => ReplyError::Overflow.into(),
+        _ => ReplyError::NotInteger.into(),
+    }
+}
+
+/// Value of a key once `delta` is added, `None` if it is not an integer or on overflow so that
+/// the key, CAS token included, is left unchanged.
+fn incremented(value: Option<&Value>, delta: i64) -> Option<Value> {
+    match value {
+        Some(value @ Value { data: Data::Integer(existing_integer), .. }) => {
+            GInteger::new(existing_integer.value())
+                .checked_add(delta)
+                .map(|updated| Value { data: Data::Integer(updated), ..value.clone() })
         }
+        Some(_) => None,
+        None => Some(Value::new(Data::Integer(GInteger::new(delta)))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::Storage;
+
+    #[test]
+    fn overflow_keeps_value() {
+        let mut storage = Storage::new();
+        let key = GString::from_static(b"counter");
+        storage.set(key.clone(), Value::new(Data::Integer(GInteger::new(i64::MAX))));
+
+        let (previous, updated) =
+            storage.update(key.clone(), Arc::new(|value| incremented(value, 1)));
+        assert!(previous.is_some() && updated.is_none());
+        let value = storage.peek(&key).map(|value| value.data.to_gstring());
+        assert_eq!(value, Some(GString::copy_from_slice(i64::MAX.to_string().as_bytes())));
+
+        let (_, updated) = storage.update(key, Arc::new(|value| incremented(value, -1)));
+        assert!(
+            matches!(updated, Some(Value { data: Data::Integer(value), .. }) if value.value() == i64::MAX - 1)
+        );
     }
 }
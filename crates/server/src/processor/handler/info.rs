@@ -0,0 +1,359 @@
+use std::{
+    fmt::Write,
+    fs,
+    process,
+};
+
+use goosekv_protocol::{
+    command::InfoGCommand,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::handler::{
+        Handler,
+        bulk_string,
+    },
+    replication::Replication,
+    stats::Counters,
+    storage::{
+        response::StatsResponse,
+        router::StorageRouter,
+    },
+};
+
+/// Sections listed by `INFO` without arguments and by `INFO default`.
+const DEFAULT_SECTIONS: &[&str] = &[
+    "server",
+    "clients",
+    "memory",
+    "persistence",
+    "stats",
+    "replication",
+    "cpu",
+    "errorstats",
+    "cluster",
+    "keyspace",
+    "shards",
+];
+/// Sections built from the statistics collected from every shard.
+const SHARD_SECTIONS: &[&str] = &["stats", "commandstats", "errorstats", "keyspace", "shards"];
+/// Clock ticks per second of the CPU times in `/proc`, `USER_HZ` is fixed on Linux.
+const CLOCK_TICKS: f64 = 100.0;
+
+pub struct InfoHandler;
+
+impl Handler<InfoGCommand> for InfoHandler {
+    async fn handle(&self, command: InfoGCommand, storage: &StorageRouter) -> GFrame {
+        let named =
+            |name: &str| command.sections.iter().any(|s| s.eq_ignore_ascii_case(name.as_bytes()));
+        let all = named("all") || named("everything");
+        let default = command.sections.is_empty() || named("default");
+        let wanted =
+            |name: &str| all || named(name) || (default && DEFAULT_SECTIONS.contains(&name));
+
+        let shards = match SHARD_SECTIONS.iter().any(|name| wanted(name)) {
+            true => match storage.collect_stats().await {
+                Ok(shards) => shards,
+                Err(error) => return ReplyError::from(error).into(),
+            },
+            false => Vec::new(),
+        };
+        let mut total = Counters::default();
+        for shard in &shards {
+            total.merge(&shard.counters);
+        }
+
+        let mut info = String::new();
+        if wanted("server") {
+            section(&mut info, "Server", &server(storage));
+        }
+        if wanted("clients") {
+            section(&mut info, "Clients", &clients(storage));
+        }
+        if wanted("memory") {
+            section(&mut info, "Memory", &memory(storage));
+        }
+        if wanted("persistence") {
+            section(&mut info, "Persistence", &persistence());
+        }
+        if wanted("stats") {
+            section(&mut info, "Stats", &stats(storage, &total, &shards));
+        }
+        if wanted("replication") {
+            section(&mut info, "Replication", &replication(storage.replication()));
+        }
+        if wanted("cpu") {
+            section(&mut info, "CPU", &cpu());
+        }
+        if wanted("commandstats") {
+            section(&mut info, "Commandstats", &commandstats(&total));
+        }
+        if wanted("errorstats") {
+            let fields: Vec<_> = total
+                .errors
+                .iter()
+                .map(|(code, count)| (format!("errorstat_{code}"), format!("count={count}")))
+                .collect();
+            section(&mut info, "Errorstats", &fields);
+        }
+        if wanted("cluster") {
+            let enabled = storage.cluster().is_some() as u8;
+            section(&mut info, "Cluster", &[("cluster_enabled".into(), enabled.to_string())]);
+        }
+        if wanted("keyspace") {
+            section(&mut info, "Keyspace", &keyspace(&shards));
+        }
+        if wanted("shards") {
+            section(&mut info, "Shards", &shard_fields(storage, &shards));
+        }
+
+        bulk_string(&info)
+    }
+}
+
+fn section(info: &mut String, name: &str, fields: &[(String, String)]) {
+    if !info.is_empty() {
+        info.push_str("\r\n");
+    }
+
+    let _ = write!(info, "# {name}\r\n");
+    for (name, value) in fields {
+        let _ = write!(info, "{name}:{value}\r\n");
+    }
+}
+
+fn server(storage: &StorageRouter) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut field = |name: &str, value: String| fields.push((name.to_string(), value));
+
+    let uptime = storage.shard_stats().uptime().as_secs();
+    let config = storage.config();
+    let (port, memcache_port) = {
+        let config = config.read();
+        (config.port, config.memcache_port)
+    };
+    // Version of Redis whose commands are served, checked by some clients.
+    field("redis_version", "7.2.0".into());
+    field("goosekv_version", env!("CARGO_PKG_VERSION").into());
+    let mode = if storage.cluster().is_some() { "cluster" } else { "standalone" };
+    field("redis_mode", mode.into());
+    field("os", format!("{} {}", std::env::consts::OS, std::env::consts::ARCH));
+    field("arch_bits", usize::BITS.to_string());
+    field("multiplexing_api", "io_uring".into());
+    field("process_id", process::id().to_string());
+    field("tcp_port", port.to_string());
+    field("memcache_port", memcache_port.to_string());
+    field("uptime_in_seconds", uptime.to_string());
+    field("uptime_in_days", (uptime / 86_400).to_string());
+    let config_file = config.shared().file().map(|file| file.display().to_string());
+    field("config_file", config_file.unwrap_or_default());
+    field("shards", storage.shard_count().to_string());
+    fields
+}
+
+fn clients(storage: &StorageRouter) -> Vec<(String, String)> {
+    let maxclients = storage.config().read().maxclients;
+    vec![
+        ("connected_clients".into(), storage.clients().connected().to_string()),
+        ("maxclients".into(), maxclients.to_string()),
+        ("tracking_clients".into(), storage.clients().trackers().len().to_string()),
+    ]
+}
+
+fn memory(storage: &StorageRouter) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut field = |name: &str, value: String| fields.push((name.to_string(), value));
+
+    let used = storage.memory().used() as u64;
+    let (maxmemory, policy) = {
+        let config = storage.config().read();
+        (config.maxmemory.0, config.maxmemory_policy)
+    };
+    field("used_memory", used.to_string());
+    field("used_memory_human", human(used));
+    field("maxmemory", maxmemory.to_string());
+    field("maxmemory_human", human(maxmemory));
+    field("maxmemory_policy", policy.as_str().into());
+    fields
+}
+
+/// Amount of bytes as Redis renders it, `1.50M` for instance.
+fn human(bytes: u64) -> String {
+    let units = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes}B"),
+        _ => format!("{value:.2}{}", units[unit]),
+    }
+}
+
+/// Nothing is persisted while running, the snapshot is only written on shutdown.
+fn persistence() -> Vec<(String, String)> {
+    ["loading", "async_loading", "rdb_bgsave_in_progress", "aof_enabled", "aof_rewrite_in_progress"]
+        .into_iter()
+        .map(|name| (name.to_string(), "0".to_string()))
+        .collect()
+}
+
+fn stats(
+    storage: &StorageRouter,
+    total: &Counters,
+    shards: &[StatsResponse],
+) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut field = |name: &str, value: u64| fields.push((name.to_string(), value.to_string()));
+
+    field("total_connections_received", total.connections_received);
+    field("total_commands_processed", total.commands_processed);
+    field("total_net_input_bytes", total.net_input_bytes);
+    field("total_net_output_bytes", total.net_output_bytes);
+    field("rejected_connections", total.rejected_connections);
+    field("expired_keys", total.expired_keys);
+    field("evicted_keys", storage.memory().evicted());
+    field("keyspace_hits", total.keyspace_hits);
+    field("keyspace_misses", total.keyspace_misses);
+    field("total_error_replies", total.errors.values().sum());
+    let tracked_keys = shards.iter().map(|shard| shard.tracked_keys as u64).sum();
+    let tracked_items = shards.iter().map(|shard| shard.tracked_items as u64).sum();
+    field("tracking_total_keys", tracked_keys);
+    field("tracking_total_items", tracked_items);
+    field("tracking_total_prefixes", storage.clients().trackers().prefixes() as u64);
+    field("acl_access_denied_auth", total.acl_access_denied_auth);
+    field("acl_access_denied_cmd", total.acl_access_denied_cmd);
+    field("acl_access_denied_key", total.acl_access_denied_key);
+    fields
+}
+
+/// CPU seconds used by the process and its children, from `/proc/self/stat`.
+fn cpu() -> Vec<(String, String)> {
+    let stat = fs::read_to_string("/proc/self/stat").unwrap_or_default();
+    // Fields following the command name, which may contain spaces, start at the state.
+    let fields: Vec<&str> =
+        stat.rsplit_once(')').map_or(vec![], |(_, rest)| rest.split_whitespace().collect());
+    let seconds = |index: usize| {
+        let ticks = fields.get(index).and_then(|ticks| ticks.parse::<f64>().ok()).unwrap_or(0.0);
+        format!("{:.6}", ticks / CLOCK_TICKS)
+    };
+
+    vec![
+        ("used_cpu_sys".into(), seconds(12)),
+        ("used_cpu_user".into(), seconds(11)),
+        ("used_cpu_sys_children".into(), seconds(14)),
+        ("used_cpu_user_children".into(), seconds(13)),
+    ]
+}
+
+fn commandstats(total: &Counters) -> Vec<(String, String)> {
+    let mut fields: Vec<_> = total
+        .named_commands()
+        .map(|(name, stats)| {
+            let usec_per_call = stats.usec as f64 / stats.calls.max(1) as f64;
+            let value = format!(
+                "calls={},usec={},usec_per_call={usec_per_call:.2},rejected_calls={},failed_calls={}",
+                stats.calls, stats.usec, stats.rejected_calls, stats.failed_calls
+            );
+            (format!("cmdstat_{name}"), value)
+        })
+        .collect();
+    fields.sort();
+    fields
+}
+
+fn keyspace(shards: &[StatsResponse]) -> Vec<(String, String)> {
+    let keys: usize = shards.iter().map(|shard| shard.keys).sum();
+    let expires: usize = shards.iter().map(|shard| shard.expires).sum();
+    match keys {
+        0 => Vec::new(),
+        _ => vec![("db0".into(), format!("keys={keys},expires={expires},avg_ttl=0"))],
+    }
+}
+
+/// Load of every shard: its keys, memory, requests queued for its storage and connections
+/// handed to it by its acceptor.
+fn shard_fields(storage: &StorageRouter, shards: &[StatsResponse]) -> Vec<(String, String)> {
+    let memory = storage.memory();
+    let depths = storage.lane_depths();
+    shards
+        .iter()
+        .enumerate()
+        .map(|(index, shard)| {
+            let value = format!(
+                "keys={},expires={},used_memory={},storage_queue={},connected_clients={},connections_received={},local_requests={},remote_requests={},local_ratio={:.2}",
+                shard.keys,
+                shard.expires,
+                memory.shard(index),
+                depths.incoming(index),
+                shard.connected,
+                shard.counters.connections_received,
+                shard.counters.local_requests,
+                shard.counters.remote_requests,
+                shard.counters.local_ratio(),
+            );
+            (format!("shard{index}"), value)
+        })
+        .collect()
+}
+
+fn replication(replication: &Replication) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut field = |name: &str, value: String| fields.push((name.to_string(), value));
+
+    let backlog = replication.backlog();
+    let (replid, offset) = backlog.position();
+    let replicas = replication.replicas();
+
+    match replication.primary() {
+        Some(primary) => {
+            let link = replication.link();
+            field("role", "slave".into());
+            field("master_host", primary.host);
+            field("master_port", primary.port.to_string());
+            field("master_link_status", if link.up { "up" } else { "down" }.into());
+            let last_io = link.last_io.map_or(-1, |last_io| last_io.elapsed().as_secs() as i64);
+            field("master_last_io_seconds_ago", last_io.to_string());
+            field("master_sync_in_progress", (link.sync_in_progress as u8).to_string());
+            field("slave_read_repl_offset", offset.to_string());
+            field("slave_repl_offset", offset.to_string());
+            field("slave_priority", "100".into());
+            field("slave_read_only", "1".into());
+            field("replica_announced", "1".into());
+        }
+        None => field("role", "master".into()),
+    }
+
+    field("connected_slaves", replicas.len().to_string());
+    for (index, replica) in replicas.iter().enumerate() {
+        let state = if replica.is_online() { "online" } else { "wait_bgsave" };
+        field(
+            &format!("slave{index}"),
+            format!(
+                "ip={},port={},state={state},offset={},lag={}",
+                replica.ip,
+                replica.port,
+                replica.acked_offset(),
+                replica.lag()
+            ),
+        );
+    }
+
+    let stats = backlog.stats();
+    field("master_failover_state", "no-failover".into());
+    field("master_replid", replid);
+    field("master_replid2", stats.replid2);
+    field("master_repl_offset", offset.to_string());
+    let second_offset = if stats.second_offset < 0 { -1 } else { stats.second_offset + 1 };
+    field("second_repl_offset", second_offset.to_string());
+    field("repl_backlog_active", (backlog.is_active() as u8).to_string());
+    field("repl_backlog_size", stats.capacity.to_string());
+    field("repl_backlog_first_byte_offset", (stats.first_offset + 1).to_string());
+    field("repl_backlog_histlen", stats.histlen.to_string());
+
+    fields
+}
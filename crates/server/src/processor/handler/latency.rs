@@ -0,0 +1,294 @@
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fmt::Write,
+};
+
+use goosekv_protocol::{
+    command::{
+        LatencyDoctorGCommand,
+        LatencyHistogramGCommand,
+        LatencyHistoryGCommand,
+        LatencyLatestGCommand,
+        LatencyResetGCommand,
+    },
+    data_type::GInteger,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    latency::{
+        EventHistory,
+        LatencyEvent,
+    },
+    processor::handler::{
+        Handler,
+        bulk_string,
+    },
+    stats::{
+        CommandStats,
+        Counters,
+        LATENCY_BUCKETS,
+    },
+    storage::{
+        request::LatencyRequest,
+        response::StorageResult,
+        router::StorageRouter,
+    },
+};
+
+pub struct LatencyLatestHandler;
+
+impl Handler<LatencyLatestGCommand> for LatencyLatestHandler {
+    async fn handle(&self, _command: LatencyLatestGCommand, storage: &StorageRouter) -> GFrame {
+        let events = match events(storage).await {
+            Ok(events) => events,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let latest = events.iter().filter_map(|(event, history)| {
+            let sample = history.latest()?;
+            Some(GFrame::Array(Box::new([
+                bulk_string(event.name()),
+                integer(sample.time),
+                integer(sample.latency),
+                integer(history.max),
+            ])))
+        });
+        GFrame::Array(latest.collect())
+    }
+}
+
+pub struct LatencyHistoryHandler;
+
+impl Handler<LatencyHistoryGCommand> for LatencyHistoryHandler {
+    async fn handle(&self, command: LatencyHistoryGCommand, storage: &StorageRouter) -> GFrame {
+        let Some(event) = LatencyEvent::from_name(command.event.as_ref()) else {
+            return GFrame::Array(Box::new([]));
+        };
+        let events = match events(storage).await {
+            Ok(events) => events,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let samples = events.get(&event).into_iter().flat_map(|history| &history.samples);
+        GFrame::Array(
+            samples
+                .map(|sample| {
+                    GFrame::Array(Box::new([integer(sample.time), integer(sample.latency)]))
+                })
+                .collect(),
+        )
+    }
+}
+
+pub struct LatencyResetHandler;
+
+impl Handler<LatencyResetGCommand> for LatencyResetHandler {
+    async fn handle(&self, command: LatencyResetGCommand, storage: &StorageRouter) -> GFrame {
+        let events: Vec<_> = command
+            .events
+            .iter()
+            .filter_map(|event| LatencyEvent::from_name(event.as_ref()))
+            .collect();
+        // Unknown events reset nothing, rather than every event as no event at all does.
+        if events.is_empty() && !command.events.is_empty() {
+            return integer(0);
+        }
+
+        let responses = match storage.collect_latency(LatencyRequest::Reset(events)).await {
+            Ok(responses) => responses,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let reset: BTreeSet<_> =
+            responses.into_iter().flat_map(|response| response.reset).collect();
+        integer(reset.len() as u64)
+    }
+}
+
+pub struct LatencyHistogramHandler;
+
+impl Handler<LatencyHistogramGCommand> for LatencyHistogramHandler {
+    async fn handle(&self, command: LatencyHistogramGCommand, storage: &StorageRouter) -> GFrame {
+        let shards = match storage.collect_stats().await {
+            Ok(shards) => shards,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let mut total = Counters::default();
+        for shard in shards {
+            total.merge(&shard.counters);
+        }
+
+        // A container name selects all of its subcommands.
+        let wanted = |name: &str| {
+            command.commands.is_empty()
+                || command.commands.iter().any(|command| {
+                    let command = String::from_utf8_lossy(command.as_ref()).to_lowercase();
+                    name == command || name.strip_prefix(&format!("{command}|")).is_some()
+                })
+        };
+
+        let mut reply = Vec::new();
+        for (name, stats) in total.named_commands() {
+            if stats.calls > 0 && wanted(&name) {
+                reply.push(bulk_string(&name));
+                reply.push(histogram(stats));
+            }
+        }
+        GFrame::Array(reply.into())
+    }
+}
+
+pub struct LatencyDoctorHandler;
+
+impl Handler<LatencyDoctorGCommand> for LatencyDoctorHandler {
+    async fn handle(&self, _command: LatencyDoctorGCommand, storage: &StorageRouter) -> GFrame {
+        let threshold = storage.config().read().latency_monitor_threshold;
+        let events = match events(storage).await {
+            Ok(events) => events,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        bulk_string(&doctor(threshold, &events))
+    }
+}
+
+/// Samples of every shard, merged by event.
+async fn events(storage: &StorageRouter) -> StorageResult<BTreeMap<LatencyEvent, EventHistory>> {
+    let mut events: BTreeMap<LatencyEvent, EventHistory> = BTreeMap::new();
+    for response in storage.collect_latency(LatencyRequest::Events).await? {
+        for (event, history) in response.events {
+            events.entry(event).or_default().merge(&history);
+        }
+    }
+    Ok(events)
+}
+
+/// Calls of a command and their cumulative count per power of two microseconds, from the first
+/// to the last bucket with calls.
+fn histogram(stats: &CommandStats) -> GFrame {
+    let first = stats.latency.iter().position(|calls| *calls > 0);
+    let last = stats.latency.iter().rposition(|calls| *calls > 0);
+    let mut buckets = Vec::new();
+    if let (Some(first), Some(last)) = (first, last) {
+        let mut calls = stats.latency[..first].iter().sum::<u64>();
+        let bounds = LATENCY_BUCKETS.iter().zip(stats.latency);
+        for (bound, count) in bounds.take(last + 1).skip(first) {
+            calls += count;
+            buckets.push(integer(*bound));
+            buckets.push(integer(calls));
+        }
+    }
+
+    GFrame::Array(Box::new([
+        bulk_string("calls"),
+        integer(stats.calls),
+        bulk_string("histogram_usec"),
+        GFrame::Array(buckets.into()),
+    ]))
+}
+
+/// Human-readable report of the latency spikes of every event, with advice to reduce them.
+fn doctor(threshold: u64, events: &BTreeMap<LatencyEvent, EventHistory>) -> String {
+    if threshold == 0 {
+        return "Latency monitoring is disabled. Use CONFIG SET latency-monitor-threshold \
+                <milliseconds> to enable it.\n"
+            .to_string();
+    }
+    if events.values().all(|history| history.samples.is_empty()) {
+        return format!(
+            "No latency spike above {threshold} milliseconds was detected since the monitor was \
+             enabled or last reset.\n"
+        );
+    }
+
+    let mut report = String::from("Latency spikes were detected for the following events:\n\n");
+    let events = events.iter().filter(|(_, history)| !history.samples.is_empty());
+    for (index, (event, history)) in events.clone().enumerate() {
+        let samples = &history.samples;
+        let count = samples.len() as f64;
+        let average = samples.iter().map(|sample| sample.latency as f64).sum::<f64>() / count;
+        let deviation =
+            samples.iter().map(|sample| (sample.latency as f64 - average).abs()).sum::<f64>()
+                / count;
+        let (first, last) = (samples[0].time, samples[samples.len() - 1].time);
+        let period = (last - first) as f64 / (count - 1.0).max(1.0);
+        let _ = writeln!(
+            report,
+            "{}. {}: {} latency spikes (average {:.0}ms, mean deviation {:.0}ms, period {:.2} \
+             sec). Worst all time event {}ms.",
+            index + 1,
+            event.name(),
+            samples.len(),
+            average,
+            deviation,
+            period,
+            history.max,
+        );
+    }
+
+    report.push_str("\nI have a few pieces of advice for you:\n\n");
+    for (event, _) in events {
+        let _ = writeln!(report, "- {}", advice(*event));
+    }
+    report
+}
+
+fn advice(event: LatencyEvent) -> &'static str {
+    match event {
+        LatencyEvent::Command => {
+            "Slow commands were executed, check SLOWLOG GET for commands that are O(N) over \
+             large collections or many keys."
+        }
+        LatencyEvent::FastCommand => {
+            "Commands flagged as fast took long to execute, which usually means the shard was \
+             starved of CPU. Check the load of the host and whether cpu-pinning shares cores."
+        }
+        LatencyEvent::StorageRoundTrip => {
+            "Requests to other shards waited in their queues. Use {hashtags} to keep the keys of a \
+             client on one shard, or raise storage-channel-capacity if the queues are full."
+        }
+        LatencyEvent::EvictionCycle => {
+            "Evicting keys to fit maxmemory took long. Raise maxmemory or lower \
+             maxmemory-samples."
+        }
+        LatencyEvent::ExpireCycle => {
+            "Removing expired keys took long, many keys expire at the same time. Spread their \
+             expiration times."
+        }
+        LatencyEvent::Snapshot => {
+            "Snapshots of every shard for replicas or shutdown took long, they copy all keys of \
+             each shard at once."
+        }
+        LatencyEvent::Fsync => {
+            "Syncing files to disk took long. Check the disk holding dir and whether other \
+             processes write to it."
+        }
+    }
+}
+
+fn integer(value: u64) -> GFrame {
+    GFrame::Integer(GInteger::new(value as i64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::latency::LatencySample;
+
+    #[test]
+    fn reports_spikes() {
+        assert!(doctor(0, &BTreeMap::new()).contains("disabled"));
+        assert!(doctor(10, &BTreeMap::new()).starts_with("No latency spike above 10"));
+
+        let mut history = EventHistory::default();
+        history.add(LatencySample { time: 100, latency: 10 });
+        history.add(LatencySample { time: 104, latency: 30 });
+        let report = doctor(10, &BTreeMap::from([(LatencyEvent::Command, history)]));
+        assert!(report.contains(
+            "1. command: 2 latency spikes (average 20ms, mean deviation 10ms, period 4.00 sec). \
+             Worst all time event 30ms."
+        ));
+        assert!(report.contains("SLOWLOG GET"));
+    }
+}
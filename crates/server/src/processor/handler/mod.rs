@@ -1,40 +1,255 @@
 use goosekv_protocol::{
     command::GCommand,
+    data_type::GString,
     frame::GFrame,
 };
 
 use crate::{
-    processor::handler::{
-        del::DelHandler,
-        exists::ExistsHandler,
-        get::GetHandler,
-        incr::IncrHandler,
-        ping::PingHandler,
-        set::SetHandler,
+    processor::{
+        handler::{
+            acl::{
+                AclCatHandler,
+                AclDelUserHandler,
+                AclGetUserHandler,
+                AclListHandler,
+                AclLoadHandler,
+                AclLogHandler,
+                AclSaveHandler,
+                AclSetUserHandler,
+                AclUsersHandler,
+                AclWhoAmIHandler,
+            },
+            auth::AuthHandler,
+            client::{
+                ClientCachingHandler,
+                ClientGetNameHandler,
+                ClientGetRedirHandler,
+                ClientIdHandler,
+                ClientInfoHandler,
+                ClientKillHandler,
+                ClientListHandler,
+                ClientNoEvictHandler,
+                ClientNoTouchHandler,
+                ClientPauseHandler,
+                ClientReplyHandler,
+                ClientSetInfoHandler,
+                ClientSetNameHandler,
+                ClientTrackingHandler,
+                ClientTrackingInfoHandler,
+                ClientUnpauseHandler,
+            },
+            cluster::{
+                AskingHandler,
+                ClusterInfoHandler,
+                ClusterKeyslotHandler,
+                ClusterMyIdHandler,
+                ClusterNodesHandler,
+                ClusterShardsHandler,
+                ClusterSlotsHandler,
+                ReadOnlyHandler,
+                ReadWriteHandler,
+            },
+            command::{
+                CommandCountHandler,
+                CommandDocsHandler,
+                CommandGetKeysHandler,
+                CommandHandler,
+                CommandInfoHandler,
+                CommandListHandler,
+            },
+            config::{
+                ConfigGetHandler,
+                ConfigResetStatHandler,
+                ConfigRewriteHandler,
+                ConfigSetHandler,
+            },
+            decr::DecrHandler,
+            del::DelHandler,
+            exists::ExistsHandler,
+            get::GetHandler,
+            hello::HelloHandler,
+            incr::IncrHandler,
+            info::InfoHandler,
+            latency::{
+                LatencyDoctorHandler,
+                LatencyHistogramHandler,
+                LatencyHistoryHandler,
+                LatencyLatestHandler,
+                LatencyResetHandler,
+            },
+            monitor::MonitorHandler,
+            ping::PingHandler,
+            replication::{
+                PsyncHandler,
+                ReplConfHandler,
+                ReplicaOfHandler,
+                WaitHandler,
+            },
+            reshard::ReshardHandler,
+            set::SetHandler,
+            shutdown::ShutdownHandler,
+            slowlog::{
+                SlowlogGetHandler,
+                SlowlogLenHandler,
+                SlowlogResetHandler,
+            },
+        },
+        session::Session,
     },
     storage::router::StorageRouter,
 };
 
+pub mod acl;
+pub mod auth;
+pub mod client;
+pub mod cluster;
+pub mod command;
+pub mod config;
+pub mod decr;
 pub mod del;
 pub mod exists;
 pub mod get;
+pub mod hello;
 pub mod incr;
+pub mod info;
+pub mod latency;
+pub mod monitor;
 pub mod ping;
+pub mod replication;
+pub mod reshard;
 pub mod set;
+pub mod shutdown;
+pub mod slowlog;
 
 pub trait Handler<C> {
     fn handle(&self, command: C, storage: &StorageRouter) -> impl Future<Output = GFrame>;
 }
 
-pub async fn handle_gcommand(command: GCommand, storage: &StorageRouter) -> GFrame {
+/// Handler of a command changing the state of the connection it was sent on.
+pub trait SessionHandler<C> {
+    fn handle(
+        &self,
+        command: C,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> impl Future<Output = GFrame>;
+}
+
+pub async fn handle_gcommand(
+    command: GCommand,
+    storage: &StorageRouter,
+    session: &mut Session,
+) -> GFrame {
     match command {
         GCommand::Ping(ping_command) => PingHandler.handle(ping_command, storage).await,
-        GCommand::Get(get_command) => GetHandler.handle(get_command, storage).await,
-        GCommand::Set(set_command) => SetHandler.handle(set_command, storage).await,
-        GCommand::Del(del_command) => DelHandler.handle(del_command, storage).await,
-        GCommand::Exists(exists_command) => ExistsHandler.handle(exists_command, storage).await,
-        GCommand::Incr(incr_gcommand) => IncrHandler.handle(incr_gcommand, storage).await,
-        GCommand::Decr(_decr_gcommand) => todo!("not implemented"),
-        GCommand::ConfigGet(_config_get_command) => GFrame::Null,
+        GCommand::Get(get_command) => GetHandler.handle(get_command, storage, session).await,
+        GCommand::Set(set_command) => SetHandler.handle(set_command, storage, session).await,
+        GCommand::Del(del_command) => DelHandler.handle(del_command, storage, session).await,
+        GCommand::Exists(exists_command) => {
+            ExistsHandler.handle(exists_command, storage, session).await
+        }
+        GCommand::Incr(incr_gcommand) => IncrHandler.handle(incr_gcommand, storage, session).await,
+        GCommand::Decr(decr_gcommand) => DecrHandler.handle(decr_gcommand, storage, session).await,
+        GCommand::ConfigGet(command) => ConfigGetHandler.handle(command, storage).await,
+        GCommand::ConfigSet(command) => ConfigSetHandler.handle(command, storage).await,
+        GCommand::ConfigRewrite(command) => ConfigRewriteHandler.handle(command, storage).await,
+        GCommand::ConfigResetStat(command) => ConfigResetStatHandler.handle(command, storage).await,
+        GCommand::Command(command) => CommandHandler.handle(command, storage).await,
+        GCommand::CommandCount(command) => CommandCountHandler.handle(command, storage).await,
+        GCommand::CommandInfo(command) => CommandInfoHandler.handle(command, storage).await,
+        GCommand::CommandDocs(command) => CommandDocsHandler.handle(command, storage).await,
+        GCommand::CommandList(command) => CommandListHandler.handle(command, storage).await,
+        GCommand::CommandGetKeys(command) => CommandGetKeysHandler.handle(command, storage).await,
+        GCommand::Reshard(command) => ReshardHandler.handle(command, storage).await,
+        GCommand::ClusterSlots(command) => ClusterSlotsHandler.handle(command, storage).await,
+        GCommand::ClusterShards(command) => ClusterShardsHandler.handle(command, storage).await,
+        GCommand::ClusterNodes(command) => ClusterNodesHandler.handle(command, storage).await,
+        GCommand::ClusterKeyslot(command) => ClusterKeyslotHandler.handle(command, storage).await,
+        GCommand::ClusterInfo(command) => ClusterInfoHandler.handle(command, storage).await,
+        GCommand::ClusterMyId(command) => ClusterMyIdHandler.handle(command, storage).await,
+        GCommand::ReadOnly(command) => ReadOnlyHandler.handle(command, storage, session).await,
+        GCommand::ReadWrite(command) => ReadWriteHandler.handle(command, storage, session).await,
+        GCommand::Asking(command) => AskingHandler.handle(command, storage, session).await,
+        GCommand::Info(command) => InfoHandler.handle(command, storage).await,
+        GCommand::ReplicaOf(command) => ReplicaOfHandler.handle(command, storage).await,
+        GCommand::Psync(command) => PsyncHandler.handle(command, storage, session).await,
+        GCommand::ReplConf(command) => ReplConfHandler.handle(command, storage, session).await,
+        GCommand::Wait(command) => WaitHandler.handle(command, storage).await,
+        GCommand::Shutdown(command) => ShutdownHandler.handle(command, storage, session).await,
+        GCommand::SlowlogGet(command) => SlowlogGetHandler.handle(command, storage).await,
+        GCommand::SlowlogLen(command) => SlowlogLenHandler.handle(command, storage).await,
+        GCommand::SlowlogReset(command) => SlowlogResetHandler.handle(command, storage).await,
+        GCommand::LatencyLatest(command) => LatencyLatestHandler.handle(command, storage).await,
+        GCommand::LatencyHistory(command) => LatencyHistoryHandler.handle(command, storage).await,
+        GCommand::LatencyReset(command) => LatencyResetHandler.handle(command, storage).await,
+        GCommand::LatencyHistogram(command) => {
+            LatencyHistogramHandler.handle(command, storage).await
+        }
+        GCommand::LatencyDoctor(command) => LatencyDoctorHandler.handle(command, storage).await,
+        GCommand::Monitor(command) => MonitorHandler.handle(command, storage, session).await,
+        GCommand::ClientId(command) => ClientIdHandler.handle(command, storage, session).await,
+        GCommand::ClientSetName(command) => {
+            ClientSetNameHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientGetName(command) => {
+            ClientGetNameHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientList(command) => ClientListHandler.handle(command, storage).await,
+        GCommand::ClientInfo(command) => ClientInfoHandler.handle(command, storage, session).await,
+        GCommand::ClientKill(command) => ClientKillHandler.handle(command, storage, session).await,
+        GCommand::ClientPause(command) => ClientPauseHandler.handle(command, storage).await,
+        GCommand::ClientUnpause(command) => ClientUnpauseHandler.handle(command, storage).await,
+        GCommand::ClientReply(command) => {
+            ClientReplyHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientNoEvict(command) => {
+            ClientNoEvictHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientNoTouch(command) => {
+            ClientNoTouchHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientSetInfo(command) => {
+            ClientSetInfoHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientTracking(command) => {
+            ClientTrackingHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientCaching(command) => {
+            ClientCachingHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientGetRedir(command) => {
+            ClientGetRedirHandler.handle(command, storage, session).await
+        }
+        GCommand::ClientTrackingInfo(command) => {
+            ClientTrackingInfoHandler.handle(command, storage, session).await
+        }
+        GCommand::Hello(command) => HelloHandler.handle(command, storage, session).await,
+        GCommand::Auth(command) => AuthHandler.handle(command, storage, session).await,
+        GCommand::AclSetUser(command) => AclSetUserHandler.handle(command, storage).await,
+        GCommand::AclGetUser(command) => AclGetUserHandler.handle(command, storage, session).await,
+        GCommand::AclDelUser(command) => AclDelUserHandler.handle(command, storage).await,
+        GCommand::AclList(command) => AclListHandler.handle(command, storage).await,
+        GCommand::AclUsers(command) => AclUsersHandler.handle(command, storage).await,
+        GCommand::AclWhoAmI(command) => AclWhoAmIHandler.handle(command, storage, session).await,
+        GCommand::AclCat(command) => AclCatHandler.handle(command, storage).await,
+        GCommand::AclLog(command) => AclLogHandler.handle(command, storage, session).await,
+        GCommand::AclSave(command) => AclSaveHandler.handle(command, storage).await,
+        GCommand::AclLoad(command) => AclLoadHandler.handle(command, storage).await,
+    }
+}
+
+fn bulk_string(value: &str) -> GFrame {
+    GFrame::BulkString(GString::copy_from_slice(value.as_bytes()))
+}
+
+fn simple_string(value: &str) -> GFrame {
+    GFrame::SimpleString(GString::copy_from_slice(value.as_bytes()))
+}
+
+/// Map reply in RESP3, flattened into an array of keys and values for RESP2 clients.
+fn map(resp: u8, entries: Vec<(GFrame, GFrame)>) -> GFrame {
+    match resp {
+        3 => GFrame::Map(entries.into()),
+        _ => GFrame::Array(entries.into_iter().flat_map(|(key, value)| [key, value]).collect()),
     }
 }
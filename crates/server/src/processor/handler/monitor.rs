@@ -0,0 +1,30 @@
+use goosekv_protocol::{
+    command::MonitorGCommand,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::{
+        handler::{
+            SessionHandler,
+            simple_string,
+        },
+        session::Session,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct MonitorHandler;
+
+impl SessionHandler<MonitorGCommand> for MonitorHandler {
+    async fn handle(
+        &self,
+        _command: MonitorGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        session.monitor = true;
+        session.client.update(|info| info.flags.monitor = true);
+        simple_string("OK")
+    }
+}
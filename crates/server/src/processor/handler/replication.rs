@@ -0,0 +1,157 @@
+use std::{
+    pin::pin,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use futures::{
+    StreamExt,
+    future::{
+        Either,
+        select,
+    },
+};
+use glommio::timer::sleep;
+use goosekv_protocol::{
+    command::{
+        PsyncGCommand,
+        ReplConfGCommand,
+        ReplicaOfGCommand,
+        WaitGCommand,
+    },
+    data_type::GInteger,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::{
+        handler::{
+            Handler,
+            SessionHandler,
+            simple_string,
+        },
+        session::Session,
+    },
+    replication::{
+        Primary,
+        sync::ReplicaSync,
+    },
+    storage::router::StorageRouter,
+};
+
+pub struct ReplicaOfHandler;
+
+impl Handler<ReplicaOfGCommand> for ReplicaOfHandler {
+    async fn handle(&self, command: ReplicaOfGCommand, storage: &StorageRouter) -> GFrame {
+        if storage.cluster().is_some() {
+            return ReplyError::Err("REPLICAOF not allowed in cluster mode.".into()).into();
+        }
+
+        let primary = command.primary.map(|(host, port)| Primary {
+            host: String::from_utf8_lossy(host.as_ref()).into_owned(),
+            port,
+        });
+        let replica = primary.is_some();
+
+        match storage.replication().set_primary(primary) {
+            false if replica => simple_string("OK Already connected to specified master"),
+            _ => simple_string("OK"),
+        }
+    }
+}
+
+pub struct PsyncHandler;
+
+impl SessionHandler<PsyncGCommand> for PsyncHandler {
+    async fn handle(
+        &self,
+        command: PsyncGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let backlog = storage.replication().backlog();
+        // Writes from now on are kept for the replica.
+        backlog.activate();
+
+        let replid = String::from_utf8_lossy(command.replid.as_ref());
+        let sync = ReplicaSync::negotiate(backlog, &replid, command.offset);
+        let reply = if sync.full {
+            format!("FULLRESYNC {} {}", sync.replid, sync.offset)
+        } else {
+            format!("CONTINUE {}", sync.replid)
+        };
+
+        session.replica_sync = Some(sync);
+        simple_string(&reply)
+    }
+}
+
+pub struct ReplConfHandler;
+
+impl SessionHandler<ReplConfGCommand> for ReplConfHandler {
+    async fn handle(
+        &self,
+        command: ReplConfGCommand,
+        _storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        for (name, value) in &command.options {
+            if name.eq_ignore_ascii_case(b"listening-port") {
+                let port = std::str::from_utf8(value.as_ref()).ok().and_then(|v| v.parse().ok());
+                match port {
+                    Some(port) => session.listening_port = Some(port),
+                    None => return ReplyError::NotInteger.into(),
+                }
+            } else if !["ip-address", "capa", "ack", "getack"]
+                .iter()
+                .any(|option| name.eq_ignore_ascii_case(option.as_bytes()))
+            {
+                let name = String::from_utf8_lossy(name.as_ref());
+                return ReplyError::Err(format!("Unrecognized REPLCONF option: {name}")).into();
+            }
+        }
+
+        simple_string("OK")
+    }
+}
+
+/// Waits for replicas to acknowledge every write applied by this node so far.
+pub struct WaitHandler;
+
+impl Handler<WaitGCommand> for WaitHandler {
+    async fn handle(&self, command: WaitGCommand, storage: &StorageRouter) -> GFrame {
+        let replication = storage.replication();
+        if replication.is_replica() {
+            return ReplyError::Err("WAIT cannot be used with replica instances.".into()).into();
+        }
+
+        let offset = replication.backlog().offset();
+        let wanted = command.replicas.max(0) as usize;
+        let deadline = (command.timeout > 0)
+            .then(|| Instant::now() + Duration::from_millis(command.timeout as u64));
+
+        let mut acks = replication.listen_acks();
+        let mut acked = replication.acked(offset);
+        if acked < wanted {
+            replication.request_acks();
+        }
+        while acked < wanted {
+            let woken = match deadline {
+                Some(deadline) => {
+                    let timer = pin!(sleep(deadline.saturating_duration_since(Instant::now())));
+                    matches!(select(acks.next(), timer).await, Either::Left((Some(()), _)))
+                }
+                None => acks.next().await.is_some(),
+            };
+            if !woken {
+                break;
+            }
+            acked = replication.acked(offset);
+        }
+
+        GFrame::Integer(GInteger::new(acked as i64))
+    }
+}
@@ -0,0 +1,26 @@
+use goosekv_protocol::{
+    command::ReshardGCommand,
+    data_type::GString,
+    error::ReplyError,
+    frame::GFrame,
+};
+use tracing::info;
+
+use crate::{
+    processor::handler::Handler,
+    storage::router::StorageRouter,
+};
+
+pub struct ReshardHandler;
+
+impl Handler<ReshardGCommand> for ReshardHandler {
+    async fn handle(&self, command: ReshardGCommand, storage: &StorageRouter) -> GFrame {
+        match storage.reshard(command.shards).await {
+            Ok(keys) => {
+                info!(shards = command.shards, keys, "reshard finished");
+                GFrame::SimpleString(GString::from_static(b"OK"))
+            }
+            Err(error) => ReplyError::Err(error.to_string()).into(),
+        }
+    }
+}
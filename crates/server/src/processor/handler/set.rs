@@ -1,11 +1,15 @@
 use goosekv_protocol::{
     command::SetGCommand,
     data_type::GString,
+    error::ReplyError,
     frame::GFrame,
 };
 
 use crate::{
-    processor::handler::Handler,
+    processor::{
+        handler::SessionHandler,
+        session::Session,
+    },
     storage::{
         request::SetRequest,
         router::StorageRouter,
@@ -20,15 +24,24 @@ pub struct SetHandler;
 
 const OK_MESSAGE: &[u8] = b"OK";
 
-impl Handler<SetGCommand> for SetHandler {
-    async fn handle(&self, command: SetGCommand, storage: &StorageRouter) -> GFrame {
-        storage
+impl SessionHandler<SetGCommand> for SetHandler {
+    async fn handle(
+        &self,
+        command: SetGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let response = storage
             .set(SetRequest {
                 key: command.key,
-                value: Value { data: Data::from_gstring(command.value) },
+                value: Value::new(Data::from_gstring(command.value)),
+                client: Some(session.client.id()),
             })
             .await;
 
-        GFrame::SimpleString(GString::from_static(OK_MESSAGE))
+        match response {
+            Ok(_) => GFrame::SimpleString(GString::from_static(OK_MESSAGE)),
+            Err(error) => ReplyError::from(error).into(),
+        }
     }
 }
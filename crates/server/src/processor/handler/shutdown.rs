@@ -0,0 +1,49 @@
+use goosekv_protocol::{
+    command::ShutdownGCommand,
+    error::ReplyError,
+    frame::GFrame,
+};
+use tracing::warn;
+
+use crate::{
+    processor::{
+        handler::{
+            SessionHandler,
+            simple_string,
+        },
+        session::Session,
+    },
+    shutdown::ShutdownMode,
+    storage::router::StorageRouter,
+};
+
+pub struct ShutdownHandler;
+
+impl SessionHandler<ShutdownGCommand> for ShutdownHandler {
+    async fn handle(
+        &self,
+        command: ShutdownGCommand,
+        storage: &StorageRouter,
+        session: &mut Session,
+    ) -> GFrame {
+        let shutdown = storage.shutdown();
+        if command.abort {
+            return match shutdown.abort() {
+                true => simple_string("OK"),
+                false => ReplyError::Err("No shutdown in progress.".into()).into(),
+            };
+        }
+
+        let mode = ShutdownMode { save: command.save, now: command.now, force: command.force };
+        match shutdown.run(storage, mode).await {
+            Ok(()) => {
+                session.quit = true;
+                GFrame::Null
+            }
+            Err(error) => {
+                warn!("{error}");
+                ReplyError::Err("Errors trying to SHUTDOWN. Check logs.".into()).into()
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::UNIX_EPOCH;
+
+use goosekv_protocol::{
+    command::{
+        SlowlogGetGCommand,
+        SlowlogLenGCommand,
+        SlowlogResetGCommand,
+    },
+    data_type::GInteger,
+    error::ReplyError,
+    frame::GFrame,
+};
+
+use crate::{
+    processor::handler::{
+        Handler,
+        bulk_string,
+        simple_string,
+    },
+    slowlog::SlowLogEntry,
+    storage::{
+        request::SlowLogRequest,
+        router::StorageRouter,
+    },
+};
+
+/// Entries returned by `SLOWLOG GET` without a count.
+const DEFAULT_COUNT: usize = 10;
+
+pub struct SlowlogGetHandler;
+
+impl Handler<SlowlogGetGCommand> for SlowlogGetHandler {
+    async fn handle(&self, command: SlowlogGetGCommand, storage: &StorageRouter) -> GFrame {
+        let count = match command.count {
+            None => DEFAULT_COUNT,
+            Some(count) if count < 0 => usize::MAX,
+            Some(count) => count as usize,
+        };
+
+        // Each shard returns its newest entries, the newest of all of them are kept.
+        let responses = match storage.collect_slowlog(SlowLogRequest::Get(count)).await {
+            Ok(responses) => responses,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let mut entries: Vec<_> =
+            responses.into_iter().flat_map(|response| response.entries).collect();
+        entries.sort_by(|a, b| b.logged_at.cmp(&a.logged_at).then(b.id.cmp(&a.id)));
+        entries.truncate(count);
+
+        GFrame::Array(entries.iter().map(entry).collect())
+    }
+}
+
+pub struct SlowlogLenHandler;
+
+impl Handler<SlowlogLenGCommand> for SlowlogLenHandler {
+    async fn handle(&self, _command: SlowlogLenGCommand, storage: &StorageRouter) -> GFrame {
+        let responses = match storage.collect_slowlog(SlowLogRequest::Len).await {
+            Ok(responses) => responses,
+            Err(error) => return ReplyError::from(error).into(),
+        };
+        let len: usize = responses.iter().map(|response| response.len).sum();
+        GFrame::Integer(GInteger::new(len as i64))
+    }
+}
+
+pub struct SlowlogResetHandler;
+
+impl Handler<SlowlogResetGCommand> for SlowlogResetHandler {
+    async fn handle(&self, _command: SlowlogResetGCommand, storage: &StorageRouter) -> GFrame {
+        match storage.collect_slowlog(SlowLogRequest::Reset).await {
+            Ok(_) => simple_string("OK"),
+            Err(error) => ReplyError::from(error).into(),
+        }
+    }
+}
+
+/// Entry as Redis replies it: id, unix time, microseconds, arguments, client address and name.
+fn entry(entry: &SlowLogEntry) -> GFrame {
+    let timestamp = entry.logged_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    GFrame::Array(Box::new([
+        GFrame::Integer(GInteger::new(entry.id as i64)),
+        GFrame::Integer(GInteger::new(timestamp as i64)),
+        GFrame::Integer(GInteger::new(entry.duration.as_micros() as i64)),
+        GFrame::Array(entry.args.iter().cloned().map(GFrame::BulkString).collect()),
+        bulk_string(&entry.addr),
+        GFrame::BulkString(entry.name.clone()),
+    ]))
+}
@@ -1,4 +1,7 @@
 pub mod actor;
+pub mod clients;
 pub mod command;
 pub mod handle;
-mod handler;
+pub(crate) mod handler;
+pub mod monitor;
+pub mod session;
@@ -0,0 +1,232 @@
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    pin::pin,
+    rc::Rc,
+    sync::{
+        Mutex,
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use futures::{
+    SinkExt,
+    StreamExt,
+    channel::mpsc,
+    future::select,
+    stream::{
+        SplitSink,
+        SplitStream,
+    },
+};
+use goosekv_protocol::{
+    command::{
+        args_from_frame,
+        table::{
+            CommandFlag,
+            CommandSpec,
+        },
+    },
+    data_type::GString,
+    frame::GFrame,
+    stream::{
+        GFrameStream,
+        GFrameStreamError,
+    },
+};
+use tracing::{
+    debug,
+    info,
+    warn,
+};
+
+use crate::{
+    outbox::Outbox,
+    storage::router::StorageRouter,
+    tls::Connection,
+};
+
+/// Connections that sent `MONITOR`, fed the commands processed by every shard.
+#[derive(Debug, Default)]
+pub struct Monitors {
+    /// Monitors attached, checked by every command so that shards do nothing without them.
+    count: AtomicUsize,
+    /// Lines waiting to be written to each monitor.
+    monitors: Mutex<Vec<Outbox<String>>>,
+}
+
+impl Monitors {
+    pub fn is_empty(&self) -> bool {
+        self.count.load(Ordering::Acquire) == 0
+    }
+
+    /// Attach a monitor, detached when the returned guard is dropped.
+    pub fn attach(&self) -> MonitorGuard<'_> {
+        let (outbox, lines) = Outbox::new();
+        let mut monitors = self.monitors.lock().unwrap();
+        monitors.push(outbox);
+        self.count.store(monitors.len(), Ordering::Release);
+        MonitorGuard { monitors: self, lines }
+    }
+
+    /// Send the line built by `line` to every monitor, `line` is only called if there are any.
+    pub fn feed(&self, line: impl FnOnce() -> String) {
+        if self.is_empty() {
+            return;
+        }
+
+        let line = line();
+        for monitor in self.monitors.lock().unwrap().iter_mut() {
+            monitor.push(line.clone());
+        }
+    }
+}
+
+/// Attached monitor, detached when dropped.
+#[derive(Debug)]
+pub struct MonitorGuard<'a> {
+    monitors: &'a Monitors,
+    /// Ends once the monitor fell too far behind.
+    lines: mpsc::Receiver<String>,
+}
+
+impl Drop for MonitorGuard<'_> {
+    fn drop(&mut self) {
+        let mut monitors = self.monitors.monitors.lock().unwrap();
+        monitors.retain(|monitor| !monitor.feeds(&self.lines));
+        self.monitors.count.store(monitors.len(), Ordering::Release);
+    }
+}
+
+/// Whether monitors are shown `spec`, not admin commands nor those carrying a password.
+pub fn shows(spec: &CommandSpec) -> bool {
+    !spec.has_flag(CommandFlag::Admin) && !spec.has_flag(CommandFlag::NoAuth)
+}
+
+/// Line describing a command for monitors, as Redis formats it.
+pub fn line(time: SystemTime, addr: Option<SocketAddr>, args: &[GString]) -> String {
+    let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let addr = addr.map_or_else(|| "?".to_string(), |addr| addr.to_string());
+    let mut line = format!("{}.{:06} [0 {addr}]", time.as_secs(), time.subsec_micros());
+    for arg in args {
+        line.push(' ');
+        quote(&mut line, arg.as_ref());
+    }
+    line
+}
+
+/// Append `arg` between double quotes, escaping what is not printable.
+fn quote(line: &mut String, arg: &[u8]) {
+    line.push('"');
+    for byte in arg {
+        match byte {
+            b'\\' => line.push_str("\\\\"),
+            b'"' => line.push_str("\\\""),
+            b'\n' => line.push_str("\\n"),
+            b'\r' => line.push_str("\\r"),
+            b'\t' => line.push_str("\\t"),
+            0x07 => line.push_str("\\a"),
+            0x08 => line.push_str("\\b"),
+            byte if byte.is_ascii_graphic() || *byte == b' ' => line.push(*byte as char),
+            byte => {
+                let _ = write!(line, "\\x{byte:02x}");
+            }
+        }
+    }
+    line.push('"');
+}
+
+type Sink = SplitSink<GFrameStream<Connection>, GFrame>;
+
+/// Stream the commands processed by every shard to a connection that sent `MONITOR`.
+///
+/// The client may only send `QUIT` from then on, anything else is ignored.
+pub async fn serve(stream: GFrameStream<Connection>, router: Rc<StorageRouter>) {
+    let monitors = router.clients().monitors();
+    let mut guard = monitors.attach();
+    info!("monitor attached");
+
+    let (mut sink, mut stream) = stream.split();
+    let feed = pin!(feed(&mut sink, &mut guard.lines));
+    let quit = pin!(quit(&mut stream));
+    if let futures::future::Either::Left((Err(error), _)) = select(feed, quit).await {
+        warn!("failed to stream to monitor: {error}");
+    }
+    info!("monitor detached");
+}
+
+async fn feed(
+    sink: &mut Sink,
+    lines: &mut mpsc::Receiver<String>,
+) -> Result<(), GFrameStreamError> {
+    let frame = |line: String| GFrame::SimpleString(GString::copy_from_slice(line.as_bytes()));
+    while let Some(line) = lines.next().await {
+        sink.feed(frame(line)).await?;
+        // Lines queued meanwhile are written at once.
+        while let Ok(Some(line)) = lines.try_next() {
+            sink.feed(frame(line)).await?;
+        }
+        sink.flush().await?;
+    }
+
+    warn!("monitor fell behind, disconnecting it");
+    Ok(())
+}
+
+/// Wait for the client to send `QUIT` or to disconnect.
+async fn quit(stream: &mut SplitStream<GFrameStream<Connection>>) {
+    while let Some(Ok(frame)) = stream.next().await {
+        match args_from_frame(&frame) {
+            Ok(args) if args.first().is_some_and(|name| name.eq_ignore_ascii_case(b"QUIT")) => {
+                return;
+            }
+            _ => debug!("ignored command sent by a monitor"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_lines() {
+        let time = UNIX_EPOCH + std::time::Duration::from_micros(1_700_000_000_000_042);
+        let addr = "127.0.0.1:6000".parse().ok();
+        let args = [GString::from_static(b"set"), GString::from_static(b"k \"1\"\n\x01")];
+        assert_eq!(
+            line(time, addr, &args),
+            "1700000000.000042 [0 127.0.0.1:6000] \"set\" \"k \\\"1\\\"\\n\\x01\""
+        );
+    }
+
+    #[test]
+    fn hides_passwords_and_admin_commands() {
+        let spec = |name: &[u8]| CommandSpec::find(name).unwrap();
+        assert!(shows(spec(b"GET")));
+        assert!(!shows(spec(b"AUTH")));
+        assert!(!shows(spec(b"HELLO")));
+        assert!(!shows(spec(b"SHUTDOWN")));
+    }
+
+    #[test]
+    fn feeds_attached_monitors() {
+        let monitors = Monitors::default();
+        monitors.feed(|| unreachable!("no monitor is attached"));
+
+        let mut guard = monitors.attach();
+        monitors.feed(|| "line".to_string());
+        assert_eq!(guard.lines.try_next().unwrap().as_deref(), Some("line"));
+        assert!(guard.lines.try_next().is_err());
+
+        drop(guard);
+        assert!(monitors.is_empty());
+    }
+}
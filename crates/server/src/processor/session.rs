@@ -0,0 +1,91 @@
+use std::{
+    rc::Rc,
+    sync::Arc,
+};
+
+use goosekv_protocol::command::ClientReplyMode;
+
+use crate::{
+    acl::{
+        Acl,
+        User,
+    },
+    processor::clients::Client,
+    replication::sync::ReplicaSync,
+    tracking::{
+        Listener,
+        TrackingGuard,
+    },
+};
+
+/// State of one client connection, kept across the commands it sends.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Identity of the connection, listed by `CLIENT LIST`.
+    pub client: Rc<Client>,
+    /// Set by `READONLY`, reads of slots served by the primary of this node are accepted.
+    pub readonly: bool,
+    /// Set by `ASKING`, the next command may access a slot being imported.
+    pub asking: bool,
+    /// Port a replica listens on, announced with `REPLCONF listening-port`.
+    pub listening_port: Option<u16>,
+    /// Set by `PSYNC`, the connection then carries the replication stream to a replica.
+    pub replica_sync: Option<ReplicaSync>,
+    /// Set by `MONITOR`, the connection then streams the commands processed by every shard.
+    pub monitor: bool,
+    /// Set by `CLIENT REPLY`, `Skip` drops the reply of the next command only.
+    pub reply: ClientReplyMode,
+    /// Set when the reply of the current command must not be sent, as for `CLIENT REPLY OFF`.
+    pub skip_reply: bool,
+    /// Set once the connection has to be closed without replying, by a successful `SHUTDOWN`.
+    pub quit: bool,
+    /// Set by `CLIENT TRACKING ON`, the keys read by the client are tracked until it is dropped.
+    pub tracking: Option<TrackingGuard>,
+    /// Set by `HELLO 3`, invalidations are pushed to the connection between replies.
+    pub invalidations: Option<Listener>,
+    /// Set by `CLIENT CACHING`, overrides OPTIN or OPTOUT for the next command only.
+    pub caching: Option<bool>,
+    /// Set by a successful `AUTH` or `HELLO AUTH`, required unless the `default` user needs no
+    /// password.
+    pub authenticated: bool,
+    /// User named in the client info, as of the ACL epoch it was looked up at.
+    pub(crate) user: Option<(Arc<User>, u64)>,
+}
+
+impl Session {
+    /// ACL user the connection runs as, looked up again whenever the users changed.
+    ///
+    /// None once the user was deleted, the connection is then about to be killed.
+    pub fn user(&mut self, acl: &Acl) -> Option<Arc<User>> {
+        let epoch = acl.epoch();
+        match &self.user {
+            Some((user, at)) if *at == epoch => Some(user.clone()),
+            _ => {
+                let user = acl.user(&self.client.info().user)?;
+                self.user = Some((user.clone(), epoch));
+                Some(user)
+            }
+        }
+    }
+
+    /// Run as user `name` from now on, once authenticated.
+    pub fn login(&mut self, name: &str) {
+        self.client.update(|info| info.user = name.to_string());
+        self.authenticated = true;
+        self.user = None;
+    }
+
+    /// Client the keys read by the current command are tracked for, if any.
+    ///
+    /// Keys are not tracked one by one in BCAST mode.
+    pub fn tracking_reads(&self) -> Option<u64> {
+        let mode = &self.tracking.as_ref()?.mode;
+        let tracked = match (mode.bcast, mode.optin, mode.optout) {
+            (true, _, _) => false,
+            (_, true, _) => self.caching == Some(true),
+            (_, _, true) => self.caching != Some(false),
+            _ => true,
+        };
+        tracked.then(|| self.client.id())
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::VecDeque,
+    hash::{
+        BuildHasher,
+        RandomState,
+    },
+    sync::{
+        Mutex,
+        MutexGuard,
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+    },
+};
+
+use futures::channel::mpsc;
+use goosekv_protocol::frame::GFrame;
+
+use crate::event::Event;
+
+pub const DEFAULT_BACKLOG_SIZE: usize = 1 << 20;
+
+/// Replication ID announced before any primary was synced with.
+pub const NO_REPLID: &str = "0000000000000000000000000000000000000000";
+
+/// Stream of write commands applied by every shard, kept for replicas to resume from.
+///
+/// Offsets count the bytes of serialized frames since the stream identified by the replication
+/// ID started, like `master_repl_offset` in Redis.
+pub struct Backlog {
+    /// Set once a replica attached or this node became one, writes are not recorded before.
+    active: AtomicBool,
+    inner: Mutex<Inner>,
+    /// Fired by every change of the stream, listened to by each task streaming it to a replica.
+    changes: Event,
+}
+
+struct Inner {
+    replid: String,
+    /// ID of the stream this node followed before being promoted.
+    replid2: String,
+    /// Offset up to which `replid2` can be resumed, `-1` if there is none.
+    second_offset: i64,
+    offset: u64,
+    /// Frames with the offset of their first byte.
+    frames: VecDeque<(u64, GFrame)>,
+    size: usize,
+    capacity: usize,
+}
+
+impl Backlog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            inner: Mutex::new(Inner {
+                replid: random_replid(),
+                replid2: NO_REPLID.to_string(),
+                second_offset: -1,
+                offset: 0,
+                frames: VecDeque::new(),
+                size: 0,
+                capacity,
+            }),
+            changes: Event::default(),
+        }
+    }
+
+    pub fn activate(&self) {
+        self.active.store(true, Ordering::Release);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Append the frame built by `frame`, which is only called while the backlog is active.
+    pub fn feed(&self, frame: impl FnOnce() -> GFrame) {
+        if !self.is_active() {
+            return;
+        }
+
+        let frame = frame();
+        let len = frame.bytes().len();
+        let mut inner = self.lock();
+        let offset = inner.offset;
+        inner.frames.push_back((offset, frame));
+        inner.offset += len as u64;
+        inner.size += len;
+        inner.evict();
+        self.changes.notify();
+    }
+
+    /// Wakeups sent once the stream changes, repeated ones merged until they are received.
+    pub fn listen(&self) -> mpsc::Receiver<()> {
+        self.changes.listen()
+    }
+
+    /// Wake the listeners without a change of the stream, to send them something else.
+    pub fn notify(&self) {
+        self.changes.notify();
+    }
+
+    /// Keep at most `capacity` bytes of frames, evicting the oldest ones right away.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.lock();
+        inner.capacity = capacity;
+        inner.evict();
+    }
+
+    pub fn replid(&self) -> String {
+        self.lock().replid.clone()
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.lock().offset
+    }
+
+    /// Replication ID and offset at the same point of the stream.
+    pub fn position(&self) -> (String, u64) {
+        let inner = self.lock();
+        (inner.replid.clone(), inner.offset)
+    }
+
+    /// Append the frames starting at or after `offset` to `frames` and return the offset following
+    /// them, `None` if `offset` is no longer or was never the start of a frame.
+    pub fn read_from(&self, offset: u64, frames: &mut Vec<GFrame>) -> Option<u64> {
+        let inner = self.lock();
+        if offset == inner.offset {
+            return Some(offset);
+        }
+
+        let start = inner.frames.partition_point(|(start, _)| *start < offset);
+        if inner.frames.get(start).is_none_or(|(start, _)| *start != offset) {
+            return None;
+        }
+
+        frames.extend(inner.frames.range(start..).map(|(_, frame)| frame.clone()));
+        Some(inner.offset)
+    }
+
+    /// Whether a replica that followed `replid` up to `offset` can resume from the backlog.
+    pub fn can_continue(&self, replid: &str, offset: u64) -> bool {
+        let inner = self.lock();
+        let known = replid == inner.replid
+            || (replid == inner.replid2 && offset as i64 <= inner.second_offset);
+        let buffered = offset == inner.offset
+            || inner.frames.binary_search_by_key(&offset, |(start, _)| *start).is_ok();
+
+        known && buffered
+    }
+
+    /// Start following the stream of a primary from `offset`, after a full sync.
+    pub fn reset(&self, replid: String, offset: u64) {
+        let mut inner = self.lock();
+        inner.replid = replid;
+        inner.replid2 = NO_REPLID.to_string();
+        inner.second_offset = -1;
+        inner.offset = offset;
+        inner.frames.clear();
+        inner.size = 0;
+        self.changes.notify();
+    }
+
+    /// Start a new stream after being promoted, replicas of the old primary can still resume.
+    pub fn shift_replid(&self) {
+        let mut inner = self.lock();
+        inner.replid2 = std::mem::replace(&mut inner.replid, random_replid());
+        inner.second_offset = inner.offset as i64;
+        self.changes.notify();
+    }
+
+    /// Follow the stream of a primary that changed its replication ID, keeping the offset.
+    pub fn adopt_replid(&self, replid: String) {
+        let mut inner = self.lock();
+        if inner.replid != replid {
+            inner.replid2 = std::mem::replace(&mut inner.replid, replid);
+            inner.second_offset = inner.offset as i64;
+            self.changes.notify();
+        }
+    }
+
+    pub fn stats(&self) -> BacklogStats {
+        let inner = self.lock();
+        BacklogStats {
+            replid2: inner.replid2.clone(),
+            second_offset: inner.second_offset,
+            first_offset: inner.frames.front().map_or(inner.offset, |(offset, _)| *offset),
+            histlen: inner.size,
+            capacity: inner.capacity,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+}
+
+impl Inner {
+    fn evict(&mut self) {
+        while self.size > self.capacity && self.frames.len() > 1 {
+            let (first, _) = self.frames.pop_front().unwrap();
+            let next = self.frames.front().map_or(self.offset, |(offset, _)| *offset);
+            self.size -= (next - first) as usize;
+        }
+    }
+}
+
+impl Default for Backlog {
+    fn default() -> Self {
+        Self::new(DEFAULT_BACKLOG_SIZE)
+    }
+}
+
+/// Backlog fields reported by `INFO replication`.
+#[derive(Debug, Clone)]
+pub struct BacklogStats {
+    pub replid2: String,
+    pub second_offset: i64,
+    pub first_offset: u64,
+    pub histlen: usize,
+    pub capacity: usize,
+}
+
+fn random_replid() -> String {
+    let mut replid: String = (0..3)
+        .map(|_| format!("{:016x}", RandomState::new().hash_one(std::process::id())))
+        .collect();
+    replid.truncate(NO_REPLID.len());
+    replid
+}
+
+#[cfg(test)]
+mod test {
+    use goosekv_protocol::data_type::GString;
+
+    use super::*;
+
+    fn frame(value: &'static [u8]) -> GFrame {
+        GFrame::Array([GFrame::BulkString(GString::from_static(value))].into())
+    }
+
+    #[test]
+    fn resume() {
+        let backlog = Backlog::new(64);
+        backlog.feed(|| frame(b"ignored"));
+        assert_eq!(backlog.offset(), 0);
+
+        backlog.activate();
+        backlog.feed(|| frame(b"a"));
+        let len = frame(b"a").bytes().len() as u64;
+        backlog.feed(|| frame(b"b"));
+
+        let replid = backlog.replid();
+        assert!(backlog.can_continue(&replid, len));
+        assert!(!backlog.can_continue(&replid, 1));
+        assert!(!backlog.can_continue(NO_REPLID, 0));
+
+        let mut frames = Vec::new();
+        assert_eq!(backlog.read_from(len, &mut frames), Some(2 * len));
+        assert_eq!(frames.len(), 1);
+
+        backlog.shift_replid();
+        assert!(backlog.can_continue(&replid, 2 * len));
+        assert_ne!(backlog.replid(), replid);
+    }
+
+    #[test]
+    fn wakes_listeners() {
+        let backlog = Backlog::new(64);
+        backlog.activate();
+        let mut changes = backlog.listen();
+        assert!(changes.try_next().is_err());
+
+        backlog.feed(|| frame(b"a"));
+        backlog.feed(|| frame(b"b"));
+        assert_eq!(changes.try_next().unwrap(), Some(()));
+        assert!(changes.try_next().is_err());
+
+        backlog.shift_replid();
+        assert_eq!(changes.try_next().unwrap(), Some(()));
+    }
+
+    #[test]
+    fn evicts_oldest() {
+        let backlog = Backlog::new(32);
+        backlog.activate();
+        for _ in 0..10 {
+            backlog.feed(|| frame(b"value"));
+        }
+
+        let stats = backlog.stats();
+        assert!(stats.histlen <= 32);
+        assert!(!backlog.can_continue(&backlog.replid(), 0));
+        assert!(backlog.can_continue(&backlog.replid(), stats.first_offset));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    io,
+    pin::pin,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use bytes::BytesMut;
+use futures::{
+    SinkExt,
+    StreamExt,
+    future::{
+        Either,
+        select,
+    },
+};
+use glommio::{
+    net::TcpStream,
+    timer::sleep,
+};
+use goosekv_protocol::{
+    command::{
+        AuthGCommand,
+        GCommand,
+        PingGCommand,
+        PsyncGCommand,
+    },
+    data_type::GString,
+    frame::GFrame,
+    parser::{
+        ParseError,
+        parse_frame,
+    },
+    stream::{
+        GFrameStream,
+        GFrameStreamError,
+    },
+};
+use thiserror::Error;
+use tracing::{
+    info,
+    warn,
+};
+
+use crate::{
+    processor::{
+        handler::handle_gcommand,
+        session::Session,
+    },
+    replication::{
+        Primary,
+        parse_set_frame,
+        sync::replconf,
+    },
+    storage::{
+        request::SetRequest,
+        response::StorageError,
+        router::StorageRouter,
+    },
+};
+
+/// Delay before reconnecting to a primary after the link failed.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// Delay between two acknowledgements of the applied offset sent to the primary.
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Stream(#[from] GFrameStreamError),
+    #[error("invalid snapshot: {0}")]
+    Snapshot(#[from] ParseError),
+    #[error("{0}")]
+    Storage(#[from] StorageError),
+    #[error("connection closed by the primary")]
+    Closed,
+    #[error("unexpected reply from the primary: {0}")]
+    Reply(GFrame),
+}
+
+/// Replicate the primary configured with `REPLICAOF`, reconnecting whenever the link drops.
+///
+/// Runs on a single shard, the stream is applied through `router` like client commands.
+pub async fn run(router: Rc<StorageRouter>, listening_port: u16) {
+    let replication = router.replication().clone();
+    let mut primary_changes = replication.listen_primary();
+    loop {
+        let (Some(primary), generation) = replication.primary_generation() else {
+            primary_changes.next().await;
+            continue;
+        };
+
+        info!(host = primary.host, port = primary.port, "connecting to primary");
+        if let Err(error) = replicate(&router, &primary, generation, listening_port).await {
+            warn!("replication link to {}:{} failed: {error}", primary.host, primary.port);
+        }
+        replication.update_link(generation, |link| {
+            link.up = false;
+            link.sync_in_progress = false;
+        });
+
+        if replication.generation() == generation {
+            sleep(RETRY_INTERVAL).await;
+        }
+    }
+}
+
+async fn replicate(
+    router: &StorageRouter,
+    primary: &Primary,
+    generation: u64,
+    listening_port: u16,
+) -> Result<(), LinkError> {
+    let replication = router.replication();
+    let backlog = replication.backlog();
+
+    let stream =
+        TcpStream::connect((primary.host.as_str(), primary.port)).await.map_err(io::Error::from)?;
+    let mut stream = GFrameStream::new(stream);
+    let (user, password) = {
+        let config = router.config().read();
+        (config.masteruser.clone(), config.masterauth.clone())
+    };
+    if !password.is_empty() {
+        let username = (!user.is_empty()).then(|| GString::copy_from_slice(user.as_bytes()));
+        let password = GString::copy_from_slice(password.as_bytes());
+        request(&mut stream, GCommand::Auth(AuthGCommand { username, password }).to_frame())
+            .await?;
+    }
+    request(&mut stream, GCommand::Ping(PingGCommand { message: None }).to_frame()).await?;
+    let port = GString::copy_from_slice(listening_port.to_string().as_bytes());
+    request(&mut stream, replconf(b"listening-port", port)).await?;
+    request(&mut stream, replconf(b"capa", GString::from_static(b"psync2"))).await?;
+
+    replication.update_link(generation, |link| link.sync_in_progress = true);
+    let (replid, offset) = backlog.position();
+    let replid = GString::copy_from_slice(replid.as_bytes());
+    let psync = GCommand::Psync(PsyncGCommand { replid, offset: offset as i64 + 1 });
+    let reply = request(&mut stream, psync.to_frame()).await?;
+
+    let reply_text = reply.as_simple_string().map_err(|_| LinkError::Reply(reply.clone()))?;
+    let reply_text = String::from_utf8_lossy(reply_text.as_ref()).into_owned();
+    let mut words = reply_text.split_whitespace();
+    let mut session = Session::default();
+    match (words.next(), words.next(), words.next().and_then(|offset| offset.parse().ok())) {
+        (Some("FULLRESYNC"), Some(replid), Some(offset)) => {
+            let snapshot = next(&mut stream).await?;
+            let snapshot = snapshot.as_bulk_string().map_err(|_| LinkError::Reply(snapshot))?;
+
+            let flushed = router.flush().await?;
+            let mut snapshot = BytesMut::from(snapshot.as_ref());
+            let mut keys = 0;
+            while let Some(frame) = parse_frame(&mut snapshot)? {
+                apply(router, frame, &mut session).await?;
+                keys += 1;
+            }
+            // Loading the snapshot fed the backlog, the stream starts after it.
+            backlog.reset(replid.to_string(), offset);
+            info!(flushed, keys, replid, offset, "full sync with primary done");
+        }
+        (Some("CONTINUE"), replid, _) => {
+            if let Some(replid) = replid {
+                backlog.adopt_replid(replid.to_string());
+            }
+            info!(offset, "partial sync with primary done");
+        }
+        _ => return Err(LinkError::Reply(reply)),
+    }
+
+    replication.update_link(generation, |link| {
+        link.up = true;
+        link.sync_in_progress = false;
+        link.last_io = Some(Instant::now());
+    });
+
+    let mut ack_at = Instant::now() + ACK_INTERVAL;
+    loop {
+        if replication.generation() != generation {
+            return Ok(());
+        }
+
+        let frame = {
+            let timer = sleep(ack_at.saturating_duration_since(Instant::now()));
+            match select(pin!(stream.next()), pin!(timer)).await {
+                Either::Left((frame, _)) => Some(frame.ok_or(LinkError::Closed)??),
+                Either::Right(_) => None,
+            }
+        };
+
+        match frame {
+            Some(frame) => {
+                replication.update_link(generation, |link| link.last_io = Some(Instant::now()));
+                if let Ok(GCommand::ReplConf(_)) = GCommand::from_frame(&frame) {
+                    // GETACK, the only option a primary sends.
+                    ack(&mut stream, backlog.offset()).await?;
+                } else {
+                    apply(router, frame, &mut session).await?;
+                }
+            }
+            None => {
+                ack(&mut stream, backlog.offset()).await?;
+                ack_at = Instant::now() + ACK_INTERVAL;
+            }
+        }
+    }
+}
+
+async fn apply(
+    router: &StorageRouter,
+    frame: GFrame,
+    session: &mut Session,
+) -> Result<(), LinkError> {
+    if let Some((key, value)) = parse_set_frame(&frame) {
+        router.set(SetRequest { key, value, client: None }).await?;
+        return Ok(());
+    }
+
+    match GCommand::from_frame(&frame) {
+        Ok(command) => {
+            handle_gcommand(command, router, session).await;
+        }
+        Err(error) => warn!("invalid command from primary: {error}"),
+    }
+    Ok(())
+}
+
+async fn ack(stream: &mut GFrameStream<TcpStream>, offset: u64) -> Result<(), LinkError> {
+    let offset = GString::copy_from_slice(offset.to_string().as_bytes());
+    stream.send(replconf(b"ACK", offset)).await?;
+    Ok(())
+}
+
+async fn request(stream: &mut GFrameStream<TcpStream>, frame: GFrame) -> Result<GFrame, LinkError> {
+    stream.send(frame).await?;
+    match next(stream).await? {
+        error @ GFrame::SimpleError(_) => Err(LinkError::Reply(error)),
+        reply => Ok(reply),
+    }
+}
+
+async fn next(stream: &mut GFrameStream<TcpStream>) -> Result<GFrame, LinkError> {
+    Ok(stream.next().await.ok_or(LinkError::Closed)??)
+}
@@ -0,0 +1,322 @@
+use std::{
+    net::IpAddr,
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            Ordering,
+        },
+    },
+    time::Instant,
+};
+
+use futures::channel::mpsc;
+use goosekv_protocol::{
+    command::{
+        DelGCommand,
+        GCommand,
+    },
+    data_type::GString,
+    frame::GFrame,
+};
+
+use crate::{
+    event::Event,
+    replication::backlog::Backlog,
+    storage::value::{
+        Data,
+        Value,
+        instant_from_unix_ms,
+    },
+};
+
+pub mod backlog;
+pub mod link;
+pub mod sync;
+
+/// Replication state of the process, shared by every shard.
+///
+/// A primary records the writes applied by its storage actors into the [`Backlog`] streamed to
+/// its replicas, a replica applies the stream of its primary and records it the same way so that
+/// both sides agree on offsets.
+#[derive(Default)]
+pub struct Replication {
+    backlog: Backlog,
+    /// Whether a primary is set, checked by every write command.
+    replica: AtomicBool,
+    state: Mutex<State>,
+    replicas: Mutex<Vec<Arc<ReplicaInfo>>>,
+    /// Bumped to ask replicas for an acknowledgement of their offset.
+    ack_requests: AtomicU64,
+    /// Fired by every acknowledgement of a replica, waited on by `WAIT`.
+    acks: Event,
+    /// Fired once the primary changed, waited on by the replication link.
+    primary_changes: Event,
+}
+
+#[derive(Default)]
+struct State {
+    primary: Option<Primary>,
+    /// Bumped whenever the primary changes, the link to the previous one is then dropped.
+    generation: u64,
+    link: LinkStatus,
+}
+
+/// Primary this node replicates, set by `REPLICAOF host port`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Primary {
+    pub host: String,
+    pub port: u16,
+}
+
+/// State of the connection of a replica to its primary.
+#[derive(Debug, Clone, Default)]
+pub struct LinkStatus {
+    pub up: bool,
+    pub sync_in_progress: bool,
+    pub last_io: Option<Instant>,
+}
+
+/// Replica connected to this node.
+#[derive(Debug)]
+pub struct ReplicaInfo {
+    pub ip: IpAddr,
+    /// Port the replica listens on, as announced with `REPLCONF listening-port`.
+    pub port: u16,
+    /// Set once the replica received its full sync and follows the stream.
+    online: AtomicBool,
+    acked_offset: AtomicU64,
+    acked_at: Mutex<Instant>,
+}
+
+impl ReplicaInfo {
+    pub fn new(ip: IpAddr, port: u16) -> Self {
+        Self {
+            ip,
+            port,
+            online: AtomicBool::new(false),
+            acked_offset: AtomicU64::new(0),
+            acked_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn set_online(&self) {
+        self.online.store(true, Ordering::Release);
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Acquire)
+    }
+
+    pub fn ack(&self, offset: u64) {
+        self.acked_offset.store(offset, Ordering::Release);
+        *self.acked_at.lock().unwrap() = Instant::now();
+    }
+
+    pub fn acked_offset(&self) -> u64 {
+        self.acked_offset.load(Ordering::Acquire)
+    }
+
+    /// Seconds since the last acknowledgement.
+    pub fn lag(&self) -> u64 {
+        self.acked_at.lock().unwrap().elapsed().as_secs()
+    }
+}
+
+/// Name of the internal command recreating a key on a replica, unknown to the command table.
+const REPLSET: &[u8] = b"REPLSET";
+
+/// `REPLSET key value expires-at flags` recreating `value` on a replica.
+///
+/// Unlike `SET` it carries the absolute expiry in unix milliseconds, `0` for none, so that both
+/// sides agree, and the memcached flags. Only the replication link and snapshots accept it.
+pub fn set_frame(key: GString, value: &Value) -> GFrame {
+    let number = |number: u64| GString::copy_from_slice(number.to_string().as_bytes());
+    let args = [
+        GString::from_static(REPLSET),
+        key,
+        value.data.to_gstring(),
+        number(value.expires_at_unix_ms().unwrap_or(0)),
+        number(value.flags.into()),
+    ];
+    GFrame::Array(args.into_iter().map(GFrame::BulkString).collect())
+}
+
+/// Key and value recreated by a frame of [`set_frame`], `None` for any other frame.
+pub fn parse_set_frame(frame: &GFrame) -> Option<(GString, Value)> {
+    let args = frame.as_array().ok()?;
+    let [name, key, data, expires_at, flags] = args else {
+        return None;
+    };
+    if name.as_bulk_string().ok()?.as_ref() != REPLSET {
+        return None;
+    }
+
+    let number = |arg: &GFrame| -> Option<u64> {
+        std::str::from_utf8(arg.as_bulk_string().ok()?.as_ref()).ok()?.parse().ok()
+    };
+    let expires_at = number(expires_at)?;
+    let value = Value {
+        flags: number(flags)?.try_into().ok()?,
+        expires_at: (expires_at > 0).then(|| instant_from_unix_ms(expires_at)),
+        ..Value::new(Data::from_gstring(data.as_bulk_string().ok()?))
+    };
+    Some((key.as_bulk_string().ok()?, value))
+}
+
+impl Replication {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn backlog(&self) -> &Backlog {
+        &self.backlog
+    }
+
+    /// Record a write of `key` applied by a storage actor, its expiry or flags included.
+    pub fn feed_set(&self, key: &GString, value: &Value) {
+        self.backlog.feed(|| set_frame(key.clone(), value));
+    }
+
+    /// Record the removal of `key` by a storage actor.
+    pub fn feed_delete(&self, key: &GString) {
+        self.backlog.feed(|| GCommand::Del(DelGCommand { keys: [key.clone()].into() }).to_frame());
+    }
+
+    pub fn primary(&self) -> Option<Primary> {
+        self.lock().primary.clone()
+    }
+
+    pub fn is_replica(&self) -> bool {
+        self.replica.load(Ordering::Acquire)
+    }
+
+    /// Primary along with the generation identifying this configuration.
+    pub fn primary_generation(&self) -> (Option<Primary>, u64) {
+        let state = self.lock();
+        (state.primary.clone(), state.generation)
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// Replicate `primary`, or stop replicating and become a primary with `None`.
+    ///
+    /// Returns `false` if nothing changed.
+    pub fn set_primary(&self, primary: Option<Primary>) -> bool {
+        let mut state = self.lock();
+        if state.primary == primary {
+            return false;
+        }
+
+        if primary.is_none() {
+            self.backlog.shift_replid();
+        }
+        self.backlog.activate();
+        self.replica.store(primary.is_some(), Ordering::Release);
+        state.primary = primary;
+        state.generation += 1;
+        state.link = LinkStatus::default();
+        self.primary_changes.notify();
+        true
+    }
+
+    /// Wakeups sent once the primary changed.
+    pub fn listen_primary(&self) -> mpsc::Receiver<()> {
+        self.primary_changes.listen()
+    }
+
+    pub fn link(&self) -> LinkStatus {
+        self.lock().link.clone()
+    }
+
+    /// Update the link status if `generation` is still the current configuration.
+    pub fn update_link(&self, generation: u64, update: impl FnOnce(&mut LinkStatus)) {
+        let mut state = self.lock();
+        if state.generation == generation {
+            update(&mut state.link);
+        }
+    }
+
+    pub fn register(&self, replica: Arc<ReplicaInfo>) {
+        self.replicas.lock().unwrap().push(replica);
+    }
+
+    pub fn unregister(&self, replica: &Arc<ReplicaInfo>) {
+        self.replicas.lock().unwrap().retain(|registered| !Arc::ptr_eq(registered, replica));
+    }
+
+    pub fn replicas(&self) -> Vec<Arc<ReplicaInfo>> {
+        self.replicas.lock().unwrap().clone()
+    }
+
+    /// Number of online replicas that acknowledged the stream up to `offset`.
+    pub fn acked(&self, offset: u64) -> usize {
+        let replicas = self.replicas.lock().unwrap();
+        replicas
+            .iter()
+            .filter(|replica| replica.is_online() && replica.acked_offset() >= offset)
+            .count()
+    }
+
+    /// Record the offset acknowledged by `replica`.
+    pub fn ack(&self, replica: &ReplicaInfo, offset: u64) {
+        replica.ack(offset);
+        self.acks.notify();
+    }
+
+    /// Wakeups sent once a replica acknowledged its offset.
+    pub fn listen_acks(&self) -> mpsc::Receiver<()> {
+        self.acks.listen()
+    }
+
+    /// Have every replica acknowledge its offset, the tasks streaming to them are woken to ask.
+    pub fn request_acks(&self) {
+        self.ack_requests.fetch_add(1, Ordering::AcqRel);
+        self.backlog.notify();
+    }
+
+    pub fn ack_requests(&self) -> u64 {
+        self.ack_requests.load(Ordering::Acquire)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        thread::sleep,
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::storage::Storage;
+
+    #[test]
+    fn replicated_expiry_applies_on_replicas() {
+        let replication = Replication::new();
+        replication.backlog().activate();
+        let key = GString::from_static(b"key");
+        let data = Data::from_gstring(GString::from_static(b"value"));
+        let expires_at = Some(Instant::now() + Duration::from_millis(20));
+        replication.feed_set(&key, &Value { flags: 7, expires_at, ..Value::new(data) });
+
+        let mut frames = Vec::new();
+        replication.backlog().read_from(0, &mut frames).unwrap();
+        assert!(GCommand::from_frame(&frames[0]).is_err(), "clients cannot send REPLSET");
+        let (key, value) = parse_set_frame(&frames[0]).unwrap();
+        let mut replica = Storage::new();
+        replica.set(key.clone(), value);
+        assert_eq!(replica.peek(&key).map(|value| value.flags), Some(7));
+
+        sleep(Duration::from_millis(30));
+        assert!(replica.peek(&key).is_none());
+    }
+}
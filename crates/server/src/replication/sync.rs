@@ -0,0 +1,202 @@
+use std::{
+    io,
+    net::{
+        IpAddr,
+        Ipv4Addr,
+    },
+    pin::pin,
+    rc::Rc,
+    sync::Arc,
+};
+
+use bytes::BytesMut;
+use futures::{
+    SinkExt,
+    StreamExt,
+    future::select,
+    stream::{
+        SplitSink,
+        SplitStream,
+    },
+};
+use goosekv_protocol::{
+    command::{
+        GCommand,
+        ReplConfGCommand,
+    },
+    data_type::GString,
+    frame::GFrame,
+    stream::{
+        GFrameStream,
+        GFrameStreamError,
+    },
+};
+use tracing::{
+    info,
+    warn,
+};
+
+use crate::{
+    replication::{
+        ReplicaInfo,
+        Replication,
+        backlog::Backlog,
+        set_frame,
+    },
+    storage::{
+        response::StorageResult,
+        router::StorageRouter,
+    },
+    tls::Connection,
+};
+
+/// Where the stream sent to a replica starts, negotiated by `PSYNC`.
+#[derive(Debug, Clone)]
+pub struct ReplicaSync {
+    pub replid: String,
+    pub offset: u64,
+    /// The replica needs a snapshot of all keys before the stream.
+    pub full: bool,
+}
+
+impl ReplicaSync {
+    /// Resume from `offset`, the first byte the replica is missing, or fall back to a full sync.
+    pub fn negotiate(backlog: &Backlog, replid: &str, offset: i64) -> Self {
+        let (current, position) = backlog.position();
+
+        match offset.checked_sub(1).and_then(|offset| u64::try_from(offset).ok()) {
+            Some(offset) if backlog.can_continue(replid, offset) => {
+                Self { replid: current, offset, full: false }
+            }
+            _ => Self { replid: current, offset: position, full: true },
+        }
+    }
+}
+
+type Sink = SplitSink<GFrameStream<Connection>, GFrame>;
+
+/// Stream writes to the replica connected on `stream` until it disconnects.
+pub async fn serve(
+    stream: GFrameStream<Connection>,
+    sync: ReplicaSync,
+    router: Rc<StorageRouter>,
+    listening_port: Option<u16>,
+) {
+    let peer = stream.get_ref().tcp().peer_addr().ok();
+    let ip = peer.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |peer| peer.ip());
+    let port = listening_port.or(peer.map(|peer| peer.port())).unwrap_or_default();
+    let replica = Arc::new(ReplicaInfo::new(ip, port));
+
+    let replication = router.replication();
+    replication.register(replica.clone());
+    info!(%ip, port, full = sync.full, offset = sync.offset, "replica attached");
+
+    let (mut sink, mut stream) = stream.split();
+    let feed = pin!(feed(&mut sink, &sync, &router, &replica));
+    let acks = pin!(acks(&mut stream, replication, &replica));
+    if let futures::future::Either::Left((Err(error), _)) = select(feed, acks).await {
+        warn!("failed to stream to replica: {error}");
+    }
+
+    replication.unregister(&replica);
+    info!(%ip, port, "replica detached");
+}
+
+/// Encode the keys of every shard as `REPLSET` commands, with their expiry and flags.
+pub async fn snapshot(router: &StorageRouter) -> StorageResult<BytesMut> {
+    let mut payload = BytesMut::new();
+    for (key, value) in router.snapshot().await? {
+        payload.extend_from_slice(&set_frame(key, &value).bytes());
+    }
+    Ok(payload)
+}
+
+async fn feed(
+    sink: &mut Sink,
+    sync: &ReplicaSync,
+    router: &StorageRouter,
+    replica: &ReplicaInfo,
+) -> Result<(), GFrameStreamError> {
+    if sync.full {
+        let payload = snapshot(router).await.map_err(io::Error::other)?;
+        sink.send(GFrame::BulkString(GString::copy_from_slice(&payload))).await?;
+    }
+    replica.set_online();
+
+    let replication = router.replication();
+    let backlog = replication.backlog();
+    // Listen before the first read, so that no write is missed in between.
+    let mut changes = backlog.listen();
+    let mut offset = sync.offset;
+    let mut ack_requests = replication.ack_requests();
+    let mut frames = Vec::new();
+    loop {
+        if backlog.replid() != sync.replid {
+            info!("replication stream restarted, replica has to sync again");
+            return Ok(());
+        }
+
+        offset = match backlog.read_from(offset, &mut frames) {
+            Some(offset) => offset,
+            None => {
+                warn!("replica fell behind the replication backlog");
+                return Ok(());
+            }
+        };
+
+        if replication.ack_requests() != ack_requests {
+            ack_requests = replication.ack_requests();
+            frames.push(replconf(b"GETACK", GString::from_static(b"*")));
+        }
+
+        if frames.is_empty() {
+            changes.next().await;
+            continue;
+        }
+
+        for frame in frames.drain(..) {
+            sink.feed(frame).await?;
+        }
+        sink.flush().await?;
+    }
+}
+
+/// Record the offsets acknowledged by the replica with `REPLCONF ACK`.
+async fn acks(
+    stream: &mut SplitStream<GFrameStream<Connection>>,
+    replication: &Replication,
+    replica: &ReplicaInfo,
+) {
+    while let Some(Ok(frame)) = stream.next().await {
+        let Ok(GCommand::ReplConf(command)) = GCommand::from_frame(&frame) else {
+            continue;
+        };
+
+        for (name, value) in &command.options {
+            let offset = std::str::from_utf8(value.as_ref()).ok().and_then(|v| v.parse().ok());
+            if let (true, Some(offset)) = (name.eq_ignore_ascii_case(b"ACK"), offset) {
+                replication.ack(replica, offset);
+            }
+        }
+    }
+}
+
+pub(crate) fn replconf(name: &'static [u8], value: GString) -> GFrame {
+    let options = [(GString::from_static(name), value)].into();
+    GCommand::ReplConf(ReplConfGCommand { options }).to_frame()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negotiates_full_sync_for_invalid_offsets() {
+        let backlog = Backlog::new(1024);
+        let (replid, _) = backlog.position();
+
+        for offset in [i64::MIN, -1, 0] {
+            assert!(ReplicaSync::negotiate(&backlog, &replid, offset).full);
+        }
+    }
+}
@@ -1,50 +1,151 @@
-use std::net::SocketAddr;
+use std::{
+    num::NonZeroUsize,
+    pin::pin,
+    rc::Rc,
+    sync::Arc,
+    thread::available_parallelism,
+};
 
+use futures::future::select;
 use glommio::{
     ExecutorJoinHandle,
     LocalExecutorBuilder,
+    Placement,
     sync::Gate,
 };
 
 use crate::{
     acceptor::actor::AcceptorActor,
-    processor::actor::ProcessorActor,
+    acl::Acl,
+    cluster::Cluster,
+    config::SharedConfig,
+    processor::{
+        actor::ProcessorActor,
+        clients::Clients,
+    },
+    replication::{
+        self,
+        Replication,
+    },
+    shutdown::{
+        self,
+        Shutdown,
+        Snapshot,
+    },
     storage::{
         actor::StorageActor,
+        mesh::StorageMesh,
         router::StorageRouter,
     },
+    tls::Tls,
 };
 
 pub struct Shard {
+    index: usize,
     name: String,
     acceptor: AcceptorActor,
     processor: ProcessorActor,
     storage: StorageActor,
+    mesh: StorageMesh,
+    cluster: Option<Arc<Cluster>>,
+    config: Arc<SharedConfig>,
+    clients: Arc<Clients>,
+    shutdown: Arc<Shutdown>,
+    snapshot: Arc<Snapshot>,
+    acl: Arc<Acl>,
+    tls: Arc<Tls>,
 }
 
 impl Shard {
-    pub fn new(addr: SocketAddr, name: String) -> Self {
+    /// Create the shard `index` of the mesh, sharing the state of `builder` with the others.
+    pub fn new(index: usize, name: String, mesh: StorageMesh, builder: &ShardBuilder) -> Self {
+        let (acceptor, processor) = {
+            let config = builder.config.read();
+            (
+                AcceptorActor::new(
+                    config.addrs(),
+                    config.tls_addrs(),
+                    config.memcache_addrs(),
+                    config.metrics_addrs(),
+                ),
+                ProcessorActor::new(config.processor_channel_capacity),
+            )
+        };
+
         Self {
+            index,
             name,
-            acceptor: AcceptorActor::new(addr),
-            processor: ProcessorActor::new(),
-            storage: StorageActor::new(),
+            acceptor,
+            processor,
+            storage: StorageActor::new(
+                builder.replication.clone(),
+                builder.config.clone(),
+                builder.clients.trackers().clone(),
+            ),
+            mesh,
+            cluster: builder.cluster.clone(),
+            config: builder.config.clone(),
+            clients: builder.clients.clone(),
+            shutdown: builder.shutdown.clone(),
+            snapshot: builder.snapshot.clone(),
+            acl: builder.acl.clone(),
+            tls: builder.tls.clone(),
         }
     }
 
-    pub fn start(self, storage: StorageRouter) -> ExecutorJoinHandle<()> {
-        LocalExecutorBuilder::default()
+    /// Start the shard on its own executor, it joins the storage mesh before serving clients.
+    ///
+    /// The executor exits once the process stopped, see [`Shutdown`].
+    pub fn start(self) -> ExecutorJoinHandle<()> {
+        let placement = if self.config.read().cpu_pinning {
+            let cpus = available_parallelism().map_or(1, NonZeroUsize::get);
+            Placement::Fixed(self.index % cpus)
+        } else {
+            Placement::Unbound
+        };
+
+        LocalExecutorBuilder::new(placement)
             .name(&self.name)
             .spawn(async move || {
                 let gate = Gate::new();
-                let storage_task = self.storage.run();
-                let (processor_task, processor_handle) = self.processor.run(storage);
-                let acceptor_task = self.acceptor.run(processor_handle);
+                let (storage_handle, lanes) = self.mesh.join().await;
+                let (local_storage, storage_task) = self.storage.run(lanes, storage_handle.clone());
+                let first = storage_handle.shard() == 0;
+                let mut storage = StorageRouter::new(storage_handle, local_storage)
+                    .with_clients(self.clients)
+                    .with_shutdown(self.shutdown.clone())
+                    .with_acl(self.acl)
+                    .with_tls(self.tls);
+                if let Some(cluster) = self.cluster {
+                    storage = storage.with_cluster(cluster);
+                }
+                let storage = Rc::new(storage);
+                // No shard serves clients before every key of the snapshot is loaded.
+                self.snapshot.restore(&storage).await;
+                drop(self.snapshot);
+                let (stopping, watch_task) = shutdown::watch(storage.clone());
+                gate.spawn(watch_task).unwrap().detach();
+                // The link to a primary is kept by the first shard only.
+                if first {
+                    let port = self.config.read().port;
+                    let link_task =
+                        stopping.clone().until(replication::link::run(storage.clone(), port));
+                    gate.spawn(link_task).unwrap().detach();
+                }
+                let (processor_task, processor_handle) =
+                    self.processor.run(storage.clone(), stopping.clone());
+                let acceptor_task = self.acceptor.run(processor_handle, stopping);
+                // Other shards keep sending to this storage until they drained their clients.
+                let shutdown = self.shutdown.clone();
+                let storage_task = async move {
+                    select(pin!(storage_task), pin!(shutdown.finished())).await;
+                };
 
                 gate.spawn(storage_task).unwrap().detach();
-                gate.spawn(processor_task).unwrap().detach();
                 gate.spawn(acceptor_task).unwrap().detach();
 
+                processor_task.await;
+                self.shutdown.drained(&storage).await;
                 gate.close().await.unwrap()
             })
             .unwrap()
@@ -52,16 +153,65 @@ impl Shard {
 }
 
 pub struct ShardBuilder {
-    addr: SocketAddr,
+    config: Arc<SharedConfig>,
+    cluster: Option<Arc<Cluster>>,
+    replication: Arc<Replication>,
+    clients: Arc<Clients>,
+    shutdown: Arc<Shutdown>,
+    snapshot: Arc<Snapshot>,
+    acl: Arc<Acl>,
+    tls: Arc<Tls>,
 }
 
 impl ShardBuilder {
-    pub fn new(addr: SocketAddr) -> Self {
-        Self { addr }
+    /// Build shards serving with the settings of `config`.
+    pub fn new(config: Arc<SharedConfig>) -> Self {
+        let replication = Replication::new();
+        replication.backlog().set_capacity(config.read().repl_backlog_size.0 as usize);
+
+        Self {
+            config,
+            cluster: None,
+            replication: Arc::new(replication),
+            clients: Default::default(),
+            shutdown: Default::default(),
+            snapshot: Default::default(),
+            acl: Default::default(),
+            tls: Default::default(),
+        }
+    }
+
+    /// Shutdown state of the shards built, to handle signals and get the exit status from.
+    pub fn shutdown(&self) -> &Arc<Shutdown> {
+        &self.shutdown
     }
 
-    pub fn build(&self, name: String) -> Shard {
-        Shard::new(self.addr, name)
+    /// Run as one node of `cluster`, serving only the slots it owns.
+    pub fn cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(Arc::new(cluster));
+        self
+    }
+
+    /// Load the keys of `snapshot` before serving.
+    pub fn snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.snapshot = Arc::new(snapshot);
+        self
+    }
+
+    /// Authenticate connections against the users of `acl`.
+    pub fn acl(mut self, acl: Acl) -> Self {
+        self.acl = Arc::new(acl);
+        self
+    }
+
+    /// Accept TLS connections with the certificates of `tls`.
+    pub fn tls(mut self, tls: Tls) -> Self {
+        self.tls = Arc::new(tls);
+        self
+    }
+
+    pub fn build(&self, index: usize, name: String, mesh: StorageMesh) -> Shard {
+        Shard::new(index, name, mesh, self)
     }
 }
 
@@ -71,22 +221,14 @@ pub struct Shards {
 
 impl Shards {
     pub fn from_builder(builder: ShardBuilder, count: usize, name: String) -> Shards {
-        let shards = (0..count).map(|_| builder.build(name.clone())).collect();
+        let mesh = StorageMesh::new(count, builder.config.read().storage_channel_capacity);
+        let shards =
+            (0..count).map(|index| builder.build(index, name.clone(), mesh.clone())).collect();
         Shards { inner: shards }
     }
 
     pub fn start(self) -> ShardsHandle {
-        let storage_handles =
-            self.inner.iter().map(|shard| shard.storage.handle()).collect::<Box<[_]>>();
-
-        let handles = self
-            .inner
-            .into_iter()
-            .map(|shard| {
-                let router = StorageRouter::new(storage_handles.iter().cloned().collect());
-                shard.start(router)
-            })
-            .collect();
+        let handles = self.inner.into_iter().map(Shard::start).collect();
 
         ShardsHandle { inner: handles }
     }
@@ -97,6 +239,7 @@ pub struct ShardsHandle {
 }
 
 impl ShardsHandle {
+    /// Wait for every shard to stop.
     pub fn join_all(self) {
         self.inner.into_iter().for_each(|handle| {
             handle.join().unwrap();
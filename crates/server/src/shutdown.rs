@@ -0,0 +1,575 @@
+use std::{
+    fs,
+    io::{
+        self,
+        ErrorKind,
+    },
+    path::Path,
+    pin::pin,
+    rc::Rc,
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        atomic::{
+            AtomicBool,
+            AtomicUsize,
+            Ordering,
+        },
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use bytes::BytesMut;
+use futures::{
+    FutureExt,
+    StreamExt,
+    channel::oneshot,
+    future::{
+        Either,
+        Shared,
+        select,
+    },
+    stream,
+};
+use glommio::{
+    spawn_local,
+    timer::sleep,
+};
+use goosekv_protocol::{
+    data_type::GString,
+    parser::parse_frame,
+};
+use signal_hook::{
+    consts::TERM_SIGNALS,
+    flag,
+};
+use thiserror::Error;
+use tracing::{
+    error,
+    info,
+    warn,
+};
+
+use crate::{
+    config::write_atomically,
+    event::Event,
+    latency::LatencyEvent,
+    processor::clients::idle_timeout,
+    replication::{
+        parse_set_frame,
+        sync,
+    },
+    storage::{
+        request::SetRequest,
+        router::StorageRouter,
+        value::Value,
+    },
+};
+
+/// Delay between two checks for a termination signal, the signal handler can only set a flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Once the process stops, a client that sends nothing for this long has no pipeline left.
+const DRAIN_QUIET: Duration = Duration::from_millis(50);
+
+/// How a shutdown is requested, by `SHUTDOWN` or by a signal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShutdownMode {
+    /// Write a snapshot, by default only if save points are configured.
+    pub save: Option<bool>,
+    /// Skip waiting for lagging replicas.
+    pub now: bool,
+    /// Exit successfully even if the snapshot could not be written.
+    pub force: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum ShutdownError {
+    #[error("shutdown aborted")]
+    Aborted,
+}
+
+/// Shutdown state of the process, shared by every shard.
+///
+/// A shutdown first waits for replicas to catch up, until then it can be aborted. The process
+/// then stops: every shard stops accepting connections and drains the pipelines of its clients,
+/// the first shard writes the snapshot once all of them are done and the storages stop last.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    state: Mutex<State>,
+    /// Set by `SIGTERM` and `SIGINT`, picked up by the first shard.
+    signaled: Arc<AtomicBool>,
+    /// Number of shards done serving their clients.
+    drained: AtomicUsize,
+    /// Set once the snapshot is written, the storages then stop.
+    finished: AtomicBool,
+    /// Set if the snapshot could not be written, the process then exits with a failure.
+    failed: AtomicBool,
+    /// Fired whenever the process stops, a shard drains, the snapshot is written or an abort.
+    changes: Event,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Shutdowns waiting for replicas.
+    waiting: usize,
+    /// Bumped by `SHUTDOWN ABORT`, cancelling the shutdowns waiting at that time.
+    aborts: u64,
+    /// Set once the process stops, along with the time clients have to drain their pipelines.
+    stop: Option<(ShutdownMode, Instant)>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shut down on `SIGTERM` and `SIGINT`, a second signal exits immediately.
+    pub fn handle_signals(&self) -> io::Result<()> {
+        for signal in TERM_SIGNALS {
+            flag::register_conditional_shutdown(*signal, 1, self.signaled.clone())?;
+            flag::register(*signal, self.signaled.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Cancel the shutdowns waiting for replicas, `false` if there is none.
+    pub fn abort(&self) -> bool {
+        let mut state = self.lock();
+        if state.waiting == 0 || state.stop.is_some() {
+            return false;
+        }
+        state.aborts += 1;
+        self.changes.notify();
+        true
+    }
+
+    /// Exit status of the process once every shard stopped.
+    pub fn exit_code(&self) -> u8 {
+        self.failed.load(Ordering::Acquire) as u8
+    }
+
+    /// Run a shutdown requested with `mode`, returns once every shard was told to stop.
+    pub async fn run(
+        &self,
+        router: &StorageRouter,
+        mode: ShutdownMode,
+    ) -> Result<(), ShutdownError> {
+        let aborts = {
+            let mut state = self.lock();
+            state.waiting += 1;
+            state.aborts
+        };
+        let caught_up = match mode.now {
+            true => Ok(()),
+            false => self.wait_replicas(router, aborts).await,
+        };
+
+        let mut state = self.lock();
+        state.waiting -= 1;
+        caught_up?;
+        if state.stop.is_none() {
+            let timeout = Duration::from_secs(router.config().read().shutdown_timeout);
+            info!(?mode, "shutting down");
+            state.stop = Some((mode, Instant::now() + timeout));
+            self.changes.notify();
+        }
+        Ok(())
+    }
+
+    /// Wait for the online replicas to acknowledge every write, up to `shutdown-timeout`.
+    async fn wait_replicas(
+        &self,
+        router: &StorageRouter,
+        aborts: u64,
+    ) -> Result<(), ShutdownError> {
+        let replication = router.replication();
+        let offset = replication.backlog().offset();
+        let online = replication.replicas().iter().filter(|replica| replica.is_online()).count();
+        let timeout = Duration::from_secs(router.config().read().shutdown_timeout);
+        let deadline = Instant::now() + timeout;
+
+        // Listen before checking, so that no ack or abort is missed in between.
+        let mut wakeups = stream::select(replication.listen_acks(), self.changes.listen());
+        if replication.acked(offset) < online {
+            info!(online, "waiting for replicas before shutting down");
+            replication.request_acks();
+        }
+        while replication.acked(offset) < online && Instant::now() < deadline {
+            if self.lock().aborts != aborts {
+                return Err(ShutdownError::Aborted);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            select(wakeups.next(), pin!(sleep(remaining))).await;
+        }
+
+        match self.lock().aborts != aborts {
+            true => Err(ShutdownError::Aborted),
+            false => Ok(()),
+        }
+    }
+
+    /// Resolves once the snapshot is written, the storages can then stop.
+    pub async fn finished(&self) {
+        self.until(|shutdown| shutdown.finished.load(Ordering::Acquire)).await;
+    }
+
+    /// Record that the current shard drained its clients.
+    ///
+    /// The first shard then waits for the others and writes the snapshot.
+    pub async fn drained(&self, router: &StorageRouter) {
+        self.drained.fetch_add(1, Ordering::AcqRel);
+        self.changes.notify();
+        if router.shard() != 0 {
+            return;
+        }
+
+        let shards = router.shard_count();
+        self.until(|shutdown| shutdown.drained.load(Ordering::Acquire) >= shards).await;
+        let mode = self.lock().stop.map(|(mode, _)| mode).unwrap_or_default();
+        let config = router.config().read().clone();
+        if mode.save.unwrap_or(!config.save.is_empty()) {
+            let path = Path::new(&config.dir).join(&config.dbfilename);
+            match save(router, &path).await {
+                Ok(()) => info!(path = %path.display(), "snapshot saved"),
+                Err(error) if mode.force => warn!("failed to save the snapshot: {error}"),
+                Err(error) => {
+                    error!("failed to save the snapshot: {error}");
+                    self.failed.store(true, Ordering::Release);
+                }
+            }
+        }
+        self.finished.store(true, Ordering::Release);
+        self.changes.notify();
+    }
+
+    /// Resolves once `done` holds, checked again whenever the state changes.
+    async fn until(&self, done: impl Fn(&Self) -> bool) {
+        let mut changes = self.changes.listen();
+        while !done(self) {
+            changes.next().await;
+        }
+    }
+
+    /// Time clients have to drain their pipelines, once the process stops.
+    async fn stopped(&self) -> Instant {
+        self.until(|shutdown| shutdown.lock().stop.is_some()).await;
+        self.lock().stop.map(|(_, deadline)| deadline).expect("the process stopped")
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Watch for the process to stop from the current shard.
+///
+/// Returns the [`Stopping`] handed to the tasks serving clients, and the task telling them. On
+/// the first shard, the task also starts the shutdowns requested by signals.
+pub fn watch(router: Rc<StorageRouter>) -> (Stopping, impl Future<Output = ()>) {
+    let (sender, receiver) = oneshot::channel();
+    let stopping = Stopping { deadline: receiver.shared() };
+    let task = async move {
+        if router.shard() == 0 {
+            spawn_local(signals(router.clone())).detach();
+        }
+        let deadline = router.shutdown().stopped().await;
+        let _ = sender.send(deadline);
+    };
+    (stopping, task)
+}
+
+/// Start a shutdown once a termination signal is received, unless the process stops first.
+async fn signals(router: Rc<StorageRouter>) {
+    let shutdown = router.shutdown();
+    while !shutdown.signaled.load(Ordering::Acquire) {
+        if shutdown.lock().stop.is_some() {
+            return;
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+
+    info!("received a termination signal");
+    if let Err(error) = shutdown.run(&router, ShutdownMode::default()).await {
+        warn!("{error}");
+    }
+}
+
+/// Told to the tasks of one shard once the process stops.
+#[derive(Debug, Clone)]
+pub struct Stopping {
+    /// Resolves to the time clients have to drain their pipelines.
+    deadline: Shared<oneshot::Receiver<Instant>>,
+}
+
+impl Stopping {
+    pub fn is_stopping(&self) -> bool {
+        self.deadline.peek().is_some()
+    }
+
+    /// Resolves once the process stops.
+    pub async fn wait(&self) {
+        let _ = self.deadline.clone().await;
+    }
+
+    /// Run `future` until the process stops.
+    pub async fn until(self, future: impl Future<Output = ()>) {
+        select(pin!(future), pin!(self.wait())).await;
+    }
+
+    /// Resolves once the process stopped and the time to drain clients elapsed.
+    pub async fn deadline(&self) {
+        if let Ok(deadline) = self.deadline.clone().await {
+            sleep(deadline.saturating_duration_since(Instant::now())).await;
+        }
+    }
+
+    /// Wait for the next request of a client, `None` once it stays idle for `timeout` seconds.
+    ///
+    /// Once the process stops, only the requests the client already sent are waited for.
+    pub async fn next<F: Future>(&self, timeout: u64, future: F) -> Option<F::Output> {
+        let mut future = pin!(future);
+        if !self.is_stopping() {
+            let next = pin!(idle_timeout(timeout, future.as_mut()));
+            if let Either::Left((output, _)) = select(next, pin!(self.wait())).await {
+                return output;
+            }
+        }
+
+        match select(future, pin!(sleep(DRAIN_QUIET))).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+/// Write the keys of every shard to `path`, as the commands a full sync sends to replicas.
+pub async fn save(router: &StorageRouter, path: &Path) -> io::Result<()> {
+    let snapshot = sync::snapshot(router).await.map_err(io::Error::other)?;
+    let started = Instant::now();
+    write_atomically(path, snapshot)?;
+    router.latency().record(LatencyEvent::Fsync, started.elapsed());
+    Ok(())
+}
+
+/// Keys of the snapshot written on the last shutdown, loaded by every shard before serving.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    entries: Vec<(GString, Value)>,
+    /// Number of shards done loading their keys.
+    loaded: AtomicUsize,
+    /// Fired whenever a shard is done loading its keys.
+    loads: Event,
+}
+
+impl Snapshot {
+    /// Read the snapshot [`save`] wrote to `path`, empty if there is none.
+    ///
+    /// Keys that expired in the meantime are skipped.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut buffer = match fs::read(path) {
+            Ok(contents) => BytesMut::from(contents.as_slice()),
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+
+        let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+        let mut entries = Vec::new();
+        while let Some(frame) =
+            parse_frame(&mut buffer).map_err(|error| invalid(error.to_string()))?
+        {
+            let Some((key, value)) = parse_set_frame(&frame) else {
+                return Err(invalid(format!("expected a REPLSET command, got {frame:?}")));
+            };
+            if !value.is_expired() {
+                entries.push((key, value));
+            }
+        }
+        if !buffer.is_empty() {
+            return Err(invalid("truncated command at the end".into()));
+        }
+        Ok(Self { entries, ..Default::default() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Store the keys owned by the current shard, resolves once every shard stored its keys.
+    pub async fn restore(&self, router: &StorageRouter) {
+        for (key, value) in &self.entries {
+            if router.slots().key_shard(key.as_ref()) == router.shard() {
+                let request = SetRequest { key: key.clone(), value: value.clone(), client: None };
+                if let Err(error) = router.set(request).await {
+                    warn!("failed to restore the snapshot: {error}");
+                    break;
+                }
+            }
+        }
+
+        let mut loads = self.loads.listen();
+        self.loaded.fetch_add(1, Ordering::AcqRel);
+        self.loads.notify();
+        while self.loaded.load(Ordering::Acquire) < router.shard_count() {
+            loads.next().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env,
+        future::{
+            pending,
+            ready,
+        },
+        process,
+    };
+
+    use glommio::LocalExecutor;
+
+    use super::*;
+    use crate::{
+        config::{
+            Config,
+            SharedConfig,
+        },
+        replication::{
+            ReplicaInfo,
+            Replication,
+        },
+        storage::{
+            actor::StorageActor,
+            mesh::StorageMesh,
+            request::GetRequest,
+            value::Data,
+        },
+    };
+
+    /// Run `test` on a single shard with a replica that never acknowledges the writes.
+    fn run<F: Future<Output = ()>>(config: Config, test: impl FnOnce(Rc<StorageRouter>) -> F) {
+        LocalExecutor::default().run(async {
+            let replication = Arc::new(Replication::new());
+            replication.backlog().activate();
+            let replica = Arc::new(ReplicaInfo::new([127, 0, 0, 1].into(), 6380));
+            replica.set_online();
+            replication.register(replica);
+            let config = Arc::new(SharedConfig::new(config, None));
+            let (handle, lanes) = StorageMesh::new(1, 4).join().await;
+            let (local, task) = StorageActor::new(replication, config.clone(), Default::default())
+                .run(lanes, handle.clone());
+            spawn_local(task).detach();
+            let router = StorageRouter::new(handle, local).with_shutdown(Default::default());
+            test(Rc::new(router)).await;
+        });
+    }
+
+    async fn set(router: &StorageRouter, key: &'static [u8], value: Value) {
+        router
+            .set(SetRequest { key: GString::from_static(key), value, client: None })
+            .await
+            .unwrap();
+    }
+
+    fn value(data: &'static [u8]) -> Value {
+        Value::new(Data::from_gstring(GString::from_static(data)))
+    }
+
+    #[test]
+    fn aborts_while_waiting_for_replicas() {
+        let config = Config { shutdown_timeout: 10, ..Default::default() };
+        run(config, async |router| {
+            set(&router, b"key", value(b"value")).await;
+            let shutdown = router.shutdown().clone();
+            assert!(!shutdown.abort());
+
+            let waiting = spawn_local({
+                let router = router.clone();
+                async move { router.shutdown().run(&router, ShutdownMode::default()).await }
+            });
+            sleep(Duration::from_millis(20)).await;
+            assert!(shutdown.abort());
+            assert!(matches!(waiting.await, Err(ShutdownError::Aborted)));
+
+            let (stopping, task) = watch(router.clone());
+            spawn_local(task).detach();
+            sleep(Duration::from_millis(20)).await;
+            assert!(!stopping.is_stopping());
+        });
+    }
+
+    #[test]
+    fn drains_clients_until_the_deadline() {
+        let config = Config { shutdown_timeout: 1, ..Default::default() };
+        run(config, async |router| {
+            let (stopping, task) = watch(router.clone());
+            spawn_local(task).detach();
+            let mode = ShutdownMode { now: true, ..Default::default() };
+            let started = Instant::now();
+            router.shutdown().run(&router, mode).await.unwrap();
+            assert!(!router.shutdown().abort());
+
+            stopping.wait().await;
+            assert!(stopping.is_stopping());
+            assert_eq!(stopping.next(10, ready(1)).await, Some(1));
+            assert_eq!(stopping.next(10, pending::<()>()).await, None);
+            stopping.deadline().await;
+            assert!(started.elapsed() >= Duration::from_millis(900));
+        });
+    }
+
+    #[test]
+    fn stops_once_replicas_time_out() {
+        let config = Config { shutdown_timeout: 0, ..Default::default() };
+        run(config, async |router| {
+            set(&router, b"key", value(b"value")).await;
+            router.shutdown().run(&router, ShutdownMode::default()).await.unwrap();
+            assert!(router.shutdown().lock().stop.is_some());
+        });
+    }
+
+    #[test]
+    fn saves_and_loads_snapshot() {
+        let dir = env::temp_dir().join(format!("goosekv-shutdown-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = Config { dir: dir.display().to_string(), ..Default::default() };
+        let path = dir.join(&config.dbfilename);
+        run(config, async |router| {
+            let expires_at = Some(Instant::now() + Duration::from_secs(60));
+            set(&router, b"kept", Value { flags: 3, expires_at, ..value(b"1") }).await;
+            let expires_at = Some(Instant::now() + Duration::from_millis(10));
+            set(&router, b"expiring", Value { expires_at, ..value(b"value") }).await;
+
+            let mode = ShutdownMode { save: Some(true), now: true, force: false };
+            router.shutdown().run(&router, mode).await.unwrap();
+            router.shutdown().drained(&router).await;
+            router.shutdown().finished().await;
+            assert_eq!(router.shutdown().exit_code(), 0);
+
+            sleep(Duration::from_millis(20)).await;
+            let snapshot = Snapshot::load(&path).unwrap();
+            assert_eq!(snapshot.len(), 1);
+            router.flush().await.unwrap();
+            snapshot.restore(&router).await;
+            let key = GString::from_static(b"kept");
+            let response =
+                router.get(GetRequest { key, touch: false, tracking: None }).await.unwrap();
+            let value = response.value.unwrap();
+            assert_eq!((value.data.to_gstring(), value.flags), (GString::from_static(b"1"), 3));
+            assert!(value.expires_at.is_some());
+        });
+
+        fs::write(&path, b"*1\r\n$4\r\nPING\r\n").unwrap();
+        assert!(Snapshot::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(Snapshot::load(&path).unwrap().is_empty());
+    }
+}
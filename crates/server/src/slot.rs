@@ -0,0 +1,129 @@
+use std::sync::atomic::{
+    AtomicBool,
+    AtomicU16,
+    AtomicUsize,
+    Ordering,
+};
+
+/// Number of hash slots the keyspace is divided into, same as Redis Cluster.
+pub const SLOT_COUNT: usize = 16384;
+
+const CRC16_TABLE: [u16; 256] = crc16_table();
+
+/// CRC16-CCITT (XMODEM) as used by Redis Cluster for key hashing.
+pub fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &byte| (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize])
+}
+
+/// Hash slot of `key`, only the part inside the first non-empty `{...}` is hashed if present.
+///
+/// Keys sharing such a hash tag always map to the same slot.
+pub fn key_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key).unwrap_or(key)) % SLOT_COUNT as u16
+}
+
+fn hash_tag(key: &[u8]) -> Option<&[u8]> {
+    let start = key.iter().position(|&c| c == b'{')? + 1;
+    let len = key[start..].iter().position(|&c| c == b'}')?;
+    (len > 0).then(|| &key[start..start + len])
+}
+
+/// Owner shard of every hash slot, shared by all shards and updated while resharding.
+#[derive(Debug)]
+pub struct SlotTable {
+    shards: Box<[AtomicU16]>,
+    active: AtomicUsize,
+    resharding: AtomicBool,
+}
+
+impl SlotTable {
+    /// Split the slots into contiguous ranges of nearly equal size, one per shard.
+    pub fn new(shard_count: usize) -> Self {
+        assert!(shard_count > 0 && shard_count <= SLOT_COUNT, "invalid shard count");
+        let shards = (0..SLOT_COUNT as u16)
+            .map(|slot| AtomicU16::new(Self::owner(slot, shard_count) as u16))
+            .collect();
+        Self { shards, active: AtomicUsize::new(shard_count), resharding: AtomicBool::new(false) }
+    }
+
+    /// Owner of `slot` when the keyspace is evenly split between `shard_count` shards.
+    pub fn owner(slot: u16, shard_count: usize) -> usize {
+        slot as usize * shard_count / SLOT_COUNT
+    }
+
+    pub fn shard(&self, slot: u16) -> usize {
+        self.shards[slot as usize].load(Ordering::Acquire) as usize
+    }
+
+    pub fn key_shard(&self, key: &[u8]) -> usize {
+        self.shard(key_slot(key))
+    }
+
+    /// Number of shards owning slots.
+    pub fn active_shards(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn assign(&self, slot: u16, shard: usize) {
+        self.shards[slot as usize].store(shard as u16, Ordering::Release);
+    }
+
+    /// Mark a reshard as started, returns `false` if another one is still running.
+    pub(crate) fn begin_reshard(&self) -> bool {
+        !self.resharding.swap(true, Ordering::AcqRel)
+    }
+
+    pub(crate) fn end_reshard(&self, active: usize) {
+        self.active.store(active, Ordering::Release);
+        self.resharding.store(false, Ordering::Release);
+    }
+}
+
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"bar"), 5061);
+    }
+
+    #[test]
+    fn hash_tags() {
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"{user1000}.followers"));
+        assert_eq!(key_slot(b"foo{bar}{zap}"), key_slot(b"bar"));
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+        assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % SLOT_COUNT as u16);
+    }
+
+    #[test]
+    fn slot_table() {
+        let table = SlotTable::new(3);
+        assert_eq!(table.shard(0), 0);
+        assert_eq!(table.shard(5461), 0);
+        assert_eq!(table.shard(5462), 1);
+        assert_eq!(table.shard(SLOT_COUNT as u16 - 1), 2);
+        assert_eq!(table.key_shard(b"{a}x"), table.key_shard(b"{a}y"));
+
+        table.assign(0, 2);
+        assert_eq!(table.shard(0), 2);
+    }
+}
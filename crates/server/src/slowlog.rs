@@ -0,0 +1,236 @@
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    collections::VecDeque,
+    rc::Rc,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use goosekv_protocol::{
+    command::table::CommandSpec,
+    data_type::GString,
+};
+
+use crate::config::LocalConfig;
+
+/// Arguments kept per entry, the last one kept tells how many were left out.
+const MAX_ARGS: usize = 32;
+/// Bytes kept per argument, the rest is replaced by how many bytes were left out.
+const MAX_ARG_LEN: usize = 128;
+/// Replaces arguments carrying a secret, such as a password.
+const REDACTED: &[u8] = b"(redacted)";
+/// Parameters whose value `CONFIG SET` must not log.
+const SECRET_PARAMETERS: &[&str] = &["masterauth"];
+
+/// Command that ran for longer than `slowlog-log-slower-than`.
+#[derive(Debug, Clone)]
+pub struct SlowLogEntry {
+    /// Unique across shards, increasing with the entries of each shard.
+    pub id: u64,
+    pub logged_at: SystemTime,
+    pub duration: Duration,
+    /// Arguments of the command, name included, truncated.
+    pub args: Vec<GString>,
+    /// Address of the client, empty if unknown.
+    pub addr: String,
+    /// Name of the client, empty if it has none.
+    pub name: GString,
+}
+
+/// Slow commands executed by one shard, newest first, up to `slowlog-max-len` of them.
+#[derive(Debug)]
+pub struct SlowLog {
+    config: Rc<LocalConfig>,
+    shard: usize,
+    shard_count: usize,
+    entries: RefCell<VecDeque<SlowLogEntry>>,
+    /// Entries logged by this shard so far, making up their ids.
+    logged: Cell<u64>,
+}
+
+impl SlowLog {
+    pub fn new(config: Rc<LocalConfig>, shard: usize, shard_count: usize) -> Self {
+        Self { config, shard, shard_count, entries: Default::default(), logged: Cell::new(0) }
+    }
+
+    /// Log a command that ran for `duration` if it is slower than `slowlog-log-slower-than`.
+    ///
+    /// `command` returns the arguments of the command along with the address and name of the
+    /// client, it is only called for commands that are logged.
+    pub fn record(
+        &self,
+        duration: Duration,
+        command: impl FnOnce() -> (Vec<GString>, String, GString),
+    ) {
+        let (slower_than, max_len) = {
+            let config = self.config.read();
+            (config.slowlog_log_slower_than, config.slowlog_max_len)
+        };
+        if slower_than < 0 || (duration.as_micros() as i64) < slower_than {
+            return;
+        }
+
+        let logged = self.logged.get();
+        self.logged.set(logged + 1);
+        let (args, addr, name) = command();
+        let entry = SlowLogEntry {
+            id: logged * self.shard_count as u64 + self.shard as u64,
+            logged_at: SystemTime::now(),
+            duration,
+            args: truncate(args),
+            addr,
+            name,
+        };
+
+        let mut entries = self.entries.borrow_mut();
+        entries.push_front(entry);
+        entries.truncate(max_len);
+    }
+
+    /// Newest `count` entries.
+    pub fn get(&self, count: usize) -> Vec<SlowLogEntry> {
+        self.entries.borrow().iter().take(count).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    pub fn reset(&self) {
+        self.entries.borrow_mut().clear();
+    }
+}
+
+/// Replace the passwords in the arguments of `spec` before they are logged, like Redis.
+pub fn redact(spec: &CommandSpec, mut args: Vec<GString>) -> Vec<GString> {
+    let redacted: Vec<usize> = match (spec.container, spec.name) {
+        (None, "AUTH") => (1..args.len()).collect(),
+        (None, "HELLO") => match args.iter().position(|arg| arg.eq_ignore_ascii_case(b"AUTH")) {
+            Some(auth) => vec![auth + 1, auth + 2],
+            None => Vec::new(),
+        },
+        (Some("ACL"), "SETUSER") => (3..args.len()).collect(),
+        (Some("CONFIG"), "SET") => (2..args.len())
+            .step_by(2)
+            .filter(|&index| {
+                let name = String::from_utf8_lossy(args[index].as_ref());
+                SECRET_PARAMETERS.iter().any(|secret| secret.eq_ignore_ascii_case(&name))
+            })
+            .map(|index| index + 1)
+            .collect(),
+        _ => Vec::new(),
+    };
+    for index in redacted {
+        if let Some(arg) = args.get_mut(index) {
+            *arg = GString::from_static(REDACTED);
+        }
+    }
+    args
+}
+
+fn truncate(mut args: Vec<GString>) -> Vec<GString> {
+    if args.len() > MAX_ARGS {
+        let more = args.len() - MAX_ARGS + 1;
+        args.truncate(MAX_ARGS - 1);
+        args.push(GString::copy_from_slice(format!("... ({more} more arguments)").as_bytes()));
+    }
+
+    args.into_iter()
+        .map(|arg| match arg.as_ref().len() {
+            len if len > MAX_ARG_LEN => {
+                let mut truncated = arg.as_ref()[..MAX_ARG_LEN].to_vec();
+                truncated.extend_from_slice(
+                    format!("... ({} more bytes)", len - MAX_ARG_LEN).as_bytes(),
+                );
+                GString::copy_from_slice(&truncated)
+            }
+            _ => arg,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::{
+        Config,
+        SharedConfig,
+    };
+
+    fn args(count: usize) -> Vec<GString> {
+        (0..count).map(|index| GString::copy_from_slice(index.to_string().as_bytes())).collect()
+    }
+
+    #[test]
+    fn logs_slow_commands() {
+        let config =
+            Config { slowlog_log_slower_than: 100, slowlog_max_len: 2, ..Default::default() };
+        let config = Rc::new(LocalConfig::new(Arc::new(SharedConfig::new(config, None))));
+        let slowlog = SlowLog::new(config, 1, 3);
+        let command = || (args(1), String::new(), GString::from_static(b""));
+
+        slowlog.record(Duration::from_micros(99), command);
+        assert!(slowlog.is_empty());
+
+        for _ in 0..3 {
+            slowlog.record(Duration::from_micros(100), command);
+        }
+        let entries = slowlog.get(10);
+        assert_eq!(slowlog.len(), 2);
+        assert_eq!(entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![7, 4]);
+
+        slowlog.reset();
+        assert!(slowlog.is_empty());
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let redact = |args: &[&str]| {
+            let args: Vec<_> =
+                args.iter().map(|arg| GString::copy_from_slice(arg.as_bytes())).collect();
+            let spec = CommandSpec::resolve(&args).unwrap();
+            redact(spec, args)
+                .iter()
+                .map(|arg| String::from_utf8_lossy(arg.as_ref()).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(redact(&["AUTH", "alice", "pw"]), ["AUTH", "(redacted)", "(redacted)"]);
+        assert_eq!(
+            redact(&["HELLO", "3", "auth", "alice", "pw", "SETNAME", "c"]),
+            ["HELLO", "3", "auth", "(redacted)", "(redacted)", "SETNAME", "c"]
+        );
+        assert_eq!(
+            redact(&["ACL", "SETUSER", "alice", "on", ">pw"]),
+            ["ACL", "SETUSER", "alice", "(redacted)", "(redacted)"]
+        );
+        assert_eq!(
+            redact(&["CONFIG", "SET", "maxclients", "10", "MASTERAUTH", "pw"]),
+            ["CONFIG", "SET", "maxclients", "10", "MASTERAUTH", "(redacted)"]
+        );
+        assert_eq!(redact(&["SET", "k", "v"]), ["SET", "k", "v"]);
+    }
+
+    #[test]
+    fn truncates_arguments() {
+        let args = truncate(args(40));
+        assert_eq!(args.len(), MAX_ARGS);
+        assert_eq!(args[MAX_ARGS - 1].as_ref(), b"... (9 more arguments)");
+
+        let long = GString::copy_from_slice(&[b'a'; MAX_ARG_LEN + 5]);
+        let args = truncate(vec![long]);
+        assert!(args[0].as_ref().ends_with(b"a... (5 more bytes)"));
+        assert_eq!(args[0].as_ref().len(), MAX_ARG_LEN + b"... (5 more bytes)".len());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+        RefMut,
+    },
+    collections::BTreeMap,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use goosekv_protocol::{
+    command::table::CommandSpec,
+    frame::GFrame,
+};
+
+use crate::config::SharedConfig;
+
+/// Upper bounds in microseconds of the buckets of command latencies, powers of two from 1µs to
+/// about 16s as `LATENCY HISTOGRAM` reports them.
+pub const LATENCY_BUCKETS: [u64; 25] = {
+    let mut buckets = [0; 25];
+    let mut index = 0;
+    while index < buckets.len() {
+        buckets[index] = 1 << index;
+        index += 1;
+    }
+    buckets
+};
+
+/// Statistics of one shard, updated without synchronization by the tasks of its executor.
+///
+/// `INFO` collects them from every shard. Counters are reset once `CONFIG RESETSTAT` bumps the
+/// epoch of the configuration.
+#[derive(Debug)]
+pub struct ShardStats {
+    config: Arc<SharedConfig>,
+    started: Instant,
+    /// Connections currently served by this shard.
+    connected: Cell<usize>,
+    /// `CONFIG RESETSTAT` epoch the counters started at.
+    epoch: Cell<u64>,
+    counters: RefCell<Counters>,
+}
+
+/// Counters of one shard, or summed over several of them.
+#[derive(Debug, Default, Clone)]
+pub struct Counters {
+    pub connections_received: u64,
+    /// Connections refused because of `maxclients`.
+    pub rejected_connections: u64,
+    pub commands_processed: u64,
+    pub net_input_bytes: u64,
+    pub net_output_bytes: u64,
+    pub keyspace_hits: u64,
+    pub keyspace_misses: u64,
+    /// Keys removed once read after they expired.
+    pub expired_keys: u64,
+    /// Requests that could not be parsed, from either protocol.
+    pub protocol_errors: u64,
+    /// Storage requests served by the storage of the shard versus sent to another shard.
+    pub local_requests: u64,
+    pub remote_requests: u64,
+    /// Authentications failed, and commands denied by the ACL rules of their user.
+    pub acl_access_denied_auth: u64,
+    pub acl_access_denied_cmd: u64,
+    pub acl_access_denied_key: u64,
+    /// Calls by command, keyed by container and name.
+    pub commands: BTreeMap<(Option<&'static str>, &'static str), CommandStats>,
+    /// Error replies by code, such as `WRONGTYPE`.
+    pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CommandStats {
+    pub calls: u64,
+    pub usec: u64,
+    /// Calls refused before being executed, with `OOM` or `MOVED` for instance.
+    pub rejected_calls: u64,
+    /// Calls executed and answered with an error.
+    pub failed_calls: u64,
+    /// Calls by latency, counted in the first of [`LATENCY_BUCKETS`] they fit in, the rest are
+    /// slower than all of them.
+    pub latency: [u64; LATENCY_BUCKETS.len()],
+}
+
+impl ShardStats {
+    pub fn new(config: Arc<SharedConfig>) -> Self {
+        let epoch = config.stats_epoch();
+        Self {
+            config,
+            started: Instant::now(),
+            connected: Cell::new(0),
+            epoch: Cell::new(epoch),
+            counters: Default::default(),
+        }
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn connected(&self) -> usize {
+        self.connected.get()
+    }
+
+    /// Record a connection handed to this shard, until [`ShardStats::disconnect`].
+    pub fn connect(&self) {
+        self.connected.set(self.connected.get() + 1);
+        self.counters().connections_received += 1;
+    }
+
+    pub fn disconnect(&self) {
+        self.connected.set(self.connected.get() - 1);
+    }
+
+    /// Record bytes read from and written to clients.
+    pub fn record_bytes(&self, input: u64, output: u64) {
+        let mut counters = self.counters();
+        counters.net_input_bytes += input;
+        counters.net_output_bytes += output;
+    }
+
+    /// Counters since the last `CONFIG RESETSTAT`.
+    pub fn counters(&self) -> RefMut<'_, Counters> {
+        let epoch = self.config.stats_epoch();
+        let mut counters = self.counters.borrow_mut();
+        if self.epoch.get() != epoch {
+            *counters = Counters::default();
+            self.epoch.set(epoch);
+        }
+        counters
+    }
+
+    /// Record a command executed in `elapsed` and answered with `reply`.
+    pub fn record_call(&self, spec: &'static CommandSpec, elapsed: Duration, reply: &GFrame) {
+        let mut counters = self.counters();
+        counters.commands_processed += 1;
+        let command = counters.commands.entry((spec.container, spec.name)).or_default();
+        command.calls += 1;
+        let usec = elapsed.as_micros() as u64;
+        command.usec += usec;
+        if let Some(bucket) =
+            command.latency.get_mut(LATENCY_BUCKETS.partition_point(|bound| *bound < usec))
+        {
+            *bucket += 1;
+        }
+        if let GFrame::SimpleError(_) = reply {
+            command.failed_calls += 1;
+        }
+        counters.record_error(reply);
+    }
+
+    /// Record a command answered with the error `reply` without being executed, `spec` is `None`
+    /// for unknown commands.
+    pub fn record_rejection(&self, spec: Option<&'static CommandSpec>, reply: &GFrame) {
+        let mut counters = self.counters();
+        counters.commands_processed += 1;
+        if let Some(spec) = spec {
+            counters.commands.entry((spec.container, spec.name)).or_default().rejected_calls += 1;
+        }
+        counters.record_error(reply);
+    }
+}
+
+impl Counters {
+    fn record_error(&mut self, reply: &GFrame) {
+        if let GFrame::SimpleError(message) = reply {
+            let code = message.as_ref().split(|byte| *byte == b' ').next().unwrap_or_default();
+            *self.errors.entry(String::from_utf8_lossy(code).into_owned()).or_default() += 1;
+        }
+    }
+
+    /// Calls by lowercase command name, `container|name` for subcommands.
+    pub fn named_commands(&self) -> impl Iterator<Item = (String, &CommandStats)> {
+        self.commands.iter().map(|((container, name), stats)| {
+            let name = match container {
+                Some(container) => format!("{container}|{name}"),
+                None => name.to_string(),
+            };
+            (name.to_ascii_lowercase(), stats)
+        })
+    }
+
+    /// Fraction of storage requests served without crossing shards, `0.0` if there were none.
+    pub fn local_ratio(&self) -> f64 {
+        let total = self.local_requests + self.remote_requests;
+        if total == 0 { 0.0 } else { self.local_requests as f64 / total as f64 }
+    }
+
+    /// Add the counters of another shard.
+    pub fn merge(&mut self, other: &Counters) {
+        self.connections_received += other.connections_received;
+        self.rejected_connections += other.rejected_connections;
+        self.commands_processed += other.commands_processed;
+        self.net_input_bytes += other.net_input_bytes;
+        self.net_output_bytes += other.net_output_bytes;
+        self.keyspace_hits += other.keyspace_hits;
+        self.keyspace_misses += other.keyspace_misses;
+        self.expired_keys += other.expired_keys;
+        self.protocol_errors += other.protocol_errors;
+        self.local_requests += other.local_requests;
+        self.remote_requests += other.remote_requests;
+        self.acl_access_denied_auth += other.acl_access_denied_auth;
+        self.acl_access_denied_cmd += other.acl_access_denied_cmd;
+        self.acl_access_denied_key += other.acl_access_denied_key;
+        for (name, stats) in &other.commands {
+            let command = self.commands.entry(*name).or_default();
+            command.calls += stats.calls;
+            command.usec += stats.usec;
+            command.rejected_calls += stats.rejected_calls;
+            command.failed_calls += stats.failed_calls;
+            for (bucket, calls) in command.latency.iter_mut().zip(stats.latency) {
+                *bucket += calls;
+            }
+        }
+        for (code, count) in &other.errors {
+            *self.errors.entry(code.clone()).or_default() += count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use goosekv_protocol::{
+        data_type::GString,
+        error::ReplyError,
+    };
+
+    use super::*;
+
+    #[test]
+    fn counts_calls_and_resets() {
+        let config = Arc::new(SharedConfig::default());
+        let stats = ShardStats::new(config.clone());
+        let get = CommandSpec::find(b"GET").unwrap();
+        let elapsed = Duration::from_micros(3);
+        stats.record_call(get, elapsed, &GFrame::BulkString(GString::from_static(b"v")));
+        stats.record_call(get, elapsed, &ReplyError::WrongType.into());
+        stats.record_rejection(Some(get), &ReplyError::OutOfMemory.into());
+        stats.record_rejection(None, &ReplyError::Syntax.into());
+        stats.counters().local_requests += 3;
+        stats.counters().remote_requests += 1;
+
+        let mut total = Counters::default();
+        total.merge(&stats.counters());
+        total.merge(&stats.counters());
+        let command = total.commands[&(None, get.name)];
+        assert_eq!((command.calls, command.usec), (4, 12));
+        assert_eq!((command.failed_calls, command.rejected_calls), (2, 2));
+        assert_eq!(command.latency[2], 4);
+        assert_eq!(total.commands_processed, 8);
+        assert_eq!(total.errors["WRONGTYPE"], 2);
+        assert_eq!(total.errors["OOM"], 2);
+        assert_eq!(total.errors["ERR"], 2);
+        assert_eq!((total.local_requests, total.remote_requests), (6, 2));
+        assert_eq!(total.local_ratio(), 0.75);
+        assert_eq!(Counters::default().local_ratio(), 0.0);
+
+        config.reset_stats();
+        assert_eq!(stats.counters().commands_processed, 0);
+        assert!(stats.counters().commands.is_empty());
+        assert_eq!(stats.counters().local_requests, 0);
+    }
+}
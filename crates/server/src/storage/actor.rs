@@ -1,67 +1,107 @@
-use async_channel::{
-    Receiver,
-    Sender,
+use std::{
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
 };
-use tracing::debug;
 
-use crate::storage::{
-    Storage,
-    handle::StorageHandle,
-    request::Request,
-    response::{
-        DeleteResponse,
-        GetResponse,
-        SetResponse,
-        UpdateResponse,
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::{
+        join,
+        join_all,
     },
 };
+use glommio::{
+    channels::shared_channel::ConnectedReceiver,
+    spawn_local,
+    timer::sleep,
+};
+
+use crate::{
+    config::SharedConfig,
+    replication::Replication,
+    storage::{
+        Storage,
+        handle::StorageHandle,
+        local::LocalStorage,
+        mesh::{
+            LaneDepths,
+            StorageLanes,
+        },
+        request::Request,
+    },
+    tracking::Trackers,
+};
+
+/// Delay between two active expire passes of a shard.
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct StorageActor {
-    sender: Sender<Request>,
-    receiver: Receiver<Request>,
     storage: Storage,
+    replication: Arc<Replication>,
+    config: Arc<SharedConfig>,
+    trackers: Arc<Trackers>,
 }
 
 impl StorageActor {
-    pub fn new() -> Self {
-        let (sender, receiver) = async_channel::bounded(4);
-        Self { sender, receiver, storage: Storage::new() }
+    /// Create the storage of one shard, feeding its writes to `replication` and invalidating the
+    /// keys cached by `trackers`.
+    ///
+    /// Keys are evicted according to the `maxmemory` settings of `config`.
+    pub fn new(
+        replication: Arc<Replication>,
+        config: Arc<SharedConfig>,
+        trackers: Arc<Trackers>,
+    ) -> Self {
+        Self { storage: Storage::new(), replication, config, trackers }
     }
 
-    pub fn handle(&self) -> StorageHandle {
-        StorageHandle::new(self.sender.clone())
+    /// Start serving requests from other shards, returning the storage for direct local access.
+    ///
+    /// The task also removes expired keys every [`ACTIVE_EXPIRE_INTERVAL`], it runs until dropped.
+    pub fn run(
+        self,
+        lanes: StorageLanes,
+        handle: Rc<StorageHandle>,
+    ) -> (LocalStorage, impl Future<Output = ()>) {
+        let storage =
+            LocalStorage::new(self.storage, handle, self.replication, self.config, self.trackers);
+        let StorageLanes { shard, lanes, depths } = lanes;
+        let local = storage.clone();
+        let task = async move {
+            // One task per lane, so a wakeup from one lane never polls the others.
+            let tasks = lanes.into_iter().map(|(from, receiver)| {
+                spawn_local(run(receiver, local.clone(), depths.clone(), from, shard))
+            });
+            join(join_all(tasks), expire(local)).await;
+        };
+        (storage, task)
     }
+}
 
-    pub async fn run(mut self) {
-        while let Ok(request) = self.receiver.recv().await {
-            match request {
-                Request::Get(get_request, respond) => {
-                    debug!("get value for key: {:?}", get_request.key);
-                    let value = self.storage.get(&get_request.key);
-                    respond.send(GetResponse { value }).unwrap();
-                }
-                Request::Set(set_request, respond) => {
-                    debug!("set value for key: {:?}", set_request.key);
-                    let original_value = self.storage.set(set_request.key, set_request.value);
-                    respond.send(SetResponse { original_value }).unwrap();
-                }
-                Request::Delete(delete_request, respond) => {
-                    debug!("delete value for key: {:?}", delete_request.key);
-                    let deleted = self.storage.delete(&delete_request.key);
-                    respond.send(DeleteResponse { deleted }).unwrap()
-                }
-                Request::Update(update_request, respond) => {
-                    debug!("update value for key: {:?}", update_request.key);
-                    let updated = self.storage.update(update_request.key, update_request.f);
-                    respond.send(UpdateResponse { updated }).unwrap()
-                }
-            }
-        }
+async fn expire(storage: LocalStorage) {
+    loop {
+        sleep(ACTIVE_EXPIRE_INTERVAL).await;
+        storage.active_expire();
     }
 }
 
-impl Default for StorageActor {
-    fn default() -> Self {
-        Self::new()
+async fn run(
+    mut receiver: ConnectedReceiver<Request>,
+    storage: LocalStorage,
+    depths: LaneDepths,
+    from: usize,
+    to: usize,
+) {
+    while let Some(request) = receiver.next().await {
+        depths.decrement(from, to);
+        storage.handle(request);
+
+        // Drain everything queued since the last wakeup before yielding to the executor.
+        while let Some(Some(request)) = receiver.next().now_or_never() {
+            depths.decrement(from, to);
+            storage.handle(request);
+        }
     }
 }
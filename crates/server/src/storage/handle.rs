@@ -1,52 +1,235 @@
-use futures::channel::oneshot;
+use std::{
+    rc::Rc,
+    sync::Arc,
+};
 
-use crate::storage::{
-    request::{
-        DeleteRequest,
-        GetRequest,
-        Request,
-        SetRequest,
-        UpdateRequest,
+use futures::channel::oneshot;
+use glommio::{
+    channels::{
+        channel_mesh::Senders,
+        local_channel::{
+            self,
+            LocalSender,
+        },
     },
-    response::{
-        DeleteResponse,
-        GetResponse,
-        SetResponse,
-        UpdateResponse,
+    spawn_local,
+};
+use tracing::error;
+
+use crate::{
+    slot::SlotTable,
+    storage::{
+        memory::MemoryUsage,
+        mesh::LaneDepths,
+        request::{
+            ClientsRequest,
+            DeleteRequest,
+            FlushRequest,
+            GetRequest,
+            LatencyRequest,
+            MigrateRequest,
+            ModifyRequest,
+            Operation,
+            Request,
+            SetRequest,
+            SlowLogRequest,
+            SnapshotRequest,
+            StatsRequest,
+            UpdateRequest,
+        },
+        response::{
+            ClientsResponse,
+            DeleteResponse,
+            FlushResponse,
+            GetResponse,
+            LatencyResponse,
+            MigrateResponse,
+            ModifyResponse,
+            OperationResponse,
+            SetResponse,
+            SlowLogResponse,
+            SnapshotResponse,
+            StatsResponse,
+            StorageError,
+            StorageResult,
+            UpdateResponse,
+        },
     },
 };
 
 macro_rules! handle_request {
-    ($request_name:ident, $request:expr, $sender:expr) => {{
+    ($request_name:ident, $request:expr, $handle:expr, $shard:expr) => {{
         let (sender, receiver) = oneshot::channel();
-        $sender.send(Request::$request_name($request, sender)).await.unwrap();
-        receiver.await.unwrap()
+        $handle.send($shard, Request::$request_name($request, sender)).await;
+        receiver.await.map_err(|_| StorageError($shard))
     }};
 }
 
-#[derive(Clone)]
+/// Sending ends of the lanes from the current shard to every other shard.
 pub struct StorageHandle {
-    sender: async_channel::Sender<Request>,
+    senders: Rc<Senders<Request>>,
+    depths: LaneDepths,
+    slots: Arc<SlotTable>,
+    memory: MemoryUsage,
+    forward: Box<[LocalSender<Request>]>,
 }
 
 impl StorageHandle {
-    pub fn new(sender: async_channel::Sender<Request>) -> Self {
-        Self { sender }
+    /// Must be created on the executor that joined the mesh, forwarding tasks are spawned on it.
+    pub fn new(
+        senders: Senders<Request>,
+        depths: LaneDepths,
+        slots: Arc<SlotTable>,
+        memory: MemoryUsage,
+    ) -> Self {
+        let senders = Rc::new(senders);
+        let forward = (0..depths.shards())
+            .map(|to| {
+                let (sender, receiver) = local_channel::new_unbounded();
+                let senders = senders.clone();
+                let depths = depths.clone();
+                spawn_local(async move {
+                    while let Some(request) = receiver.recv().await {
+                        send(&senders, &depths, to, request).await;
+                    }
+                })
+                .detach();
+                sender
+            })
+            .collect();
+
+        Self { senders, depths, slots, memory, forward }
     }
 
-    pub async fn get(&self, request: GetRequest) -> GetResponse {
-        handle_request!(Get, request, self.sender)
+    /// Index of the current shard in the mesh.
+    pub fn shard(&self) -> usize {
+        self.senders.peer_id()
     }
 
-    pub async fn set(&self, request: SetRequest) -> SetResponse {
-        handle_request!(Set, request, self.sender)
+    pub fn shard_count(&self) -> usize {
+        self.depths.shards()
     }
 
-    pub async fn delete(&self, request: DeleteRequest) -> DeleteResponse {
-        handle_request!(Delete, request, self.sender)
+    pub fn memory(&self) -> &MemoryUsage {
+        &self.memory
     }
 
-    pub async fn update(&self, request: UpdateRequest) -> UpdateResponse {
-        handle_request!(Update, request, self.sender)
+    pub fn lane_depths(&self) -> &LaneDepths {
+        &self.depths
+    }
+
+    pub fn slots(&self) -> &SlotTable {
+        &self.slots
+    }
+
+    pub async fn get(&self, shard: usize, request: GetRequest) -> StorageResult<GetResponse> {
+        handle_request!(Get, request, self, shard)
+    }
+
+    pub async fn set(&self, shard: usize, request: SetRequest) -> StorageResult<SetResponse> {
+        handle_request!(Set, request, self, shard)
+    }
+
+    pub async fn delete(
+        &self,
+        shard: usize,
+        request: DeleteRequest,
+    ) -> StorageResult<DeleteResponse> {
+        handle_request!(Delete, request, self, shard)
+    }
+
+    pub async fn update(
+        &self,
+        shard: usize,
+        request: UpdateRequest,
+    ) -> StorageResult<UpdateResponse> {
+        handle_request!(Update, request, self, shard)
+    }
+
+    pub async fn modify(
+        &self,
+        shard: usize,
+        request: ModifyRequest,
+    ) -> StorageResult<ModifyResponse> {
+        handle_request!(Modify, request, self, shard)
+    }
+
+    pub async fn batch(
+        &self,
+        shard: usize,
+        operations: Vec<Operation>,
+    ) -> StorageResult<Vec<OperationResponse>> {
+        handle_request!(Batch, operations, self, shard)
+    }
+
+    pub async fn migrate(
+        &self,
+        shard: usize,
+        request: MigrateRequest,
+    ) -> StorageResult<MigrateResponse> {
+        handle_request!(Migrate, request, self, shard)
+    }
+
+    pub async fn snapshot(
+        &self,
+        shard: usize,
+        request: SnapshotRequest,
+    ) -> StorageResult<SnapshotResponse> {
+        handle_request!(Snapshot, request, self, shard)
+    }
+
+    pub async fn flush(&self, shard: usize, request: FlushRequest) -> StorageResult<FlushResponse> {
+        handle_request!(Flush, request, self, shard)
+    }
+
+    pub async fn stats(&self, shard: usize, request: StatsRequest) -> StorageResult<StatsResponse> {
+        handle_request!(Stats, request, self, shard)
+    }
+
+    pub async fn slowlog(
+        &self,
+        shard: usize,
+        request: SlowLogRequest,
+    ) -> StorageResult<SlowLogResponse> {
+        handle_request!(SlowLog, request, self, shard)
+    }
+
+    pub async fn latency(
+        &self,
+        shard: usize,
+        request: LatencyRequest,
+    ) -> StorageResult<LatencyResponse> {
+        handle_request!(Latency, request, self, shard)
+    }
+
+    pub async fn clients(
+        &self,
+        shard: usize,
+        request: ClientsRequest,
+    ) -> StorageResult<ClientsResponse> {
+        handle_request!(Clients, request, self, shard)
+    }
+
+    /// Queue a request for shard `to` behind everything forwarded to it before.
+    ///
+    /// Unlike the other methods this keeps the order of requests, which is needed once slots are
+    /// migrated as their keys must be imported before any request for them is served.
+    pub fn forward(&self, to: usize, request: Request) {
+        if self.forward[to].try_send(request).is_err() {
+            error!("storage forwarding to shard {to} has stopped, dropping request");
+        }
+    }
+
+    async fn send(&self, shard: usize, request: Request) {
+        send(&self.senders, &self.depths, shard, request).await
+    }
+}
+
+async fn send(senders: &Senders<Request>, depths: &LaneDepths, to: usize, request: Request) {
+    let from = senders.peer_id();
+    depths.increment(from, to);
+    if senders.send_to(to, request).await.is_err() {
+        depths.decrement(from, to);
+        error!("storage lane to shard {to} is closed, dropping request");
     }
 }
@@ -0,0 +1,566 @@
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    collections::BTreeMap,
+    rc::Rc,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use futures::{
+    channel::oneshot,
+    future::join_all,
+};
+use glommio::spawn_local;
+use goosekv_protocol::data_type::GString;
+use tracing::debug;
+
+use crate::{
+    config::{
+        LocalConfig,
+        SharedConfig,
+    },
+    latency::{
+        LatencyEvent,
+        LatencyMonitor,
+    },
+    processor::clients::ShardClients,
+    replication::Replication,
+    slot::key_slot,
+    slowlog::SlowLog,
+    stats::ShardStats,
+    storage::{
+        Storage,
+        handle::StorageHandle,
+        memory::EvictionPolicy,
+        request::{
+            ClientsRequest,
+            DeleteRequest,
+            FlushRequest,
+            GetRequest,
+            ImportRequest,
+            LatencyRequest,
+            MigrateRequest,
+            Modification,
+            ModifyRequest,
+            Operation,
+            Request,
+            SetRequest,
+            SlowLogRequest,
+            SnapshotRequest,
+            StatsRequest,
+            UpdateRequest,
+        },
+        response::{
+            ClientsResponse,
+            DeleteResponse,
+            FlushResponse,
+            GetResponse,
+            ImportResponse,
+            LatencyResponse,
+            MigrateResponse,
+            ModifyResponse,
+            OperationResponse,
+            SetResponse,
+            SlowLogResponse,
+            SnapshotResponse,
+            StatsResponse,
+            UpdateResponse,
+        },
+        value::Value,
+    },
+    tracking::{
+        Trackers,
+        TrackingTable,
+    },
+};
+
+/// Keys with an expiration time sampled by each round of an active expire pass.
+const ACTIVE_EXPIRE_SAMPLES: usize = 20;
+/// Longest an active expire pass keeps sampling while many sampled keys expired.
+const ACTIVE_EXPIRE_BUDGET: Duration = Duration::from_millis(5);
+
+/// Storage of the current shard, borrowed directly by tasks running on the same executor.
+#[derive(Clone)]
+pub struct LocalStorage {
+    storage: Rc<RefCell<Storage>>,
+    handle: Rc<StorageHandle>,
+    replication: Arc<Replication>,
+    config: Rc<LocalConfig>,
+    stats: Rc<ShardStats>,
+    slowlog: Rc<SlowLog>,
+    latency: Rc<LatencyMonitor>,
+    clients: Rc<ShardClients>,
+    tracking: Rc<TrackingTable>,
+    /// Writes applied so far, making up the CAS tokens of this shard.
+    writes: Rc<Cell<u64>>,
+}
+
+impl LocalStorage {
+    pub fn new(
+        storage: Storage,
+        handle: Rc<StorageHandle>,
+        replication: Arc<Replication>,
+        config: Arc<SharedConfig>,
+        trackers: Arc<Trackers>,
+    ) -> Self {
+        let stats = ShardStats::new(config.clone());
+        let config = Rc::new(LocalConfig::new(config));
+        let slowlog = SlowLog::new(config.clone(), handle.shard(), handle.shard_count());
+        let tracking = TrackingTable::new(trackers, config.clone(), handle.shard_count());
+        Self {
+            storage: Rc::new(RefCell::new(storage)),
+            handle,
+            replication,
+            stats: Rc::new(stats),
+            slowlog: Rc::new(slowlog),
+            latency: Rc::new(LatencyMonitor::new(config.clone())),
+            clients: Default::default(),
+            tracking: Rc::new(tracking),
+            config,
+            writes: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub fn replication(&self) -> &Arc<Replication> {
+        &self.replication
+    }
+
+    /// Copy of the configuration kept by the current shard.
+    pub fn config(&self) -> &LocalConfig {
+        &self.config
+    }
+
+    /// Statistics of the current shard, also updated by the processor.
+    pub fn stats(&self) -> &Rc<ShardStats> {
+        &self.stats
+    }
+
+    /// Slow commands executed by the processor of the current shard.
+    pub fn slowlog(&self) -> &Rc<SlowLog> {
+        &self.slowlog
+    }
+
+    /// Latency spikes of the current shard, sampled by its processor and storage.
+    pub fn latency(&self) -> &Rc<LatencyMonitor> {
+        &self.latency
+    }
+
+    /// Clients served by the processor of the current shard.
+    pub fn clients(&self) -> &Rc<ShardClients> {
+        &self.clients
+    }
+
+    /// Keys of the current shard read by clients tracking them.
+    pub fn tracking(&self) -> &Rc<TrackingTable> {
+        &self.tracking
+    }
+
+    /// Shard the slot of `key` was migrated to, `None` if it is still served here.
+    pub fn moved_to(&self, key: &GString) -> Option<usize> {
+        self.storage.borrow().moved_to(key_slot(key.as_ref()))
+    }
+
+    pub fn get(&self, request: GetRequest) -> GetResponse {
+        self.expire(&request.key);
+        if let Some(client) = request.tracking {
+            self.tracking.record(&request.key, client);
+        }
+        let value = match request.touch {
+            true => self.storage.borrow_mut().get(&request.key),
+            false => self.storage.borrow().peek(&request.key),
+        };
+        let mut counters = self.stats.counters();
+        match value {
+            Some(_) => counters.keyspace_hits += 1,
+            None => counters.keyspace_misses += 1,
+        }
+        GetResponse { value }
+    }
+
+    // Writes are fed to the replication backlog as they are applied, so the stream follows the
+    // order in which each key changed.
+
+    pub fn set(&self, mut request: SetRequest) -> SetResponse {
+        self.replication.feed_set(&request.key, &request.value);
+        self.tracking.invalidate(&request.key, request.client);
+        request.value.cas = self.next_cas();
+        let original_value = self.storage.borrow_mut().set(request.key, request.value);
+        self.evict();
+        SetResponse { original_value }
+    }
+
+    pub fn delete(&self, request: DeleteRequest) -> DeleteResponse {
+        self.replication.feed_delete(&request.key);
+        let deleted = self.storage.borrow_mut().delete(&request.key);
+        if deleted.is_some() {
+            self.tracking.invalidate(&request.key, request.client);
+        }
+        self.evict();
+        DeleteResponse { deleted }
+    }
+
+    pub fn update(&self, request: UpdateRequest) -> UpdateResponse {
+        let key = request.key.clone();
+        let cas = self.next_cas();
+        let f = request.f;
+        let (previous, updated) = self.storage.borrow_mut().update(
+            request.key,
+            Arc::new(move |value| f(value).map(|value| Value { cas, ..value })),
+        );
+        if let Some(value) = &updated {
+            self.replication.feed_set(&key, value);
+            self.tracking.invalidate(&key, request.client);
+            self.evict();
+        }
+        UpdateResponse { previous, updated }
+    }
+
+    pub fn modify(&self, request: ModifyRequest) -> ModifyResponse {
+        self.expire(&request.key);
+        let previous = self.storage.borrow_mut().get(&request.key);
+        let modification = (request.f)(previous.as_ref());
+        if !matches!(modification, Modification::Keep) {
+            self.tracking.invalidate(&request.key, None);
+        }
+        let current = match modification {
+            Modification::Keep => previous.clone(),
+            Modification::Set(mut value) => {
+                self.replication.feed_set(&request.key, &value);
+                value.cas = self.next_cas();
+                self.storage.borrow_mut().set(request.key, value.clone());
+                Some(value)
+            }
+            Modification::Touch(expires_at) => {
+                let value = previous.clone().map(|value| Value { expires_at, ..value });
+                if let Some(value) = &value {
+                    self.replication.feed_set(&request.key, value);
+                    self.storage.borrow_mut().set(request.key, value.clone());
+                }
+                value
+            }
+            Modification::Delete => {
+                self.replication.feed_delete(&request.key);
+                self.storage.borrow_mut().delete(&request.key);
+                None
+            }
+        };
+        self.evict();
+
+        ModifyResponse { previous, current }
+    }
+
+    pub fn snapshot(&self, _request: SnapshotRequest) -> SnapshotResponse {
+        SnapshotResponse { entries: self.storage.borrow().snapshot() }
+    }
+
+    pub fn flush(&self, _request: FlushRequest) -> FlushResponse {
+        let keys = self.storage.borrow_mut().flush();
+        self.tracking.clear();
+        self.evict();
+        FlushResponse { keys }
+    }
+
+    pub fn collect_stats(&self, _request: StatsRequest) -> StatsResponse {
+        let storage = self.storage.borrow();
+        StatsResponse {
+            counters: self.stats.counters().clone(),
+            keys: storage.len(),
+            expires: storage.expires(),
+            connected: self.stats.connected(),
+            tracked_keys: self.tracking.len(),
+            tracked_items: self.tracking.items(),
+        }
+    }
+
+    pub fn read_slowlog(&self, request: SlowLogRequest) -> SlowLogResponse {
+        let len = self.slowlog.len();
+        let entries = match request {
+            SlowLogRequest::Get(count) => self.slowlog.get(count),
+            SlowLogRequest::Len => Vec::new(),
+            SlowLogRequest::Reset => {
+                self.slowlog.reset();
+                Vec::new()
+            }
+        };
+        SlowLogResponse { entries, len }
+    }
+
+    pub fn read_latency(&self, request: LatencyRequest) -> LatencyResponse {
+        match request {
+            LatencyRequest::Events => {
+                LatencyResponse { events: self.latency.events(), reset: Vec::new() }
+            }
+            LatencyRequest::Reset(events) => {
+                LatencyResponse { events: BTreeMap::new(), reset: self.latency.reset(&events) }
+            }
+        }
+    }
+
+    pub fn read_clients(&self, request: ClientsRequest) -> ClientsResponse {
+        let clients = match request {
+            ClientsRequest::List(filter) => self.clients.list(&filter),
+            ClientsRequest::Kill(filter) => self.clients.kill(&filter),
+        };
+        ClientsResponse { clients }
+    }
+
+    pub fn batch(&self, operations: Vec<Operation>) -> Vec<OperationResponse> {
+        operations.into_iter().map(|operation| self.operation(operation)).collect()
+    }
+
+    fn operation(&self, operation: Operation) -> OperationResponse {
+        match operation {
+            Operation::Get(request) => OperationResponse::Get(self.get(request)),
+            Operation::Set(request) => OperationResponse::Set(self.set(request)),
+            Operation::Delete(request) => OperationResponse::Delete(self.delete(request)),
+            Operation::Update(request) => OperationResponse::Update(self.update(request)),
+        }
+    }
+
+    /// Serve a request, forwarding it to the new owner if its slot was migrated away.
+    pub fn handle(&self, request: Request) {
+        if let Some(to) = request.key().and_then(|key| self.moved_to(key)) {
+            debug!("forward request to shard {to}");
+            self.handle.forward(to, request);
+            return;
+        }
+
+        match request {
+            Request::Get(get_request, respond) => {
+                debug!("get value for key: {:?}", get_request.key);
+                let _ = respond.send(self.get(get_request));
+            }
+            Request::Set(set_request, respond) => {
+                debug!("set value for key: {:?}", set_request.key);
+                let _ = respond.send(self.set(set_request));
+            }
+            Request::Delete(delete_request, respond) => {
+                debug!("delete value for key: {:?}", delete_request.key);
+                let _ = respond.send(self.delete(delete_request));
+            }
+            Request::Update(update_request, respond) => {
+                debug!("update value for key: {:?}", update_request.key);
+                let _ = respond.send(self.update(update_request));
+            }
+            Request::Modify(modify_request, respond) => {
+                debug!("modify value for key: {:?}", modify_request.key);
+                let _ = respond.send(self.modify(modify_request));
+            }
+            Request::Batch(operations, respond) => {
+                debug!("batch of {} operations", operations.len());
+                self.handle_batch(operations, respond);
+            }
+            Request::Migrate(migrate_request, respond) => {
+                debug!(
+                    "migrate {} slots to shard {}",
+                    migrate_request.slots.len(),
+                    migrate_request.to
+                );
+                self.migrate(migrate_request, respond);
+            }
+            Request::Import(import_request, respond) => {
+                debug!("import {} keys", import_request.entries.len());
+                let _ = respond.send(self.import(import_request));
+            }
+            Request::Snapshot(snapshot_request, respond) => {
+                debug!("snapshot keys");
+                let _ = respond.send(self.snapshot(snapshot_request));
+            }
+            Request::Flush(flush_request, respond) => {
+                debug!("flush keys");
+                let _ = respond.send(self.flush(flush_request));
+            }
+            Request::Stats(stats_request, respond) => {
+                debug!("collect stats");
+                let _ = respond.send(self.collect_stats(stats_request));
+            }
+            Request::SlowLog(slowlog_request, respond) => {
+                debug!("slowlog {slowlog_request:?}");
+                let _ = respond.send(self.read_slowlog(slowlog_request));
+            }
+            Request::Latency(latency_request, respond) => {
+                debug!("latency {latency_request:?}");
+                let _ = respond.send(self.read_latency(latency_request));
+            }
+            Request::Clients(clients_request, respond) => {
+                debug!("clients {clients_request:?}");
+                let _ = respond.send(self.read_clients(clients_request));
+            }
+        }
+    }
+
+    /// Evict keys while this shard uses more than its share of `maxmemory`, then publish the
+    /// memory it uses.
+    ///
+    /// Replicas leave eviction to their primary, whose evictions are streamed as deletions.
+    fn evict(&self) {
+        let (maxmemory, policy, samples) = {
+            let config = self.config.read();
+            (config.maxmemory.0 as usize, config.maxmemory_policy, config.maxmemory_samples)
+        };
+
+        let mut storage = self.storage.borrow_mut();
+        let memory = self.handle.memory();
+        let shard = self.handle.shard();
+        if maxmemory > 0 && policy != EvictionPolicy::NoEviction && !self.replication.is_replica() {
+            let limit = maxmemory / self.handle.shard_count();
+            let started = Instant::now();
+            let evicted = storage.evict(limit, policy, samples);
+            if !evicted.is_empty() {
+                self.latency.record(LatencyEvent::EvictionCycle, started.elapsed());
+            }
+            for key in &evicted {
+                debug!("evicted key: {key:?}");
+                self.replication.feed_delete(key);
+                self.tracking.invalidate(key, None);
+            }
+            memory.record_evictions(evicted.len());
+            memory.set_exhausted(shard, storage.used() > limit);
+        } else {
+            memory.set_exhausted(shard, false);
+        }
+        memory.set_shard(shard, storage.used());
+    }
+
+    /// Remove expired keys sampled at random, until few of the sampled keys expired or the pass
+    /// ran for [`ACTIVE_EXPIRE_BUDGET`], returning the number removed.
+    ///
+    /// Keys that are never accessed again would otherwise stay until evicted.
+    pub fn active_expire(&self) -> usize {
+        if self.replication.is_replica() {
+            return 0;
+        }
+
+        let started = Instant::now();
+        let mut expired = 0;
+        loop {
+            let sampled = self.storage.borrow().sample_expired(ACTIVE_EXPIRE_SAMPLES);
+            for key in &sampled {
+                self.expire(key);
+            }
+            expired += sampled.len();
+            if sampled.len() <= ACTIVE_EXPIRE_SAMPLES / 4
+                || started.elapsed() > ACTIVE_EXPIRE_BUDGET
+            {
+                break;
+            }
+        }
+        if expired > 0 {
+            self.latency.record(LatencyEvent::ExpireCycle, started.elapsed());
+            self.evict();
+        }
+        expired
+    }
+
+    /// Remove `key` if it expired, as a deletion replicas and tracking clients are told about.
+    ///
+    /// Replicas keep expired keys until their primary deletes them.
+    fn expire(&self, key: &GString) {
+        if self.replication.is_replica() || !self.storage.borrow_mut().expire(key) {
+            return;
+        }
+        debug!("expired key: {key:?}");
+        self.replication.feed_delete(key);
+        self.tracking.invalidate(key, None);
+        self.stats.counters().expired_keys += 1;
+    }
+
+    /// CAS token unique across shards, so keys migrated between them never reuse one.
+    fn next_cas(&self) -> u64 {
+        let writes = self.writes.get() + 1;
+        self.writes.set(writes);
+        (writes << 10) | self.handle.shard() as u64
+    }
+
+    /// Execute the operations still served here and forward the rest to their new owners.
+    fn handle_batch(
+        &self,
+        operations: Vec<Operation>,
+        respond: oneshot::Sender<Vec<OperationResponse>>,
+    ) {
+        let moved: Vec<_> =
+            operations.iter().map(|operation| self.moved_to(operation.key())).collect();
+        if moved.iter().all(Option::is_none) {
+            let _ = respond.send(self.batch(operations));
+            return;
+        }
+
+        let mut responses: Vec<Option<OperationResponse>> = Vec::with_capacity(operations.len());
+        let mut forwarded: Vec<(usize, Vec<usize>, Vec<Operation>)> = Vec::new();
+        for (index, (operation, moved)) in operations.into_iter().zip(moved).enumerate() {
+            match moved {
+                None => responses.push(Some(self.operation(operation))),
+                Some(to) => {
+                    responses.push(None);
+                    match forwarded.iter_mut().find(|(shard, ..)| *shard == to) {
+                        Some((_, indices, operations)) => {
+                            indices.push(index);
+                            operations.push(operation);
+                        }
+                        None => forwarded.push((to, vec![index], vec![operation])),
+                    }
+                }
+            }
+        }
+
+        let replies = forwarded
+            .into_iter()
+            .map(|(to, indices, operations)| {
+                let (sender, receiver) = oneshot::channel();
+                self.handle.forward(to, Request::Batch(operations, sender));
+                async move { receiver.await.map(|responses| indices.into_iter().zip(responses)) }
+            })
+            .collect::<Vec<_>>();
+
+        spawn_local(async move {
+            for replies in join_all(replies).await {
+                // Dropping `respond` answers the whole batch with an error.
+                let Ok(replies) = replies else {
+                    return;
+                };
+                for (index, response) in replies {
+                    responses[index] = Some(response);
+                }
+            }
+            let responses = responses
+                .into_iter()
+                .map(|response| response.expect("every operation is answered"));
+            let _ = respond.send(responses.collect());
+        })
+        .detach();
+    }
+
+    /// Hand the keys of migrated slots to their new owner, queued before any forwarded request.
+    fn migrate(&self, request: MigrateRequest, respond: oneshot::Sender<MigrateResponse>) {
+        let entries = self.storage.borrow_mut().export(&request.slots, request.to);
+        for (key, _) in &entries {
+            self.tracking.release(key);
+        }
+        let keys = entries.len();
+        self.evict();
+
+        let (sender, receiver) = oneshot::channel();
+        let import = ImportRequest { slots: request.slots, entries };
+        self.handle.forward(request.to, Request::Import(import, sender));
+
+        spawn_local(async move {
+            // The keys are lost if the new owner stopped, dropping `respond` reports it.
+            if receiver.await.is_ok() {
+                let _ = respond.send(MigrateResponse { keys });
+            }
+        })
+        .detach();
+    }
+
+    fn import(&self, request: ImportRequest) -> ImportResponse {
+        let keys = self.storage.borrow_mut().import(&request.slots, request.entries);
+        self.evict();
+        ImportResponse { keys }
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{
+            AtomicBool,
+            AtomicU64,
+            AtomicUsize,
+            Ordering,
+        },
+    },
+    time::Instant,
+};
+
+use crate::storage::value::Value;
+
+/// Frequency counter of new keys, so they are not evicted before they had a chance to be read.
+const LFU_INIT: u8 = 5;
+/// The higher, the more accesses are needed to increment a frequency counter.
+const LFU_LOG_FACTOR: f64 = 10.0;
+/// Minutes without access after which a frequency counter is decremented.
+const LFU_DECAY_MINUTES: u32 = 1;
+
+/// Which keys are evicted once `maxmemory` is reached, named as in Redis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Writes are rejected instead.
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    VolatileLru,
+    VolatileLfu,
+    VolatileRandom,
+    VolatileTtl,
+}
+
+impl EvictionPolicy {
+    const ALL: [EvictionPolicy; 8] = [
+        EvictionPolicy::NoEviction,
+        EvictionPolicy::AllKeysLru,
+        EvictionPolicy::AllKeysLfu,
+        EvictionPolicy::AllKeysRandom,
+        EvictionPolicy::VolatileLru,
+        EvictionPolicy::VolatileLfu,
+        EvictionPolicy::VolatileRandom,
+        EvictionPolicy::VolatileTtl,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::AllKeysLfu => "allkeys-lfu",
+            EvictionPolicy::AllKeysRandom => "allkeys-random",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+            EvictionPolicy::VolatileLfu => "volatile-lfu",
+            EvictionPolicy::VolatileRandom => "volatile-random",
+            EvictionPolicy::VolatileTtl => "volatile-ttl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Only keys with an expiration time are evicted.
+    pub fn is_volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+
+    /// Rank of a sampled key, the one ranked highest is evicted first.
+    pub fn rank(&self, value: &Value, access: &Access, now: u32) -> u64 {
+        if value.is_expired() {
+            return u64::MAX;
+        }
+
+        match self {
+            EvictionPolicy::AllKeysLru | EvictionPolicy::VolatileLru => access.idle(now) as u64,
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => {
+                (u8::MAX - access.frequency(now)) as u64
+            }
+            EvictionPolicy::VolatileTtl => value.expires_at.map_or(0, |expires_at| {
+                let ttl = expires_at.saturating_duration_since(Instant::now());
+                u64::MAX - 1 - ttl.as_millis().min(u64::MAX as u128 - 1) as u64
+            }),
+            EvictionPolicy::NoEviction
+            | EvictionPolicy::AllKeysRandom
+            | EvictionPolicy::VolatileRandom => 0,
+        }
+    }
+}
+
+/// Approximate access clocks of a key, kept by the storage for eviction.
+///
+/// Times are milliseconds of the clock of the storage, wrapping after about 49 days.
+#[derive(Debug, Clone, Copy)]
+pub struct Access {
+    accessed: u32,
+    /// Logarithmic access counter, decaying while the key is not accessed.
+    frequency: u8,
+}
+
+impl Access {
+    pub fn new(now: u32) -> Self {
+        Self { accessed: now, frequency: LFU_INIT }
+    }
+
+    /// Record an access to the key.
+    pub fn touch(&mut self, now: u32) {
+        let frequency = self.frequency(now);
+        let base = frequency.saturating_sub(LFU_INIT) as f64;
+        let increment =
+            frequency < u8::MAX && fastrand::f64() < 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+        self.frequency = frequency + increment as u8;
+        self.accessed = now;
+    }
+
+    /// Milliseconds since the last access.
+    pub fn idle(&self, now: u32) -> u32 {
+        now.wrapping_sub(self.accessed)
+    }
+
+    /// Access counter, decayed by the time elapsed since the last access.
+    pub fn frequency(&self, now: u32) -> u8 {
+        let periods = self.idle(now) / 60_000 / LFU_DECAY_MINUTES;
+        self.frequency.saturating_sub(periods.min(u8::MAX as u32) as u8)
+    }
+}
+
+/// Memory used by the storage of every shard, shared by all shards.
+///
+/// Each entry is only written by the storage actor of its shard.
+#[derive(Debug, Clone)]
+pub struct MemoryUsage {
+    used: Arc<[AtomicUsize]>,
+    /// Set for the shards that could not evict enough keys to fit their share of `maxmemory`.
+    exhausted: Arc<[AtomicBool]>,
+    evicted: Arc<AtomicU64>,
+}
+
+impl MemoryUsage {
+    pub fn new(shards: usize) -> Self {
+        Self {
+            used: (0..shards).map(|_| AtomicUsize::new(0)).collect(),
+            exhausted: (0..shards).map(|_| AtomicBool::new(false)).collect(),
+            evicted: Default::default(),
+        }
+    }
+
+    /// Bytes used by every shard.
+    pub fn used(&self) -> usize {
+        self.used.iter().map(|used| used.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn shard(&self, shard: usize) -> usize {
+        self.used[shard].load(Ordering::Relaxed)
+    }
+
+    pub fn set_shard(&self, shard: usize, used: usize) {
+        self.used[shard].store(used, Ordering::Relaxed);
+    }
+
+    /// Whether a shard could not evict enough keys on its last write.
+    pub fn exhausted(&self) -> bool {
+        self.exhausted.iter().any(|exhausted| exhausted.load(Ordering::Relaxed))
+    }
+
+    pub fn set_exhausted(&self, shard: usize, exhausted: bool) {
+        self.exhausted[shard].store(exhausted, Ordering::Relaxed);
+    }
+
+    /// Keys evicted so far by every shard.
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    pub fn record_evictions(&self, keys: usize) {
+        self.evicted.fetch_add(keys as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frequency_decays() {
+        let mut access = Access::new(0);
+        for _ in 0..1000 {
+            access.touch(0);
+        }
+        let frequency = access.frequency(0);
+        assert!(frequency > LFU_INIT);
+        assert_eq!(access.frequency(3 * 60_000), frequency - 3);
+        assert_eq!(access.idle(3 * 60_000), 3 * 60_000);
+    }
+}
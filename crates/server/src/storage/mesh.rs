@@ -0,0 +1,133 @@
+use std::{
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    },
+};
+
+use glommio::channels::{
+    channel_mesh::{
+        Full,
+        MeshBuilder,
+        Receivers,
+    },
+    shared_channel::ConnectedReceiver,
+};
+
+use crate::{
+    slot::SlotTable,
+    storage::{
+        handle::StorageHandle,
+        memory::MemoryUsage,
+        request::Request,
+    },
+};
+
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 4;
+
+/// Shard-to-shard transport with a dedicated SPSC lane for every ordered pair of shards.
+///
+/// Cloned into every shard before it starts, each shard then joins from its own executor.
+#[derive(Debug, Clone)]
+pub struct StorageMesh {
+    builder: MeshBuilder<Request, Full>,
+    depths: LaneDepths,
+    slots: Arc<SlotTable>,
+    memory: MemoryUsage,
+}
+
+impl StorageMesh {
+    pub fn new(shards: usize, channel_capacity: usize) -> Self {
+        Self {
+            builder: MeshBuilder::full(shards, channel_capacity),
+            depths: LaneDepths::new(shards),
+            slots: Arc::new(SlotTable::new(shards)),
+            memory: MemoryUsage::new(shards),
+        }
+    }
+
+    /// Connect the current executor to the mesh, resolves once every shard has joined.
+    pub async fn join(self) -> (Rc<StorageHandle>, StorageLanes) {
+        let (senders, mut receivers) = self.builder.join().await.expect("storage mesh join");
+        let lanes = StorageLanes::new(&mut receivers, self.depths.clone());
+        let handle = StorageHandle::new(senders, self.depths, self.slots, self.memory);
+        (Rc::new(handle), lanes)
+    }
+}
+
+/// Receiving ends of the lanes from every other shard into the current one.
+pub struct StorageLanes {
+    pub(crate) shard: usize,
+    pub(crate) lanes: Vec<(usize, ConnectedReceiver<Request>)>,
+    pub(crate) depths: LaneDepths,
+}
+
+impl StorageLanes {
+    fn new(receivers: &mut Receivers<Request>, depths: LaneDepths) -> Self {
+        Self { shard: receivers.peer_id(), lanes: receivers.streams(), depths }
+    }
+}
+
+/// Number of requests sent on each lane and not yet picked up by the destination shard.
+///
+/// Shared by all shards, every lane is only incremented by its sender and decremented by its
+/// receiver.
+#[derive(Debug, Clone)]
+pub struct LaneDepths {
+    shards: usize,
+    depths: Arc<[AtomicUsize]>,
+}
+
+impl LaneDepths {
+    fn new(shards: usize) -> Self {
+        Self { shards, depths: (0..shards * shards).map(|_| AtomicUsize::new(0)).collect() }
+    }
+
+    pub fn shards(&self) -> usize {
+        self.shards
+    }
+
+    /// Requests queued from shard `from` to shard `to`.
+    pub fn depth(&self, from: usize, to: usize) -> usize {
+        self.lane(from, to).load(Ordering::Relaxed)
+    }
+
+    /// Requests queued from every other shard to shard `to`.
+    pub fn incoming(&self, to: usize) -> usize {
+        (0..self.shards).map(|from| self.depth(from, to)).sum()
+    }
+
+    pub(crate) fn increment(&self, from: usize, to: usize) {
+        self.lane(from, to).fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn decrement(&self, from: usize, to: usize) {
+        self.lane(from, to).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn lane(&self, from: usize, to: usize) -> &AtomicUsize {
+        &self.depths[from * self.shards + to]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lane_depths() {
+        let depths = LaneDepths::new(3);
+        depths.increment(0, 2);
+        depths.increment(0, 2);
+        depths.increment(1, 2);
+        depths.decrement(0, 2);
+
+        assert_eq!(depths.depth(0, 2), 1);
+        assert_eq!(depths.depth(2, 0), 0);
+        assert_eq!(depths.incoming(2), 2);
+    }
+}
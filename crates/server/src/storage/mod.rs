@@ -1,83 +1,364 @@
 use std::{
-    collections::{
-        HashMap,
-        hash_map::Entry,
-    },
-    sync::Arc,
+    collections::HashMap,
+    time::Instant,
 };
 
 use goosekv_protocol::data_type::GString;
 
-use crate::storage::value::Value;
+use crate::{
+    slot::{
+        SLOT_COUNT,
+        key_slot,
+    },
+    storage::{
+        memory::{
+            Access,
+            EvictionPolicy,
+        },
+        request::UpdateFn,
+        value::{
+            Data,
+            Value,
+        },
+    },
+};
 
 pub mod actor;
 pub mod handle;
+pub mod local;
+pub mod memory;
+pub mod mesh;
 pub mod request;
 pub mod response;
 pub mod router;
 pub mod value;
 
+/// Estimated bytes used by a key besides its own bytes and those of its data.
+const ENTRY_OVERHEAD: usize = size_of::<(GString, Entry)>() + size_of::<GString>() + 16;
+
 pub struct Storage {
-    data: HashMap<GString, Value>,
+    data: HashMap<GString, Entry>,
+    /// Keys of `data` in no particular order, sampled for eviction.
+    keys: Vec<GString>,
+    /// Keys of `data` with an expiration time, sampled by the volatile eviction policies.
+    volatile: Vec<GString>,
+    /// Slots migrated away from this storage and the shard owning them now.
+    moved: HashMap<u16, usize>,
+    /// Estimated bytes used by the keys and their values.
+    used: usize,
+    /// Origin of the access clocks of the keys.
+    epoch: Instant,
+}
+
+struct Entry {
+    value: Value,
+    access: Access,
+    /// Position of the key in `keys`.
+    index: usize,
+    /// Position of the key in `volatile`, if it expires.
+    volatile: Option<usize>,
 }
 
 impl Storage {
     pub fn new() -> Self {
-        Self { data: Default::default() }
+        Self {
+            data: Default::default(),
+            keys: Default::default(),
+            volatile: Default::default(),
+            moved: Default::default(),
+            used: 0,
+            epoch: Instant::now(),
+        }
     }
 
-    pub fn get(&self, key: &GString) -> Option<Value> {
-        self.data.get(key).cloned()
+    /// Estimated bytes used by the keys and their values.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Number of keys with an expiration time.
+    pub fn expires(&self) -> usize {
+        self.volatile.len()
+    }
+
+    pub fn moved_to(&self, slot: u16) -> Option<usize> {
+        if self.moved.is_empty() { None } else { self.moved.get(&slot).copied() }
+    }
+
+    /// Remove all keys of `slots`, requests for them are forwarded to shard `to` from now on.
+    pub fn export(&mut self, slots: &[u16], to: usize) -> Vec<(GString, Value)> {
+        let mut exported = vec![false; SLOT_COUNT];
+        for &slot in slots {
+            exported[slot as usize] = true;
+            self.moved.insert(slot, to);
+        }
+
+        let keys: Vec<_> = self
+            .keys
+            .iter()
+            .filter(|key| exported[key_slot(key.as_ref()) as usize])
+            .cloned()
+            .collect();
+        keys.into_iter().filter_map(|key| self.take(&key).map(|entry| (key, entry.value))).collect()
+    }
+
+    /// Take ownership of `slots` with their keys exported by another storage.
+    pub fn import(&mut self, slots: &[u16], entries: Vec<(GString, Value)>) -> usize {
+        for slot in slots {
+            self.moved.remove(slot);
+        }
+
+        let keys = entries.len();
+        for (key, value) in entries {
+            self.set(key, value);
+        }
+        keys
+    }
+
+    pub fn snapshot(&self) -> Vec<(GString, Value)> {
+        self.data
+            .iter()
+            .filter(|(_, entry)| !entry.value.is_expired())
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    /// Remove all keys, slots migrated away stay forwarded.
+    pub fn flush(&mut self) -> usize {
+        let keys = self.data.len();
+        self.data.clear();
+        self.keys.clear();
+        self.volatile.clear();
+        self.used = 0;
+        keys
+    }
+
+    /// Value of `key`, expired keys are treated as missing and removed on their next write.
+    pub fn get(&mut self, key: &GString) -> Option<Value> {
+        let now = self.clock();
+        let entry = self.data.get_mut(key).filter(|entry| !entry.value.is_expired())?;
+        entry.access.touch(now);
+        Some(entry.value.clone())
+    }
+
+    /// Value of `key` like [`Storage::get`], without counting as an access for eviction.
+    pub fn peek(&self, key: &GString) -> Option<Value> {
+        let entry = self.data.get(key).filter(|entry| !entry.value.is_expired())?;
+        Some(entry.value.clone())
+    }
+
+    /// Remove `key` if it expired, returning whether it did.
+    pub fn expire(&mut self, key: &GString) -> bool {
+        if !self.data.get(key).is_some_and(|entry| entry.value.is_expired()) {
+            return false;
+        }
+        self.take(key);
+        true
     }
 
     pub fn set(&mut self, key: GString, value: Value) -> Option<Value> {
-        self.data.insert(key, value)
+        let now = self.clock();
+        let previous = self.take(&key);
+        let mut access = previous.as_ref().map_or(Access::new(now), |entry| entry.access);
+        access.touch(now);
+        self.insert(key, value, access);
+        previous.map(|entry| entry.value)
     }
 
     pub fn delete(&mut self, key: &GString) -> Option<Value> {
-        self.data.remove(key)
+        self.take(key).map(|entry| entry.value).filter(|value| !value.is_expired())
     }
 
-    /// Update a key and return new value. Returns updated value.
+    /// Replace the value of `key` with the one `f` computes from it, returning the value before
+    /// and after the update.
     ///
-    /// Update function runs even if the key is not yet present.
-    pub fn update(
-        &mut self,
-        key: GString,
-        f: Arc<dyn Fn(Option<Value>) -> Option<Value> + Send + Sync>,
-    ) -> Option<Value> {
-        let entry = self.data.entry(key);
-
-        match entry {
-            Entry::Occupied(mut occupied_entry) => {
-                let updated = f(Some(occupied_entry.get().clone()));
-                match updated {
-                    Some(updated) => {
-                        *occupied_entry.get_mut() = updated.clone();
-                        Some(updated)
-                    }
-                    None => {
-                        occupied_entry.remove_entry();
-                        None
-                    }
-                }
-            }
-            Entry::Vacant(vacant_entry) => {
-                let updated = f(None);
-                match updated {
-                    Some(updated) => {
-                        vacant_entry.insert(updated.clone());
-                        Some(updated)
-                    }
-                    None => None,
-                }
+    /// Update function runs even if the key is not yet present, the key is left untouched if it
+    /// returns `None`.
+    pub fn update(&mut self, key: GString, f: UpdateFn) -> (Option<Value>, Option<Value>) {
+        let now = self.clock();
+        let previous = self.peek(&key);
+        let Some(updated) = f(previous.as_ref()) else {
+            return (previous, None);
+        };
+
+        let previous = self.take(&key).filter(|entry| !entry.value.is_expired());
+        let mut access = previous.as_ref().map_or(Access::new(now), |entry| entry.access);
+        access.touch(now);
+        self.insert(key, updated.clone(), access);
+        (previous.map(|entry| entry.value), Some(updated))
+    }
+
+    /// Evict keys picked by `policy` until at most `limit` bytes are used.
+    ///
+    /// Each key evicted is the best of `samples` keys picked at random. Returns the evicted keys,
+    /// fewer than needed if no key can be evicted under `policy`.
+    pub fn evict(&mut self, limit: usize, policy: EvictionPolicy, samples: usize) -> Vec<GString> {
+        let mut evicted = Vec::new();
+        while self.used > limit {
+            let Some(key) = self.sample(policy, samples) else {
+                break;
+            };
+            self.take(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+
+    /// Expired keys among `samples` keys with an expiration time picked at random.
+    pub fn sample_expired(&self, samples: usize) -> Vec<GString> {
+        if self.volatile.is_empty() {
+            return Vec::new();
+        }
+
+        let mut indices: Vec<_> =
+            (0..samples).map(|_| fastrand::usize(..self.volatile.len())).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|index| &self.volatile[index])
+            .filter(|key| self.data[*key].value.is_expired())
+            .cloned()
+            .collect()
+    }
+
+    fn sample(&self, policy: EvictionPolicy, samples: usize) -> Option<GString> {
+        let keys = if policy.is_volatile() { &self.volatile } else { &self.keys };
+        if keys.is_empty() || policy == EvictionPolicy::NoEviction {
+            return None;
+        }
+
+        let now = self.clock();
+        (0..samples.max(1))
+            .map(|_| &keys[fastrand::usize(..keys.len())])
+            .max_by_key(|key| {
+                let entry = &self.data[*key];
+                policy.rank(&entry.value, &entry.access, now)
+            })
+            .cloned()
+    }
+
+    fn clock(&self) -> u32 {
+        self.epoch.elapsed().as_millis() as u32
+    }
+
+    fn insert(&mut self, key: GString, value: Value, access: Access) {
+        self.used += entry_size(&key, &value);
+        self.keys.push(key.clone());
+        let index = self.keys.len() - 1;
+        let volatile = value.expires_at.map(|_| {
+            self.volatile.push(key.clone());
+            self.volatile.len() - 1
+        });
+        self.data.insert(key, Entry { value, access, index, volatile });
+    }
+
+    fn take(&mut self, key: &GString) -> Option<Entry> {
+        let entry = self.data.remove(key)?;
+        self.used -= entry_size(key, &entry.value);
+
+        self.keys.swap_remove(entry.index);
+        if let Some(moved) = self.keys.get(entry.index) {
+            self.data.get_mut(moved).expect("sampled key is stored").index = entry.index;
+        }
+        if let Some(index) = entry.volatile {
+            self.volatile.swap_remove(index);
+            if let Some(moved) = self.volatile.get(index) {
+                self.data.get_mut(moved).expect("sampled key is stored").volatile = Some(index);
             }
         }
+        Some(entry)
     }
 }
 
+fn entry_size(key: &GString, value: &Value) -> usize {
+    let data = match &value.data {
+        Data::String(data) => data.len(),
+        Data::Integer(_) => 0,
+    };
+    key.len() + data + ENTRY_OVERHEAD
+}
+
 impl Default for Storage {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn key(index: usize) -> GString {
+        GString::copy_from_slice(format!("key:{index}").as_bytes())
+    }
+
+    fn value(expires: bool) -> Value {
+        let expires_at = expires.then(|| Instant::now() + Duration::from_secs(60));
+        Value { expires_at, ..Value::new(Data::from_gstring(GString::from_static(b"value"))) }
+    }
+
+    #[test]
+    fn accounts_memory() {
+        let mut storage = Storage::new();
+        for index in 0..10 {
+            storage.set(key(index), value(index % 2 == 0));
+        }
+        let used = storage.used();
+        storage.set(key(0), value(false));
+        assert_eq!(storage.used(), used);
+
+        for index in 0..10 {
+            storage.delete(&key(index));
+        }
+        assert_eq!(storage.used(), 0);
+        assert!(storage.keys.is_empty() && storage.volatile.is_empty());
+    }
+
+    #[test]
+    fn evicts_by_policy() {
+        let mut storage = Storage::new();
+        for index in 0..100 {
+            storage.set(key(index), value(index < 10));
+        }
+        let limit = storage.used() / 2;
+
+        assert!(storage.evict(limit, EvictionPolicy::NoEviction, 5).is_empty());
+        let evicted = storage.evict(limit, EvictionPolicy::VolatileTtl, 5);
+        assert_eq!(evicted.len(), 10);
+        assert!(storage.volatile.is_empty());
+
+        let evicted = storage.evict(limit, EvictionPolicy::AllKeysLru, 5);
+        assert!(!evicted.is_empty() && storage.used() <= limit);
+        assert_eq!(storage.keys.len(), storage.data.len());
+    }
+
+    #[test]
+    fn samples_expired_keys() {
+        let mut storage = Storage::new();
+        assert!(storage.sample_expired(20).is_empty());
+
+        let expired = Value { expires_at: Some(Instant::now()), ..value(false) };
+        storage.set(key(0), expired);
+        storage.set(key(1), value(true));
+        storage.set(key(2), value(false));
+
+        let mut sampled = Vec::new();
+        while sampled.is_empty() {
+            sampled = storage.sample_expired(1);
+        }
+        assert_eq!(sampled, vec![key(0)]);
+        assert!(storage.expire(&key(0)));
+        assert!(storage.sample_expired(20).is_empty());
+    }
+}
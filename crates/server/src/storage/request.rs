@@ -1,16 +1,33 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::Instant,
+};
 
 use futures::channel::oneshot;
 use goosekv_protocol::data_type::GString;
 
-use crate::storage::{
-    response::{
-        DeleteResponse,
-        GetResponse,
-        SetResponse,
-        UpdateResponse,
+use crate::{
+    latency::LatencyEvent,
+    processor::clients::ClientFilter,
+    storage::{
+        response::{
+            ClientsResponse,
+            DeleteResponse,
+            FlushResponse,
+            GetResponse,
+            ImportResponse,
+            LatencyResponse,
+            MigrateResponse,
+            ModifyResponse,
+            OperationResponse,
+            SetResponse,
+            SlowLogResponse,
+            SnapshotResponse,
+            StatsResponse,
+            UpdateResponse,
+        },
+        value::Value,
     },
-    value::Value,
 };
 
 pub enum Request {
@@ -18,22 +35,148 @@ pub enum Request {
     Set(SetRequest, oneshot::Sender<SetResponse>),
     Delete(DeleteRequest, oneshot::Sender<DeleteResponse>),
     Update(UpdateRequest, oneshot::Sender<UpdateResponse>),
+    Modify(ModifyRequest, oneshot::Sender<ModifyResponse>),
+    Batch(Vec<Operation>, oneshot::Sender<Vec<OperationResponse>>),
+    Migrate(MigrateRequest, oneshot::Sender<MigrateResponse>),
+    Import(ImportRequest, oneshot::Sender<ImportResponse>),
+    Snapshot(SnapshotRequest, oneshot::Sender<SnapshotResponse>),
+    Flush(FlushRequest, oneshot::Sender<FlushResponse>),
+    Stats(StatsRequest, oneshot::Sender<StatsResponse>),
+    SlowLog(SlowLogRequest, oneshot::Sender<SlowLogResponse>),
+    Latency(LatencyRequest, oneshot::Sender<LatencyResponse>),
+    Clients(ClientsRequest, oneshot::Sender<ClientsResponse>),
+}
+
+impl Request {
+    /// Key the request operates on, `None` for requests spanning many keys.
+    pub fn key(&self) -> Option<&GString> {
+        match self {
+            Request::Get(request, _) => Some(&request.key),
+            Request::Set(request, _) => Some(&request.key),
+            Request::Delete(request, _) => Some(&request.key),
+            Request::Update(request, _) => Some(&request.key),
+            Request::Modify(request, _) => Some(&request.key),
+            Request::Batch(..)
+            | Request::Migrate(..)
+            | Request::Import(..)
+            | Request::Snapshot(..)
+            | Request::Flush(..)
+            | Request::Stats(..)
+            | Request::SlowLog(..)
+            | Request::Latency(..)
+            | Request::Clients(..) => None,
+        }
+    }
+}
+
+/// Single operation of a batch, executed in order by one storage actor.
+pub enum Operation {
+    Get(GetRequest),
+    Set(SetRequest),
+    Delete(DeleteRequest),
+    Update(UpdateRequest),
+}
+
+impl Operation {
+    pub fn key(&self) -> &GString {
+        match self {
+            Operation::Get(request) => &request.key,
+            Operation::Set(request) => &request.key,
+            Operation::Delete(request) => &request.key,
+            Operation::Update(request) => &request.key,
+        }
+    }
 }
 
 pub struct GetRequest {
     pub key: GString,
+    /// Count the read as an access of the key for eviction, unless the client set `NO-TOUCH`.
+    pub touch: bool,
+    /// Client to invalidate the key for once it changes, if it tracks the keys it reads.
+    pub tracking: Option<u64>,
 }
 
+// Writes carry the client sending them, which is not told about its own writes with `NOLOOP`.
+
 pub struct SetRequest {
     pub key: GString,
     pub value: Value,
+    pub client: Option<u64>,
 }
 
 pub struct DeleteRequest {
     pub key: GString,
+    pub client: Option<u64>,
 }
 
 pub struct UpdateRequest {
     pub key: GString,
-    pub f: Arc<dyn Fn(Option<Value>) -> Option<Value> + Send + Sync>,
+    pub f: UpdateFn,
+    pub client: Option<u64>,
+}
+
+/// New value of a key computed from the current one, `None` leaves the key untouched.
+pub type UpdateFn = Arc<dyn Fn(Option<&Value>) -> Option<Value> + Send + Sync>;
+
+/// Conditionally change a key, `f` decides from the current value what to do with it.
+pub struct ModifyRequest {
+    pub key: GString,
+    pub f: ModifyFn,
+}
+
+pub type ModifyFn = Arc<dyn Fn(Option<&Value>) -> Modification + Send + Sync>;
+
+pub enum Modification {
+    Keep,
+    /// Store the value, its CAS token is assigned by the storage.
+    Set(Value),
+    /// Change when the key expires, keeping its data and CAS token.
+    Touch(Option<Instant>),
+    Delete,
+}
+
+/// Move all keys of `slots` to shard `to`.
+pub struct MigrateRequest {
+    pub slots: Vec<u16>,
+    pub to: usize,
+}
+
+/// Keys of `slots` migrated from another shard, which become owned by the receiving shard.
+pub struct ImportRequest {
+    pub slots: Vec<u16>,
+    pub entries: Vec<(GString, Value)>,
+}
+
+/// Copy all keys of the storage, for the full sync of a replica.
+pub struct SnapshotRequest;
+
+/// Remove all keys of the storage, before loading the snapshot of a primary.
+pub struct FlushRequest;
+
+/// Collect the statistics of the shard, for `INFO`.
+pub struct StatsRequest;
+
+/// Read or clear the slow log of the shard.
+#[derive(Debug, Clone, Copy)]
+pub enum SlowLogRequest {
+    /// Newest entries, up to the given number.
+    Get(usize),
+    Len,
+    Reset,
+}
+
+/// Read or clear the samples of the latency monitor of the shard.
+#[derive(Debug, Clone)]
+pub enum LatencyRequest {
+    Events,
+    /// Forget the samples of the given events, of every event if empty.
+    Reset(Vec<LatencyEvent>),
+}
+
+/// List or kill the clients of the shard.
+#[derive(Debug, Clone)]
+pub enum ClientsRequest {
+    List(ClientFilter),
+    /// Close the connections of the matching clients once their current command is answered.
+    Kill(ClientFilter),
 }
@@ -1,4 +1,34 @@
-use crate::storage::value::Value;
+use std::collections::BTreeMap;
+
+use goosekv_protocol::{
+    data_type::GString,
+    error::ReplyError,
+};
+use thiserror::Error;
+
+use crate::{
+    latency::{
+        EventHistory,
+        LatencyEvent,
+    },
+    processor::clients::ClientInfo,
+    slowlog::SlowLogEntry,
+    stats::Counters,
+    storage::value::Value,
+};
+
+/// The shard a request was sent to stopped before answering it, as the process shuts down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("shard {0} stopped before answering")]
+pub struct StorageError(pub usize);
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+impl From<StorageError> for ReplyError {
+    fn from(error: StorageError) -> Self {
+        ReplyError::Err(error.to_string())
+    }
+}
 
 #[derive(Debug)]
 pub struct GetResponse {
@@ -17,5 +47,77 @@ pub struct DeleteResponse {
 
 #[derive(Debug)]
 pub struct UpdateResponse {
+    pub previous: Option<Value>,
+    /// `None` if the key was left untouched.
     pub updated: Option<Value>,
 }
+
+#[derive(Debug)]
+pub struct ModifyResponse {
+    pub previous: Option<Value>,
+    pub current: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct MigrateResponse {
+    pub keys: usize,
+}
+
+#[derive(Debug)]
+pub struct ImportResponse {
+    pub keys: usize,
+}
+
+#[derive(Debug)]
+pub struct SnapshotResponse {
+    pub entries: Vec<(GString, Value)>,
+}
+
+#[derive(Debug)]
+pub struct FlushResponse {
+    pub keys: usize,
+}
+
+#[derive(Debug)]
+pub struct StatsResponse {
+    pub counters: Counters,
+    pub keys: usize,
+    /// Keys with an expiration time.
+    pub expires: usize,
+    /// Connections currently served by the shard.
+    pub connected: usize,
+    /// Keys read by tracking clients.
+    pub tracked_keys: usize,
+    /// Pairs of a tracked key and a client that read it.
+    pub tracked_items: usize,
+}
+
+#[derive(Debug)]
+pub struct SlowLogResponse {
+    /// Entries asked for by `SlowLogRequest::Get`, newest first.
+    pub entries: Vec<SlowLogEntry>,
+    /// Entries in the slow log, before a reset.
+    pub len: usize,
+}
+
+#[derive(Debug)]
+pub struct LatencyResponse {
+    /// Samples of every event sampled so far, empty for `LatencyRequest::Reset`.
+    pub events: BTreeMap<LatencyEvent, EventHistory>,
+    /// Events whose samples were reset.
+    pub reset: Vec<LatencyEvent>,
+}
+
+#[derive(Debug)]
+pub struct ClientsResponse {
+    /// Clients listed or killed.
+    pub clients: Vec<ClientInfo>,
+}
+
+#[derive(Debug)]
+pub enum OperationResponse {
+    Get(GetResponse),
+    Set(SetResponse),
+    Delete(DeleteResponse),
+    Update(UpdateResponse),
+}
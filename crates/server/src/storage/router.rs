@@ -1,52 +1,659 @@
-use std::hash::{
-    DefaultHasher,
-    Hash,
-    Hasher,
+use std::{
+    collections::BTreeMap,
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
 };
 
-use crate::storage::{
-    handle::StorageHandle,
-    request::{
-        DeleteRequest,
-        GetRequest,
-        SetRequest,
-        UpdateRequest,
+use futures::{
+    channel::oneshot,
+    future::join_all,
+};
+use goosekv_protocol::{
+    data_type::GString,
+    error::ReplyError,
+};
+use thiserror::Error;
+
+use crate::{
+    acl::Acl,
+    cluster::Cluster,
+    config::LocalConfig,
+    latency::{
+        LatencyEvent,
+        LatencyMonitor,
+    },
+    processor::clients::{
+        Clients,
+        ShardClients,
+    },
+    replication::Replication,
+    shutdown::Shutdown,
+    slot::{
+        SLOT_COUNT,
+        SlotTable,
+        key_slot,
     },
-    response::{
-        DeleteResponse,
-        GetResponse,
-        SetResponse,
-        UpdateResponse,
+    slowlog::SlowLog,
+    stats::ShardStats,
+    storage::{
+        handle::StorageHandle,
+        local::LocalStorage,
+        memory::{
+            EvictionPolicy,
+            MemoryUsage,
+        },
+        mesh::LaneDepths,
+        request::{
+            ClientsRequest,
+            DeleteRequest,
+            FlushRequest,
+            GetRequest,
+            LatencyRequest,
+            MigrateRequest,
+            ModifyRequest,
+            Operation,
+            Request,
+            SetRequest,
+            SlowLogRequest,
+            SnapshotRequest,
+            StatsRequest,
+            UpdateRequest,
+        },
+        response::{
+            ClientsResponse,
+            DeleteResponse,
+            GetResponse,
+            LatencyResponse,
+            ModifyResponse,
+            OperationResponse,
+            SetResponse,
+            SlowLogResponse,
+            StatsResponse,
+            StorageError,
+            StorageResult,
+            UpdateResponse,
+        },
+        value::Value,
     },
+    tls::Tls,
 };
 
+/// Sends each request to the shard owning the hash slot of its key.
+///
+/// Keys sharing a `{hashtag}` are always owned by one shard, so a [`StorageRouter::batch`] of
+/// operations on them is applied atomically.
 pub struct StorageRouter {
-    handles: Box<[StorageHandle]>,
+    handle: Rc<StorageHandle>,
+    local_index: usize,
+    local: LocalStorage,
+    cluster: Option<Arc<Cluster>>,
+    clients: Arc<Clients>,
+    shutdown: Arc<Shutdown>,
+    acl: Arc<Acl>,
+    tls: Arc<Tls>,
 }
 
-macro_rules! route {
-    ($method:ident, $request:ty, $response:ty) => {
-        pub async fn $method(&self, request: $request) -> $response {
-            let mut hasher = DefaultHasher::default();
-            request.key.hash(&mut hasher);
-            let hash = hasher.finish();
+/// How a command accesses its keys, deciding whether it is served in cluster mode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyAccess {
+    /// The command only reads keys.
+    pub readonly: bool,
+    /// The connection sent `READONLY` and accepts reads served by a replica.
+    pub replica_reads: bool,
+    /// The connection sent `ASKING` right before this command.
+    pub asking: bool,
+}
 
-            let route = hash as usize % self.handles.len();
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReshardError {
+    #[error("shard count must be between 1 and {0}")]
+    ShardCount(usize),
+    #[error("reshard already in progress")]
+    InProgress,
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
 
-            self.handles[route].$method(request).await
+macro_rules! route {
+    ($method:ident, $variant:ident, $request:ty, $response:ty) => {
+        pub async fn $method(&self, request: $request) -> StorageResult<$response> {
+            let route = self.route(&request.key);
+
+            if route == self.local_index && self.local.moved_to(&request.key).is_none() {
+                self.shard_stats().counters().local_requests += 1;
+                Ok(self.local.$method(request))
+            } else if route == self.local_index {
+                // Migrated while the slot table still points here, forwarded behind its keys.
+                self.shard_stats().counters().remote_requests += 1;
+                self.local_request(|respond| Request::$variant(request, respond)).await
+            } else {
+                self.shard_stats().counters().remote_requests += 1;
+                self.round_trip(self.handle.$method(route, request)).await
+            }
         }
     };
 }
 
 impl StorageRouter {
-    route!(get, GetRequest, GetResponse);
-    route!(set, SetRequest, SetResponse);
-    route!(delete, DeleteRequest, DeleteResponse);
-    route!(update, UpdateRequest, UpdateResponse);
+    route!(get, Get, GetRequest, GetResponse);
+    route!(set, Set, SetRequest, SetResponse);
+    route!(delete, Delete, DeleteRequest, DeleteResponse);
+    route!(update, Update, UpdateRequest, UpdateResponse);
+    route!(modify, Modify, ModifyRequest, ModifyResponse);
 }
 
 impl StorageRouter {
-    pub fn new(handles: Box<[StorageHandle]>) -> Self {
-        Self { handles }
+    /// Create a router for the shard `handle` sends from, whose storage is accessed directly.
+    pub fn new(handle: Rc<StorageHandle>, local: LocalStorage) -> Self {
+        let local_index = handle.shard();
+        Self {
+            handle,
+            local_index,
+            local,
+            cluster: None,
+            clients: Default::default(),
+            shutdown: Default::default(),
+            acl: Default::default(),
+            tls: Default::default(),
+        }
+    }
+
+    /// Count connections in `clients` shared by every shard.
+    pub fn with_clients(mut self, clients: Arc<Clients>) -> Self {
+        self.clients = clients;
+        self
+    }
+
+    /// Follow the shutdown of the process in `shutdown` shared by every shard.
+    pub fn with_shutdown(mut self, shutdown: Arc<Shutdown>) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Authenticate connections against the users of `acl` shared by every shard.
+    pub fn with_acl(mut self, acl: Arc<Acl>) -> Self {
+        self.acl = acl;
+        self
+    }
+
+    /// Accept TLS connections with the settings of `tls` shared by every shard.
+    pub fn with_tls(mut self, tls: Arc<Tls>) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Configuration as seen by the current shard, its storage reads the same copy.
+    pub fn config(&self) -> &LocalConfig {
+        self.local.config()
+    }
+
+    pub fn clients(&self) -> &Arc<Clients> {
+        &self.clients
+    }
+
+    pub fn shutdown(&self) -> &Arc<Shutdown> {
+        &self.shutdown
+    }
+
+    pub fn acl(&self) -> &Arc<Acl> {
+        &self.acl
+    }
+
+    pub fn tls(&self) -> &Arc<Tls> {
+        &self.tls
+    }
+
+    /// Index of the shard this router sends from.
+    pub fn shard(&self) -> usize {
+        self.local_index
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.handle.shard_count()
+    }
+
+    /// Serve only the slots owned by this node of `cluster`, redirecting clients for the others.
+    pub fn with_cluster(mut self, cluster: Arc<Cluster>) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    pub fn cluster(&self) -> Option<&Cluster> {
+        self.cluster.as_deref()
+    }
+
+    pub fn replication(&self) -> &Arc<Replication> {
+        self.local.replication()
+    }
+
+    /// Check that the keys of a command can be served by this node in cluster mode.
+    ///
+    /// Returns the `MOVED` or `ASK` redirection to send instead, or an error if the keys span
+    /// several slots.
+    pub async fn redirect(&self, keys: &[GString], access: KeyAccess) -> Result<(), ReplyError> {
+        let (Some(cluster), Some(first)) = (self.cluster(), keys.first()) else {
+            return Ok(());
+        };
+
+        let slot = key_slot(first.as_ref());
+        if keys.iter().any(|key| key_slot(key.as_ref()) != slot) {
+            return Err(ReplyError::CrossSlot);
+        }
+
+        let owner = cluster
+            .owner(slot)
+            .ok_or_else(|| ReplyError::ClusterDown("Hash slot not served".to_string()))?;
+        let myself = cluster.myself();
+
+        if owner.id == myself.id {
+            // Keys of a migrating slot missing here may already be on the target node.
+            if let Some(target) = cluster.migrating(slot) {
+                let operations = keys
+                    .iter()
+                    .map(|key| {
+                        Operation::Get(GetRequest {
+                            key: key.clone(),
+                            touch: false,
+                            tracking: None,
+                        })
+                    })
+                    .collect();
+                let missing = self.batch(operations).await?.into_iter().any(|response| {
+                    matches!(response, OperationResponse::Get(GetResponse { value: None }))
+                });
+                if missing {
+                    return Err(ReplyError::Ask { slot, addr: target.addr.to_string() });
+                }
+            }
+            return Ok(());
+        }
+
+        let importing = access.asking && cluster.importing(slot).is_some();
+        let replica_read =
+            access.readonly && access.replica_reads && myself.primary.as_ref() == Some(&owner.id);
+        if importing || replica_read {
+            return Ok(());
+        }
+
+        Err(ReplyError::Moved { slot, addr: owner.addr.to_string() })
+    }
+
+    /// Execute operations on any keys with one message per destination shard.
+    ///
+    /// Operations sent to one shard are applied together without interleaving other requests.
+    /// Responses are returned in the order of `operations`.
+    pub async fn batch(&self, operations: Vec<Operation>) -> StorageResult<Vec<OperationResponse>> {
+        let count = operations.len();
+
+        let mut routed: Vec<(Vec<usize>, Vec<Operation>)> =
+            (0..self.handle.shard_count()).map(|_| Default::default()).collect();
+        for (index, operation) in operations.into_iter().enumerate() {
+            let (indices, operations) = &mut routed[self.route(operation.key())];
+            indices.push(index);
+            operations.push(operation);
+        }
+
+        let tasks =
+            routed.into_iter().enumerate().filter(|(_, (indices, _))| !indices.is_empty()).map(
+                |(route, (indices, operations))| async move {
+                    let moved = operations
+                        .iter()
+                        .any(|operation| self.local.moved_to(operation.key()).is_some());
+                    let responses = if route == self.local_index && !moved {
+                        self.shard_stats().counters().local_requests += operations.len() as u64;
+                        Ok(self.local.batch(operations))
+                    } else if route == self.local_index {
+                        self.shard_stats().counters().remote_requests += operations.len() as u64;
+                        self.local_request(|respond| Request::Batch(operations, respond)).await
+                    } else {
+                        self.shard_stats().counters().remote_requests += operations.len() as u64;
+                        self.round_trip(self.handle.batch(route, operations)).await
+                    };
+                    Ok(indices.into_iter().zip(responses?))
+                },
+            );
+
+        let mut responses: Vec<Option<OperationResponse>> = (0..count).map(|_| None).collect();
+        for routed in join_all(tasks).await {
+            for (index, response) in routed? {
+                responses[index] = Some(response);
+            }
+        }
+
+        Ok(responses
+            .into_iter()
+            .map(|response| response.expect("every operation is answered"))
+            .collect())
+    }
+
+    /// Statistics of the current shard.
+    pub fn shard_stats(&self) -> &Rc<ShardStats> {
+        self.local.stats()
+    }
+
+    /// Slow log of the current shard.
+    pub fn slowlog(&self) -> &Rc<SlowLog> {
+        self.local.slowlog()
+    }
+
+    /// Latency monitor of the current shard.
+    pub fn latency(&self) -> &Rc<LatencyMonitor> {
+        self.local.latency()
+    }
+
+    /// Clients served by the current shard.
+    pub fn shard_clients(&self) -> &Rc<ShardClients> {
+        self.local.clients()
+    }
+
+    pub fn memory(&self) -> &MemoryUsage {
+        self.handle.memory()
+    }
+
+    /// Whether the keys of every shard use more than `maxmemory`, writes adding data are then
+    /// rejected.
+    ///
+    /// Unless the policy is `noeviction`, shards evict keys after each write to fit their share,
+    /// so writes are only rejected once a shard could not evict enough keys.
+    pub fn out_of_memory(&self) -> bool {
+        let (maxmemory, policy) = {
+            let config = self.config().read();
+            (config.maxmemory.0 as usize, config.maxmemory_policy)
+        };
+        let memory = self.memory();
+        maxmemory > 0
+            && !self.replication().is_replica()
+            && memory.used() > maxmemory
+            && (policy == EvictionPolicy::NoEviction || memory.exhausted())
+    }
+
+    /// Requests queued on the lanes between shards.
+    pub fn lane_depths(&self) -> &LaneDepths {
+        self.handle.lane_depths()
+    }
+
+    /// Migrate slots between shards so the keyspace is evenly split over the first `shards`.
+    ///
+    /// Requests keep being served meanwhile, those reaching the old owner of a slot after its keys
+    /// were migrated are forwarded to the new one. Returns the number of keys moved.
+    pub async fn reshard(&self, shards: usize) -> Result<usize, ReshardError> {
+        let max = self.handle.shard_count();
+        if shards == 0 || shards > max {
+            return Err(ReshardError::ShardCount(max));
+        }
+
+        let slots = self.slots();
+        if !slots.begin_reshard() {
+            return Err(ReshardError::InProgress);
+        }
+
+        let mut moves: BTreeMap<(usize, usize), Vec<u16>> = BTreeMap::new();
+        for slot in 0..SLOT_COUNT as u16 {
+            let (from, to) = (slots.shard(slot), SlotTable::owner(slot, shards));
+            if from != to {
+                moves.entry((from, to)).or_default().push(slot);
+            }
+        }
+
+        let tasks = moves.into_iter().map(|((from, to), moved)| async move {
+            let request = MigrateRequest { slots: moved.clone(), to };
+            let response = if from == self.local_index {
+                self.local_request(|respond| Request::Migrate(request, respond)).await
+            } else {
+                self.handle.migrate(from, request).await
+            }?;
+
+            for slot in moved {
+                slots.assign(slot, to);
+            }
+            Ok(response.keys)
+        });
+        let keys = join_all(tasks).await.into_iter().sum::<StorageResult<usize>>();
+
+        slots.end_reshard(shards);
+        Ok(keys?)
+    }
+
+    /// Copy the keys of every shard, each shard being copied at once.
+    pub async fn snapshot(&self) -> StorageResult<Vec<(GString, Value)>> {
+        let started = Instant::now();
+        let tasks = (0..self.handle.shard_count()).map(|shard| async move {
+            if shard == self.local_index {
+                Ok(self.local.snapshot(SnapshotRequest))
+            } else {
+                self.handle.snapshot(shard, SnapshotRequest).await
+            }
+        });
+
+        let mut entries = Vec::new();
+        for response in join_all(tasks).await {
+            entries.extend(response?.entries);
+        }
+        self.latency().record(LatencyEvent::Snapshot, started.elapsed());
+        Ok(entries)
+    }
+
+    /// Remove the keys of every shard, returning how many were removed.
+    ///
+    /// Tracking clients are told once that every key was invalidated.
+    pub async fn flush(&self) -> StorageResult<usize> {
+        self.clients().trackers().invalidate_all();
+        let tasks = (0..self.handle.shard_count()).map(|shard| async move {
+            if shard == self.local_index {
+                Ok(self.local.flush(FlushRequest))
+            } else {
+                self.handle.flush(shard, FlushRequest).await
+            }
+        });
+
+        join_all(tasks).await.into_iter().map(|response| Ok(response?.keys)).sum()
+    }
+
+    /// Collect the statistics of every shard, in shard order.
+    pub async fn collect_stats(&self) -> StorageResult<Vec<StatsResponse>> {
+        let tasks = (0..self.handle.shard_count()).map(|shard| async move {
+            if shard == self.local_index {
+                Ok(self.local.collect_stats(StatsRequest))
+            } else {
+                self.handle.stats(shard, StatsRequest).await
+            }
+        });
+
+        join_all(tasks).await.into_iter().collect()
+    }
+
+    /// Read or clear the slow log of every shard, in shard order.
+    pub async fn collect_slowlog(
+        &self,
+        request: SlowLogRequest,
+    ) -> StorageResult<Vec<SlowLogResponse>> {
+        let tasks = (0..self.handle.shard_count()).map(|shard| async move {
+            if shard == self.local_index {
+                Ok(self.local.read_slowlog(request))
+            } else {
+                self.handle.slowlog(shard, request).await
+            }
+        });
+
+        join_all(tasks).await.into_iter().collect()
+    }
+
+    /// Read or clear the samples of the latency monitor of every shard, in shard order.
+    pub async fn collect_latency(
+        &self,
+        request: LatencyRequest,
+    ) -> StorageResult<Vec<LatencyResponse>> {
+        let tasks = (0..self.handle.shard_count()).map(|shard| {
+            let request = request.clone();
+            async move {
+                if shard == self.local_index {
+                    Ok(self.local.read_latency(request))
+                } else {
+                    self.handle.latency(shard, request).await
+                }
+            }
+        });
+
+        join_all(tasks).await.into_iter().collect()
+    }
+
+    /// List or kill the clients of every shard, in shard order.
+    pub async fn collect_clients(
+        &self,
+        request: ClientsRequest,
+    ) -> StorageResult<Vec<ClientsResponse>> {
+        let tasks = (0..self.handle.shard_count()).map(|shard| {
+            let request = request.clone();
+            async move {
+                if shard == self.local_index {
+                    Ok(self.local.read_clients(request))
+                } else {
+                    self.handle.clients(shard, request).await
+                }
+            }
+        });
+
+        join_all(tasks).await.into_iter().collect()
+    }
+
+    pub fn slots(&self) -> &SlotTable {
+        self.handle.slots()
+    }
+
+    fn route(&self, key: &GString) -> usize {
+        self.slots().key_shard(key.as_ref())
+    }
+
+    /// Await the response of another shard, sampling how long it took.
+    async fn round_trip<R>(&self, response: impl Future<Output = R>) -> R {
+        let started = Instant::now();
+        let response = response.await;
+        self.latency().record(LatencyEvent::StorageRoundTrip, started.elapsed());
+        response
+    }
+
+    /// Pass a request through the local storage, which forwards it if its slot was migrated.
+    async fn local_request<R>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<R>) -> Request,
+    ) -> StorageResult<R> {
+        let (sender, receiver) = oneshot::channel();
+        self.local.handle(request(sender));
+        receiver.await.map_err(|_| StorageError(self.local_index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glommio::{
+        LocalExecutor,
+        LocalExecutorBuilder,
+    };
+
+    use super::*;
+    use crate::storage::{
+        actor::StorageActor,
+        mesh::StorageMesh,
+        value::{
+            Data,
+            Value,
+        },
+    };
+
+    const SHARDS: usize = 3;
+
+    /// Start the other shards on their own executors and return a router for `executor`.
+    fn router(executor: &LocalExecutor) -> StorageRouter {
+        let mesh = StorageMesh::new(SHARDS, 4);
+
+        for _ in 1..SHARDS {
+            let mesh = mesh.clone();
+            LocalExecutorBuilder::default()
+                .spawn(move || async move {
+                    let (handle, lanes) = mesh.join().await;
+                    let (_, task) = StorageActor::new(
+                        Default::default(),
+                        Default::default(),
+                        Default::default(),
+                    )
+                    .run(lanes, handle);
+                    task.await;
+                })
+                .unwrap();
+        }
+
+        executor.run(async {
+            let (handle, lanes) = mesh.join().await;
+            let (local, task) =
+                StorageActor::new(Default::default(), Default::default(), Default::default())
+                    .run(lanes, handle.clone());
+            glommio::spawn_local(task).detach();
+            StorageRouter::new(handle, local)
+        })
+    }
+
+    fn key(i: usize) -> GString {
+        GString::copy_from_slice(format!("key:{i}").as_bytes())
+    }
+
+    #[test]
+    fn reshard_keeps_keys() {
+        let executor = LocalExecutor::default();
+        let router = router(&executor);
+
+        executor.run(async {
+            for i in 0..100 {
+                let value = Value::new(Data::from_gstring(key(i)));
+                router.set(SetRequest { key: key(i), value, client: None }).await.unwrap();
+            }
+
+            // Writes racing with the migration must not be lost.
+            let writes = async {
+                for i in 100..200 {
+                    let value = Value::new(Data::from_gstring(key(i)));
+                    router.set(SetRequest { key: key(i), value, client: None }).await.unwrap();
+                }
+            };
+            let (moved, _) = futures::join!(router.reshard(1), writes);
+            assert!(moved.unwrap() > 0);
+            assert!((0..SLOT_COUNT as u16).all(|slot| router.slots().shard(slot) == 0));
+            assert_eq!(router.slots().active_shards(), 1);
+
+            assert!(router.reshard(SHARDS).await.unwrap() > 0);
+            for i in 0..200 {
+                let value = router
+                    .get(GetRequest { key: key(i), touch: true, tracking: None })
+                    .await
+                    .unwrap()
+                    .value;
+                assert_eq!(value.map(|value| value.data.to_gstring()), Some(key(i)));
+            }
+
+            assert_eq!(router.reshard(0).await, Err(ReshardError::ShardCount(SHARDS)));
+        });
+    }
+
+    #[test]
+    fn cluster_redirect() {
+        let nodes = "a 127.0.0.1:7000 master - 0-8000\nb 127.0.0.1:7001 master - 8001-16383";
+        let cluster = Cluster::parse(nodes, "127.0.0.1:7000".parse().unwrap()).unwrap();
+        let executor = LocalExecutor::default();
+        let router = router(&executor).with_cluster(Arc::new(cluster));
+
+        executor.run(async {
+            let access = KeyAccess::default();
+            assert_eq!(router.redirect(&[GString::from_static(b"bar")], access).await, Ok(()));
+            assert_eq!(
+                router.redirect(&[GString::from_static(b"foo")], access).await,
+                Err(ReplyError::Moved { slot: 12182, addr: "127.0.0.1:7001".to_string() })
+            );
+            assert_eq!(
+                router
+                    .redirect(&[GString::from_static(b"foo"), GString::from_static(b"bar")], access)
+                    .await,
+                Err(ReplyError::CrossSlot)
+            );
+        });
     }
 }
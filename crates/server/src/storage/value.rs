@@ -1,3 +1,10 @@
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
 use bytes::Bytes;
 use goosekv_protocol::data_type::{
     GInteger,
@@ -7,6 +14,38 @@ use goosekv_protocol::data_type::{
 #[derive(Debug, Clone)]
 pub struct Value {
     pub data: Data,
+    /// Opaque flags stored along the data by memcached clients.
+    pub flags: u32,
+    /// Token changed by every write, compared by memcached `cas`. Assigned by the storage.
+    pub cas: u64,
+    /// The key is treated as missing from this instant on.
+    pub expires_at: Option<Instant>,
+}
+
+impl Value {
+    pub fn new(data: Data) -> Self {
+        Self { data, flags: 0, cas: 0, expires_at: None }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Instant::now())
+    }
+
+    /// Unix time in milliseconds the key expires at, as replicated with `REPLSET`.
+    pub fn expires_at_unix_ms(&self) -> Option<u64> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.expires_at.map(|expires_at| {
+            let left = expires_at.saturating_duration_since(Instant::now());
+            // `REPLSET` takes `0` for no expiry.
+            ((now + left).as_millis() as u64).max(1)
+        })
+    }
+}
+
+/// Instant of a unix time in milliseconds, already passed if it is in the past.
+pub fn instant_from_unix_ms(unix_ms: u64) -> Instant {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    Instant::now() + Duration::from_millis(unix_ms).saturating_sub(now)
 }
 
 #[derive(Debug, Clone)]
@@ -16,6 +55,8 @@ pub enum Data {
 }
 
 impl Data {
+    /// Store `data` as an integer if it is the canonical representation of one, so that it is
+    /// read back unchanged.
     pub fn from_gstring(data: GString) -> Self {
         if let Ok(ginteger) = ginteger_from_gstring(data.clone()) {
             return Self::Integer(ginteger);
@@ -40,8 +81,38 @@ impl Data {
 }
 
 fn ginteger_from_gstring(data: GString) -> Result<GInteger, ()> {
-    println!("{data:?}");
     let bytes = data.bytes();
     let utf8 = str::from_utf8(&bytes).map_err(|_| ())?;
-    utf8.parse().map_err(|_| ())
+    let ginteger: GInteger = utf8.parse().map_err(|_| ())?;
+    if ginteger.value().to_string() != utf8 {
+        return Err(());
+    }
+
+    Ok(ginteger)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_expiry_to_unix_time() {
+        let expires_at = Some(Instant::now() + Duration::from_secs(60));
+        let value = Value { expires_at, ..Value::new(Data::Integer(GInteger::new(1))) };
+        let unix_ms = value.expires_at_unix_ms().unwrap();
+        let left = instant_from_unix_ms(unix_ms).saturating_duration_since(Instant::now());
+        assert!(left > Duration::from_secs(59) && left <= Duration::from_secs(60));
+
+        assert!(instant_from_unix_ms(1) <= Instant::now());
+    }
+
+    #[test]
+    fn keeps_representation() {
+        let data = |value: &'static [u8]| Data::from_gstring(GString::from_static(value));
+
+        assert!(matches!(data(b"-42"), Data::Integer(_)));
+        assert!(matches!(data(b"007"), Data::String(_)));
+        assert!(matches!(data(b"+1"), Data::String(_)));
+        assert_eq!(data(b"007").to_gstring(), GString::from_static(b"007"));
+    }
 }
@@ -0,0 +1,411 @@
+use std::{
+    io,
+    pin::{
+        Pin,
+        pin,
+    },
+    sync::{
+        Arc,
+        RwLock,
+    },
+    task::{
+        Context,
+        Poll,
+    },
+    time::Duration,
+};
+
+use futures::{
+    AsyncRead,
+    AsyncWrite,
+    future::{
+        Either,
+        select,
+    },
+};
+use futures_rustls::{
+    TlsAcceptor,
+    pki_types::{
+        CertificateDer,
+        PrivateKeyDer,
+        pem::{
+            self,
+            PemObject,
+        },
+    },
+    rustls::{
+        self,
+        RootCertStore,
+        ServerConfig,
+        crypto::ring,
+        server::{
+            VerifierBuilderError,
+            WebPkiClientVerifier,
+        },
+    },
+    server::TlsStream,
+};
+use glommio::{
+    net::TcpStream,
+    timer::sleep,
+};
+use thiserror::Error;
+use x509_parser::{
+    certificate::X509Certificate,
+    prelude::FromDer,
+};
+
+use crate::{
+    acl::Acl,
+    config::Config,
+};
+
+/// Time a client has to complete the TLS handshake before it is disconnected.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("failed to load {path}: {error}")]
+    File { path: String, error: pem::Error },
+    #[error("tls-ca-cert-file is required to authenticate clients, or set tls-auth-clients no")]
+    NoCaCert,
+    #[error("{0}")]
+    Rustls(#[from] rustls::Error),
+    #[error("{0}")]
+    Verifier(#[from] VerifierBuilderError),
+}
+
+/// Whether clients of the TLS port must present a certificate signed by `tls-ca-cert-file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsAuthClients {
+    Yes,
+    No,
+    /// A certificate is verified if presented, but not required.
+    Optional,
+}
+
+impl TlsAuthClients {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TlsAuthClients::Yes => "yes",
+            TlsAuthClients::No => "no",
+            TlsAuthClients::Optional => "optional",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [TlsAuthClients::Yes, TlsAuthClients::No, TlsAuthClients::Optional]
+            .into_iter()
+            .find(|auth| auth.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Field of a client certificate naming the ACL user the client is authenticated as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateUser {
+    Off,
+    /// Subject common name.
+    Cn,
+}
+
+impl CertificateUser {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CertificateUser::Off => "off",
+            CertificateUser::Cn => "CN",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [CertificateUser::Off, CertificateUser::Cn]
+            .into_iter()
+            .find(|field| field.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+/// TLS settings of the listener shared by every shard.
+///
+/// `CONFIG SET` of a `tls-*` parameter loads the certificates again, connections already
+/// established keep the settings they were accepted with.
+#[derive(Debug, Default)]
+pub struct Tls {
+    config: RwLock<Option<Arc<ServerConfig>>>,
+}
+
+/// Certificates loaded by [`Tls::load`], accepting connections once passed to [`Tls::set`].
+#[derive(Debug, Clone)]
+pub struct TlsSettings(Option<Arc<ServerConfig>>);
+
+impl Tls {
+    /// Load the certificates named by `config`, none if the TLS port is disabled.
+    pub fn new(config: &Config) -> Result<Self, TlsError> {
+        let tls = Tls::default();
+        tls.set(Tls::load(config)?);
+        Ok(tls)
+    }
+
+    /// Load the certificates named by `config` without touching the current ones.
+    pub fn load(config: &Config) -> Result<TlsSettings, TlsError> {
+        match config.tls_port {
+            0 => Ok(TlsSettings(None)),
+            _ => Ok(TlsSettings(Some(Arc::new(server_config(config)?)))),
+        }
+    }
+
+    /// Accept new connections with `settings`.
+    pub fn set(&self, settings: TlsSettings) {
+        *self.config.write().unwrap() = settings.0;
+    }
+
+    /// Acceptor of new connections with the current settings, none if TLS is disabled.
+    pub fn acceptor(&self) -> Option<TlsAcceptor> {
+        self.config.read().unwrap().clone().map(TlsAcceptor::from)
+    }
+}
+
+fn server_config(config: &Config) -> Result<ServerConfig, TlsError> {
+    let certs = certificates(&config.tls_cert_file)?;
+    let key = PrivateKeyDer::from_pem_file(&config.tls_key_file)
+        .map_err(|error| TlsError::File { path: config.tls_key_file.clone(), error })?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match config.tls_auth_clients {
+        TlsAuthClients::No => builder.with_no_client_auth(),
+        auth => {
+            if config.tls_ca_cert_file.is_empty() {
+                return Err(TlsError::NoCaCert);
+            }
+            let mut roots = RootCertStore::empty();
+            for cert in certificates(&config.tls_ca_cert_file)? {
+                roots.add(cert)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match auth {
+                TlsAuthClients::Optional => verifier.allow_unauthenticated(),
+                _ => verifier,
+            };
+            builder.with_client_cert_verifier(verifier.build()?)
+        }
+    };
+
+    Ok(builder.with_single_cert(certs, key)?)
+}
+
+/// ACL user a client `certificate` authenticates its client as, if `field` names an enabled one.
+pub fn certificate_user(
+    field: CertificateUser,
+    certificate: &CertificateDer,
+    acl: &Acl,
+) -> Option<String> {
+    if field != CertificateUser::Cn {
+        return None;
+    }
+    let (_, certificate) = X509Certificate::from_der(certificate).ok()?;
+    let name = certificate.subject().iter_common_name().next()?.as_str().ok()?;
+    acl.user(name).is_some_and(|user| user.is_enabled()).then(|| name.to_string())
+}
+
+fn certificates(path: &str) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect())
+        .map_err(|error| TlsError::File { path: path.to_string(), error })
+}
+
+/// Client connection, encrypted if it was accepted on the TLS port.
+pub enum Connection {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Connection {
+    /// Complete the TLS handshake of a client connected on the TLS port.
+    pub async fn accept(acceptor: TlsAcceptor, stream: TcpStream) -> io::Result<Self> {
+        let handshake = pin!(acceptor.accept(stream));
+        match select(handshake, pin!(sleep(HANDSHAKE_TIMEOUT))).await {
+            Either::Left((stream, _)) => Ok(Connection::Tls(Box::new(stream?))),
+            Either::Right(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "handshake timed out")),
+        }
+    }
+
+    /// Socket the connection runs over.
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            Connection::Plain(stream) => stream,
+            Connection::Tls(stream) => stream.get_ref().0,
+        }
+    }
+
+    /// Certificate the client presented, if any.
+    pub fn peer_certificate(&self) -> Option<&CertificateDer<'static>> {
+        let Connection::Tls(stream) = self else {
+            return None;
+        };
+        stream.get_ref().1.peer_certificates()?.first()
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Connection::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Connection::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Connection::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_close(cx),
+            Connection::Tls(stream) => Pin::new(stream).poll_close(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_rustls::{
+        pki_types::ServerName,
+        rustls::{
+            ClientConfig,
+            ClientConnection,
+            ServerConnection,
+        },
+    };
+
+    use super::*;
+    use crate::config::SharedConfig;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/testdata/tls/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn config(auth: TlsAuthClients) -> Config {
+        Config {
+            tls_port: 6380,
+            tls_cert_file: fixture("server.crt"),
+            tls_key_file: fixture("server.key"),
+            tls_ca_cert_file: fixture("ca.crt"),
+            tls_auth_clients: auth,
+            ..Default::default()
+        }
+    }
+
+    fn current(tls: &Tls) -> Arc<ServerConfig> {
+        tls.config.read().unwrap().clone().unwrap()
+    }
+
+    /// Connect to `tls` in memory, presenting the client certificate if `authenticate` is set.
+    ///
+    /// Returns the server side of the connection once the handshake completed.
+    fn handshake(tls: &Tls, authenticate: bool) -> Result<rustls::Connection, rustls::Error> {
+        let mut roots = RootCertStore::empty();
+        roots.add(certificates(&fixture("ca.crt")).unwrap().remove(0))?;
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+        let client_config = match authenticate {
+            true => {
+                let key = PrivateKeyDer::from_pem_file(fixture("client.key")).unwrap();
+                builder.with_client_auth_cert(certificates(&fixture("client.crt")).unwrap(), key)?
+            }
+            false => builder.with_no_client_auth(),
+        };
+        let name = ServerName::try_from("localhost").unwrap();
+        let client = ClientConnection::new(Arc::new(client_config), name)?;
+        let server = ServerConnection::new(current(tls))?;
+
+        let mut sides = [rustls::Connection::from(client), rustls::Connection::from(server)];
+        while sides.iter().any(|side| side.is_handshaking()) {
+            for from in [0, 1] {
+                let mut bytes = Vec::new();
+                sides[from].write_tls(&mut bytes).unwrap();
+                let to = &mut sides[1 - from];
+                to.read_tls(&mut bytes.as_slice()).unwrap();
+                to.process_new_packets()?;
+            }
+        }
+        let [_, server] = sides;
+        Ok(server)
+    }
+
+    #[test]
+    fn failed_reload_keeps_acceptor() {
+        let shared = SharedConfig::new(config(TlsAuthClients::No), None);
+        let tls = Tls::new(&shared.read()).unwrap();
+        let previous = current(&tls);
+
+        let set = [("tls-key-file".to_string(), fixture("missing.key"))];
+        let load = |updated: &Config| Tls::load(updated).map_err(|error| error.to_string());
+        assert!(shared.set_with(&set, load, |settings| tls.set(settings)).is_err());
+        assert!(Arc::ptr_eq(&current(&tls), &previous));
+        assert_eq!(shared.read().tls_key_file, fixture("server.key"));
+        assert!(handshake(&tls, false).is_ok());
+
+        let set = [("tls-auth-clients".to_string(), "yes".to_string())];
+        shared.set_with(&set, load, |settings| tls.set(settings)).unwrap();
+        assert!(!Arc::ptr_eq(&current(&tls), &previous));
+        assert!(handshake(&tls, false).is_err());
+    }
+
+    #[test]
+    fn authenticates_clients() {
+        let tls = Tls::new(&config(TlsAuthClients::No)).unwrap();
+        let server = handshake(&tls, true).unwrap();
+        assert!(server.peer_certificates().is_none());
+
+        let tls = Tls::new(&config(TlsAuthClients::Yes)).unwrap();
+        assert!(handshake(&tls, false).is_err());
+        assert!(handshake(&tls, true).unwrap().peer_certificates().is_some());
+
+        let tls = Tls::new(&config(TlsAuthClients::Optional)).unwrap();
+        assert!(handshake(&tls, false).unwrap().peer_certificates().is_none());
+        assert!(handshake(&tls, true).unwrap().peer_certificates().is_some());
+
+        let config = Config { tls_ca_cert_file: String::new(), ..config(TlsAuthClients::Yes) };
+        assert!(matches!(Tls::new(&config), Err(TlsError::NoCaCert)));
+        assert!(Tls::new(&Config::default()).unwrap().acceptor().is_none());
+    }
+
+    #[test]
+    fn maps_certificates_to_users() {
+        let tls = Tls::new(&config(TlsAuthClients::Yes)).unwrap();
+        let server = handshake(&tls, true).unwrap();
+        let certificate = &server.peer_certificates().unwrap()[0];
+
+        let acl = Acl::default();
+        assert_eq!(certificate_user(CertificateUser::Cn, certificate, &acl), None);
+        acl.set_user("alice", ["on"]).unwrap();
+        assert_eq!(
+            certificate_user(CertificateUser::Cn, certificate, &acl).as_deref(),
+            Some("alice")
+        );
+        assert_eq!(certificate_user(CertificateUser::Off, certificate, &acl), None);
+        acl.set_user("alice", ["off"]).unwrap();
+        assert_eq!(certificate_user(CertificateUser::Cn, certificate, &acl), None);
+    }
+}
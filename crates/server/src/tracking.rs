@@ -0,0 +1,343 @@
+use std::{
+    cell::RefCell,
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    rc::Rc,
+    sync::{
+        Arc,
+        Mutex,
+        RwLock,
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    },
+};
+
+use futures::{
+    StreamExt,
+    channel::mpsc,
+};
+use goosekv_protocol::{
+    data_type::{
+        GInteger,
+        GString,
+    },
+    frame::GFrame,
+};
+
+use crate::{
+    config::LocalConfig,
+    outbox::Outbox,
+};
+
+/// Message sent to a RESP3 connection about keys a client may have cached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invalidation {
+    Keys(Vec<GString>),
+    /// Every key, once the keys of every shard were flushed.
+    All,
+    /// The client invalidations are redirected to is gone, sent to the tracking client itself.
+    RedirectBroken(u64),
+}
+
+impl Invalidation {
+    /// Push frame of the message, as Redis sends it.
+    pub fn frame(&self) -> GFrame {
+        let kind = |kind: &'static [u8]| GFrame::BulkString(GString::from_static(kind));
+        let frames = match self {
+            Invalidation::Keys(keys) => {
+                let keys = keys.iter().cloned().map(GFrame::BulkString).collect();
+                [kind(b"invalidate"), GFrame::Array(keys)]
+            }
+            Invalidation::All => [kind(b"invalidate"), GFrame::Null],
+            Invalidation::RedirectBroken(client) => {
+                [kind(b"tracking-redir-broken"), GFrame::Integer(GInteger::new(*client as i64))]
+            }
+        };
+        GFrame::Push(frames.into())
+    }
+}
+
+/// How a client tracks keys, set by `CLIENT TRACKING ON`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackingMode {
+    /// Client receiving the invalidations instead of the tracking one.
+    pub redirect: Option<u64>,
+    /// Keys matching `prefixes` are invalidated whether they were read or not.
+    pub bcast: bool,
+    /// Prefixes of the keys invalidated in BCAST mode, every key if empty.
+    pub prefixes: Vec<GString>,
+    pub optin: bool,
+    pub optout: bool,
+    /// Keys modified by the client itself are not invalidated.
+    pub noloop: bool,
+}
+
+impl TrackingMode {
+    fn matches(&self, key: &GString) -> bool {
+        self.prefixes.is_empty()
+            || self.prefixes.iter().any(|prefix| key.as_ref().starts_with(prefix.as_ref()))
+    }
+}
+
+/// Clients tracking keys and the connections receiving invalidations, shared by every shard.
+#[derive(Debug, Default)]
+pub struct Trackers {
+    /// Clients tracking keys, checked by every write so that shards do nothing without them.
+    count: AtomicUsize,
+    modes: RwLock<HashMap<u64, Arc<TrackingMode>>>,
+    /// Invalidations waiting to be pushed to the connections that switched to RESP3, by client id.
+    connections: Mutex<HashMap<u64, Outbox<Invalidation>>>,
+}
+
+impl Trackers {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clients tracking keys.
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Prefixes tracked by the clients in BCAST mode.
+    pub fn prefixes(&self) -> usize {
+        let modes = self.modes.read().unwrap();
+        modes.values().filter(|mode| mode.bcast).map(|mode| mode.prefixes.len().max(1)).sum()
+    }
+
+    /// Track keys for `client`, until the returned guard is dropped.
+    pub fn enable(self: &Arc<Self>, client: u64, mode: TrackingMode) -> TrackingGuard {
+        let mode = Arc::new(mode);
+        let mut modes = self.modes.write().unwrap();
+        modes.insert(client, mode.clone());
+        self.count.store(modes.len(), Ordering::Release);
+        TrackingGuard { trackers: self.clone(), client, mode }
+    }
+
+    /// Deliver invalidations to connection `client`, until the returned listener is dropped.
+    pub fn listen(self: &Arc<Self>, client: u64) -> Listener {
+        let (outbox, receiver) = Outbox::new();
+        self.connections.lock().unwrap().insert(client, outbox);
+        Listener { trackers: self.clone(), client, receiver }
+    }
+
+    /// Invalidate `key` modified by `writer` for the clients in `readers`, and for those in
+    /// BCAST mode if `bcast` is set.
+    fn invalidate(
+        &self,
+        key: &GString,
+        readers: impl IntoIterator<Item = u64>,
+        writer: Option<u64>,
+        bcast: bool,
+    ) {
+        let modes = self.modes.read().unwrap();
+        // Clients that stopped tracking, or switched to BCAST mode, are left out.
+        let readers =
+            readers.into_iter().filter(|reader| modes.get(reader).is_some_and(|mode| !mode.bcast));
+        let broadcast = modes.iter().filter(|(_, mode)| bcast && mode.bcast && mode.matches(key));
+        let clients = readers.chain(broadcast.map(|(client, _)| *client));
+        for client in clients {
+            let mode = &modes[&client];
+            if mode.noloop && writer == Some(client) {
+                continue;
+            }
+            self.deliver(client, mode, Invalidation::Keys(vec![key.clone()]));
+        }
+    }
+
+    /// Tell every tracking client that all keys were flushed.
+    pub fn invalidate_all(&self) {
+        let modes = self.modes.read().unwrap();
+        for (client, mode) in modes.iter() {
+            self.deliver(*client, mode, Invalidation::All);
+        }
+    }
+
+    fn deliver(&self, client: u64, mode: &TrackingMode, invalidation: Invalidation) {
+        let mut connections = self.connections.lock().unwrap();
+        let target = mode.redirect.unwrap_or(client);
+        // Clients still in RESP2 without redirection get nothing, like in Redis.
+        let (target, invalidation) = match connections.contains_key(&target) {
+            true => (target, invalidation),
+            false if mode.redirect.is_some() => (client, Invalidation::RedirectBroken(target)),
+            false => return,
+        };
+        if let Some(outbox) = connections.get_mut(&target) {
+            outbox.push(invalidation);
+        }
+    }
+}
+
+/// Tracking mode of a client, which stops tracking keys once this is dropped.
+#[derive(Debug)]
+pub struct TrackingGuard {
+    trackers: Arc<Trackers>,
+    client: u64,
+    pub mode: Arc<TrackingMode>,
+}
+
+impl Drop for TrackingGuard {
+    fn drop(&mut self) {
+        let mut modes = self.trackers.modes.write().unwrap();
+        // Tracking may have been enabled again with another mode in the meantime.
+        if modes.get(&self.client).is_some_and(|mode| Arc::ptr_eq(mode, &self.mode)) {
+            modes.remove(&self.client);
+        }
+        self.trackers.count.store(modes.len(), Ordering::Release);
+    }
+}
+
+/// Invalidations delivered to one connection, which stops receiving them once this is dropped.
+#[derive(Debug)]
+pub struct Listener {
+    trackers: Arc<Trackers>,
+    client: u64,
+    receiver: mpsc::Receiver<Invalidation>,
+}
+
+impl Listener {
+    /// Next invalidation to push, `None` once the connection fell too far behind.
+    pub async fn next(&mut self) -> Option<Invalidation> {
+        self.receiver.next().await
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.trackers.connections.lock().unwrap().remove(&self.client);
+    }
+}
+
+/// Keys of one shard read by tracking clients, with the clients that read each of them.
+#[derive(Debug)]
+pub struct TrackingTable {
+    trackers: Arc<Trackers>,
+    config: Rc<LocalConfig>,
+    shards: usize,
+    keys: RefCell<HashMap<GString, HashSet<u64>>>,
+}
+
+impl TrackingTable {
+    pub fn new(trackers: Arc<Trackers>, config: Rc<LocalConfig>, shards: usize) -> Self {
+        Self { trackers, config, shards, keys: Default::default() }
+    }
+
+    pub fn trackers(&self) -> &Arc<Trackers> {
+        &self.trackers
+    }
+
+    /// Keys read by tracking clients.
+    pub fn len(&self) -> usize {
+        self.keys.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.borrow().is_empty()
+    }
+
+    /// Pairs of a key and a client that read it.
+    pub fn items(&self) -> usize {
+        self.keys.borrow().values().map(HashSet::len).sum()
+    }
+
+    /// Remember that `client` read `key`.
+    ///
+    /// Once the table holds more than this shard's share of `tracking-table-max-keys`, other keys
+    /// are invalidated to make room.
+    pub fn record(&self, key: &GString, client: u64) {
+        let max_keys = self.config.read().tracking_table_max_keys;
+        let mut keys = self.keys.borrow_mut();
+        keys.entry(key.clone()).or_default().insert(client);
+
+        let limit = (max_keys / self.shards).max(1);
+        while max_keys > 0 && keys.len() > limit {
+            let Some(evicted) = keys.keys().find(|evicted| *evicted != key).cloned() else {
+                break;
+            };
+            let readers = keys.remove(&evicted).unwrap_or_default();
+            self.trackers.invalidate(&evicted, readers, None, false);
+        }
+    }
+
+    /// Invalidate `key`, modified by client `writer` if any, for the clients that read it and
+    /// those in BCAST mode.
+    pub fn invalidate(&self, key: &GString, writer: Option<u64>) {
+        let readers = self.keys.borrow_mut().remove(key).unwrap_or_default();
+        if !self.trackers.is_empty() {
+            self.trackers.invalidate(key, readers, writer, true);
+        }
+    }
+
+    /// Invalidate `key` for the clients that read it, as it moves to another shard unaware of
+    /// them.
+    pub fn release(&self, key: &GString) {
+        if let Some(readers) = self.keys.borrow_mut().remove(key) {
+            self.trackers.invalidate(key, readers, None, false);
+        }
+    }
+
+    /// Forget every key, after [`Trackers::invalidate_all`].
+    pub fn clear(&self) {
+        self.keys.borrow_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::FutureExt;
+
+    use super::*;
+
+    fn key(key: &'static [u8]) -> GString {
+        GString::from_static(key)
+    }
+
+    fn received(listener: &mut Listener) -> Option<Invalidation> {
+        listener.receiver.next().now_or_never().flatten()
+    }
+
+    #[test]
+    fn invalidates_readers() {
+        let trackers = Arc::new(Trackers::default());
+        let table = TrackingTable::new(trackers.clone(), Default::default(), 1);
+        let mut listener = trackers.listen(1);
+        let _tracking = trackers.enable(1, TrackingMode { noloop: true, ..Default::default() });
+
+        table.record(&key(b"a"), 1);
+        table.invalidate(&key(b"b"), None);
+        assert_eq!(received(&mut listener), None);
+        table.invalidate(&key(b"a"), Some(1));
+        assert_eq!(received(&mut listener), None);
+        assert!(table.is_empty());
+
+        table.record(&key(b"a"), 1);
+        table.invalidate(&key(b"a"), Some(2));
+        assert_eq!(received(&mut listener), Some(Invalidation::Keys(vec![key(b"a")])));
+    }
+
+    #[test]
+    fn broadcasts_prefixes() {
+        let trackers = Arc::new(Trackers::default());
+        let table = TrackingTable::new(trackers.clone(), Default::default(), 1);
+        let mut listener = trackers.listen(2);
+        let mode = TrackingMode {
+            redirect: Some(2),
+            bcast: true,
+            prefixes: vec![key(b"user:")],
+            ..Default::default()
+        };
+        let tracking = trackers.enable(1, mode);
+
+        table.invalidate(&key(b"user:1"), None);
+        table.invalidate(&key(b"order:1"), None);
+        assert_eq!(received(&mut listener), Some(Invalidation::Keys(vec![key(b"user:1")])));
+        assert_eq!(received(&mut listener), None);
+
+        drop(tracking);
+        assert!(trackers.is_empty());
+    }
+}